@@ -204,6 +204,10 @@ pub struct Operating {
     pub keys_path: Option<String>,
     pub password_path: Option<String>,
     pub chain: Option<ChainType>,
+    pub index_addresses: Option<bool>,
+    pub parallel_shard_transactions: Option<bool>,
+    pub light: Option<bool>,
+    pub history: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -221,6 +225,7 @@ pub struct Mining {
     pub reseal_max_period: Option<u64>,
     pub no_reseal_timer: Option<bool>,
     pub work_queue_size: Option<usize>,
+    pub mine_threads: Option<usize>,
 }
 
 #[derive(Deserialize)]
@@ -320,6 +325,18 @@ impl Operating {
         if other.chain.is_some() {
             self.chain = other.chain.clone();
         }
+        if other.index_addresses.is_some() {
+            self.index_addresses = other.index_addresses;
+        }
+        if other.parallel_shard_transactions.is_some() {
+            self.parallel_shard_transactions = other.parallel_shard_transactions;
+        }
+        if other.light.is_some() {
+            self.light = other.light;
+        }
+        if other.history.is_some() {
+            self.history = other.history;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -341,6 +358,18 @@ impl Operating {
         if let Some(chain) = matches.value_of("chain") {
             self.chain = Some(chain.parse().unwrap());
         }
+        if matches.is_present("index-addresses") {
+            self.index_addresses = Some(true);
+        }
+        if matches.is_present("parallel-shard-transactions") {
+            self.parallel_shard_transactions = Some(true);
+        }
+        if matches.is_present("light") {
+            self.light = Some(true);
+        }
+        if let Some(history) = matches.value_of("history") {
+            self.history = Some(history.parse().map_err(|_| "Invalid history")?);
+        }
         Ok(())
     }
 }
@@ -383,6 +412,9 @@ impl Mining {
         if other.work_queue_size.is_some() {
             self.work_queue_size = other.work_queue_size;
         }
+        if other.mine_threads.is_some() {
+            self.mine_threads = other.mine_threads;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -423,6 +455,9 @@ impl Mining {
         if let Some(work_queue_size) = matches.value_of("work-queue-size") {
             self.work_queue_size = Some(work_queue_size.parse().map_err(|_| "Invalid size")?);
         }
+        if let Some(mine_threads) = matches.value_of("mine-threads") {
+            self.mine_threads = Some(mine_threads.parse().map_err(|_| "Invalid number of threads")?);
+        }
         Ok(())
     }
 }
@@ -49,6 +49,7 @@ extern crate never;
 extern crate panic_hook;
 extern crate parking_lot;
 extern crate primitives;
+extern crate rlp;
 extern crate rpassword;
 extern crate toml;
 
@@ -18,6 +18,7 @@ use std::io;
 use std::net::SocketAddr;
 
 use crate::rpc_apis;
+use crpc::v1::Metadata;
 use crpc::{start_http, start_ipc, start_ws, HttpServer, IpcServer, WsError, WsErrorKind, WsServer};
 use crpc::{Compatibility, MetaIoHandler};
 
@@ -111,7 +112,7 @@ pub fn rpc_ws_start(
     }
 }
 
-fn setup_rpc_server(enable_devel_api: bool, deps: &rpc_apis::ApiDependencies) -> MetaIoHandler<()> {
+fn setup_rpc_server(enable_devel_api: bool, deps: &rpc_apis::ApiDependencies) -> MetaIoHandler<Metadata> {
     let mut handler = MetaIoHandler::with_compatibility(Compatibility::Both);
     deps.extend_api(enable_devel_api, &mut handler);
     rpc_apis::setup_rpc(handler)
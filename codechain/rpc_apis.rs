@@ -19,6 +19,7 @@ use std::sync::Arc;
 use ccore::{AccountProvider, Client, Miner};
 use clogger::slogger;
 use cnetwork::NetworkControl;
+use crpc::v1::Metadata;
 use crpc::{MetaIoHandler, Params, Value};
 use csync::BlockSyncExtension;
 
@@ -31,7 +32,7 @@ pub struct ApiDependencies {
 }
 
 impl ApiDependencies {
-    pub fn extend_api(&self, enable_devel_api: bool, handler: &mut MetaIoHandler<()>) {
+    pub fn extend_api(&self, enable_devel_api: bool, handler: &mut MetaIoHandler<Metadata>) {
         use crpc::v1::*;
         handler.extend_with(ChainClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate());
         if enable_devel_api {
@@ -41,6 +42,7 @@ impl ApiDependencies {
             );
         }
         handler.extend_with(EngineClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate());
+        handler.extend_with(EngineSubscriptionClient::new(&*self.client).to_delegate());
         handler.extend_with(MinerClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate());
         handler.extend_with(NetClient::new(Arc::clone(&self.network_control)).to_delegate());
         handler.extend_with(
@@ -55,7 +57,7 @@ impl ApiDependencies {
     }
 }
 
-pub fn setup_rpc(mut handler: MetaIoHandler<()>) -> MetaIoHandler<()> {
+pub fn setup_rpc(mut handler: MetaIoHandler<Metadata>) -> MetaIoHandler<Metadata> {
     handler.add_method("ping", |_params: Params| Ok(Value::String("pong".to_string())));
     handler.add_method("version", |_params: Params| Ok(Value::String(env!("CARGO_PKG_VERSION").to_string())));
     handler.add_method("commitHash", |_params: Params| Ok(Value::String(env!("VERGEN_SHA").to_string())));
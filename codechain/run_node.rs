@@ -21,8 +21,8 @@ use std::sync::{Arc, Weak};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use ccore::{
-    AccountProvider, AccountProviderError, ChainNotify, Client, ClientService, EngineType, Miner, MinerService, Scheme,
-    Stratum, StratumConfig, StratumError,
+    AccountProvider, AccountProviderError, ChainNotify, Client, ClientConfig, ClientService, CpuMiner, EngineType,
+    Miner, MinerService, Scheme, Stratum, StratumConfig, StratumError,
 };
 use cdiscovery::{KademliaConfig, KademliaExtension, UnstructuredConfig, UnstructuredExtension};
 use ckey::Address;
@@ -95,12 +95,20 @@ fn client_start(
     cinfo!(CLIENT, "Starting client");
     let db_path = cfg.db_path.as_ref().map(|s| s.as_str()).unwrap();
     let client_path = Path::new(db_path);
-    let client_config = Default::default();
+    let client_config = ClientConfig {
+        index_addresses: cfg.index_addresses.unwrap(),
+        parallel_shard_transactions: cfg.parallel_shard_transactions.unwrap(),
+        light: cfg.light.unwrap(),
+        history: cfg.history,
+        ..Default::default()
+    };
     let service = ClientService::start(&client_config, &scheme, &client_path, miner)
         .map_err(|e| format!("Client service error: {}", e))?;
 
-    let reseal_timer = timer_loop.new_timer("Client reseal timer", service.client());
-    service.client().register_reseal_timer(reseal_timer);
+    if !client_config.light {
+        let reseal_timer = timer_loop.new_timer("Client reseal timer", service.client());
+        service.client().register_reseal_timer(reseal_timer);
+    }
 
     Ok(service)
 }
@@ -119,9 +127,25 @@ fn stratum_start(cfg: &StratumConfig, miner: &Arc<Miner>, client: Arc<Client>) -
     }
 }
 
+fn cpu_miner_start(threads: usize, miner: &Arc<Miner>, client: Arc<Client>) {
+    miner.add_work_listener(Box::new(CpuMiner::new(threads, miner, &client)));
+    // The miner seals only while someone asks for the work.
+    miner.map_sealing_work(&*client, |_| ());
+    cinfo!(MINER, "CPU miner is running on {} threads", threads);
+}
+
 fn new_miner(config: &config::Config, scheme: &Scheme, ap: Arc<AccountProvider>) -> Result<Arc<Miner>, String> {
     let miner = Miner::new(config.miner_options()?, scheme, Some(ap));
 
+    if config.operating.light.unwrap() {
+        // The light client doesn't have the state to create blocks.
+        if config.mining.author.is_some() || config.mining.engine_signer.is_some() {
+            return Err("The light client cannot seal blocks. Remove --author and --engine-signer.".to_string())
+        }
+        miner.stop_sealing();
+        return Ok(miner)
+    }
+
     if !config.mining.disable.unwrap() {
         match miner.engine_type() {
             EngineType::PoW => match &config.mining.author {
@@ -238,9 +262,15 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
 
     let miner = new_miner(&config, &scheme, ap.clone())?;
     let client = client_start(&timer_loop, &config.operating, &scheme, miner.clone())?;
-    let sync = BlockSyncExtension::new(client.client());
+    let light = config.operating.light.unwrap();
+    let sync = BlockSyncExtension::new(client.client(), light);
+    if light {
+        cinfo!(CLIENT, "Running as a light client");
+        let fetcher = BlockSyncExtension::state_node_fetcher(&sync);
+        client.client().state_db().write().set_remote(Arc::new(fetcher));
+    }
 
-    scheme.engine.register_chain_notify(client.client().as_ref());
+    scheme.engine.register_chain_notify(&client.client());
 
     let network_service: Arc<NetworkControl> = {
         if !config.network.disable.unwrap() {
@@ -304,14 +334,20 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
         }
     };
 
-    if (!config.stratum.disable.unwrap()) && (miner.engine_type() == EngineType::PoW) {
+    if !light && (!config.stratum.disable.unwrap()) && (miner.engine_type() == EngineType::PoW) {
         stratum_start(&config.stratum_config(), &miner, client.client())?
     }
 
+    if !light && !config.mining.disable.unwrap() && miner.engine_type() == EngineType::PoW {
+        let threads = config.mining.mine_threads.unwrap();
+        if threads > 0 {
+            cpu_miner_start(threads, &miner, client.client())
+        }
+    }
+
     let _snapshot_service = {
-        if !config.snapshot.disable.unwrap() {
-            let service =
-                SnapshotService::new(client.client(), config.snapshot.path.unwrap(), scheme.params().snapshot_period);
+        if !light && !config.snapshot.disable.unwrap() {
+            let service = SnapshotService::new(client.client(), config.snapshot.path.unwrap());
             client.client().add_notify(Arc::downgrade(&service) as Weak<ChainNotify>);
             Some(service)
         } else {
@@ -0,0 +1,360 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use ccore::encoded;
+use ccore::{
+    BlockChainClient, BlockId, BlockImportError, BlockInfo, ChainInfo, ClientConfig, ClientService, ImportBlock,
+    ImportError, Miner,
+};
+use clap::ArgMatches;
+use clogger::{self, LoggerConfig};
+use crpc::hex::{FromHex, ToHex};
+use ctypes::BlockNumber;
+use primitives::{remove_0x_prefix, Bytes, H256};
+use rlp::UntrustedRlp;
+
+use crate::config::{self, Config};
+
+const PROGRESS_INTERVAL: usize = 1000;
+/// The largest block which is read, so a corrupted length doesn't allocate an arbitrary size of memory.
+const MAX_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+const QUEUE_POLL_INTERVAL_MS: u64 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    /// The RLP encoded blocks are concatenated.
+    Rlp,
+    /// A JSON object per line, which has the number, the hash and the RLP encoded block in hex.
+    Json,
+}
+
+impl Format {
+    fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        match matches.value_of("format").unwrap_or("rlp") {
+            "rlp" => Ok(Format::Rlp),
+            "json" => Ok(Format::Json),
+            format => Err(format!("Invalid format {}, it must be one of rlp and json", format)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonBlock {
+    number: BlockNumber,
+    hash: H256,
+    rlp: String,
+}
+
+pub fn run_export_blocks_command(matches: &ArgMatches) -> Result<(), String> {
+    clogger::init(&LoggerConfig::new(0)).expect("Logger must be successfully initialized");
+
+    let format = Format::from_matches(matches)?;
+    let config = config::load_config(matches)?;
+    let service = open_client(&config)?;
+    let client = service.client();
+
+    let from = parse_block_number(matches.value_of("from"))?.unwrap_or(0);
+    let to = parse_block_number(matches.value_of("to"))?.unwrap_or_else(|| client.chain_info().best_block_number);
+    if from > to {
+        return Err(format!("The start block #{} is after the end block #{}", from, to))
+    }
+
+    let mut out: Box<Write> = match matches.value_of("FILE") {
+        Some(path) => Box::new(io::BufWriter::new(fs::File::create(path).map_err(|e| e.to_string())?)),
+        None => Box::new(io::BufWriter::new(io::stdout())),
+    };
+
+    for number in from..=to {
+        let id = BlockId::Number(number);
+        let block = client.block(&id).ok_or_else(|| {
+            if client.is_pruned(&id) {
+                format!("The body of block #{} is pruned", number)
+            } else {
+                format!("Block #{} doesn't exist", number)
+            }
+        })?;
+        match format {
+            Format::Rlp => out.write_all(&block.into_inner()).map_err(|e| e.to_string())?,
+            Format::Json => {
+                let json = JsonBlock {
+                    number,
+                    hash: block.hash(),
+                    rlp: format!("0x{}", block.into_inner().to_hex()),
+                };
+                serde_json::to_writer(&mut out, &json).map_err(|e| e.to_string())?;
+                out.write_all(b"\n").map_err(|e| e.to_string())?;
+            }
+        }
+        let exported = (number - from + 1) as usize;
+        if exported % PROGRESS_INTERVAL == 0 {
+            cinfo!(CLIENT, "Exported {} blocks (#{})", exported, number);
+        }
+    }
+    out.flush().map_err(|e| e.to_string())?;
+    cinfo!(CLIENT, "Exported the blocks from #{} to #{}", from, to);
+    Ok(())
+}
+
+pub fn run_import_blocks_command(matches: &ArgMatches) -> Result<(), String> {
+    clogger::init(&LoggerConfig::new(0)).expect("Logger must be successfully initialized");
+
+    let format = Format::from_matches(matches)?;
+    let config = config::load_config(matches)?;
+    if config.operating.light.unwrap() {
+        return Err("The light client cannot import the blocks".to_string())
+    }
+
+    let input: Box<BufRead> = match matches.value_of("FILE") {
+        Some(path) => Box::new(io::BufReader::new(fs::File::open(path).map_err(|e| e.to_string())?)),
+        None => Box::new(io::BufReader::new(io::stdin())),
+    };
+
+    let service = open_client(&config)?;
+    let client = service.client();
+
+    let mut imported = 0;
+    let mut skipped = 0;
+    let mut last_block = None;
+    for (index, block) in BlockReader::new(input, format).enumerate() {
+        let block = block?;
+        let header =
+            UntrustedRlp::new(&block).at(0).map_err(|e| format!("The block at {} is invalid: {:?}", index, e))?;
+        let hash = encoded::Header::new(header.as_raw().to_vec()).hash();
+        while client.queue_info().is_full() {
+            thread::sleep(Duration::from_millis(QUEUE_POLL_INTERVAL_MS));
+        }
+        match client.import_block(block) {
+            Ok(_) => imported += 1,
+            Err(BlockImportError::Import(ImportError::AlreadyInChain)) => skipped += 1,
+            Err(err) => return Err(format!("Cannot import the block at {}: {:?}", index, err)),
+        }
+        last_block = Some((index, hash));
+        if (index + 1) % PROGRESS_INTERVAL == 0 {
+            cinfo!(CLIENT, "Queued {} blocks, the best block is #{}", index + 1, client.chain_info().best_block_number);
+        }
+    }
+    while client.queue_info().total_queue_size() > 0 {
+        thread::sleep(Duration::from_millis(QUEUE_POLL_INTERVAL_MS));
+    }
+
+    let chain_info = client.chain_info();
+    // The queue drops the blocks which fail the verification, so the last block must be the best.
+    if let Some((index, hash)) = last_block {
+        if chain_info.best_block_hash != hash {
+            return Err(format!(
+                "The best block is #{} ({}), not the last block at {} ({})",
+                chain_info.best_block_number, chain_info.best_block_hash, index, hash
+            ))
+        }
+    }
+    cinfo!(
+        CLIENT,
+        "Imported {} blocks and skipped {} existing blocks, the best block is #{}",
+        imported,
+        skipped,
+        chain_info.best_block_number
+    );
+    Ok(())
+}
+
+/// Reads the blocks one by one from the exported blocks.
+struct BlockReader<R: BufRead> {
+    input: R,
+    format: Format,
+    /// The number of the blocks which are read.
+    count: usize,
+    is_done: bool,
+}
+
+impl<R: BufRead> BlockReader<R> {
+    fn new(input: R, format: Format) -> Self {
+        Self {
+            input,
+            format,
+            count: 0,
+            is_done: false,
+        }
+    }
+
+    fn read_block(&mut self) -> Result<Option<Bytes>, String> {
+        match self.format {
+            Format::Rlp => self.read_rlp_block(),
+            Format::Json => self.read_json_block(),
+        }
+    }
+
+    /// Reads an RLP list, whose header has the length of the block.
+    fn read_rlp_block(&mut self) -> Result<Option<Bytes>, String> {
+        let mut first = [0u8];
+        if self.input.read(&mut first).map_err(|e| e.to_string())? == 0 {
+            return Ok(None)
+        }
+        let mut block = vec![first[0]];
+        let value_len = match first[0] {
+            0xc0..=0xf7 => usize::from(first[0] - 0xc0),
+            0xf8..=0xff => {
+                let mut len_of_len = vec![0u8; usize::from(first[0] - 0xf7)];
+                self.read_exact(&mut len_of_len)?;
+                block.extend_from_slice(&len_of_len);
+                len_of_len.iter().try_fold(0usize, |len, byte| {
+                    len.checked_mul(256)
+                        .map(|len| len | usize::from(*byte))
+                        .ok_or_else(|| format!("The length of the block at {} overflows", self.count))
+                })?
+            }
+            _ => return Err(format!("The block at {} is not an RLP list", self.count)),
+        };
+        let header_len = block.len();
+        let block_len = match header_len.checked_add(value_len) {
+            Some(block_len) if block_len <= MAX_BLOCK_SIZE => block_len,
+            _ => return Err(format!("The block at {} is larger than {} bytes", self.count, MAX_BLOCK_SIZE)),
+        };
+        block.resize(block_len, 0);
+        self.read_exact(&mut block[header_len..])?;
+        Ok(Some(block))
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), String> {
+        let count = self.count;
+        self.input.read_exact(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => format!("The block at {} is truncated", count),
+            _ => e.to_string(),
+        })
+    }
+
+    /// Reads the next non-empty line.
+    fn read_json_block(&mut self) -> Result<Option<Bytes>, String> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.input.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                return Ok(None)
+            }
+            if !line.trim().is_empty() {
+                break
+            }
+        }
+        let json: JsonBlock = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+        let block =
+            remove_0x_prefix(&json.rlp).from_hex().map_err(|e| format!("Invalid block #{}: {}", json.number, e))?;
+        Ok(Some(block))
+    }
+}
+
+impl<R: BufRead> Iterator for BlockReader<R> {
+    type Item = Result<Bytes, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None
+        }
+        match self.read_block() {
+            Ok(Some(block)) => {
+                self.count += 1;
+                Some(Ok(block))
+            }
+            Ok(None) => {
+                self.is_done = true;
+                None
+            }
+            // It stops at the first error.
+            Err(err) => {
+                self.is_done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn open_client(config: &Config) -> Result<ClientService, String> {
+    let scheme = match &config.operating.chain {
+        Some(chain) => chain.scheme()?,
+        None => return Err("chain is not specified".to_string()),
+    };
+    let miner = Miner::new(config.miner_options()?, &scheme, None);
+    let client_config = ClientConfig {
+        index_addresses: config.operating.index_addresses.unwrap(),
+        parallel_shard_transactions: config.operating.parallel_shard_transactions.unwrap(),
+        light: config.operating.light.unwrap(),
+        history: config.operating.history,
+        ..Default::default()
+    };
+    let db_path = config.operating.db_path.as_ref().map(|s| s.as_str()).unwrap();
+    ClientService::start(&client_config, &scheme, Path::new(db_path), Arc::clone(&miner))
+        .map_err(|e| format!("Client service error: {}", e))
+}
+
+fn parse_block_number(value: Option<&str>) -> Result<Option<BlockNumber>, String> {
+    match value {
+        Some(value) => value.parse().map(Some).map_err(|_| format!("Invalid block number {}", value)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::RlpStream;
+
+    use super::*;
+
+    #[test]
+    fn read_concatenated_rlp_blocks() {
+        let first = {
+            let mut s = RlpStream::new_list(2);
+            s.append(&1u8).append(&"block");
+            s.out()
+        };
+        let second = {
+            let mut s = RlpStream::new_list(1);
+            s.append(&vec![0u8; 100]);
+            s.out()
+        };
+        let mut data = first.clone();
+        data.extend_from_slice(&second);
+
+        let read = |data: &[u8]| BlockReader::new(data, Format::Rlp).collect::<Result<Vec<_>, _>>();
+        assert_eq!(Ok(vec![first, second.clone()]), read(&data));
+        assert!(read(&second[..second.len() - 1]).is_err());
+        assert!(read(b"\x01").is_err());
+    }
+
+    #[test]
+    fn reject_too_large_rlp_block() {
+        let read = |data: &[u8]| BlockReader::new(data, Format::Rlp).collect::<Result<Vec<_>, _>>();
+        // The length of the length is 8 bytes, and the length is larger than the maximum.
+        assert!(read(b"\xff\x00\x00\x00\x00\x40\x00\x00\x00").is_err());
+        assert!(read(b"\xff\xff\xff\xff\xff\xff\xff\xff\xff").is_err());
+    }
+
+    #[test]
+    fn read_exported_json_blocks() {
+        let json = JsonBlock {
+            number: 1,
+            hash: H256::zero(),
+            rlp: "0xc20102".to_string(),
+        };
+        let data = format!("{}\n\n", serde_json::to_string(&json).unwrap());
+        let blocks = BlockReader::new(data.as_bytes(), Format::Json).collect::<Result<Vec<_>, _>>();
+        assert_eq!(Ok(vec![vec![0xc2, 0x01, 0x02]]), blocks);
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use ccore::{check_database, repair_database, ClientConfig, Scheme};
+use clap::ArgMatches;
+use clogger::{self, LoggerConfig};
+
+use crate::config;
+
+pub fn run_db_command(matches: &ArgMatches) -> Result<(), String> {
+    if matches.subcommand.is_none() {
+        println!("{}", matches.usage());
+        return Ok(())
+    }
+
+    clogger::init(&LoggerConfig::new(0)).expect("Logger must be successfully initialized");
+
+    let config = config::load_config(matches)?;
+    let scheme = match &config.operating.chain {
+        Some(chain) => chain.scheme()?,
+        None => return Err("chain is not specified".to_string()),
+    };
+    let client_config = ClientConfig {
+        index_addresses: config.operating.index_addresses.unwrap(),
+        ..Default::default()
+    };
+    let db_path = config.operating.db_path.as_ref().map(|s| s.as_str()).unwrap();
+
+    match matches.subcommand() {
+        ("check", _) => check(&client_config, &scheme, Path::new(db_path)),
+        ("repair", _) => repair(&client_config, &scheme, Path::new(db_path)),
+        _ => Err("Invalid subcommand".to_string()),
+    }
+}
+
+fn check(client_config: &ClientConfig, scheme: &Scheme, db_path: &Path) -> Result<(), String> {
+    let inconsistencies = check_database(client_config, scheme, db_path).map_err(|e| e.to_string())?;
+    if inconsistencies.is_empty() {
+        println!("The database is consistent");
+        return Ok(())
+    }
+    for inconsistency in &inconsistencies {
+        println!("{}", inconsistency);
+    }
+    Err(format!("Found {} inconsistencies, run `codechain db repair` to rewind the chain", inconsistencies.len()))
+}
+
+fn repair(client_config: &ClientConfig, scheme: &Scheme, db_path: &Path) -> Result<(), String> {
+    match repair_database(client_config, scheme, db_path).map_err(|e| e.to_string())? {
+        Some((number, hash)) => println!("Rewound the best block to #{} ({})", number, hash),
+        None => println!("The database is consistent"),
+    }
+    Ok(())
+}
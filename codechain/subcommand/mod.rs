@@ -15,15 +15,25 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod account_command;
+mod blocks_command;
+mod db_command;
 
 use clap::ArgMatches;
 
 use self::account_command::run_account_command;
+use self::blocks_command::{run_export_blocks_command, run_import_blocks_command};
+use self::db_command::run_db_command;
 
 pub fn run_subcommand(matches: &ArgMatches) -> Result<(), String> {
     let subcommand = matches.subcommand.as_ref().unwrap();
     if subcommand.name == "account" {
         run_account_command(&subcommand.matches)
+    } else if subcommand.name == "export-blocks" {
+        run_export_blocks_command(&subcommand.matches)
+    } else if subcommand.name == "import-blocks" {
+        run_import_blocks_command(&subcommand.matches)
+    } else if subcommand.name == "db" {
+        run_db_command(&subcommand.matches)
     } else {
         Err("Invalid subcommand".to_string())
     }
@@ -145,6 +145,26 @@ impl AccountProvider {
         Ok(self.keystore.read().sign_schnorr(&address, &password, &message)?)
     }
 
+    pub fn sign_schnorr_aggregated_partial(
+        &self,
+        address: Address,
+        password: Option<Password>,
+        signers: &[Public],
+        nonce: &Private,
+        other_nonces: &Public,
+        message: Message,
+    ) -> Result<SchnorrSignature, SignError> {
+        let password = password.map(Ok).unwrap_or_else(|| self.password(&address))?;
+        Ok(self.keystore.read().sign_schnorr_aggregated_partial(
+            &address,
+            &password,
+            signers,
+            nonce,
+            other_nonces,
+            &message,
+        )?)
+    }
+
     pub fn public(&self, address: &Address, password: Option<Password>) -> Result<Public, SignError> {
         let password = password.map(Ok).unwrap_or_else(|| self.password(address))?;
         Ok(self.keystore.read().public(&address, &password)?)
@@ -15,15 +15,17 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::collections::HashSet;
+use std::sync::Arc;
 
 use ccrypto::BLAKE_NULL_RLP;
 use ckey::Address;
 use cmerkle::skewed_merkle_root;
-use cstate::{FindActionHandler, StateDB, StateError, StateWithCache, TopLevelState};
+use cstate::{ActionHandler, FindActionHandler, StateDB, StateError, StateWithCache, TopLevelState};
 use ctypes::invoice::Invoice;
 use ctypes::machine::{LiveBlock, Transactions};
 use ctypes::transaction::ParcelError;
 use ctypes::util::unexpected::Mismatch;
+use ctypes::BlockNumber;
 use cvm::ChainTimeInfo;
 use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
@@ -127,7 +129,8 @@ impl<'x> OpenBlock<'x> {
         is_epoch_begin: bool,
     ) -> Result<Self, Error> {
         let number = parent.number() + 1;
-        let state = TopLevelState::from_existing(db, *parent.state_root()).map_err(StateError::from)?;
+        let mut state = TopLevelState::from_existing(db, *parent.state_root()).map_err(StateError::from)?;
+        state.set_supply_tracking(engine.machine().params().is_supply_tracked(number));
         let mut r = OpenBlock {
             block: ExecutedBlock::new(state),
             engine,
@@ -149,7 +152,7 @@ impl<'x> OpenBlock<'x> {
     }
 
     /// Push a transaction into the block.
-    pub fn push_transaction<C: ChainTimeInfo + FindActionHandler>(
+    pub fn push_transaction<C: ChainTimeInfo>(
         &mut self,
         tx: SignedTransaction,
         h: Option<H256>,
@@ -159,7 +162,8 @@ impl<'x> OpenBlock<'x> {
             return Err(StateError::Parcel(ParcelError::TransactionAlreadyImported).into())
         }
 
-        let invoice = self.block.state.apply(&tx, &tx.hash(), &tx.signer_public(), client)?;
+        let client = BlockClient::new(client, self.engine, self.block.header.number());
+        let invoice = self.block.state.apply(&tx, &tx.hash(), &tx.signer_public(), &client)?;
 
         self.block.transactions_set.insert(h.unwrap_or_else(|| tx.hash()));
         self.block.transactions.push(tx);
@@ -168,7 +172,7 @@ impl<'x> OpenBlock<'x> {
     }
 
     /// Push transactions onto the block.
-    pub fn push_transactions<C: ChainTimeInfo + FindActionHandler>(
+    pub fn push_transactions<C: ChainTimeInfo>(
         &mut self,
         transactions: &[SignedTransaction],
         client: &C,
@@ -179,6 +183,31 @@ impl<'x> OpenBlock<'x> {
         Ok(())
     }
 
+    /// Push transactions onto the block, applying the shard transactions of independent shards in parallel.
+    /// The resulting state and invoices are the same as the ones of `push_transactions`.
+    pub fn push_transactions_in_parallel<C: ChainTimeInfo + Sync>(
+        &mut self,
+        transactions: &[SignedTransaction],
+        client: &C,
+    ) -> Result<(), Error> {
+        let mut hashes = HashSet::with_capacity(transactions.len());
+        for tx in transactions {
+            let hash = tx.hash();
+            if self.block.transactions_set.contains(&hash) || !hashes.insert(hash) {
+                return Err(StateError::Parcel(ParcelError::TransactionAlreadyImported).into())
+            }
+        }
+
+        let inputs: Vec<_> = transactions.iter().map(|tx| (&***tx, tx.hash(), tx.signer_public())).collect();
+        let client = BlockClient::new(client, self.engine, self.block.header.number());
+        let invoices = self.block.state.apply_in_parallel(&inputs, &client)?;
+
+        self.block.transactions_set.extend(hashes);
+        self.block.transactions.extend(transactions.iter().cloned());
+        self.block.invoices.extend(invoices);
+        Ok(())
+    }
+
     /// Populate self from a header.
     fn populate_from(&mut self, header: &Header) {
         self.block.header.set_score(*header.score());
@@ -418,8 +447,53 @@ impl IsBlock for SealedBlock {
     }
 }
 
+/// The chain seen by the transactions of a block.
+/// The transactions can use only the action handlers of the engine which handles the block.
+struct BlockClient<'a, C: 'a> {
+    client: &'a C,
+    action_handlers: &'a [Arc<ActionHandler>],
+}
+
+impl<'a, C: ChainTimeInfo> BlockClient<'a, C> {
+    fn new(client: &'a C, engine: &'a CodeChainEngine, block_number: BlockNumber) -> Self {
+        Self {
+            client,
+            action_handlers: engine.action_handlers_at(block_number),
+        }
+    }
+}
+
+impl<'a, C: ChainTimeInfo> ChainTimeInfo for BlockClient<'a, C> {
+    fn best_block_number(&self) -> u64 {
+        self.client.best_block_number()
+    }
+
+    fn best_block_timestamp(&self) -> u64 {
+        self.client.best_block_timestamp()
+    }
+
+    fn transaction_block_age(&self, hash: &H256) -> Option<u64> {
+        self.client.transaction_block_age(hash)
+    }
+
+    fn transaction_time_age(&self, hash: &H256) -> Option<u64> {
+        self.client.transaction_time_age(hash)
+    }
+}
+
+impl<'a, C> FindActionHandler for BlockClient<'a, C> {
+    fn find_action_handler_for(&self, id: u64) -> Option<&Arc<ActionHandler>> {
+        self.action_handlers.iter().find(|handler| handler.handler_id() == id)
+    }
+
+    fn action_handlers(&self) -> &[Arc<ActionHandler>] {
+        self.action_handlers
+    }
+}
+
 /// Enact the block given by block header, transactions and uncles
-pub fn enact<C: ChainTimeInfo + FindActionHandler>(
+#[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+pub fn enact<C: ChainTimeInfo + Sync>(
     header: &Header,
     transactions: &[SignedTransaction],
     engine: &CodeChainEngine,
@@ -427,11 +501,16 @@ pub fn enact<C: ChainTimeInfo + FindActionHandler>(
     db: StateDB,
     parent: &Header,
     is_epoch_begin: bool,
+    parallel_shard_transactions: bool,
 ) -> Result<LockedBlock, Error> {
     let mut b = OpenBlock::try_new(engine, db, parent, Address::default(), vec![], is_epoch_begin)?;
 
     b.populate_from(header);
-    b.push_transactions(transactions, client)?;
+    if parallel_shard_transactions {
+        b.push_transactions_in_parallel(transactions, client)?;
+    } else {
+        b.push_transactions(transactions, client)?;
+    }
 
     b.close_and_lock(*parent.transactions_root(), *parent.invoices_root())
 }
@@ -0,0 +1,485 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use ckey::{public_to_address, Address};
+use ctypes::transaction::Action;
+use kvdb::{DBTransaction, KeyValueDB};
+use primitives::H256;
+
+use super::block_info::BestBlockChanged;
+use super::body_db::BodyProvider;
+use super::extras::{
+    AddressPage, AddressTransactions, AddressTransactionsCount, ParcelAddress, ADDRESS_TRANSACTIONS_PAGE_SIZE,
+};
+use crate::db::{self, Readable, Writable};
+use crate::UnverifiedTransaction;
+
+/// Index of the parcels by the addresses they involve.
+///
+/// Only the parcels in the canonical chain are indexed. The parcels of an address are kept in the order of the chain
+/// and split into pages of `ADDRESS_TRANSACTIONS_PAGE_SIZE`, so that a block rewrites only the last pages.
+/// Since a reorganization retracts the most recent blocks, the retracted parcels are always at the end.
+pub struct AddressDB {
+    db: Arc<KeyValueDB>,
+}
+
+type AddressAndParcel = (Address, ParcelAddress);
+
+impl AddressDB {
+    pub fn new(db: Arc<KeyValueDB>) -> Self {
+        Self {
+            db,
+        }
+    }
+
+    pub fn update_best_block(
+        &self,
+        batch: &mut DBTransaction,
+        best_block_changed: &BestBlockChanged,
+        body_provider: &BodyProvider,
+    ) {
+        let block_hash = match best_block_changed.new_best_hash() {
+            Some(hash) => hash,
+            None => return,
+        };
+        let block = match best_block_changed.best_block() {
+            Some(block) => block,
+            None => return,
+        };
+
+        let (retracted, enacted): (Vec<AddressAndParcel>, Vec<AddressAndParcel>) = match best_block_changed {
+            BestBlockChanged::CanonChainAppended {
+                ..
+            } => (Vec::new(), address_parcel_entries(block_hash, block.transactions()).collect()),
+            BestBlockChanged::BranchBecomingCanonChain {
+                ref tree_route,
+                ..
+            } => {
+                let enacted = tree_route
+                    .enacted
+                    .iter()
+                    .flat_map(|hash| {
+                        let body = body_provider.block_body(hash).expect("Enacted block must be in database.");
+                        address_parcel_entries(*hash, body.transactions())
+                    })
+                    .chain(address_parcel_entries(block_hash, block.transactions()))
+                    .collect();
+
+                let retracted = tree_route
+                    .retracted
+                    .iter()
+                    .flat_map(|hash| {
+                        let body = body_provider.block_body(hash).expect("Retracted block must be in database.");
+                        address_parcel_entries(*hash, body.transactions())
+                    })
+                    .collect();
+
+                (retracted, enacted)
+            }
+            BestBlockChanged::None => return,
+        };
+        self.update(batch, retracted, enacted);
+    }
+
+    /// Removes the parcels of the given blocks from the index.
+    /// The blocks must be the most recent blocks of the canonical chain.
+    pub fn remove_blocks(&self, batch: &mut DBTransaction, hashes: &[H256], body_provider: &BodyProvider) {
+        let removed = hashes
+            .iter()
+            .filter_map(|hash| body_provider.block_body(hash).map(|body| (*hash, body)))
+            .flat_map(|(hash, body)| address_parcel_entries(hash, body.transactions()))
+            .collect();
+        self.update(batch, removed, Vec::new());
+    }
+
+    fn update(&self, batch: &mut DBTransaction, removed: Vec<AddressAndParcel>, added: Vec<AddressAndParcel>) {
+        let mut removed_parcels: HashMap<Address, HashSet<ParcelAddress>> = Default::default();
+        for (address, parcel) in removed {
+            removed_parcels.entry(address).or_insert_with(Default::default).insert(parcel);
+        }
+        let mut added_parcels: HashMap<Address, Vec<ParcelAddress>> = Default::default();
+        for (address, parcel) in added {
+            added_parcels.entry(address).or_insert_with(Default::default).push(parcel);
+        }
+
+        let addresses: HashSet<Address> = removed_parcels.keys().chain(added_parcels.keys()).cloned().collect();
+        for address in addresses {
+            let mut pages = AddressPages::load(&*self.db, address);
+            if let Some(removed) = removed_parcels.get(&address) {
+                pages.pop_while(|parcel| removed.contains(parcel));
+            }
+            for parcel in added_parcels.remove(&address).unwrap_or_default() {
+                pages.push(parcel);
+            }
+            pages.write(batch);
+        }
+    }
+}
+
+/// The pages of an address which are modified in a batch.
+struct AddressPages<'a> {
+    db: &'a KeyValueDB,
+    address: Address,
+    count: u64,
+    pages: HashMap<u64, AddressTransactions>,
+}
+
+impl<'a> AddressPages<'a> {
+    fn load(db: &'a KeyValueDB, address: Address) -> Self {
+        let AddressTransactionsCount(count) = db.read(db::COL_EXTRA, &address).unwrap_or_default();
+        Self {
+            db,
+            address,
+            count,
+            pages: HashMap::new(),
+        }
+    }
+
+    fn page(&mut self, page: u64) -> &mut AddressTransactions {
+        let db = self.db;
+        let address = self.address;
+        self.pages.entry(page).or_insert_with(|| {
+            db.read(db::COL_EXTRA, &AddressPage {
+                address,
+                page,
+            })
+            .unwrap_or_default()
+        })
+    }
+
+    /// Removes the parcels from the end while they satisfy the predicate.
+    fn pop_while(&mut self, predicate: impl Fn(&ParcelAddress) -> bool) {
+        while self.count > 0 {
+            let last_page = (self.count - 1) / ADDRESS_TRANSACTIONS_PAGE_SIZE;
+            let popped = {
+                let page = self.page(last_page);
+                let matched = page.last().map_or(false, |parcel| predicate(parcel));
+                if matched {
+                    page.pop();
+                }
+                matched
+            };
+            if !popped {
+                break
+            }
+            self.count -= 1;
+        }
+    }
+
+    fn push(&mut self, parcel: ParcelAddress) {
+        let last_page = self.count / ADDRESS_TRANSACTIONS_PAGE_SIZE;
+        self.page(last_page).push(parcel);
+        self.count += 1;
+    }
+
+    fn write(self, batch: &mut DBTransaction) {
+        let address = self.address;
+        for (page, transactions) in self.pages {
+            let key = AddressPage {
+                address,
+                page,
+            };
+            if transactions.is_empty() {
+                Writable::delete::<AddressTransactions, _>(batch, db::COL_EXTRA, &key);
+            } else {
+                batch.write(db::COL_EXTRA, &key, &transactions);
+            }
+        }
+        if self.count == 0 {
+            Writable::delete::<AddressTransactionsCount, _>(batch, db::COL_EXTRA, &address);
+        } else {
+            batch.write(db::COL_EXTRA, &address, &AddressTransactionsCount(self.count));
+        }
+    }
+}
+
+/// Interface for querying the parcels by the addresses they involve.
+pub trait AddressProvider {
+    /// Get the addresses of parcels which involve the given address, starting from the most recent one.
+    /// Returns None if the parcels are not indexed by the addresses.
+    fn address_transactions(&self, address: &Address, skip: usize, limit: usize) -> Option<Vec<ParcelAddress>>;
+}
+
+impl AddressProvider for AddressDB {
+    fn address_transactions(&self, address: &Address, skip: usize, limit: usize) -> Option<Vec<ParcelAddress>> {
+        let AddressTransactionsCount(count) = self.db.read(db::COL_EXTRA, address).unwrap_or_default();
+        let mut result = Vec::new();
+        let mut page: Option<(u64, AddressTransactions)> = None;
+        for index in (0..count.saturating_sub(skip as u64)).rev().take(limit) {
+            let page_number = index / ADDRESS_TRANSACTIONS_PAGE_SIZE;
+            if page.as_ref().map(|(number, _)| *number) != Some(page_number) {
+                let transactions = self
+                    .db
+                    .read(db::COL_EXTRA, &AddressPage {
+                        address: *address,
+                        page: page_number,
+                    })
+                    .unwrap_or_default();
+                page = Some((page_number, transactions));
+            }
+            let (_, transactions) = page.as_ref().expect("The page is loaded above");
+            match transactions.get((index % ADDRESS_TRANSACTIONS_PAGE_SIZE) as usize) {
+                Some(parcel) => result.push(*parcel),
+                None => break,
+            }
+        }
+        Some(result)
+    }
+}
+
+/// Returns the addresses involved in the given parcel.
+///
+/// The signer is always involved. The receiver of `Pay`, the account of the new regular key and the owners and users
+/// of a new or changed shard are involved too.
+pub fn involved_addresses(parcel: &UnverifiedTransaction) -> Vec<Address> {
+    let mut addresses = Vec::new();
+    match parcel.recover_public() {
+        Ok(public) => addresses.push(public_to_address(&public)),
+        Err(err) => cwarn!(BLOCKCHAIN, "Cannot recover the signer of {}: {:?}", parcel.hash(), err),
+    }
+    match &parcel.action {
+        Action::Pay {
+            receiver,
+            ..
+        } => addresses.push(*receiver),
+        Action::SetRegularKey {
+            key,
+        } => addresses.push(public_to_address(key)),
+        // The signer becomes the only owner of the new shard, which has no users.
+        Action::CreateShard => {}
+        Action::SetShardOwners {
+            owners,
+            ..
+        } => addresses.extend(owners.iter().cloned()),
+        Action::SetShardUsers {
+            users,
+            ..
+        } => addresses.extend(users.iter().cloned()),
+        _ => {}
+    }
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
+
+fn address_parcel_entries(
+    block_hash: H256,
+    parcels: impl IntoIterator<Item = UnverifiedTransaction>,
+) -> impl Iterator<Item = AddressAndParcel> {
+    parcels.into_iter().enumerate().flat_map(move |(index, parcel)| {
+        let parcel_address = ParcelAddress {
+            block_hash,
+            index,
+        };
+        involved_addresses(&parcel).into_iter().map(move |address| (address, parcel_address))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{Generator, Random};
+    use ctypes::invoice::Invoice;
+    use ctypes::transaction::Transaction;
+    use kvdb_memorydb;
+    use primitives::U256;
+
+    use super::super::{AddressProvider, BlockChain, BlockChainConfig};
+    use super::*;
+    use crate::header::Header;
+    use crate::scheme::Scheme;
+    use crate::tests::helpers::create_test_block_with_data;
+    use crate::SignedTransaction;
+
+    fn parcel(action: Action) -> (Address, UnverifiedTransaction) {
+        let keypair = Random.generate().unwrap();
+        let tx = Transaction {
+            seq: 0,
+            fee: 10,
+            network_id: "tc".into(),
+            action,
+        };
+        (keypair.address(), SignedTransaction::new_with_sign(tx, keypair.private()).into())
+    }
+
+    #[test]
+    fn pay_involves_the_signer_and_the_receiver() {
+        let receiver = Address::random();
+        let (signer, parcel) = parcel(Action::Pay {
+            receiver,
+            amount: 10,
+        });
+        let mut expected = vec![signer, receiver];
+        expected.sort();
+        assert_eq!(expected, involved_addresses(&parcel));
+    }
+
+    #[test]
+    fn set_shard_users_involves_the_users() {
+        let users = vec![Address::random(), Address::random()];
+        let (signer, parcel) = parcel(Action::SetShardUsers {
+            shard_id: 0,
+            users: users.clone(),
+        });
+        let mut expected = vec![signer, users[0], users[1]];
+        expected.sort();
+        assert_eq!(expected, involved_addresses(&parcel));
+    }
+
+    #[test]
+    fn create_shard_involves_the_signer_as_the_owner() {
+        let (signer, parcel) = parcel(Action::CreateShard);
+        assert_eq!(vec![signer], involved_addresses(&parcel));
+    }
+
+    #[test]
+    fn wrap_ccc_involves_only_the_signer() {
+        let (signer, parcel) = parcel(Action::WrapCCC {
+            shard_id: 0,
+            lock_script_hash: Default::default(),
+            parameters: vec![],
+            amount: 30,
+        });
+        assert_eq!(vec![signer], involved_addresses(&parcel));
+    }
+
+    #[test]
+    fn paying_to_oneself_is_indexed_once() {
+        let keypair = Random.generate().unwrap();
+        let tx = Transaction {
+            seq: 0,
+            fee: 10,
+            network_id: "tc".into(),
+            action: Action::Pay {
+                receiver: keypair.address(),
+                amount: 10,
+            },
+        };
+        let parcel: UnverifiedTransaction = SignedTransaction::new_with_sign(tx, keypair.private()).into();
+        assert_eq!(vec![keypair.address()], involved_addresses(&parcel));
+    }
+
+    fn insert_block(
+        chain: &BlockChain,
+        scheme: &Scheme,
+        db: &Arc<KeyValueDB>,
+        parent: H256,
+        score: u64,
+        parcels: &[SignedTransaction],
+    ) -> H256 {
+        let mut header = Header::new();
+        header.set_parent_hash(parent);
+        header.set_number(1);
+        header.set_score(U256::from(score));
+        header.set_timestamp(score);
+
+        let mut batch = DBTransaction::new();
+        let invoices = vec![Invoice::Success; parcels.len()];
+        chain.insert_block(&mut batch, &create_test_block_with_data(&header, parcels, &[]), invoices, &*scheme.engine);
+        db.write(batch).unwrap();
+        chain.commit();
+        header.hash()
+    }
+
+    #[test]
+    fn retracted_parcels_are_removed_from_the_index() {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)));
+        let config = BlockChainConfig {
+            index_addresses: true,
+            ..Default::default()
+        };
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), &config);
+        let genesis = scheme.genesis_header().hash();
+
+        let signer = Random.generate().unwrap();
+        let receiver = Address::random();
+        // More than a page, so that the retraction empties a page.
+        let count = ADDRESS_TRANSACTIONS_PAGE_SIZE as usize + 10;
+        let parcels: Vec<_> = (0..count)
+            .map(|seq| {
+                let tx = Transaction {
+                    seq: seq as u64,
+                    fee: 10,
+                    network_id: "tc".into(),
+                    action: Action::Pay {
+                        receiver,
+                        amount: 10,
+                    },
+                };
+                SignedTransaction::new_with_sign(tx, signer.private())
+            })
+            .collect();
+        let retracted = insert_block(&chain, &scheme, &db, genesis, 0x20000, &parcels);
+
+        let indexed = chain.address_transactions(&receiver, 0, count).unwrap();
+        assert_eq!(count, indexed.len());
+        assert_eq!(
+            ParcelAddress {
+                block_hash: retracted,
+                index: count - 1,
+            },
+            indexed[0]
+        );
+        // It crosses the boundary of the pages.
+        let paged = chain.address_transactions(&receiver, 7, 4).unwrap();
+        assert_eq!(indexed[7..11].to_vec(), paged);
+
+        let other_receiver = Address::random();
+        let (_, enacted_parcel) = parcel(Action::Pay {
+            receiver: other_receiver,
+            amount: 10,
+        });
+        let enacted_parcel = SignedTransaction::try_new(enacted_parcel).unwrap();
+        let enacted = insert_block(&chain, &scheme, &db, genesis, 0x40000, &[enacted_parcel]);
+        assert_eq!(enacted, chain.best_block_hash());
+
+        assert_eq!(Some(vec![]), chain.address_transactions(&receiver, 0, count));
+        assert_eq!(Some(vec![]), chain.address_transactions(&signer.address(), 0, count));
+        assert_eq!(
+            Some(vec![ParcelAddress {
+                block_hash: enacted,
+                index: 0,
+            }]),
+            chain.address_transactions(&other_receiver, 0, count)
+        );
+        let page = AddressPage {
+            address: receiver,
+            page: 0,
+        };
+        assert_eq!(None, db.read::<AddressTransactions, _>(db::COL_EXTRA, &page));
+    }
+
+    #[test]
+    fn address_index_cannot_be_enabled_on_unindexed_blocks() {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)));
+        let indexed = BlockChainConfig {
+            index_addresses: true,
+            ..Default::default()
+        };
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), &indexed);
+        assert!(chain.init_address_index());
+        insert_block(&chain, &scheme, &db, scheme.genesis_header().hash(), 0x20000, &[]);
+
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), &BlockChainConfig::default());
+        assert!(chain.init_address_index());
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), &indexed);
+        assert!(!chain.init_address_index());
+    }
+}
@@ -14,18 +14,23 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::cmp;
 use std::mem;
 use std::sync::Arc;
 
+use ckey::Address;
 use ctypes::invoice::{BlockInvoices, Invoice};
+use ctypes::transaction::ShardTransaction;
 use ctypes::BlockNumber;
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::RwLock;
 use primitives::H256;
 use rlp::RlpStream;
 
+use super::address_db::{AddressDB, AddressProvider};
 use super::block_info::BestBlockChanged;
 use super::body_db::{BodyDB, BodyProvider};
+use super::consistency::Inconsistency;
 use super::extras::{BlockDetails, EpochTransitions, ParcelAddress, TransactionAddress, EPOCH_KEY_PREFIX};
 use super::headerchain::{HeaderChain, HeaderProvider};
 use super::invoice_db::{InvoiceDB, InvoiceProvider};
@@ -40,6 +45,25 @@ use crate::views::{BlockView, HeaderView};
 
 const BEST_BLOCK_KEY: &[u8] = b"best-block";
 const HIGHEST_BLOCK_KEY: &[u8] = b"highest-block";
+const PRUNED_UNTIL_KEY: &[u8] = b"pruned-until";
+/// Exists if the parcels of all the canonical blocks are indexed by the addresses.
+const ADDRESS_INDEX_KEY: &[u8] = b"address-index";
+
+/// The maximum number of blocks pruned while importing a block,
+/// so that enabling the pruning on an old database doesn't stall the import.
+const MAX_PRUNED_BLOCKS_PER_IMPORT: BlockNumber = 1000;
+
+/// The bodies of the recent blocks are kept to handle the chain reorganizations.
+pub const MIN_HISTORY: u64 = 128;
+
+/// Configuration of the optional indexes maintained by `BlockChain`.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct BlockChainConfig {
+    /// Index the parcels by the addresses they involve.
+    pub index_addresses: bool,
+    /// Keep the bodies and the invoices of only the last `history` blocks. `None` keeps all of them.
+    pub history: Option<u64>,
+}
 
 /// Structure providing fast access to blockchain data.
 ///
@@ -51,16 +75,21 @@ pub struct BlockChain {
     headerchain: HeaderChain,
     body_db: BodyDB,
     invoice_db: InvoiceDB,
+    address_db: Option<AddressDB>,
+
+    history: Option<u64>,
+    pruned_until: RwLock<BlockNumber>,
 
     db: Arc<KeyValueDB>,
 
     pending_best_block_hash: RwLock<Option<H256>>,
     pending_highest_block_hash: RwLock<Option<H256>>,
+    pending_pruned_until: RwLock<Option<BlockNumber>>,
 }
 
 impl BlockChain {
     /// Create new instance of blockchain from given Genesis.
-    pub fn new(genesis: &[u8], db: Arc<KeyValueDB>) -> Self {
+    pub fn new(genesis: &[u8], db: Arc<KeyValueDB>, config: &BlockChainConfig) -> Self {
         let genesis_block = BlockView::new(genesis);
 
         // load best block
@@ -87,6 +116,9 @@ impl BlockChain {
             }
         };
 
+        let pruned_until =
+            db.get(db::COL_EXTRA, PRUNED_UNTIL_KEY).unwrap().map_or(0, |pruned_until| ::rlp::decode(&pruned_until));
+
         Self {
             best_block_hash: RwLock::new(best_block_hash),
             highest_block_hash: RwLock::new(highest_block_hash),
@@ -94,11 +126,20 @@ impl BlockChain {
             headerchain: HeaderChain::new(&genesis_block.header_view(), db.clone()),
             body_db: BodyDB::new(&genesis_block, db.clone()),
             invoice_db: InvoiceDB::new(db.clone()),
+            address_db: if config.index_addresses {
+                Some(AddressDB::new(db.clone()))
+            } else {
+                None
+            },
+
+            history: config.history,
+            pruned_until: RwLock::new(pruned_until),
 
             db,
 
             pending_best_block_hash: RwLock::new(None),
             pending_highest_block_hash: RwLock::new(None),
+            pending_pruned_until: RwLock::new(None),
         }
     }
 
@@ -135,12 +176,16 @@ impl BlockChain {
 
         assert!(self.pending_best_block_hash.read().is_none());
         assert!(self.pending_highest_block_hash.read().is_none());
+        assert!(self.pending_pruned_until.read().is_none());
 
         let best_block_changed = self.best_block_changed(&new_block, engine);
 
         self.headerchain.insert_header(batch, &new_header, engine);
         self.body_db.insert_body(batch, &new_block);
         self.body_db.update_best_block(batch, &best_block_changed);
+        if let Some(address_db) = &self.address_db {
+            address_db.update_best_block(batch, &best_block_changed, &self.body_db);
+        }
         self.invoice_db.insert_invoice(batch, &new_block_hash, invoices);
 
         if let Some(best_block_hash) = best_block_changed.new_best_hash() {
@@ -152,6 +197,9 @@ impl BlockChain {
             batch.put(db::COL_EXTRA, HIGHEST_BLOCK_KEY, &*new_block_hash);
             *pending_highest_block_hash = Some(new_block_hash);
         }
+        if let Some(best_block) = best_block_changed.best_block() {
+            self.prune(batch, best_block.header_view().number());
+        }
 
         ImportRoute::new(new_block_hash, &best_block_changed)
     }
@@ -160,7 +208,7 @@ impl BlockChain {
     pub fn commit(&self) {
         self.headerchain.commit();
         self.body_db.commit();
-        // NOTE: There are no commit for InvoiceDB
+        // NOTE: There are no commit for InvoiceDB and AddressDB
 
         let mut best_block_hash = self.best_block_hash.write();
         let mut pending_best_block_hash = self.pending_best_block_hash.write();
@@ -175,6 +223,47 @@ impl BlockChain {
         if let Some(hash) = pending_highest_block_hash.take() {
             *highest_block_hash = hash;
         }
+
+        if let Some(number) = self.pending_pruned_until.write().take() {
+            *self.pruned_until.write() = number;
+        }
+    }
+
+    /// Removes the bodies and the invoices of the canonical blocks which fall out of the history.
+    /// The headers and the indexes are kept.
+    fn prune(&self, batch: &mut DBTransaction, best_block_number: BlockNumber) {
+        let history = match self.history {
+            Some(history) => history,
+            None => return,
+        };
+        let pruned_until = self.pruned_until();
+        let target = cmp::min(best_block_number.saturating_sub(history), pruned_until + MAX_PRUNED_BLOCKS_PER_IMPORT);
+        if target <= pruned_until {
+            return
+        }
+
+        for number in (pruned_until + 1)..=target {
+            let hash = self.block_hash(number).expect("The canonical blocks below the best block must exist");
+            self.body_db.prune_body(batch, &hash);
+            self.invoice_db.remove_invoices(batch, &hash);
+        }
+        ctrace!(BLOCKCHAIN, "Pruned the blocks from #{} to #{}", pruned_until + 1, target);
+
+        batch.put(db::COL_EXTRA, PRUNED_UNTIL_KEY, &::rlp::encode(&target));
+        *self.pending_pruned_until.write() = Some(target);
+    }
+
+    /// Get the number of the last block whose body and invoices are pruned. It's 0 if nothing is pruned.
+    pub fn pruned_until(&self) -> BlockNumber {
+        *self.pruned_until.read()
+    }
+
+    /// Returns true if the body and the invoices of the given block are pruned.
+    pub fn is_pruned(&self, hash: &H256) -> bool {
+        match self.block_number(hash) {
+            Some(number) if number != 0 && number <= self.pruned_until() => self.block_hash(number) == Some(*hash),
+            _ => false,
+        }
     }
 
     /// Calculate how best block is changed
@@ -307,7 +396,6 @@ impl BlockChain {
     /// This will give the epoch that any children of this parent belong to.
     ///
     /// The block corresponding the the parent hash must be stored already.
-    #[allow(dead_code)]
     pub fn epoch_transition_for(&self, parent_hash: H256) -> Option<EpochTransition> {
         // slow path: loop back block by block
         for hash in self.ancestry_iter(parent_hash)? {
@@ -333,7 +421,6 @@ impl BlockChain {
     }
 
     /// Iterator that lists `first` and then all of `first`'s ancestors, by hash.
-    #[allow(dead_code)]
     pub fn ancestry_iter(&self, first: H256) -> Option<AncestryIter> {
         if self.is_known(&first) {
             Some(AncestryIter {
@@ -356,6 +443,187 @@ impl BlockChain {
     pub fn get_pending_transition(&self, hash: H256) -> Option<PendingEpochTransition> {
         self.db.read(db::COL_EXTRA, &hash)
     }
+
+    /// Returns true if the parcels are indexed by the addresses they involve.
+    pub fn is_address_indexed(&self) -> bool {
+        self.address_db.is_some()
+    }
+
+    /// Marks that the parcels are indexed from the genesis block, or removes the mark if they are not indexed anymore.
+    /// Returns false if the index is enabled on a chain whose blocks are not indexed.
+    pub fn init_address_index(&self) -> bool {
+        let is_marked = self.db.get(db::COL_EXTRA, ADDRESS_INDEX_KEY).unwrap().is_some();
+        let mut batch = DBTransaction::new();
+        match (self.is_address_indexed(), is_marked) {
+            (true, true) | (false, false) => return true,
+            (true, false) => {
+                if self.best_block_detail().number != 0 {
+                    return false
+                }
+                batch.put(db::COL_EXTRA, ADDRESS_INDEX_KEY, &[]);
+            }
+            (false, true) => batch.delete(db::COL_EXTRA, ADDRESS_INDEX_KEY),
+        }
+        self.db.write(batch).expect("Low level database error. Some issue with disk?");
+        true
+    }
+
+    /// Walks the canonical chain from the best block to the genesis block and returns the inconsistencies
+    /// in the order of the walk. The walk stops at the block whose header and details are both missing.
+    pub fn check_consistency(&self) -> Vec<Inconsistency> {
+        let mut inconsistencies = Vec::new();
+        let mut hash = self.best_block_hash();
+        while let Some(parent) = self.check_block(&hash, &mut inconsistencies) {
+            hash = parent;
+        }
+        inconsistencies
+    }
+
+    /// Checks the block and returns its parent, or `None` if it's the genesis block or its parent is unknown.
+    fn check_block(&self, hash: &H256, inconsistencies: &mut Vec<Inconsistency>) -> Option<H256> {
+        let header = self.block_header_data(hash);
+        let details = self.block_details(hash);
+        if header.is_none() || details.is_none() {
+            inconsistencies.push(Inconsistency::MissingHeader(*hash));
+        }
+        let (number, parent) = match (header, details) {
+            (Some(header), _) => (header.number(), header.parent_hash()),
+            (None, Some(details)) => (details.number, details.parent),
+            (None, None) => return None,
+        };
+
+        match self.block_body(hash) {
+            Some(body) => {
+                let parcels = body.transactions();
+                for (index, parcel) in parcels.iter().enumerate() {
+                    let address = ParcelAddress {
+                        block_hash: *hash,
+                        index,
+                    };
+                    if self.parcel_address(&parcel.hash()) != Some(address) {
+                        inconsistencies.push(Inconsistency::WrongParcelAddress {
+                            block_hash: *hash,
+                            parcel: parcel.hash(),
+                        });
+                    }
+                    if let Some(transaction) = Option::<ShardTransaction>::from(parcel.action.clone()) {
+                        let tracker = transaction.tracker();
+                        let indexed = self
+                            .transaction_address(&tracker)
+                            .map_or(false, |addresses| addresses.into_iter().any(|a| a == address));
+                        if !indexed {
+                            inconsistencies.push(Inconsistency::WrongTransactionAddress {
+                                block_hash: *hash,
+                                tracker,
+                            });
+                        }
+                    }
+                }
+
+                // The invoices of the genesis block are not stored.
+                if number != 0 {
+                    match self.block_invoices(hash) {
+                        None => inconsistencies.push(Inconsistency::MissingInvoices(*hash)),
+                        Some(invoices) if invoices.invoices.len() != parcels.len() => {
+                            inconsistencies.push(Inconsistency::InvoiceCountMismatch {
+                                block_hash: *hash,
+                                parcels: parcels.len(),
+                                invoices: invoices.invoices.len(),
+                            })
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            // The bodies and the invoices of the pruned blocks are removed on purpose.
+            None if self.is_pruned(hash) => {}
+            None => inconsistencies.push(Inconsistency::MissingBody(*hash)),
+        }
+
+        if number == 0 {
+            None
+        } else {
+            Some(parent)
+        }
+    }
+
+    /// Returns the parent of the block from its header or its details.
+    pub fn parent_hash_of(&self, hash: &H256) -> Option<H256> {
+        self.block_header_data(hash)
+            .map(|header| header.parent_hash())
+            .or_else(|| self.block_details(hash).map(|details| details.parent))
+    }
+
+    /// Rewinds the best block to the target, which must be an ancestor of the best block.
+    /// The blocks after the target are removed, so that they are downloaded again.
+    /// The caches are not updated, so it's only for the database which is reopened after the batch is written.
+    pub fn rewind(&self, batch: &mut DBTransaction, target: &H256) {
+        let target_number = self.block_number(target).expect("The target of the rewind must exist");
+
+        let mut removed = Vec::new();
+        let mut hash = self.best_block_hash();
+        while hash != *target {
+            removed.push(hash);
+            match self.parent_hash_of(&hash) {
+                Some(parent) => hash = parent,
+                None => break,
+            }
+        }
+        // The best header can be ahead of the best block.
+        let mut number = target_number + 1;
+        while let Some(hash) = self.block_hash(number) {
+            if !removed.contains(&hash) {
+                removed.push(hash);
+            }
+            number += 1;
+        }
+
+        self.body_db.remove_bodies(batch, &removed);
+        if let Some(address_db) = &self.address_db {
+            address_db.remove_blocks(batch, &removed, &self.body_db);
+        }
+        for hash in &removed {
+            self.invoice_db.remove_invoices(batch, hash);
+        }
+        self.remove_epoch_transitions(batch, &removed);
+        self.headerchain.rewind(batch, target, &removed);
+
+        batch.put(db::COL_EXTRA, BEST_BLOCK_KEY, target);
+        batch.put(db::COL_EXTRA, HIGHEST_BLOCK_KEY, target);
+    }
+
+    /// Removes the epoch transitions and the pending transitions of the removed blocks.
+    fn remove_epoch_transitions(&self, batch: &mut DBTransaction, removed: &[H256]) {
+        for hash in removed {
+            Writable::delete::<PendingEpochTransition, _>(batch, db::COL_EXTRA, hash);
+        }
+        for (key, value) in self
+            .db
+            .iter_from_prefix(db::COL_EXTRA, &EPOCH_KEY_PREFIX[..])
+            .take_while(|(key, _)| key.starts_with(&EPOCH_KEY_PREFIX[..]))
+        {
+            let mut transitions: EpochTransitions = ::rlp::decode(&value[..]);
+            let count = transitions.candidates.len();
+            transitions.candidates.retain(|candidate| !removed.contains(&candidate.block_hash));
+            if transitions.candidates.is_empty() {
+                batch.delete(db::COL_EXTRA, &key);
+            } else if transitions.candidates.len() != count {
+                batch.put(db::COL_EXTRA, &key, &::rlp::encode(&transitions));
+            }
+        }
+    }
+
+    /// Returns true if the chain was initialized from the genesis block in the database.
+    /// `BlockChain::new` writes the genesis block into an uninitialized database.
+    pub fn is_initialized(genesis: &[u8], db: &KeyValueDB) -> bool {
+        let genesis_hash = BlockView::new(genesis).hash();
+        let has =
+            |col, key: &[u8]| db.get(col, key).expect("Low level database error. Some issue with disk?").is_some();
+        has(db::COL_EXTRA, BEST_BLOCK_KEY)
+            && has(db::COL_EXTRA, HIGHEST_BLOCK_KEY)
+            && HeaderChain::is_initialized(db)
+            && has(db::COL_BODIES, &genesis_hash[..])
+    }
 }
 
 /// An iterator which walks the blockchain towards the genesis.
@@ -511,4 +779,16 @@ impl InvoiceProvider for BlockChain {
     }
 }
 
-impl BlockProvider for BlockChain {}
+impl AddressProvider for BlockChain {
+    /// Always returns None if the address index is disabled.
+    fn address_transactions(&self, address: &Address, skip: usize, limit: usize) -> Option<Vec<ParcelAddress>> {
+        self.address_db.as_ref()?.address_transactions(address, skip, limit)
+    }
+}
+
+impl BlockProvider for BlockChain {
+    /// The pruned blocks are known though their bodies are removed.
+    fn is_known(&self, hash: &H256) -> bool {
+        self.is_known_header(hash) && (self.is_known_body(hash) || self.is_pruned(hash))
+    }
+}
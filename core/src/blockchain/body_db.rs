@@ -266,6 +266,51 @@ impl BodyDB {
             .collect()
     }
 
+    /// Removes the bodies of the given blocks and their entries in the parcel and the tracker indexes.
+    /// The caches are not updated, so it's only for the database which is reopened after the batch is written.
+    pub fn remove_bodies(&self, batch: &mut DBTransaction, hashes: &[H256]) {
+        let mut transaction_addresses: HashMap<H256, TransactionAddress> = HashMap::new();
+        for hash in hashes {
+            let body = match self.block_body(hash) {
+                Some(body) => body,
+                None => continue,
+            };
+            for (index, parcel_hash) in body.transaction_hashes().into_iter().enumerate() {
+                let address = ParcelAddress {
+                    block_hash: *hash,
+                    index,
+                };
+                if self.parcel_address(&parcel_hash) == Some(address) {
+                    Writable::delete::<ParcelAddress, _>(batch, db::COL_EXTRA, &parcel_hash);
+                }
+            }
+            for (tracker, address) in transaction_address_entries(*hash, body.transactions()) {
+                let stored = match transaction_addresses.remove(&tracker) {
+                    Some(stored) => Some(stored),
+                    None => self.transaction_address(&tracker),
+                };
+                if let Some(mut stored) = stored {
+                    stored -= address;
+                    transaction_addresses.insert(tracker, stored);
+                }
+            }
+            batch.delete(db::COL_BODIES, hash);
+        }
+        for (tracker, address) in transaction_addresses {
+            if address.is_empty() {
+                Writable::delete::<TransactionAddress, _>(batch, db::COL_EXTRA, &tracker);
+            } else {
+                batch.write(db::COL_EXTRA, &tracker, &address);
+            }
+        }
+    }
+
+    /// Removes the body of the block, but keeps the indexes of its parcels and transactions.
+    pub fn prune_body(&self, batch: &mut DBTransaction, hash: &H256) {
+        batch.delete(db::COL_BODIES, hash);
+        self.body_cache.write().remove(hash);
+    }
+
     /// Create a block body from a block.
     pub fn block_to_body(block: &BlockView) -> Bytes {
         let mut body = RlpStream::new_list(1);
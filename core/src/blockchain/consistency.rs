@@ -0,0 +1,238 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use primitives::H256;
+
+/// A problem found while walking the canonical chain.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inconsistency {
+    /// The header or the details of the block are missing.
+    MissingHeader(H256),
+    MissingBody(H256),
+    MissingInvoices(H256),
+    /// The number of the invoices differs from the number of the parcels.
+    InvoiceCountMismatch {
+        block_hash: H256,
+        parcels: usize,
+        invoices: usize,
+    },
+    /// The parcel index doesn't point to the parcel in the block.
+    WrongParcelAddress {
+        block_hash: H256,
+        parcel: H256,
+    },
+    /// The tracker index doesn't have the parcel in the block.
+    WrongTransactionAddress {
+        block_hash: H256,
+        tracker: H256,
+    },
+    /// Some nodes of the state trie at the block are missing.
+    MissingStateNodes {
+        block_hash: H256,
+        nodes: Vec<H256>,
+    },
+}
+
+impl Inconsistency {
+    pub fn block_hash(&self) -> &H256 {
+        match self {
+            Inconsistency::MissingHeader(block_hash)
+            | Inconsistency::MissingBody(block_hash)
+            | Inconsistency::MissingInvoices(block_hash)
+            | Inconsistency::InvoiceCountMismatch {
+                block_hash,
+                ..
+            }
+            | Inconsistency::WrongParcelAddress {
+                block_hash,
+                ..
+            }
+            | Inconsistency::WrongTransactionAddress {
+                block_hash,
+                ..
+            }
+            | Inconsistency::MissingStateNodes {
+                block_hash,
+                ..
+            } => block_hash,
+        }
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Inconsistency::MissingHeader(hash) => write!(f, "The header of {} is missing", hash),
+            Inconsistency::MissingBody(hash) => write!(f, "The body of {} is missing", hash),
+            Inconsistency::MissingInvoices(hash) => write!(f, "The invoices of {} are missing", hash),
+            Inconsistency::InvoiceCountMismatch {
+                block_hash,
+                parcels,
+                invoices,
+            } => write!(f, "{} has {} parcels but {} invoices", block_hash, parcels, invoices),
+            Inconsistency::WrongParcelAddress {
+                block_hash,
+                parcel,
+            } => write!(f, "The index of the parcel {} doesn't point to {}", parcel, block_hash),
+            Inconsistency::WrongTransactionAddress {
+                block_hash,
+                tracker,
+            } => write!(f, "The index of the tracker {} doesn't have {}", tracker, block_hash),
+            Inconsistency::MissingStateNodes {
+                block_hash,
+                nodes,
+            } => write!(f, "{} state nodes of {} are missing", nodes.len(), block_hash),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ctypes::invoice::BlockInvoices;
+    use kvdb::{DBTransaction, KeyValueDB};
+    use kvdb_memorydb;
+    use primitives::U256;
+
+    use super::super::{
+        BlockChain, BlockChainConfig, BlockProvider, BodyProvider, HeaderProvider, InvoiceProvider, MIN_HISTORY,
+    };
+    use super::*;
+    use crate::consensus::epoch::{PendingTransition, Transition};
+    use crate::db::{self, Writable};
+    use crate::header::Header;
+    use crate::scheme::Scheme;
+    use crate::tests::helpers::create_test_block;
+
+    fn open_chain(scheme: &Scheme, db: &Arc<KeyValueDB>) -> BlockChain {
+        BlockChain::new(&scheme.genesis_block(), Arc::clone(db), &BlockChainConfig::default())
+    }
+
+    fn insert_child(chain: &BlockChain, scheme: &Scheme, db: &Arc<KeyValueDB>, parent: H256, number: u64) -> H256 {
+        let mut header = Header::new();
+        header.set_parent_hash(parent);
+        header.set_number(number);
+        header.set_score(U256::from(0x20000));
+        header.set_timestamp(40 + number);
+
+        let mut batch = DBTransaction::new();
+        chain.insert_block(&mut batch, &create_test_block(&header), vec![], &*scheme.engine);
+        db.write(batch).unwrap();
+        chain.commit();
+        header.hash()
+    }
+
+    #[test]
+    fn rewind_to_the_block_before_the_missing_invoices() {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)));
+        let chain = open_chain(&scheme, &db);
+        let first = insert_child(&chain, &scheme, &db, scheme.genesis_header().hash(), 1);
+        let second = insert_child(&chain, &scheme, &db, first, 2);
+        assert_eq!(Vec::<Inconsistency>::new(), chain.check_consistency());
+
+        let mut batch = DBTransaction::new();
+        Writable::delete::<BlockInvoices, _>(&mut batch, db::COL_EXTRA, &second);
+        db.write(batch).unwrap();
+        let chain = open_chain(&scheme, &db);
+        assert_eq!(vec![Inconsistency::MissingInvoices(second)], chain.check_consistency());
+
+        let mut batch = DBTransaction::new();
+        chain.rewind(&mut batch, &first);
+        db.write(batch).unwrap();
+        let chain = open_chain(&scheme, &db);
+        assert_eq!(first, chain.best_block_hash());
+        assert_eq!(Vec::<Inconsistency>::new(), chain.check_consistency());
+        assert!(!chain.is_known_header(&second));
+        assert_eq!(None, chain.block_hash(2));
+    }
+
+    #[test]
+    fn chain_is_initialized_when_it_is_opened() {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)));
+        assert!(!BlockChain::is_initialized(&scheme.genesis_block(), &*db));
+        open_chain(&scheme, &db);
+        assert!(BlockChain::is_initialized(&scheme.genesis_block(), &*db));
+    }
+
+    #[test]
+    fn rewind_removes_the_epoch_transitions_of_the_removed_blocks() {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)));
+        let chain = open_chain(&scheme, &db);
+        let first = insert_child(&chain, &scheme, &db, scheme.genesis_header().hash(), 1);
+        let second = insert_child(&chain, &scheme, &db, first, 2);
+        let transition = |block_hash, block_number| Transition {
+            block_hash,
+            block_number,
+            proof: vec![],
+        };
+        let mut batch = DBTransaction::new();
+        chain.insert_epoch_transition(&mut batch, 1, transition(first, 1));
+        chain.insert_epoch_transition(&mut batch, 2, transition(second, 2));
+        let pending = PendingTransition {
+            proof: vec![],
+        };
+        chain.insert_pending_transition(&mut batch, second, &pending);
+        db.write(batch).unwrap();
+
+        let mut batch = DBTransaction::new();
+        chain.rewind(&mut batch, &first);
+        db.write(batch).unwrap();
+        let chain = open_chain(&scheme, &db);
+        assert!(chain.epoch_transition(1, first).is_some());
+        assert!(chain.epoch_transition(2, second).is_none());
+        assert!(chain.get_pending_transition(second).is_none());
+        assert_eq!(vec![1], chain.epoch_transitions().map(|(number, _)| number).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn pruned_blocks_are_not_inconsistent() {
+        let scheme = Scheme::new_test();
+        let db: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)));
+        let config = BlockChainConfig {
+            history: Some(MIN_HISTORY),
+            ..Default::default()
+        };
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), &config);
+        let mut hashes = vec![scheme.genesis_header().hash()];
+        for number in 1..=(MIN_HISTORY + 3) {
+            let parent = *hashes.last().unwrap();
+            hashes.push(insert_child(&chain, &scheme, &db, parent, number));
+        }
+        assert_eq!(3, chain.pruned_until());
+        assert_eq!(Vec::<Inconsistency>::new(), chain.check_consistency());
+
+        let chain = BlockChain::new(&scheme.genesis_block(), Arc::clone(&db), &config);
+        assert_eq!(3, chain.pruned_until());
+        assert!(chain.block_body(&hashes[0]).is_some());
+        for hash in &hashes[1..=3] {
+            assert!(chain.is_pruned(hash));
+            assert!(chain.is_known(hash));
+            assert_eq!(None, chain.block_body(hash));
+            assert_eq!(None, chain.block_invoices(hash));
+        }
+        for hash in &hashes[4..] {
+            assert!(!chain.is_pruned(hash));
+            assert!(chain.block_body(hash).is_some());
+            assert!(chain.block_invoices(hash).is_some());
+        }
+    }
+}
@@ -17,6 +17,7 @@
 use std::io::Write;
 use std::ops::{self, Add, AddAssign, Deref, Sub, SubAssign};
 
+use ckey::Address;
 use ctypes::invoice::BlockInvoices;
 use ctypes::BlockNumber;
 use heapsize::HeapSizeOf;
@@ -44,6 +45,10 @@ enum ExtrasIndex {
     EpochTransitions = 5,
     /// Pending epoch transition data index.
     PendingEpochTransition = 6,
+    /// Address transactions index
+    AddressTransactions = 7,
+    /// Address transactions count index
+    AddressTransactionsCount = 8,
 }
 
 fn with_index(hash: &H256, i: ExtrasIndex) -> H264 {
@@ -110,6 +115,61 @@ impl Key<TransactionAddress> for H256 {
     }
 }
 
+pub struct AddressKey([u8; 21]);
+
+impl Deref for AddressKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Key<AddressTransactionsCount> for Address {
+    type Target = AddressKey;
+
+    fn key(&self) -> Self::Target {
+        let mut result = [0u8; 21];
+        result[0] = ExtrasIndex::AddressTransactionsCount as u8;
+        result[1..].clone_from_slice(self);
+        AddressKey(result)
+    }
+}
+
+/// The number of parcel addresses in a page of the address index.
+pub const ADDRESS_TRANSACTIONS_PAGE_SIZE: u64 = 256;
+
+/// Identifies a page of the parcels which involve an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AddressPage {
+    pub address: Address,
+    pub page: u64,
+}
+
+pub struct AddressPageKey([u8; 29]);
+
+impl Deref for AddressPageKey {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Key<AddressTransactions> for AddressPage {
+    type Target = AddressPageKey;
+
+    fn key(&self) -> Self::Target {
+        let mut result = [0u8; 29];
+        result[0] = ExtrasIndex::AddressTransactions as u8;
+        result[1..21].clone_from_slice(&self.address);
+        for (i, byte) in result[21..].iter_mut().enumerate() {
+            *byte = (self.page >> (56 - 8 * i)) as u8;
+        }
+        AddressPageKey(result)
+    }
+}
+
 /// length of epoch keys.
 const EPOCH_KEY_LEN: usize = DB_PREFIX_LEN + 16;
 
@@ -170,7 +230,7 @@ impl HeapSizeOf for BlockDetails {
 }
 
 /// Represents address of certain parcel within block
-#[derive(Debug, PartialEq, Clone, Copy, RlpEncodable, RlpDecodable)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, RlpEncodable, RlpDecodable)]
 pub struct ParcelAddress {
     /// Block hash
     pub block_hash: H256,
@@ -190,6 +250,16 @@ pub struct TransactionAddress {
     parcel_addresses: Vec<ParcelAddress>,
 }
 
+/// Represents a page of the addresses of parcels which involve a certain address, in the order of the chain
+#[derive(Debug, Default, PartialEq, Clone, RlpEncodableWrapper, RlpDecodableWrapper)]
+pub struct AddressTransactions {
+    parcel_addresses: Vec<ParcelAddress>,
+}
+
+/// Represents the number of parcels which involve a certain address
+#[derive(Debug, Default, PartialEq, Clone, Copy, RlpEncodableWrapper, RlpDecodableWrapper)]
+pub struct AddressTransactionsCount(pub u64);
+
 /// Candidate transitions to an epoch with specific number.
 #[derive(Clone, RlpEncodable, RlpDecodable)]
 pub struct EpochTransitions {
@@ -254,6 +324,28 @@ impl SubAssign for TransactionAddress {
     }
 }
 
+impl AddressTransactions {
+    pub fn is_empty(&self) -> bool {
+        self.parcel_addresses.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&ParcelAddress> {
+        self.parcel_addresses.get(index)
+    }
+
+    pub fn last(&self) -> Option<&ParcelAddress> {
+        self.parcel_addresses.last()
+    }
+
+    pub fn push(&mut self, parcel_address: ParcelAddress) {
+        self.parcel_addresses.push(parcel_address);
+    }
+
+    pub fn pop(&mut self) -> Option<ParcelAddress> {
+        self.parcel_addresses.pop()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use rlp::rlp_encode_and_decode_test;
@@ -406,4 +498,87 @@ mod tests {
             (t1 - t2).parcel_addresses
         );
     }
+
+    #[test]
+    fn encode_and_decode_address_transactions() {
+        rlp_encode_and_decode_test!(AddressTransactions {
+            parcel_addresses: vec![
+                ParcelAddress {
+                    block_hash: H256::random(),
+                    index: 0,
+                },
+                ParcelAddress {
+                    block_hash: H256::random(),
+                    index: 2,
+                },
+            ],
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_address_transactions_count() {
+        rlp_encode_and_decode_test!(AddressTransactionsCount(257));
+    }
+
+    #[test]
+    fn address_pages_have_distinct_keys_in_the_order_of_the_pages() {
+        let address = Address::random();
+        let first = AddressPage {
+            address,
+            page: 0,
+        }
+        .key();
+        let second = AddressPage {
+            address,
+            page: 1,
+        }
+        .key();
+        let far = AddressPage {
+            address,
+            page: 256,
+        }
+        .key();
+        assert_ne!(&*first, &*second);
+        assert!(&*first < &*second);
+        assert!(&*second < &*far);
+        assert_eq!(&first[1..21], &address[..]);
+    }
+
+    #[test]
+    fn address_transactions_is_a_stack() {
+        let mut transactions = AddressTransactions::default();
+        assert!(transactions.is_empty());
+        transactions.push(ParcelAddress {
+            block_hash: 0.into(),
+            index: 0,
+        });
+        transactions.push(ParcelAddress {
+            block_hash: 1.into(),
+            index: 0,
+        });
+        assert_eq!(
+            Some(&ParcelAddress {
+                block_hash: 1.into(),
+                index: 0,
+            }),
+            transactions.last()
+        );
+        assert_eq!(
+            Some(&ParcelAddress {
+                block_hash: 0.into(),
+                index: 0,
+            }),
+            transactions.get(0)
+        );
+
+        assert_eq!(
+            Some(ParcelAddress {
+                block_hash: 1.into(),
+                index: 0,
+            }),
+            transactions.pop()
+        );
+        assert_eq!(None, transactions.get(1));
+        assert!(!transactions.is_empty());
+    }
 }
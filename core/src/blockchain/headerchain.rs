@@ -179,6 +179,33 @@ impl HeaderChain {
         write_block_details.extend(mem::replace(&mut *pending_block_details, HashMap::new()));
     }
 
+    /// Returns true if the best header is written in the database.
+    pub fn is_initialized(db: &KeyValueDB) -> bool {
+        let has =
+            |key: &[u8]| db.get(db::COL_EXTRA, key).expect("Low level database error. Some issue with disk?").is_some();
+        has(BEST_HEADER_KEY) && has(HIGHEST_HEADER_KEY)
+    }
+
+    /// Removes the given headers and makes the target the best and the highest header.
+    /// The caches are not updated, so it's only for the database which is reopened after the batch is written.
+    pub fn rewind(&self, batch: &mut DBTransaction, target: &H256, removed: &[H256]) {
+        for hash in removed {
+            if let Some(details) = self.block_details(hash) {
+                if self.block_hash(details.number) == Some(*hash) {
+                    Writable::delete::<H256, _>(batch, db::COL_EXTRA, &details.number);
+                }
+                Writable::delete::<BlockDetails, _>(batch, db::COL_EXTRA, hash);
+            }
+            batch.delete(db::COL_HEADERS, hash);
+        }
+        if let Some(mut details) = self.block_details(target) {
+            details.children.retain(|child| !removed.contains(child));
+            batch.write(db::COL_EXTRA, target, &details);
+        }
+        batch.put(db::COL_EXTRA, BEST_HEADER_KEY, target);
+        batch.put(db::COL_EXTRA, HIGHEST_HEADER_KEY, target);
+    }
+
     /// This function returns modified block hashes.
     fn new_hash_entries(
         &self,
@@ -58,6 +58,12 @@ impl InvoiceDB {
         let mut invoice_cache = self.invoice_cache.write();
         batch.extend_with_cache(db::COL_EXTRA, &mut *invoice_cache, invoice_map, CacheUpdatePolicy::Remove);
     }
+
+    /// Removes the invoices of the given block.
+    pub fn remove_invoices(&self, batch: &mut DBTransaction, hash: &H256) {
+        Writable::delete::<BlockInvoices, _>(batch, db::COL_EXTRA, hash);
+        self.invoice_cache.write().remove(hash);
+    }
 }
 
 /// Interface for querying invoices.
@@ -14,18 +14,22 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod address_db;
 mod block_info;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod blockchain;
 mod body_db;
+mod consistency;
 mod extras;
 mod headerchain;
 mod invoice_db;
 mod route;
 
-pub use self::blockchain::{BlockChain, BlockProvider};
+pub use self::address_db::AddressProvider;
+pub use self::blockchain::{BlockChain, BlockChainConfig, BlockProvider, MIN_HISTORY};
 pub use self::body_db::BodyProvider;
-pub use self::extras::{BlockDetails, ParcelAddress, TransactionAddress};
+pub use self::consistency::Inconsistency;
+pub use self::extras::{AddressTransactions, BlockDetails, ParcelAddress, TransactionAddress};
 pub use self::headerchain::HeaderProvider;
 pub use self::invoice_db::InvoiceProvider;
 pub use self::route::ImportRoute;
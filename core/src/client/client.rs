@@ -19,7 +19,7 @@ use std::sync::{Arc, Weak};
 use std::time::Instant;
 
 use cio::IoChannel;
-use ckey::{Address, PlatformAddress, Public};
+use ckey::{Address, NetworkId, PlatformAddress, Public};
 use cmerkle::Result as TrieResult;
 use cnetwork::NodeId;
 use cstate::{
@@ -44,15 +44,18 @@ use super::{
     BlockProducer, ChainInfo, ChainNotify, ClientConfig, DatabaseClient, EngineClient, EngineInfo,
     Error as ClientError, ExecuteClient, ImportBlock, ImportResult, ImportSealedBlock, MiningBlockChainClient,
     ParcelInfo, PrepareOpenBlock, RegularKey, RegularKeyOwner, ReopenBlock, ResealTimer, Seq, Shard, StateInfo,
-    StateOrBlock, TextClient, TransactionInfo,
+    StateOrBlock, Supply, TextClient, TransactionInfo,
 };
 use crate::block::{ClosedBlock, IsBlock, OpenBlock, SealedBlock};
 use crate::blockchain::{
-    BlockChain, BlockProvider, BodyProvider, HeaderProvider, InvoiceProvider, ParcelAddress, TransactionAddress,
+    AddressProvider, BlockChain, BlockChainConfig, BlockProvider, BodyProvider, HeaderProvider, InvoiceProvider,
+    ParcelAddress, TransactionAddress, MIN_HISTORY,
 };
-use crate::consensus::CodeChainEngine;
+use crate::consensus::epoch::Transition as EpochTransition;
+use crate::consensus::{CodeChainEngine, CommitCertificate, ConsensusListener, ConsensusState};
 use crate::encoded;
 use crate::error::{BlockImportError, Error, ImportError, SchemeError};
+use crate::governance;
 use crate::miner::{Miner, MinerService};
 use crate::scheme::{CommonParams, Scheme};
 use crate::service::ClientIoMessage;
@@ -81,10 +84,17 @@ pub struct Client {
 
     genesis_accounts: Vec<Address>,
 
+    genesis_ccc_supply: u64,
+
     importer: Importer,
 
     /// Timer for reseal_min_period/reseal_max_period on miner client
     reseal_timer: RwLock<Option<TimerApi>>,
+
+    /// The light client has only the headers, so the best header is regarded as the latest block.
+    light: bool,
+
+    pruning: journaldb::Algorithm,
 }
 
 impl Client {
@@ -95,7 +105,12 @@ impl Client {
         miner: Arc<Miner>,
         message_channel: IoChannel<ClientIoMessage>,
     ) -> Result<Arc<Client>, Error> {
-        let journal_db = journaldb::new(Arc::clone(&db), journaldb::Algorithm::Archive, ::db::COL_STATE);
+        if let Some(history) = config.history {
+            if history < MIN_HISTORY {
+                return Err(ClientError::ShortHistory(history).into())
+            }
+        }
+        let journal_db = journaldb::new(Arc::clone(&db), config.pruning, ::db::COL_STATE);
         let mut state_db = StateDB::new(journal_db);
         if !scheme.check_genesis_root(state_db.as_hashdb()) {
             return Err(SchemeError::InvalidState.into())
@@ -109,7 +124,14 @@ impl Client {
         }
 
         let gb = scheme.genesis_block();
-        let chain = BlockChain::new(&gb, db.clone());
+        let chain_config = BlockChainConfig {
+            index_addresses: config.index_addresses,
+            history: config.history,
+        };
+        let chain = BlockChain::new(&gb, db.clone(), &chain_config);
+        if !chain.init_address_index() {
+            return Err(ClientError::IncompleteAddressIndex.into())
+        }
         scheme.check_genesis_common_params(&chain)?;
 
         let engine = scheme.engine.clone();
@@ -126,8 +148,11 @@ impl Client {
             notify: RwLock::new(Vec::new()),
             queue_transactions: AtomicUsize::new(0),
             genesis_accounts,
+            genesis_ccc_supply: scheme.genesis_ccc_supply(),
             importer,
             reseal_timer: RwLock::new(None),
+            light: config.light,
+            pruning: config.pruning,
         });
 
         // ensure buffered changes are flushed.
@@ -152,7 +177,9 @@ impl Client {
     pub fn notify<F>(&self, f: F)
     where
         F: Fn(&ChainNotify), {
-        for np in self.notify.read().iter() {
+        // The targets are copied so that a target can add another one while it is notified.
+        let targets = self.notify.read().clone();
+        for np in targets.iter() {
             if let Some(n) = np.upgrade() {
                 f(&*n);
             }
@@ -169,11 +196,12 @@ impl Client {
         self.importer.import_verified_blocks(self)
     }
 
-    fn block_hash(chain: &BlockChain, id: &BlockId) -> Option<H256> {
+    fn resolve_block_hash(&self, chain: &BlockChain, id: &BlockId) -> Option<H256> {
         match id {
             BlockId::Hash(hash) => Some(*hash),
             BlockId::Number(number) => chain.block_hash(*number),
             BlockId::Earliest => chain.block_hash(0),
+            BlockId::Latest if self.light => Some(chain.best_header().hash()),
             BlockId::Latest => Some(chain.best_block_hash()),
         }
     }
@@ -181,10 +209,12 @@ impl Client {
     fn parcel_address(&self, id: &TransactionId) -> Option<ParcelAddress> {
         match id {
             TransactionId::Hash(hash) => self.block_chain().parcel_address(hash),
-            TransactionId::Location(id, index) => Self::block_hash(&self.block_chain(), id).map(|hash| ParcelAddress {
-                block_hash: hash,
-                index: *index,
-            }),
+            TransactionId::Location(id, index) => {
+                self.resolve_block_hash(&self.block_chain(), id).map(|hash| ParcelAddress {
+                    block_hash: hash,
+                    index: *index,
+                })
+            }
         }
     }
 
@@ -223,6 +253,7 @@ impl Client {
             BlockId::Number(number) => Some(*number),
             BlockId::Hash(hash) => self.block_chain().block_number(hash),
             BlockId::Earliest => Some(0),
+            BlockId::Latest if self.light => Some(self.block_chain().best_header().number()),
             BlockId::Latest => Some(self.block_chain().best_block_detail().number),
         }
     }
@@ -301,6 +332,10 @@ impl DatabaseClient for Client {
     fn database(&self) -> Arc<KeyValueDB> {
         Arc::clone(&self.db())
     }
+
+    fn pruning(&self) -> journaldb::Algorithm {
+        self.pruning
+    }
 }
 
 impl AssetClient for Client {
@@ -351,7 +386,15 @@ impl AssetClient for Client {
             Some(_) => {}
         }
 
-        let parcel = self.transaction(&transaction_hash).expect("There is a successful transaction");
+        let chain = self.block_chain();
+        // The body of the pruned block doesn't exist.
+        if chain.is_pruned(&parcel_address.block_hash) {
+            return Ok(None)
+        }
+        let parcel = match chain.parcel(&parcel_address) {
+            Some(parcel) => parcel,
+            None => return Ok(None),
+        };
         let transaction = if let Some(tx) = Option::<ShardTransaction>::from(parcel.action.clone()) {
             tx
         } else {
@@ -425,20 +468,40 @@ impl StateInfo for Client {
 
 impl ChainInfo for Client {
     fn chain_info(&self) -> BlockChainInfo {
-        let mut chain_info = self.block_chain().chain_info();
+        let chain = self.block_chain();
+        let mut chain_info = chain.chain_info();
+        if self.light {
+            let best_header = chain.best_header();
+            chain_info.best_score =
+                chain.block_details(&best_header.hash()).expect("Best header always exists").total_score;
+            chain_info.best_block_hash = best_header.hash();
+            chain_info.best_block_number = best_header.number();
+            chain_info.best_block_timestamp = best_header.timestamp();
+        }
         chain_info.pending_total_score = chain_info.best_score + self.importer.block_queue.total_score();
         chain_info
     }
 
     fn genesis_accounts(&self) -> Vec<PlatformAddress> {
-        let network_id = self.common_params().network_id;
+        let network_id = self.network_id();
         self.genesis_accounts.iter().map(|addr| PlatformAddress::new_v1(network_id, *addr)).collect()
     }
 }
 
 impl EngineInfo for Client {
-    fn common_params(&self) -> &CommonParams {
-        self.engine().params()
+    fn common_params(&self, block_id: BlockId) -> Option<CommonParams> {
+        let state = self.state_at(block_id)?;
+        match governance::active_params(self.engine().machine().governance_handler_id(), &state) {
+            Ok(params) => Some(params.unwrap_or_else(|| self.engine().params().clone())),
+            Err(err) => {
+                cwarn!(CLIENT, "Cannot read the common params at {:?}: {:?}", block_id, err);
+                None
+            }
+        }
+    }
+
+    fn network_id(&self) -> NetworkId {
+        self.engine().params().network_id
     }
 
     fn block_reward(&self, block_number: u64) -> u64 {
@@ -447,18 +510,40 @@ impl EngineInfo for Client {
 
     fn mining_reward(&self, block_number: u64) -> Option<u64> {
         let block = self.block(&block_number.into())?;
-        let block_fee = self.engine().block_fee(Box::new(block.transactions().into_iter()));
-        Some(self.engine().block_reward(block_number) + block_fee)
+        let block_fee = self.engine().block_fee(block_number, Box::new(block.transactions().into_iter()));
+        let burned_fee = self.engine().burned_fee(block_number, block_fee);
+        Some(self.engine().block_reward(block_number) + block_fee - burned_fee)
     }
 
     fn recommended_confirmation(&self) -> u32 {
         self.engine().recommended_confirmation()
     }
+
+    fn commit_certificate(&self, block_number: BlockNumber) -> Option<CommitCertificate> {
+        self.engine().commit_certificate(block_number)
+    }
+
+    fn validators(&self, block_id: BlockId) -> Option<Vec<Public>> {
+        let header = self.block_header(&block_id)?;
+        self.engine().validators(&header.decode())
+    }
+
+    fn consensus_state(&self) -> Option<ConsensusState> {
+        self.engine().consensus_state()
+    }
+
+    fn add_consensus_listener(&self, listener: ConsensusListener) {
+        self.engine().add_consensus_listener(listener)
+    }
 }
 
 impl EngineClient for Client {
     /// Make a new block and seal it.
     fn update_sealing(&self, allow_empty_block: bool) {
+        // The light client doesn't have the state to create blocks.
+        if self.light {
+            return
+        }
         self.importer.miner.update_sealing(self, allow_empty_block)
     }
 
@@ -470,20 +555,35 @@ impl EngineClient for Client {
     }
 
     /// Convert PoW difficulty to target.
-    fn score_to_target(&self, score: &U256) -> U256 {
-        self.engine.score_to_target(score)
+    fn score_to_target(&self, score: &U256, block_number: BlockNumber) -> U256 {
+        self.engine.score_to_target(score, block_number)
     }
 
     fn get_kvdb(&self) -> Arc<KeyValueDB> {
         self.db.clone()
     }
+
+    fn queue_own_transaction(&self, transaction: SignedTransaction) {
+        let hash = transaction.hash();
+        if let Err(err) = self.importer.miner.import_own_transaction(self, transaction) {
+            cwarn!(CLIENT, "Failed to queue the transaction {} made by the engine: {:?}", hash, err);
+        }
+    }
+
+    fn next_seq(&self, public: &Public) -> u64 {
+        self.importer.miner.next_seq(self, public)
+    }
+
+    fn epoch_transition_for(&self, parent_hash: H256) -> Option<EpochTransition> {
+        self.block_chain().epoch_transition_for(parent_hash)
+    }
 }
 
 impl BlockInfo for Client {
     fn block_header(&self, id: &BlockId) -> Option<::encoded::Header> {
         let chain = self.block_chain();
 
-        Self::block_hash(&chain, id).and_then(|hash| chain.block_header_data(&hash))
+        self.resolve_block_hash(&chain, id).and_then(|hash| chain.block_header_data(&hash))
     }
 
     fn best_block_header(&self) -> encoded::Header {
@@ -497,7 +597,7 @@ impl BlockInfo for Client {
     fn block(&self, id: &BlockId) -> Option<encoded::Block> {
         let chain = self.block_chain();
 
-        Self::block_hash(&chain, id).and_then(|hash| chain.block(&hash))
+        self.resolve_block_hash(&chain, id).and_then(|hash| chain.block(&hash))
     }
 }
 
@@ -513,8 +613,8 @@ impl TransactionInfo for Client {
             .and_then(|addr| {
                 addr.into_iter()
                     .find(|addr| {
-                        let invoice = self.parcel_invoice(&TransactionId::from(*addr)).expect("Parcel must exist");
-                        invoice == Invoice::Success
+                        // The invoices of the pruned blocks don't exist.
+                        self.parcel_invoice(&TransactionId::from(*addr)) == Some(Invoice::Success)
                     })
                     .map(|hash| hash.block_hash)
             })
@@ -573,7 +673,7 @@ impl BlockChainClient for Client {
     }
 
     fn ready_transactions(&self) -> Vec<SignedTransaction> {
-        self.importer.miner.ready_transactions()
+        self.importer.miner.ready_transactions(self)
     }
 
     fn block_number(&self, id: &BlockId) -> Option<BlockNumber> {
@@ -583,12 +683,12 @@ impl BlockChainClient for Client {
     fn block_body(&self, id: &BlockId) -> Option<encoded::Body> {
         let chain = self.block_chain();
 
-        Self::block_hash(&chain, id).and_then(|hash| chain.block_body(&hash))
+        self.resolve_block_hash(&chain, id).and_then(|hash| chain.block_body(&hash))
     }
 
     fn block_status(&self, id: &BlockId) -> BlockStatus {
         let chain = self.block_chain();
-        match Self::block_hash(&chain, id) {
+        match self.resolve_block_hash(&chain, id) {
             Some(ref hash) if chain.is_known(hash) => BlockStatus::InChain,
             Some(hash) => self.importer.block_queue.status(&hash),
             None => BlockStatus::Unknown,
@@ -598,12 +698,12 @@ impl BlockChainClient for Client {
     fn block_total_score(&self, id: &BlockId) -> Option<U256> {
         let chain = self.block_chain();
 
-        Self::block_hash(&chain, id).and_then(|hash| chain.block_details(&hash)).map(|d| d.total_score)
+        self.resolve_block_hash(&chain, id).and_then(|hash| chain.block_details(&hash)).map(|d| d.total_score)
     }
 
     fn block_hash(&self, id: &BlockId) -> Option<H256> {
         let chain = self.block_chain();
-        Self::block_hash(&chain, id)
+        self.resolve_block_hash(&chain, id)
     }
 
     fn parcel(&self, id: &TransactionId) -> Option<LocalizedTransaction> {
@@ -619,20 +719,41 @@ impl BlockChainClient for Client {
     fn transaction(&self, tracker: &H256) -> Option<LocalizedTransaction> {
         let chain = self.block_chain();
         let address = self.transaction_address(tracker)?;
-        address.into_iter().map(Into::into).map(|address| chain.parcel(&address)).next()?
+        // The transactions in the pruned blocks are omitted.
+        address
+            .into_iter()
+            .filter(|address| !chain.is_pruned(&address.block_hash))
+            .map(|address| chain.parcel(&address))
+            .next()?
     }
 
     fn transaction_invoices(&self, tracker: &H256) -> Vec<Invoice> {
         self.transaction_address(tracker)
             .map(|address| {
-                address
-                    .into_iter()
-                    .map(Into::into)
-                    .map(|address| self.parcel_invoice(&address).expect("The invoice must exist"))
-                    .collect()
+                address.into_iter().map(Into::into).filter_map(|address| self.parcel_invoice(&address)).collect()
             })
             .unwrap_or_default()
     }
+
+    fn transactions_by_address(
+        &self,
+        address: &Address,
+        skip: usize,
+        limit: usize,
+    ) -> Option<Vec<LocalizedTransaction>> {
+        let chain = self.block_chain();
+        let addresses = chain.address_transactions(address, skip, limit)?;
+        Some(addresses.iter().filter_map(|address| chain.parcel(address)).collect())
+    }
+
+    fn pruned_until(&self) -> BlockNumber {
+        self.block_chain().pruned_until()
+    }
+
+    fn is_pruned(&self, id: &BlockId) -> bool {
+        let chain = self.block_chain();
+        self.resolve_block_hash(&chain, id).map_or(false, |hash| chain.is_pruned(&hash))
+    }
 }
 
 impl AccountData for Client {}
@@ -676,6 +797,25 @@ impl Shard for Client {
     }
 }
 
+impl Supply for Client {
+    fn ccc_supply(&self, state: StateOrBlock) -> Option<u64> {
+        // The CCC issued or burned before the tracking is unknown.
+        if !self.engine.machine().params().is_supply_tracked_from_genesis() {
+            return None
+        }
+        let state = self.state_info(state)?;
+        state.ccc_supply(self.genesis_ccc_supply).ok()?
+    }
+
+    fn wrapped_ccc_supply(&self, state: StateOrBlock) -> Option<u64> {
+        if !self.engine.machine().params().is_supply_tracked_from_genesis() {
+            return None
+        }
+        let state = self.state_info(state)?;
+        state.wrapped_ccc_supply().ok()?
+    }
+}
+
 impl ReopenBlock for Client {
     fn reopen_block(&self, block: ClosedBlock) -> OpenBlock {
         let engine = &*self.engine;
@@ -754,8 +894,104 @@ impl ChainTimeInfo for Client {
     }
 }
 
+/// Finds the handlers which the transactions of the block on top of the best block can use.
 impl FindActionHandler for Client {
     fn find_action_handler_for(&self, id: u64) -> Option<&Arc<ActionHandler>> {
-        self.engine.action_handlers().iter().find(|handler| handler.handler_id() == id)
+        self.action_handlers().iter().find(|handler| handler.handler_id() == id)
+    }
+
+    fn action_handlers(&self) -> &[Arc<ActionHandler>] {
+        self.engine.action_handlers_at(self.chain_info().best_block_number + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::db::NUM_COLUMNS;
+    use crate::miner::MinerOptions;
+
+    fn try_new_client(scheme: &Scheme, config: &ClientConfig) -> Result<Arc<Client>, Error> {
+        let miner = Miner::new(
+            MinerOptions {
+                force_sealing: true,
+                ..Default::default()
+            },
+            scheme,
+            None,
+        );
+        let db = Arc::new(::kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0)));
+        Client::try_new(config, scheme, db, miner, IoChannel::disconnected())
+    }
+
+    fn new_client(scheme: &Scheme, light: bool) -> Arc<Client> {
+        let config = ClientConfig {
+            light,
+            ..Default::default()
+        };
+        try_new_client(scheme, &config).unwrap()
+    }
+
+    #[test]
+    fn latest_block_of_the_light_client_is_the_best_header() {
+        let scheme = Scheme::new_test_solo();
+        let full_client = new_client(&scheme, false);
+        full_client.update_sealing(true);
+        let header = full_client.block_header(&BlockId::Number(1)).expect("Solo seals a block");
+
+        let light_client = new_client(&scheme, true);
+        light_client.import_header(header.clone().into_inner()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            light_client.importer.import_verified_headers(&light_client);
+            if light_client.block_chain().best_header().number() == 1 {
+                break
+            }
+            assert!(Instant::now() < deadline, "The header is not imported");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // The light client doesn't have the body of the block.
+        assert_eq!(0, light_client.block_chain().best_block_detail().number);
+        assert_eq!(Some(1), light_client.block_number(&BlockId::Latest));
+        assert_eq!(Some(header.hash()), light_client.block_hash(&BlockId::Latest));
+        assert_eq!(Some(header.hash()), light_client.block_header(&BlockId::Latest).map(|header| header.hash()));
+
+        let chain_info = light_client.chain_info();
+        assert_eq!(header.hash(), chain_info.best_block_hash);
+        assert_eq!(1, chain_info.best_block_number);
+        assert_eq!(full_client.chain_info().best_score, chain_info.best_score);
+        assert_eq!(chain_info.best_score, chain_info.pending_total_score);
+    }
+
+    #[test]
+    fn light_client_does_not_seal() {
+        let scheme = Scheme::new_test_solo();
+        let light_client = new_client(&scheme, true);
+        light_client.update_sealing(true);
+        assert!(light_client.block_header(&BlockId::Number(1)).is_none());
+    }
+
+    #[test]
+    fn history_shorter_than_the_minimum_is_rejected() {
+        let scheme = Scheme::new_test();
+        let config = ClientConfig {
+            history: Some(MIN_HISTORY - 1),
+            ..Default::default()
+        };
+        match try_new_client(&scheme, &config) {
+            Err(Error::Client(ClientError::ShortHistory(history))) => assert_eq!(MIN_HISTORY - 1, history),
+            Err(err) => panic!("Unexpected error: {}", err),
+            Ok(_) => panic!("The short history is accepted"),
+        }
+
+        let config = ClientConfig {
+            history: Some(MIN_HISTORY),
+            ..Default::default()
+        };
+        assert!(try_new_client(&scheme, &config).is_ok());
     }
 }
@@ -17,6 +17,7 @@
 use std::path::Path;
 use std::str::FromStr;
 
+use journaldb;
 use kvdb_rocksdb::CompactionProfile;
 
 use crate::verification::{QueueConfig, VerifierType};
@@ -75,8 +76,18 @@ pub struct ClientConfig {
     pub db_wal: bool,
     /// State db cache-size.
     pub state_cache_size: usize,
+    /// The algorithm of the journal DB which keeps the state.
+    pub pruning: journaldb::Algorithm,
     /// Type of block verifier used by client.
     pub verifier_type: VerifierType,
+    /// Should parcels be indexed by the addresses they involve?
+    pub index_addresses: bool,
+    /// Should shard transactions of independent shards be applied in parallel?
+    pub parallel_shard_transactions: bool,
+    /// Does the client sync only the headers and fetch the state from the peers?
+    pub light: bool,
+    /// Keep the bodies and the invoices of only the last `history` blocks. `None` keeps all of them.
+    pub history: Option<u64>,
 }
 
 impl Default for ClientConfig {
@@ -89,7 +100,12 @@ impl Default for ClientConfig {
             db_compaction: Default::default(),
             db_wal: true,
             state_cache_size: DEFAULT_STATE_CACHE_SIZE as usize * mb,
+            pruning: journaldb::Algorithm::Archive,
             verifier_type: Default::default(),
+            index_addresses: false,
+            parallel_shard_transactions: false,
+            light: false,
+            history: None,
         }
     }
 }
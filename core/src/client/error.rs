@@ -19,6 +19,8 @@ use std::fmt::{Display, Error as FmtError, Formatter};
 use kvdb;
 use util_error::UtilError;
 
+use crate::blockchain::{Inconsistency, MIN_HISTORY};
+
 /// Client configuration errors.
 #[derive(Debug)]
 pub enum Error {
@@ -26,6 +28,14 @@ pub enum Error {
     Database(kvdb::Error),
     /// Util error
     Util(UtilError),
+    /// The database has an inconsistency which cannot be repaired
+    Unrepairable(Inconsistency),
+    /// The address index is enabled on a database whose blocks are not indexed
+    IncompleteAddressIndex,
+    /// The database doesn't have the chain to check or to repair
+    UninitializedDatabase,
+    /// The number of the blocks whose bodies are kept is less than the minimum
+    ShortHistory(u64),
 }
 
 impl From<UtilError> for Error {
@@ -39,6 +49,14 @@ impl Display for Error {
         match self {
             Error::Util(err) => write!(f, "{}", err),
             Error::Database(s) => write!(f, "Database error: {}", s),
+            Error::Unrepairable(inconsistency) => write!(f, "Cannot repair the database: {}", inconsistency),
+            Error::IncompleteAddressIndex => {
+                write!(f, "The blocks are not indexed by the addresses. Sync from the genesis block to index them")
+            }
+            Error::UninitializedDatabase => write!(f, "The database doesn't have the chain of the genesis block"),
+            Error::ShortHistory(history) => {
+                write!(f, "The history must be at least {} blocks, but it is {}", MIN_HISTORY, history)
+            }
         }
     }
 }
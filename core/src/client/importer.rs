@@ -59,6 +59,9 @@ pub struct Importer {
 
     /// CodeChain engine to be used during import
     pub engine: Arc<CodeChainEngine>,
+
+    /// Apply the shard transactions of independent shards in parallel
+    parallel_shard_transactions: bool,
 }
 
 impl Importer {
@@ -85,6 +88,7 @@ impl Importer {
             header_queue,
             miner,
             engine,
+            parallel_shard_transactions: config.parallel_shard_transactions,
         })
     }
 
@@ -352,7 +356,16 @@ impl Importer {
         let db = client.state_db().read().clone(&parent.state_root());
 
         let is_epoch_begin = chain.epoch_transition(parent.number(), *header.parent_hash()).is_some();
-        let enact_result = enact(&block.header, &block.transactions, engine, client, db, &parent, is_epoch_begin);
+        let enact_result = enact(
+            &block.header,
+            &block.transactions,
+            engine,
+            client,
+            db,
+            &parent,
+            is_epoch_begin,
+            self.parallel_shard_transactions,
+        );
         let locked_block = enact_result.map_err(|e| {
             cwarn!(CLIENT, "Block import failed for #{} ({})\nError: {:?}", header.number(), header.hash(), e);
         })?;
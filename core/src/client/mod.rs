@@ -31,7 +31,7 @@ pub use self::test_client::TestBlockChainClient;
 
 use std::sync::Arc;
 
-use ckey::{Address, PlatformAddress, Public};
+use ckey::{Address, NetworkId, PlatformAddress, Public};
 use cmerkle::Result as TrieResult;
 use cnetwork::NodeId;
 use cstate::{AssetScheme, AssetSchemeAddress, FindActionHandler, OwnedAsset, Text, TopLevelState, TopStateView};
@@ -40,11 +40,14 @@ use ctypes::invoice::Invoice;
 use ctypes::transaction::{AssetTransferInput, PartialHashing, ShardTransaction};
 use ctypes::{BlockNumber, ShardId};
 use cvm::ChainTimeInfo;
+use journaldb;
 use kvdb::KeyValueDB;
 use primitives::{Bytes, H256, U256};
 
 use crate::block::{ClosedBlock, OpenBlock, SealedBlock};
 use crate::blockchain_info::BlockChainInfo;
+use crate::consensus::epoch::Transition as EpochTransition;
+use crate::consensus::{CommitCertificate, ConsensusListener, ConsensusState};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as CoreError};
 use crate::scheme::CommonParams;
@@ -93,24 +96,45 @@ pub trait TransactionInfo {
 }
 
 pub trait EngineInfo: Send + Sync {
-    fn common_params(&self) -> &CommonParams;
+    /// Get the common params which the child of the given block follows.
+    /// They may differ from the genesis params if the governance changed them.
+    fn common_params(&self, block_id: BlockId) -> Option<CommonParams>;
+    /// The network id never changes.
+    fn network_id(&self) -> NetworkId;
     fn block_reward(&self, block_number: u64) -> u64;
     fn mining_reward(&self, block_number: u64) -> Option<u64>;
     fn recommended_confirmation(&self) -> u32;
+    fn commit_certificate(&self, block_number: BlockNumber) -> Option<CommitCertificate>;
+    /// Get the validators of the child of the given block.
+    /// Returns None if the engine doesn't have a validator list.
+    fn validators(&self, block_id: BlockId) -> Option<Vec<Public>>;
+    /// Get the round which the consensus engine is in.
+    /// Returns None if the engine doesn't have rounds.
+    fn consensus_state(&self) -> Option<ConsensusState>;
+    fn add_consensus_listener(&self, listener: ConsensusListener);
 }
 
 /// Client facilities used by internally sealing Engines.
-pub trait EngineClient: Sync + Send + ChainInfo + ImportBlock + BlockInfo {
+pub trait EngineClient: Sync + Send + ChainInfo + ImportBlock + BlockInfo + StateInfo {
     /// Make a new block and seal it.
     fn update_sealing(&self, allow_empty_block: bool);
 
     /// Submit a seal for a block in the mining queue.
     fn submit_seal(&self, block_hash: H256, seal: Vec<Bytes>);
 
-    /// Convert PoW difficulty to target.
-    fn score_to_target(&self, score: &U256) -> U256;
+    /// Convert PoW difficulty of the block of the given number to target.
+    fn score_to_target(&self, score: &U256, block_number: BlockNumber) -> U256;
 
     fn get_kvdb(&self) -> Arc<KeyValueDB>;
+
+    /// Queue a transaction made by the engine to the mem pool.
+    fn queue_own_transaction(&self, transaction: SignedTransaction);
+
+    /// Get the seq of the next transaction made by the engine, counting the ones queued to the mem pool.
+    fn next_seq(&self, public: &Public) -> u64;
+
+    /// Get the last epoch transition on the branch of the given block, up to the block.
+    fn epoch_transition_for(&self, parent_hash: H256) -> Option<EpochTransition>;
 }
 
 /// Provides `seq` and `latest_seq` methods
@@ -186,6 +210,15 @@ pub trait Shard {
     fn shard_root(&self, shard_id: ShardId, state: StateOrBlock) -> Option<H256>;
 }
 
+/// Provides the amount of CCC in circulation.
+pub trait Supply {
+    /// Get the amount of CCC in the accounts and in the shards as wrapped CCC.
+    fn ccc_supply(&self, state: StateOrBlock) -> Option<u64>;
+
+    /// Get the amount of CCC wrapped in the shards.
+    fn wrapped_ccc_supply(&self, state: StateOrBlock) -> Option<u64>;
+}
+
 /// Provides a timer API for reseal_min_period/reseal_max_period on miner client
 pub trait ResealTimer {
     /// Register timer API
@@ -209,7 +242,7 @@ pub trait ImportBlock {
 }
 
 /// Provides various blockchain information, like block header, chain state etc.
-pub trait BlockChain: ChainInfo + BlockInfo + ParcelInfo + TransactionInfo {}
+pub trait BlockChain: ChainInfo + BlockInfo + ParcelInfo + TransactionInfo + EngineInfo {}
 
 /// Blockchain database client. Owns and manages a blockchain and a block queue.
 pub trait BlockChainClient:
@@ -248,7 +281,25 @@ pub trait BlockChainClient:
     /// Get the transaction with given tracker.
     fn transaction(&self, tracker: &H256) -> Option<LocalizedTransaction>;
 
+    /// Get the invoices of the transaction with given tracker.
+    /// The invoices in the pruned blocks are omitted.
     fn transaction_invoices(&self, tracker: &H256) -> Vec<Invoice>;
+
+    /// Get the parcels which involve the given address, starting from the most recent one.
+    /// Returns None if the parcels are not indexed by the addresses.
+    /// The parcels in the pruned blocks are omitted.
+    fn transactions_by_address(
+        &self,
+        address: &Address,
+        skip: usize,
+        limit: usize,
+    ) -> Option<Vec<LocalizedTransaction>>;
+
+    /// Get the number of the last block whose body and invoices are pruned. It's 0 if nothing is pruned.
+    fn pruned_until(&self) -> BlockNumber;
+
+    /// Returns true if the body and the invoices of the block are pruned.
+    fn is_pruned(&self, id: &BlockId) -> bool;
 }
 
 /// Result of import block operation.
@@ -281,6 +332,9 @@ pub trait MiningBlockChainClient: BlockChainClient + BlockProducer + ImportSeale
 /// Provides methods to access database.
 pub trait DatabaseClient {
     fn database(&self) -> Arc<KeyValueDB>;
+
+    /// The algorithm of the journal DB over the state column.
+    fn pruning(&self) -> journaldb::Algorithm;
 }
 
 /// Provides methods to access asset
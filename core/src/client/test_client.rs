@@ -35,10 +35,10 @@ use std::mem;
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrder};
 use std::sync::Arc;
 
-use ckey::{public_to_address, Address, Generator, NetworkId, PlatformAddress, Random};
+use ckey::{public_to_address, Address, Generator, NetworkId, PlatformAddress, Public, Random};
 use cmerkle::skewed_merkle_root;
 use cnetwork::NodeId;
-use cstate::{FindActionHandler, StateDB};
+use cstate::{FindActionHandler, StateDB, TopLevelState};
 use ctimer::{TimeoutHandler, TimerApi, TimerToken};
 use ctypes::invoice::Invoice;
 use ctypes::transaction::{Action, Transaction};
@@ -47,7 +47,7 @@ use cvm::ChainTimeInfo;
 use journaldb;
 use kvdb::KeyValueDB;
 use kvdb_memorydb;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use primitives::{Bytes, H256, U256};
 use rlp::*;
 
@@ -55,16 +55,18 @@ use crate::block::{ClosedBlock, OpenBlock, SealedBlock};
 use crate::blockchain_info::BlockChainInfo;
 use crate::client::ImportResult;
 use crate::client::{
-    AccountData, Balance, BlockChain, BlockChainClient, BlockInfo, BlockProducer, BlockStatus, ChainInfo, ImportBlock,
-    ImportSealedBlock, MiningBlockChainClient, ParcelInfo, PrepareOpenBlock, RegularKeyOwner, ReopenBlock, ResealTimer,
-    Seq, StateOrBlock, TransactionInfo,
+    AccountData, Balance, BlockChain, BlockChainClient, BlockInfo, BlockProducer, BlockStatus, ChainInfo, EngineInfo,
+    ImportBlock, ImportSealedBlock, MiningBlockChainClient, ParcelInfo, PrepareOpenBlock, RegularKeyOwner, ReopenBlock,
+    ResealTimer, Seq, StateInfo, StateOrBlock, TransactionInfo,
 };
+use crate::consensus::epoch::Transition as EpochTransition;
+use crate::consensus::{CommitCertificate, ConsensusListener, ConsensusState};
 use crate::db::{COL_STATE, NUM_COLUMNS};
 use crate::encoded;
 use crate::error::BlockImportError;
 use crate::header::Header as BlockHeader;
 use crate::miner::{Miner, MinerService, TransactionImportResult};
-use crate::scheme::Scheme;
+use crate::scheme::{CommonParams, Scheme};
 use crate::transaction::{LocalizedTransaction, SignedTransaction};
 use crate::types::{BlockId, TransactionId, VerificationQueueInfo as QueueInfo};
 
@@ -100,6 +102,12 @@ pub struct TestBlockChainClient {
     pub latest_block_timestamp: RwLock<u64>,
     /// Pruning history size to report.
     pub history: RwLock<Option<u64>>,
+    /// States of the blocks.
+    pub states: Mutex<HashMap<H256, TopLevelState>>,
+    /// Epoch transitions by the hash of the block.
+    pub epoch_transitions: RwLock<HashMap<H256, EpochTransition>>,
+    /// Database which outlives the engines registered to the client.
+    pub kvdb: Arc<KeyValueDB>,
 }
 
 impl Default for TestBlockChainClient {
@@ -149,6 +157,9 @@ impl TestBlockChainClient {
             scheme,
             latest_block_timestamp: RwLock::new(10_000_000),
             history: RwLock::new(None),
+            states: Mutex::new(HashMap::new()),
+            epoch_transitions: RwLock::new(HashMap::new()),
+            kvdb: Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0))),
         };
 
         // insert genesis hash.
@@ -287,6 +298,21 @@ impl TestBlockChainClient {
     pub fn set_history(&self, h: Option<u64>) {
         *self.history.write() = h;
     }
+
+    /// Set the state of the block.
+    pub fn set_state(&self, hash: H256, state: TopLevelState) {
+        self.states.lock().insert(hash, state);
+    }
+
+    /// Insert an epoch transition at the block.
+    pub fn insert_epoch_transition(&self, hash: H256, proof: Bytes) {
+        let block_number = self.block_header(&BlockId::Hash(hash)).expect("The block is imported").number();
+        self.epoch_transitions.write().insert(hash, EpochTransition {
+            block_hash: hash,
+            block_number,
+            proof,
+        });
+    }
 }
 
 pub fn get_temp_state_db() -> StateDB {
@@ -408,6 +434,42 @@ impl TransactionInfo for TestBlockChainClient {
     }
 }
 
+impl EngineInfo for TestBlockChainClient {
+    fn common_params(&self, _block_id: BlockId) -> Option<CommonParams> {
+        Some(self.scheme.params().clone())
+    }
+
+    fn network_id(&self) -> NetworkId {
+        self.scheme.params().network_id
+    }
+
+    fn block_reward(&self, block_number: u64) -> u64 {
+        self.scheme.engine.block_reward(block_number)
+    }
+
+    fn mining_reward(&self, _block_number: u64) -> Option<u64> {
+        None
+    }
+
+    fn recommended_confirmation(&self) -> u32 {
+        self.scheme.engine.recommended_confirmation()
+    }
+
+    fn commit_certificate(&self, _block_number: BlockNumber) -> Option<CommitCertificate> {
+        None
+    }
+
+    fn validators(&self, _block_id: BlockId) -> Option<Vec<Public>> {
+        None
+    }
+
+    fn consensus_state(&self) -> Option<ConsensusState> {
+        None
+    }
+
+    fn add_consensus_listener(&self, _listener: ConsensusListener) {}
+}
+
 impl BlockChain for TestBlockChainClient {}
 
 impl ImportBlock for TestBlockChainClient {
@@ -476,7 +538,7 @@ impl BlockChainClient for TestBlockChainClient {
     }
 
     fn ready_transactions(&self) -> Vec<SignedTransaction> {
-        self.miner.ready_transactions()
+        self.miner.ready_transactions(self)
     }
 
     fn block_number(&self, _id: &BlockId) -> Option<BlockNumber> {
@@ -525,6 +587,18 @@ impl BlockChainClient for TestBlockChainClient {
     fn transaction_invoices(&self, _: &H256) -> Vec<Invoice> {
         unimplemented!();
     }
+
+    fn transactions_by_address(&self, _: &Address, _: usize, _: usize) -> Option<Vec<LocalizedTransaction>> {
+        None
+    }
+
+    fn pruned_until(&self) -> BlockNumber {
+        0
+    }
+
+    fn is_pruned(&self, _id: &BlockId) -> bool {
+        false
+    }
 }
 
 impl TimeoutHandler for TestBlockChainClient {
@@ -559,6 +633,13 @@ impl ChainTimeInfo for TestBlockChainClient {
 
 impl FindActionHandler for TestBlockChainClient {}
 
+impl StateInfo for TestBlockChainClient {
+    fn state_at(&self, id: BlockId) -> Option<TopLevelState> {
+        let hash = self.block_hash(&id)?;
+        self.states.lock().get(&hash).cloned()
+    }
+}
+
 impl super::EngineClient for TestBlockChainClient {
     fn update_sealing(&self, allow_empty_block: bool) {
         self.miner.update_sealing(self, allow_empty_block)
@@ -570,12 +651,36 @@ impl super::EngineClient for TestBlockChainClient {
         }
     }
 
-    fn score_to_target(&self, _score: &U256) -> U256 {
+    fn score_to_target(&self, _score: &U256, _block_number: BlockNumber) -> U256 {
         U256::zero()
     }
 
     fn get_kvdb(&self) -> Arc<KeyValueDB> {
-        let db = kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0));
-        Arc::new(db)
+        Arc::clone(&self.kvdb)
+    }
+
+    fn queue_own_transaction(&self, transaction: SignedTransaction) {
+        let hash = transaction.hash();
+        if let Err(err) = self.miner.import_own_transaction(self, transaction) {
+            cwarn!(CLIENT, "Failed to queue the transaction {} made by the engine: {:?}", hash, err);
+        }
+    }
+
+    fn next_seq(&self, public: &Public) -> u64 {
+        self.miner.next_seq(self, public)
+    }
+
+    fn epoch_transition_for(&self, parent_hash: H256) -> Option<EpochTransition> {
+        let mut hash = parent_hash;
+        loop {
+            if let Some(transition) = self.epoch_transitions.read().get(&hash) {
+                return Some(transition.clone())
+            }
+            let header = self.block_header(&BlockId::Hash(hash))?;
+            if header.number() == 0 {
+                return None
+            }
+            hash = header.parent_hash();
+        }
     }
 }
@@ -15,8 +15,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 // A state machine.
 
+use std::cmp::max;
+use std::sync::Arc;
+
 use ckey::Address;
-use cstate::{StateError, TopState, TopStateView};
+use cstate::{ActionHandler, StateError, TopState, TopStateView};
 use ctypes::machine::{Machine, WithBalances};
 use ctypes::transaction::{
     Action, AssetTransferInput, Error as TransactionError, OrderOnTransfer, ParcelError, Timelock,
@@ -31,15 +34,30 @@ use crate::transaction::{SignedTransaction, UnverifiedTransaction};
 
 pub struct CodeChainMachine {
     params: CommonParams,
+    action_handlers: Vec<Arc<ActionHandler>>,
+    /// The id of the action handler which changes the params.
+    governance_handler_id: Option<u64>,
 }
 
 impl CodeChainMachine {
-    pub fn new(params: CommonParams) -> Self {
+    pub fn new(params: CommonParams, action_handlers: Vec<Arc<ActionHandler>>) -> Self {
         CodeChainMachine {
             params,
+            action_handlers,
+            governance_handler_id: None,
         }
     }
 
+    /// Lets the action handler of the id change the params.
+    pub fn with_governance(mut self, handler_id: u64) -> Self {
+        self.governance_handler_id = Some(handler_id);
+        self
+    }
+
+    pub fn governance_handler_id(&self) -> Option<u64> {
+        self.governance_handler_id
+    }
+
     /// Get the general parameters of the chain.
     pub fn params(&self) -> &CommonParams {
         &self.params
@@ -58,9 +76,15 @@ impl CodeChainMachine {
         self.params().max_text_content_size
     }
 
-    /// Does basic verification of the transaction.
-    pub fn verify_transaction_basic(&self, p: &UnverifiedTransaction, _header: &Header) -> Result<(), Error> {
-        let min_cost = self.min_cost(&p.action);
+    /// Does basic verification of the transaction with the params active at the block.
+    pub fn verify_transaction_basic(
+        &self,
+        p: &UnverifiedTransaction,
+        params: &CommonParams,
+        _header: &Header,
+        action_handlers: &[Arc<ActionHandler>],
+    ) -> Result<(), Error> {
+        let min_cost = Self::min_cost(params, &p.action, action_handlers);
         if p.fee < min_cost {
             return Err(StateError::Parcel(ParcelError::InsufficientFee {
                 minimal: min_cost,
@@ -68,11 +92,29 @@ impl CodeChainMachine {
             })
             .into())
         }
-        p.verify_basic(self.params()).map_err(StateError::from)?;
+        p.verify_basic(params).map_err(StateError::from)?;
+        if let Action::Custom {
+            handler_id,
+            bytes,
+        } = &p.action
+        {
+            Self::verify_custom_action(*handler_id, bytes, action_handlers).map_err(StateError::from)?;
+        }
 
         Ok(())
     }
 
+    /// Lets the handler of the custom action check its payload.
+    pub fn verify_custom_action(
+        handler_id: u64,
+        bytes: &[u8],
+        action_handlers: &[Arc<ActionHandler>],
+    ) -> Result<(), ParcelError> {
+        let handler = find_action_handler(action_handlers, handler_id)
+            .ok_or_else(|| ParcelError::InvalidCustomAction(format!("Unknown handler id {}", handler_id)))?;
+        handler.verify(bytes).map_err(|err| ParcelError::InvalidCustomAction(format!("{:?}", err)))
+    }
+
     /// Verify a particular transaction is valid, regardless of order.
     pub fn verify_transaction_unordered(
         &self,
@@ -184,55 +226,76 @@ impl CodeChainMachine {
         Ok(())
     }
 
-    fn min_cost(&self, action: &Action) -> u64 {
+    fn min_cost(params: &CommonParams, action: &Action, action_handlers: &[Arc<ActionHandler>]) -> u64 {
         match action {
             Action::MintAsset {
                 ..
-            } => self.params.min_asset_mint_cost,
+            } => params.min_asset_mint_cost,
             Action::TransferAsset {
                 ..
-            } => self.params.min_asset_transfer_cost,
+            } => params.min_asset_transfer_cost,
             Action::ChangeAssetScheme {
                 ..
-            } => self.params.min_asset_scheme_change_cost,
+            } => params.min_asset_scheme_change_cost,
             Action::ComposeAsset {
                 ..
-            } => self.params.min_asset_compose_cost,
+            } => params.min_asset_compose_cost,
             Action::DecomposeAsset {
                 ..
-            } => self.params.min_asset_decompose_cost,
+            } => params.min_asset_decompose_cost,
             Action::UnwrapCCC {
                 ..
-            } => self.params.min_asset_unwrap_ccc_cost,
+            } => params.min_asset_unwrap_ccc_cost,
             Action::Pay {
                 ..
-            } => self.params.min_pay_transaction_cost,
+            } => params.min_pay_transaction_cost,
             Action::SetRegularKey {
                 ..
-            } => self.params.min_set_regular_key_tranasction_cost,
-            Action::CreateShard => self.params.min_create_shard_transaction_cost,
+            } => params.min_set_regular_key_tranasction_cost,
+            Action::CreateShard => params.min_create_shard_transaction_cost,
             Action::SetShardOwners {
                 ..
-            } => self.params.min_set_shard_owners_transaction_cost,
+            } => params.min_set_shard_owners_transaction_cost,
             Action::SetShardUsers {
                 ..
-            } => self.params.min_set_shard_users_transaction_cost,
+            } => params.min_set_shard_users_transaction_cost,
             Action::WrapCCC {
                 ..
-            } => self.params.min_wrap_ccc_transaction_cost,
+            } => params.min_wrap_ccc_transaction_cost,
             Action::Custom {
-                ..
-            } => self.params.min_custom_transaction_cost,
+                handler_id,
+                bytes,
+            } => {
+                let handler_min_cost =
+                    find_action_handler(action_handlers, *handler_id).map_or(0, |handler| handler.min_cost(bytes));
+                max(params.min_custom_transaction_cost, handler_min_cost)
+            }
             Action::Store {
                 ..
-            } => self.params.min_store_transaction_cost,
+            } => params.min_store_transaction_cost,
             Action::Remove {
                 ..
-            } => self.params.min_remove_transaction_cost,
+            } => params.min_remove_transaction_cost,
         }
     }
 }
 
+fn find_action_handler(action_handlers: &[Arc<ActionHandler>], handler_id: u64) -> Option<&Arc<ActionHandler>> {
+    action_handlers.iter().find(|handler| handler.handler_id() == handler_id)
+}
+
+/// A state machine which runs the end-of-block logic of the action handlers.
+pub trait WithActionHandlers: Machine {
+    /// The action handlers enabled in the scheme.
+    fn action_handlers(&self) -> &[Arc<ActionHandler>];
+
+    fn close_action_handlers(
+        &self,
+        live: &mut Self::LiveBlock,
+        action_handlers: &[Arc<ActionHandler>],
+    ) -> Result<(), Self::Error>;
+}
+
 impl Machine for CodeChainMachine {
     type Header = Header;
     type LiveBlock = ExecutedBlock;
@@ -247,7 +310,113 @@ impl WithBalances for CodeChainMachine {
     }
 
     fn add_balance(&self, live: &mut ExecutedBlock, address: &Address, amount: u64) -> Result<(), Self::Error> {
-        live.state_mut().add_balance(address, amount).map_err(StateError::from)?;
+        live.state_mut().issue_ccc(address, amount).map_err(StateError::from)?;
+        Ok(())
+    }
+
+    fn burn_fee(&self, live: &mut ExecutedBlock, amount: u64) -> Result<(), Self::Error> {
+        live.state_mut().burn_fee(amount).map_err(StateError::from)?;
         Ok(())
     }
 }
+
+impl WithActionHandlers for CodeChainMachine {
+    fn action_handlers(&self) -> &[Arc<ActionHandler>] {
+        &self.action_handlers
+    }
+
+    fn close_action_handlers(
+        &self,
+        live: &mut ExecutedBlock,
+        action_handlers: &[Arc<ActionHandler>],
+    ) -> Result<(), Self::Error> {
+        let header = live.header().clone();
+        for handler in action_handlers {
+            handler.on_close_block(live.state_mut(), &header)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{Generator, Random};
+    use ctypes::transaction::Transaction;
+
+    use super::*;
+    use crate::tests::helpers::TestActionHandler;
+
+    const HANDLER_ID: u64 = 42;
+    const MIN_CUSTOM_COST: u64 = 20;
+
+    fn verify_custom_transaction(handler_id: u64, bytes: Vec<u8>, fee: u64) -> Result<(), ParcelError> {
+        let params = CommonParams {
+            network_id: "tc".into(),
+            min_custom_transaction_cost: MIN_CUSTOM_COST,
+            max_body_size: 4 * 1024 * 1024,
+            ..Default::default()
+        };
+        let action_handlers: Vec<Arc<ActionHandler>> = vec![Arc::new(TestActionHandler::new(HANDLER_ID))];
+        let machine = CodeChainMachine::new(params.clone(), action_handlers.clone());
+
+        let tx = Transaction {
+            seq: 0,
+            fee,
+            network_id: "tc".into(),
+            action: Action::Custom {
+                handler_id,
+                bytes,
+            },
+        };
+        let tx: UnverifiedTransaction =
+            SignedTransaction::new_with_sign(tx, Random.generate().unwrap().private()).into();
+        match machine.verify_transaction_basic(&tx, &params, &Header::default(), &action_handlers) {
+            Ok(()) => Ok(()),
+            Err(Error::State(StateError::Parcel(err))) => Err(err),
+            Err(err) => panic!("Unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn handler_verifies_the_payload() {
+        assert_eq!(Ok(()), verify_custom_transaction(HANDLER_ID, vec![1], 100));
+        match verify_custom_transaction(HANDLER_ID, vec![], 100) {
+            Err(ParcelError::InvalidCustomAction(_)) => {}
+            result => panic!("The empty payload must be rejected: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn unknown_handler_id_is_rejected() {
+        match verify_custom_transaction(HANDLER_ID + 1, vec![1], 100) {
+            Err(ParcelError::InvalidCustomAction(_)) => {}
+            result => panic!("The unknown handler id must be rejected: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn min_cost_of_the_handler_above_the_minimum_of_the_params() {
+        let bytes = vec![1, 2, 3, 4, 5];
+        assert!(50 > MIN_CUSTOM_COST);
+        assert_eq!(
+            Err(ParcelError::InsufficientFee {
+                minimal: 50,
+                got: 49,
+            }),
+            verify_custom_transaction(HANDLER_ID, bytes.clone(), 49)
+        );
+        assert_eq!(Ok(()), verify_custom_transaction(HANDLER_ID, bytes, 50));
+    }
+
+    #[test]
+    fn min_cost_of_the_handler_below_the_minimum_of_the_params() {
+        assert_eq!(
+            Err(ParcelError::InsufficientFee {
+                minimal: MIN_CUSTOM_COST,
+                got: MIN_CUSTOM_COST - 1,
+            }),
+            verify_custom_transaction(HANDLER_ID, vec![1], MIN_CUSTOM_COST - 1)
+        );
+        assert_eq!(Ok(()), verify_custom_transaction(HANDLER_ID, vec![1], MIN_CUSTOM_COST));
+    }
+}
@@ -17,9 +17,15 @@
 use cjson;
 use primitives::U256;
 
+use super::super::difficulty::DifficultyAlgorithm;
+use super::super::reward::{FeeBurn, RewardSchedule};
+
 pub struct BlakePoWParams {
-    pub block_reward: u64,
+    pub block_reward: RewardSchedule,
+    /// The portion of the block fee which is burned.
+    pub fee_burn: FeeBurn,
     pub min_score: U256,
+    pub difficulty: DifficultyAlgorithm,
     pub block_interval: u64,
     pub recommmended_confirmation: u32,
 }
@@ -27,9 +33,11 @@ pub struct BlakePoWParams {
 impl From<cjson::scheme::BlakePoWParams> for BlakePoWParams {
     fn from(p: cjson::scheme::BlakePoWParams) -> Self {
         BlakePoWParams {
-            block_reward: p.block_reward.map_or(0, Into::into),
+            block_reward: RewardSchedule::new(p.block_reward, p.reward_schedule),
+            fee_burn: FeeBurn::new(p.fee_burn_ratio),
             block_interval: p.block_interval.map_or(120, Into::into),
             min_score: p.min_score.map_or(U256::from(0x0002_0000), Into::into),
+            difficulty: p.difficulty_algorithm.map_or_else(Default::default, Into::into),
             recommmended_confirmation: p.recommended_confirmation.map_or(15, Into::into),
         }
     }
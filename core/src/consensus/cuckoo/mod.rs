@@ -16,20 +16,23 @@
 
 mod params;
 
-use std::cmp::{max, min};
+use std::sync::{Arc, Weak};
 
 use byteorder::{ByteOrder, LittleEndian};
 use ccrypto::blake256;
+use cstate::ActionHandler;
 use ctypes::machine::WithBalances;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use cuckoo::Cuckoo as CuckooVerifier;
-use primitives::U256;
+use parking_lot::RwLock;
+use primitives::{Bytes, U256};
 use rlp::UntrustedRlp;
 
 use self::params::CuckooParams;
 use super::ConsensusEngine;
 use crate::block::{ExecutedBlock, IsBlock};
-use crate::codechain_machine::CodeChainMachine;
+use crate::client::EngineClient;
+use crate::codechain_machine::{CodeChainMachine, WithActionHandlers};
 use crate::consensus::EngineType;
 use crate::error::{BlockError, Error};
 use crate::header::Header;
@@ -62,6 +65,8 @@ impl Seal {
 pub struct Cuckoo {
     params: CuckooParams,
     machine: CodeChainMachine,
+    /// Reads the recent headers for the difficulty adjustment.
+    client: RwLock<Option<Weak<EngineClient>>>,
     verifier: CuckooVerifier,
 }
 
@@ -71,23 +76,15 @@ impl Cuckoo {
         Self {
             params,
             machine,
+            client: Default::default(),
             verifier,
         }
     }
 
-    fn calculate_score(&self, header: &Header, parent: &Header) -> U256 {
-        if header.number() == 0 {
-            panic!("Can't calculate genesis block score");
-        }
-
-        //score = parent_score + parent_score // 2048 * max(1 - (block_timestamp - parent_timestamp) // block_interval, -99)
-        let diff = (header.timestamp() - parent.timestamp()) / self.params.block_interval;
-        let target = if diff <= 1 {
-            parent.score().saturating_add(*parent.score() / 2048.into() * U256::from(1 - diff))
-        } else {
-            parent.score().saturating_sub(*parent.score() / 2048.into() * U256::from(min(diff - 1, 99)))
-        };
-        max(self.params.min_score, target)
+    fn calculate_score(&self, header: &Header, parent: &Header) -> Result<U256, BlockError> {
+        let difficulty = self.params.difficulty;
+        let ancestors = difficulty.ancestors(&self.client.read(), parent)?;
+        Ok(difficulty.calculate_score(header, &ancestors, self.params.block_interval, self.params.min_score))
     }
 }
 
@@ -108,6 +105,10 @@ impl ConsensusEngine<CodeChainMachine> for Cuckoo {
         EngineType::PoW
     }
 
+    fn register_client(&self, client: Weak<EngineClient>) {
+        *self.client.write() = Some(client);
+    }
+
     fn verify_local_seal(&self, header: &Header) -> Result<(), Error> {
         self.verify_block_basic(header).and_then(|_| self.verify_block_unordered(header))
     }
@@ -134,7 +135,7 @@ impl ConsensusEngine<CodeChainMachine> for Cuckoo {
             return Err(From::from(BlockError::InvalidProofOfWork))
         }
 
-        let target = self.score_to_target(header.score());
+        let target = self.score_to_target(header.score(), header.number());
         let hash = blake256(::rlp::encode_list(&seal.proof));
         if U256::from(hash) > target {
             return Err(From::from(BlockError::PowOutOfBounds(OutOfBounds {
@@ -155,7 +156,7 @@ impl ConsensusEngine<CodeChainMachine> for Cuckoo {
             })))
         }
 
-        let expected_score = self.calculate_score(header, parent);
+        let expected_score = self.calculate_score(header, parent)?;
         if header.score() != &expected_score {
             return Err(From::from(BlockError::InvalidScore(Mismatch {
                 expected: expected_score,
@@ -167,28 +168,51 @@ impl ConsensusEngine<CodeChainMachine> for Cuckoo {
     }
 
     fn populate_from_parent(&self, header: &mut Header, parent: &Header) {
-        let score = self.calculate_score(header, parent);
-        header.set_score(score);
+        match self.calculate_score(header, parent) {
+            Ok(score) => header.set_score(score),
+            Err(err) => cwarn!(ENGINE, "Cannot calculate the score of block #{}: {}", header.number(), err),
+        }
     }
 
     fn on_close_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
         let author = *block.header().author();
-        let total_reward = self.block_reward(block.header().number())
-            + self.block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
-        self.machine.add_balance(block, &author, total_reward)
+        let block_number = block.header().number();
+        let block_fee =
+            self.block_fee(block_number, Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
+        let burned_fee = self.burned_fee(block_number, block_fee);
+        let total_reward = self.block_reward(block_number) + block_fee - burned_fee;
+        self.machine.add_balance(block, &author, total_reward)?;
+        self.machine.burn_fee(block, burned_fee)?;
+        self.machine.close_action_handlers(block, self.action_handlers())
     }
 
-    fn score_to_target(&self, score: &U256) -> U256 {
+    fn score_to_target(&self, score: &U256, _block_number: u64) -> U256 {
         (U256::max_value() - *score) / *score
     }
 
-    fn block_reward(&self, _block_number: u64) -> u64 {
-        self.params.block_reward
+    fn pow_seal(&self, header: &Header, nonce: u64) -> Option<Vec<Bytes>> {
+        let mut message = header.bare_hash().0;
+        LittleEndian::write_u64(&mut message, nonce);
+
+        let proof = self.verifier.solve(&message)?;
+        Some(vec![::rlp::encode(&nonce).into_vec(), ::rlp::encode_list(&proof).into_vec()])
+    }
+
+    fn block_reward(&self, block_number: u64) -> u64 {
+        self.params.block_reward.reward(block_number)
+    }
+
+    fn burned_fee(&self, _block_number: u64, block_fee: u64) -> u64 {
+        self.params.fee_burn.burned(block_fee)
     }
 
     fn recommended_confirmation(&self) -> u32 {
         self.params.recommmended_confirmation
     }
+
+    fn action_handlers(&self) -> &[Arc<ActionHandler>] {
+        self.machine.action_handlers()
+    }
 }
 
 #[cfg(test)]
@@ -244,7 +268,7 @@ mod tests {
     fn score_to_target() {
         let engine = Scheme::new_test_cuckoo().engine;
 
-        assert_eq!(engine.score_to_target(&U256::max_value()), U256::from(0));
+        assert_eq!(engine.score_to_target(&U256::max_value(), 0), U256::from(0));
     }
 
     #[test]
@@ -17,10 +17,16 @@
 use cjson;
 use primitives::U256;
 
+use super::super::difficulty::DifficultyAlgorithm;
+use super::super::reward::{FeeBurn, RewardSchedule};
+
 pub struct CuckooParams {
-    pub block_reward: u64,
+    pub block_reward: RewardSchedule,
+    /// The portion of the block fee which is burned.
+    pub fee_burn: FeeBurn,
     pub block_interval: u64,
     pub min_score: U256,
+    pub difficulty: DifficultyAlgorithm,
     pub max_vertex: usize,
     pub max_edge: usize,
     pub cycle_length: usize,
@@ -30,9 +36,11 @@ pub struct CuckooParams {
 impl From<cjson::scheme::CuckooParams> for CuckooParams {
     fn from(p: cjson::scheme::CuckooParams) -> Self {
         CuckooParams {
-            block_reward: p.block_reward.map_or(0, Into::into),
+            block_reward: RewardSchedule::new(p.block_reward, p.reward_schedule),
+            fee_burn: FeeBurn::new(p.fee_burn_ratio),
             block_interval: p.block_interval.map_or(120, Into::into),
             min_score: p.min_score.map_or(U256::from(0x0002_0000), Into::into),
+            difficulty: p.difficulty_algorithm.map_or_else(Default::default, Into::into),
             max_vertex: p.max_vertex.map_or(1 << 30, Into::into),
             max_edge: p.max_edge.map_or(1 << 29, Into::into),
             cycle_length: p.cycle_length.map_or(42, Into::into),
@@ -0,0 +1,322 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::cmp::{max, min};
+use std::sync::Weak;
+
+use cjson;
+use primitives::U256;
+
+use crate::client::EngineClient;
+use crate::error::BlockError;
+use crate::header::Header;
+use crate::BlockId;
+
+/// The longest solve time which LWMA counts, in block intervals.
+/// It prevents a block with a wrong timestamp from dropping the score too much.
+const LWMA_MAX_SOLVE_TIME: u64 = 6;
+
+/// The rule which adjusts the score of a PoW block by the recent block times.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DifficultyAlgorithm {
+    /// The score of the parent changes by 1/2048 of it for each block interval.
+    /// score = parent_score + parent_score // 2048 * max(1 - solve_time // block_interval, -99)
+    Homestead,
+    /// The linearly weighted moving average of the scores and the solve times of the recent `window` blocks.
+    Lwma {
+        window: u64,
+    },
+    /// The score never changes.
+    Fixed,
+}
+
+impl Default for DifficultyAlgorithm {
+    fn default() -> Self {
+        DifficultyAlgorithm::Homestead
+    }
+}
+
+impl From<cjson::scheme::DifficultyAlgorithm> for DifficultyAlgorithm {
+    fn from(algorithm: cjson::scheme::DifficultyAlgorithm) -> Self {
+        match algorithm {
+            cjson::scheme::DifficultyAlgorithm::Homestead => DifficultyAlgorithm::Homestead,
+            cjson::scheme::DifficultyAlgorithm::Lwma {
+                window,
+            } => DifficultyAlgorithm::Lwma {
+                window: max(window.into(), 1),
+            },
+            cjson::scheme::DifficultyAlgorithm::Fixed => DifficultyAlgorithm::Fixed,
+        }
+    }
+}
+
+impl DifficultyAlgorithm {
+    /// The number of the recent headers, starting from the parent, which are needed to calculate the score.
+    pub fn num_ancestors(&self) -> u64 {
+        match self {
+            DifficultyAlgorithm::Homestead => 1,
+            DifficultyAlgorithm::Lwma {
+                window,
+            } => window + 1,
+            DifficultyAlgorithm::Fixed => 1,
+        }
+    }
+
+    /// Returns the recent headers, starting from the parent, which `calculate_score` needs.
+    /// They are fewer than `num_ancestors` only near the genesis block.
+    /// Fails if an ancestor can't be read, because the score from a shorter window differs.
+    pub fn ancestors(&self, client: &Option<Weak<EngineClient>>, parent: &Header) -> Result<Vec<Header>, BlockError> {
+        let mut ancestors = vec![parent.clone()];
+        let client = client.as_ref().and_then(Weak::upgrade);
+        while (ancestors.len() as u64) < self.num_ancestors() {
+            let ancestor_hash = {
+                let oldest = ancestors.last().expect("The parent is always in the ancestors");
+                if oldest.number() == 0 {
+                    break
+                }
+                *oldest.parent_hash()
+            };
+            let header = client.as_ref().and_then(|client| client.block_header(&BlockId::Hash(ancestor_hash)));
+            match header {
+                Some(header) => ancestors.push(header.decode()),
+                None => return Err(BlockError::UnknownAncestor(ancestor_hash)),
+            }
+        }
+        Ok(ancestors)
+    }
+
+    /// Calculates the score of the block from the recent headers, which start from the parent.
+    pub fn calculate_score(&self, header: &Header, ancestors: &[Header], block_interval: u64, min_score: U256) -> U256 {
+        if header.number() == 0 {
+            panic!("Can't calculate genesis block score");
+        }
+        let parent = ancestors.first().expect("The parent must be given");
+        let target = match self {
+            DifficultyAlgorithm::Homestead => {
+                let diff = (header.timestamp() - parent.timestamp()) / block_interval;
+                if diff <= 1 {
+                    parent.score().saturating_add(*parent.score() / 2048.into() * U256::from(1 - diff))
+                } else {
+                    parent.score().saturating_sub(*parent.score() / 2048.into() * U256::from(min(diff - 1, 99)))
+                }
+            }
+            DifficultyAlgorithm::Lwma {
+                window,
+            } => lwma(ancestors, *window, block_interval),
+            DifficultyAlgorithm::Fixed => min_score,
+        };
+        max(min_score, target)
+    }
+}
+
+/// next_score = average_score * block_interval / weighted_average_solve_time
+/// The weight of the most recent solve time is `window` and that of the oldest one is 1.
+fn lwma(ancestors: &[Header], window: u64, block_interval: u64) -> U256 {
+    let n = min(window, ancestors.len() as u64 - 1);
+    if n == 0 {
+        return *ancestors[0].score()
+    }
+    let mut sum_of_scores = U256::zero();
+    let mut weighted_solve_times = 0u64;
+    for (i, pair) in ancestors.windows(2).take(n as usize).enumerate() {
+        let (block, prev) = (&pair[0], &pair[1]);
+        let solve_time = block.timestamp().saturating_sub(prev.timestamp());
+        let solve_time = max(1, min(solve_time, LWMA_MAX_SOLVE_TIME * block_interval));
+        weighted_solve_times += (n - i as u64) * solve_time;
+        sum_of_scores = sum_of_scores.saturating_add(*block.score());
+    }
+    // The sum of the weights is n * (n + 1) / 2.
+    sum_of_scores.saturating_mul(U256::from(block_interval * (n + 1))) / U256::from(2 * weighted_solve_times)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK_INTERVAL: u64 = 10;
+
+    fn genesis(score: u64) -> Header {
+        let mut header = Header::default();
+        header.set_score(score.into());
+        header
+    }
+
+    /// Appends a block which is mined `solve_time` seconds after the last block.
+    fn append(algorithm: DifficultyAlgorithm, chain: &mut Vec<Header>, solve_time: u64) {
+        let mut header = Header::default();
+        {
+            let parent = chain.last().unwrap();
+            header.set_number(parent.number() + 1);
+            header.set_timestamp(parent.timestamp() + solve_time);
+        }
+        let ancestors: Vec<_> = chain.iter().rev().take(algorithm.num_ancestors() as usize).cloned().collect();
+        let score = algorithm.calculate_score(&header, &ancestors, BLOCK_INTERVAL, 1.into());
+        header.set_score(score);
+        chain.push(header);
+    }
+
+    /// Mines `count` blocks with the given hash rate, which is the score solved in a second.
+    /// The solve times depend only on the scores to make the simulation deterministic.
+    fn mine(algorithm: DifficultyAlgorithm, chain: &mut Vec<Header>, count: usize, hash_rate: u64) {
+        for _ in 0..count {
+            let mut header = Header::default();
+            {
+                let parent = chain.last().unwrap();
+                header.set_number(parent.number() + 1);
+            }
+            let ancestors: Vec<_> = chain.iter().rev().take(algorithm.num_ancestors() as usize).cloned().collect();
+            let score = algorithm.calculate_score(&header, &ancestors, BLOCK_INTERVAL, 1.into());
+            let solve_time = score.low_u64() / hash_rate;
+            header.set_timestamp(chain.last().unwrap().timestamp() + solve_time);
+            header.set_score(score);
+            chain.push(header);
+        }
+    }
+
+    fn recent_solve_times(chain: &[Header], count: usize) -> Vec<u64> {
+        chain.windows(2).rev().take(count).map(|pair| pair[1].timestamp() - pair[0].timestamp()).collect()
+    }
+
+    #[test]
+    fn homestead_raises_the_score_of_fast_blocks() {
+        let algorithm = DifficultyAlgorithm::Homestead;
+        let mut chain = vec![genesis(0x20000)];
+        for _ in 0..100 {
+            append(algorithm, &mut chain, 1);
+        }
+        assert!(chain.windows(2).all(|pair| pair[0].score() < pair[1].score()));
+        assert_eq!(U256::from(0x20040), *chain[1].score());
+    }
+
+    #[test]
+    fn homestead_lowers_the_score_of_slow_blocks() {
+        let algorithm = DifficultyAlgorithm::Homestead;
+        let mut chain = vec![genesis(0x20000)];
+        for _ in 0..100 {
+            append(algorithm, &mut chain, 5 * BLOCK_INTERVAL);
+        }
+        assert!(chain.windows(2).all(|pair| pair[0].score() > pair[1].score()));
+        // A block after a long pause lowers the score by 99/2048 at most.
+        append(algorithm, &mut chain, 1000 * BLOCK_INTERVAL);
+        let parent_score = *chain[chain.len() - 2].score();
+        assert_eq!(parent_score - parent_score / 2048.into() * 99.into(), *chain.last().unwrap().score());
+    }
+
+    #[test]
+    fn homestead_keeps_the_min_score() {
+        let algorithm = DifficultyAlgorithm::Homestead;
+        let mut chain = vec![genesis(1)];
+        for _ in 0..10 {
+            append(algorithm, &mut chain, 100 * BLOCK_INTERVAL);
+        }
+        assert!(chain.iter().all(|header| *header.score() == 1.into()));
+    }
+
+    #[test]
+    fn lwma_keeps_the_score_of_blocks_on_time() {
+        let algorithm = DifficultyAlgorithm::Lwma {
+            window: 30,
+        };
+        let mut chain = vec![genesis(10_000)];
+        for _ in 0..100 {
+            append(algorithm, &mut chain, BLOCK_INTERVAL);
+        }
+        assert!(chain.iter().all(|header| *header.score() == 10_000.into()));
+    }
+
+    #[test]
+    fn lwma_converges_to_the_hash_rate() {
+        let algorithm = DifficultyAlgorithm::Lwma {
+            window: 30,
+        };
+        // The score is ten times lower than what the hash rate needs.
+        let mut chain = vec![genesis(1_000)];
+        mine(algorithm, &mut chain, 200, 1_000);
+        assert!(recent_solve_times(&chain, 50).iter().all(|solve_time| *solve_time == BLOCK_INTERVAL));
+    }
+
+    #[test]
+    fn lwma_follows_the_change_of_the_hash_rate() {
+        let algorithm = DifficultyAlgorithm::Lwma {
+            window: 30,
+        };
+        let mut chain = vec![genesis(10_000)];
+        mine(algorithm, &mut chain, 100, 1_000);
+        assert!(recent_solve_times(&chain, 50).iter().all(|solve_time| *solve_time == BLOCK_INTERVAL));
+
+        // The blocks become twice faster until LWMA catches up.
+        mine(algorithm, &mut chain, 1, 2_000);
+        assert_eq!(vec![BLOCK_INTERVAL / 2], recent_solve_times(&chain, 1));
+        mine(algorithm, &mut chain, 100, 2_000);
+        assert!(recent_solve_times(&chain, 50).iter().all(|solve_time| *solve_time == BLOCK_INTERVAL));
+        assert!(*chain.last().unwrap().score() >= 20_000.into());
+
+        // The blocks become slower when the hash rate drops.
+        mine(algorithm, &mut chain, 1, 500);
+        assert_eq!(vec![BLOCK_INTERVAL * 4], recent_solve_times(&chain, 1));
+        mine(algorithm, &mut chain, 100, 500);
+        assert!(recent_solve_times(&chain, 50).iter().all(|solve_time| *solve_time == BLOCK_INTERVAL));
+    }
+
+    #[test]
+    fn lwma_limits_the_effect_of_a_long_solve_time() {
+        let algorithm = DifficultyAlgorithm::Lwma {
+            window: 30,
+        };
+        let mut chain = vec![genesis(10_000)];
+        for _ in 0..30 {
+            append(algorithm, &mut chain, BLOCK_INTERVAL);
+        }
+        append(algorithm, &mut chain, 8640 * BLOCK_INTERVAL);
+        append(algorithm, &mut chain, BLOCK_INTERVAL);
+        assert!(*chain.last().unwrap().score() > 5_000.into());
+    }
+
+    #[test]
+    fn lwma_uses_the_blocks_after_the_genesis_block() {
+        let algorithm = DifficultyAlgorithm::Lwma {
+            window: 30,
+        };
+        let mut chain = vec![genesis(10_000)];
+        append(algorithm, &mut chain, BLOCK_INTERVAL / 2);
+        assert_eq!(U256::from(10_000), *chain[1].score());
+        append(algorithm, &mut chain, BLOCK_INTERVAL);
+        assert_eq!(U256::from(20_000), *chain[2].score());
+    }
+
+    #[test]
+    fn missing_ancestors_are_rejected() {
+        let mut parent = Header::default();
+        parent.set_number(10);
+        parent.set_parent_hash(5.into());
+        assert_eq!(Ok(vec![parent.clone()]), DifficultyAlgorithm::Homestead.ancestors(&None, &parent));
+        let algorithm = DifficultyAlgorithm::Lwma {
+            window: 30,
+        };
+        assert_eq!(Err(BlockError::UnknownAncestor(5.into())), algorithm.ancestors(&None, &parent));
+    }
+
+    #[test]
+    fn fixed_ignores_the_block_times() {
+        let algorithm = DifficultyAlgorithm::Fixed;
+        let mut chain = vec![genesis(0x20000)];
+        for solve_time in &[1, 1, 1, 100, 1000, 5, 10, 10] {
+            append(algorithm, &mut chain, *solve_time);
+        }
+        mine(algorithm, &mut chain, 100, 7);
+        assert!(chain[1..].iter().all(|header| *header.score() == 1.into()));
+    }
+}
@@ -16,12 +16,15 @@
 
 mod blake_pow;
 mod cuckoo;
+mod difficulty;
 pub mod epoch;
 mod null_engine;
+mod reward;
 mod signer;
 mod simple_poa;
 mod solo;
 mod tendermint;
+mod transition;
 mod validator_set;
 mod vote_collector;
 
@@ -30,7 +33,12 @@ pub use self::cuckoo::Cuckoo;
 pub use self::null_engine::NullEngine;
 pub use self::simple_poa::SimplePoA;
 pub use self::solo::Solo;
-pub use self::tendermint::{Tendermint, TendermintParams};
+pub use self::tendermint::types::{BitSet, Step};
+pub use self::tendermint::{
+    stake, verify_certificate_chain, CommitCertificate, ConsensusEvent, ConsensusListener, ConsensusState, StepVotes,
+    Tendermint, TendermintParams,
+};
+pub use self::transition::TransitionEngine;
 pub use self::validator_set::validator_list::ValidatorList;
 pub use self::validator_set::ValidatorSet;
 
@@ -42,6 +50,8 @@ use cnetwork::NetworkService;
 use cstate::ActionHandler;
 use ctypes::machine::Machine;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
+use ctypes::BlockNumber;
+use kvdb::{DBTransaction, KeyValueDB};
 use primitives::{Bytes, H256, U256};
 
 use self::epoch::{EpochVerifier, NoOp, PendingTransition};
@@ -284,21 +294,40 @@ pub trait ConsensusEngine<M: Machine>: Sync + Send {
         None
     }
 
-    fn register_network_extension_to_service(&self, _: &NetworkService) {}
+    /// Returns the validators which can sign the children of the given block.
+    /// It's `None` if the engine has no validators.
+    fn validators(&self, _parent: &M::Header) -> Option<Vec<Public>> {
+        None
+    }
+
+    fn register_network_extension_to_service(&self, _: &Arc<NetworkService>) {}
 
-    fn score_to_target(&self, _score: &U256) -> U256 {
+    /// Converts the score of the block of the given number to the PoW target.
+    fn score_to_target(&self, _score: &U256, _block_number: u64) -> U256 {
         U256::zero()
     }
 
+    /// Makes the seal of the given header with the nonce for the built-in CPU miner.
+    /// It's `None` if the engine cannot be mined or there's no solution for the nonce.
+    /// The seal may not meet the target, so check it with `verify_local_seal`.
+    fn pow_seal(&self, _header: &M::Header, _nonce: u64) -> Option<Vec<Bytes>> {
+        None
+    }
+
     fn block_reward(&self, block_number: u64) -> u64;
 
-    fn block_fee(&self, transactions: Box<Iterator<Item = UnverifiedTransaction>>) -> u64 {
+    fn block_fee(&self, _block_number: u64, transactions: Box<Iterator<Item = UnverifiedTransaction>>) -> u64 {
         transactions.map(|tx| tx.fee).sum()
     }
 
+    /// The part of the fee of the block which is burned instead of being paid.
+    fn burned_fee(&self, _block_number: u64, _block_fee: u64) -> u64 {
+        0
+    }
+
     fn recommended_confirmation(&self) -> u32;
 
-    fn register_chain_notify(&self, _: &Client) {}
+    fn register_chain_notify(&self, _: &Arc<Client>) {}
 
     fn get_best_block_from_highest_score_header(&self, header: &HeaderView) -> H256 {
         header.hash()
@@ -314,6 +343,32 @@ pub trait ConsensusEngine<M: Machine>: Sync + Send {
     fn action_handlers(&self) -> &[Arc<ActionHandler>] {
         &[]
     }
+
+    /// Returns the action handlers which the transactions of the block can use.
+    fn action_handlers_at(&self, _block_number: BlockNumber) -> &[Arc<ActionHandler>] {
+        self.action_handlers()
+    }
+
+    /// Returns the action handlers which are initialized in the genesis block.
+    fn genesis_action_handlers(&self) -> &[Arc<ActionHandler>] {
+        self.action_handlers()
+    }
+
+    /// Returns the proof that the block is finalized, if the engine has one.
+    fn commit_certificate(&self, _block_number: BlockNumber) -> Option<CommitCertificate> {
+        None
+    }
+
+    /// Returns the round which the engine is in, if the engine has rounds.
+    fn consensus_state(&self) -> Option<ConsensusState> {
+        None
+    }
+
+    /// Adds a listener of the step transitions and the timeouts of the engine.
+    fn add_consensus_listener(&self, _listener: ConsensusListener) {}
+
+    /// Removes the consensus state of the blocks above the best block, while the chain is rewound offline.
+    fn rewind_consensus_state(&self, _db: &KeyValueDB, _batch: &mut DBTransaction, _best_block_number: BlockNumber) {}
 }
 
 /// Results of a query of whether an epoch change occurred at the given block.
@@ -375,6 +430,12 @@ pub enum EngineError {
     BadSealFieldSize(OutOfBounds<usize>),
     /// Malformed consensus message.
     MalformedMessage(String),
+    /// The validators of the children of the block cannot be determined.
+    UnknownValidators(H256),
+    /// The signers don't hold more than 2/3 of the voting power.
+    InsufficientVotingPower(OutOfBounds<u64>),
+    /// The validators of the block are not the trusted ones.
+    UntrustedValidators(BlockNumber),
 }
 
 impl fmt::Display for EngineError {
@@ -399,6 +460,9 @@ impl fmt::Display for EngineError {
             UnexpectedMessage => "This Engine should not be fed messages.".into(),
             BadSealFieldSize(oob) => format!("Seal field has an unexpected length: {}", oob),
             MalformedMessage(msg) => format!("Received malformed consensus message: {}", msg),
+            UnknownValidators(hash) => format!("The validators of the children of {} are unknown", hash),
+            InsufficientVotingPower(oob) => format!("The signers have insufficient voting power: {}", oob),
+            UntrustedValidators(number) => format!("The validators of the block {} are not trusted", number),
         };
 
         f.write_fmt(format_args!("Engine error ({})", msg))
@@ -426,8 +490,14 @@ pub trait CodeChainEngine: ConsensusEngine<CodeChainMachine> {
     }
 
     /// Additional verification for transactions in blocks.
-    fn verify_transaction_basic(&self, tx: &UnverifiedTransaction, header: &Header) -> Result<(), Error> {
-        self.machine().verify_transaction_basic(tx, header)
+    /// The params are the ones active at the block, which may differ from the genesis params.
+    fn verify_transaction_basic(
+        &self,
+        tx: &UnverifiedTransaction,
+        params: &CommonParams,
+        header: &Header,
+    ) -> Result<(), Error> {
+        self.machine().verify_transaction_basic(tx, params, header, self.action_handlers_at(header.number()))
     }
 
     /// Verify a particular transaction is valid.
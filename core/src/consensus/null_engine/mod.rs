@@ -16,10 +16,14 @@
 
 mod params;
 
+use std::sync::Arc;
+
+use cstate::ActionHandler;
 use ctypes::machine::{Header, LiveBlock, Transactions, WithBalances};
 
 use self::params::NullEngineParams;
 use super::ConsensusEngine;
+use crate::codechain_machine::WithActionHandlers;
 use crate::consensus::EngineType;
 use crate::SignedTransaction;
 
@@ -45,7 +49,7 @@ impl<M: Default> Default for NullEngine<M> {
     }
 }
 
-impl<M: WithBalances> ConsensusEngine<M> for NullEngine<M>
+impl<M: WithBalances + WithActionHandlers> ConsensusEngine<M> for NullEngine<M>
 where
     M::LiveBlock: Transactions<Transaction = SignedTransaction>,
 {
@@ -67,9 +71,11 @@ where
 
     fn on_close_block(&self, block: &mut M::LiveBlock) -> Result<(), M::Error> {
         let author = *LiveBlock::header(&*block).author();
-        let total_reward = self.block_reward(block.header().number())
-            + self.block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
-        self.machine.add_balance(block, &author, total_reward)
+        let block_number = block.header().number();
+        let total_reward = self.block_reward(block_number)
+            + self.block_fee(block_number, Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
+        self.machine.add_balance(block, &author, total_reward)?;
+        self.machine.close_action_handlers(block, self.machine.action_handlers())
     }
 
     fn block_reward(&self, _block_number: u64) -> u64 {
@@ -79,4 +85,8 @@ where
     fn recommended_confirmation(&self) -> u32 {
         1
     }
+
+    fn action_handlers(&self) -> &[Arc<ActionHandler>] {
+        self.machine.action_handlers()
+    }
 }
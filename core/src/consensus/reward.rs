@@ -0,0 +1,203 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use cjson;
+
+/// Decay rates and burn ratios are given in per mille.
+const PER_MILLE: u64 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RewardCurve {
+    Constant,
+    Halving {
+        interval: u64,
+    },
+    Decay {
+        interval: u64,
+        rate: u64,
+    },
+}
+
+/// The block reward at each block number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RewardSchedule {
+    initial: u64,
+    curve: RewardCurve,
+}
+
+impl RewardSchedule {
+    pub fn new(initial: Option<cjson::uint::Uint>, schedule: Option<cjson::scheme::RewardSchedule>) -> Self {
+        let curve = match schedule {
+            None => RewardCurve::Constant,
+            Some(cjson::scheme::RewardSchedule::Halving {
+                interval,
+            }) => RewardCurve::Halving {
+                interval: interval.into(),
+            },
+            Some(cjson::scheme::RewardSchedule::Decay {
+                interval,
+                rate,
+            }) => RewardCurve::Decay {
+                interval: interval.into(),
+                rate: ::std::cmp::min(rate.into(), PER_MILLE),
+            },
+        };
+        Self {
+            initial: initial.map_or(0, Into::into),
+            curve,
+        }
+    }
+
+    pub fn constant(reward: u64) -> Self {
+        Self {
+            initial: reward,
+            curve: RewardCurve::Constant,
+        }
+    }
+
+    pub fn reward(&self, block_number: u64) -> u64 {
+        match self.curve {
+            RewardCurve::Constant => self.initial,
+            RewardCurve::Halving {
+                interval,
+            } => {
+                if interval == 0 {
+                    return self.initial
+                }
+                let halvings = block_number / interval;
+                if halvings >= 64 {
+                    0
+                } else {
+                    self.initial >> halvings
+                }
+            }
+            RewardCurve::Decay {
+                interval,
+                rate,
+            } => {
+                if interval == 0 {
+                    return self.initial
+                }
+                let periods = block_number / interval;
+                // The reward strictly decreases in every period until it becomes 0,
+                // so the loop ends quickly even if the chain is very long.
+                let mut reward = self.initial;
+                let mut period = 0;
+                while period < periods && reward != 0 && rate != 0 {
+                    reward = (u128::from(reward) * u128::from(PER_MILLE - rate) / u128::from(PER_MILLE)) as u64;
+                    period += 1;
+                }
+                reward
+            }
+        }
+    }
+}
+
+impl Default for RewardSchedule {
+    fn default() -> Self {
+        Self::constant(0)
+    }
+}
+
+/// The portion of the block fee which is burned instead of being paid.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeeBurn {
+    ratio: u64,
+}
+
+impl FeeBurn {
+    pub fn new(ratio: Option<cjson::uint::Uint>) -> Self {
+        Self {
+            ratio: ratio.map_or(0, |ratio| ::std::cmp::min(ratio.into(), PER_MILLE)),
+        }
+    }
+
+    pub fn burned(&self, block_fee: u64) -> u64 {
+        (u128::from(block_fee) * u128::from(self.ratio) / u128::from(PER_MILLE)) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cjson::scheme::RewardSchedule as JsonSchedule;
+    use cjson::uint::Uint;
+    use primitives::U256;
+
+    use super::*;
+
+    fn uint(value: u64) -> Option<Uint> {
+        Some(Uint(U256::from(value)))
+    }
+
+    #[test]
+    fn constant_reward() {
+        let schedule = RewardSchedule::new(uint(50), None);
+        assert_eq!(50, schedule.reward(1));
+        assert_eq!(50, schedule.reward(1_000_000));
+    }
+
+    #[test]
+    fn halving_reward() {
+        let schedule = RewardSchedule::new(
+            uint(100),
+            Some(JsonSchedule::Halving {
+                interval: Uint(U256::from(10)),
+            }),
+        );
+        assert_eq!(100, schedule.reward(1));
+        assert_eq!(100, schedule.reward(9));
+        assert_eq!(50, schedule.reward(10));
+        assert_eq!(25, schedule.reward(25));
+        assert_eq!(0, schedule.reward(70));
+        assert_eq!(0, schedule.reward(::std::u64::MAX));
+    }
+
+    #[test]
+    fn decaying_reward() {
+        let schedule = RewardSchedule::new(
+            uint(1000),
+            Some(JsonSchedule::Decay {
+                interval: Uint(U256::from(10)),
+                rate: Uint(U256::from(100)),
+            }),
+        );
+        assert_eq!(1000, schedule.reward(9));
+        assert_eq!(900, schedule.reward(10));
+        assert_eq!(810, schedule.reward(20));
+        assert_eq!(729, schedule.reward(30));
+        assert_eq!(0, schedule.reward(::std::u64::MAX));
+    }
+
+    #[test]
+    fn zero_interval_keeps_the_reward() {
+        let schedule = RewardSchedule::new(
+            uint(100),
+            Some(JsonSchedule::Halving {
+                interval: Uint(U256::from(0)),
+            }),
+        );
+        assert_eq!(100, schedule.reward(1000));
+    }
+
+    #[test]
+    fn burn_fee() {
+        assert_eq!(0, FeeBurn::default().burned(1000));
+        assert_eq!(300, FeeBurn::new(uint(300)).burned(1000));
+        assert_eq!(3, FeeBurn::new(uint(300)).burned(11));
+        assert_eq!(1000, FeeBurn::new(uint(2000)).burned(1000));
+        assert_eq!(::std::u64::MAX, FeeBurn::new(uint(1000)).burned(::std::u64::MAX));
+    }
+}
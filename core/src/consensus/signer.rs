@@ -16,7 +16,7 @@
 
 use std::sync::Arc;
 
-use ckey::{Address, Password, Public, SchnorrSignature};
+use ckey::{Address, Password, Private, Public, SchnorrSignature, Signature};
 use primitives::H256;
 
 use crate::account_provider::{AccountProvider, SignError};
@@ -57,6 +57,38 @@ impl EngineSigner {
         )
     }
 
+    /// Sign a consensus message hash as a part of the aggregated signature of the signers.
+    pub fn sign_aggregated_partial(
+        &self,
+        signers: &[Public],
+        nonce: &Private,
+        other_nonces: &Public,
+        hash: H256,
+    ) -> Result<SchnorrSignature, SignError> {
+        self.account_provider.sign_schnorr_aggregated_partial(
+            self.signer.map(|(address, _public)| address).unwrap_or_else(Default::default),
+            self.password.clone(),
+            signers,
+            nonce,
+            other_nonces,
+            hash,
+        )
+    }
+
+    /// Sign a transaction hash.
+    pub fn sign_ecdsa(&self, hash: H256) -> Result<Signature, SignError> {
+        self.account_provider.sign(
+            self.signer.map(|(address, _public)| address).unwrap_or_else(Default::default),
+            self.password.clone(),
+            hash,
+        )
+    }
+
+    /// Address of signer.
+    pub fn address(&self) -> Option<&Address> {
+        self.signer.as_ref().map(|(address, _public)| address)
+    }
+
     /// Public Key of signer.
     pub fn public(&self) -> Option<&Public> {
         self.signer.as_ref().map(|(_address, public)| public)
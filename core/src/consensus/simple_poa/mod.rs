@@ -15,23 +15,27 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod params;
+pub mod voting;
 
 use std::sync::{Arc, Weak};
 
 use ckey::{public_to_address, recover, Address, Password, Public, SchnorrSignature, Signature};
+use cstate::ActionHandler;
 use ctypes::machine::WithBalances;
 use parking_lot::RwLock;
 use primitives::H256;
 
 use self::params::SimplePoAParams;
+use self::voting::Voting;
+use super::reward::{FeeBurn, RewardSchedule};
 use super::signer::EngineSigner;
 use super::validator_set::validator_list::ValidatorList;
-use super::validator_set::ValidatorSet;
+use super::validator_set::{new_validator_set, new_voted_validator_set, ValidatorSet};
 use super::{ConsensusEngine, ConstructedVerifier, EngineError, Seal};
 use crate::account_provider::AccountProvider;
 use crate::block::{ExecutedBlock, IsBlock};
 use crate::client::EngineClient;
-use crate::codechain_machine::CodeChainMachine;
+use crate::codechain_machine::{CodeChainMachine, WithActionHandlers};
 use crate::consensus::EngineType;
 use crate::error::{BlockError, Error};
 use crate::header::Header;
@@ -40,18 +44,32 @@ pub struct SimplePoA {
     machine: CodeChainMachine,
     signer: RwLock<EngineSigner>,
     validators: Box<ValidatorSet>,
+    action_handlers: Vec<Arc<ActionHandler>>,
     /// Reward per block, in base units.
-    block_reward: u64,
+    block_reward: RewardSchedule,
+    /// The portion of the block fee which is burned.
+    fee_burn: FeeBurn,
 }
 
 impl SimplePoA {
     /// Create a new instance of SimplePoA engine
     pub fn new(params: SimplePoAParams, machine: CodeChainMachine) -> Self {
+        let mut action_handlers: Vec<Arc<ActionHandler>> = Vec::new();
+        let validators = match params.epoch_length {
+            Some(epoch_length) => {
+                action_handlers.push(Arc::new(Voting::new(params.validators.clone(), epoch_length)));
+                new_voted_validator_set(params.validators, epoch_length)
+            }
+            None => new_validator_set(params.validators),
+        };
+        action_handlers.extend(machine.action_handlers().iter().cloned());
         SimplePoA {
             machine,
             signer: Default::default(),
-            validators: Box::new(ValidatorList::new(params.validators)),
+            validators,
+            action_handlers,
             block_reward: params.block_reward,
+            fee_burn: params.fee_burn,
         }
     }
 }
@@ -179,9 +197,14 @@ impl ConsensusEngine<CodeChainMachine> for SimplePoA {
 
     fn on_close_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
         let author = *block.header().author();
-        let total_reward = self.block_reward(block.header().number())
-            + self.block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
-        self.machine.add_balance(block, &author, total_reward)
+        let block_number = block.header().number();
+        let block_fee =
+            self.block_fee(block_number, Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
+        let burned_fee = self.burned_fee(block_number, block_fee);
+        let total_reward = self.block_reward(block_number) + block_fee - burned_fee;
+        self.machine.add_balance(block, &author, total_reward)?;
+        self.machine.burn_fee(block, burned_fee)?;
+        self.machine.close_action_handlers(block, self.action_handlers())
     }
 
     fn register_client(&self, client: Weak<EngineClient>) {
@@ -201,13 +224,26 @@ impl ConsensusEngine<CodeChainMachine> for SimplePoA {
         self.signer.read().public().cloned()
     }
 
-    fn block_reward(&self, _block_number: u64) -> u64 {
-        self.block_reward
+    fn validators(&self, parent: &Header) -> Option<Vec<Public>> {
+        let hash = parent.hash();
+        Some((0..self.validators.count(&hash)).map(|index| self.validators.get(&hash, index)).collect())
+    }
+
+    fn block_reward(&self, block_number: u64) -> u64 {
+        self.block_reward.reward(block_number)
+    }
+
+    fn burned_fee(&self, _block_number: u64, block_fee: u64) -> u64 {
+        self.fee_burn.burned(block_fee)
     }
 
     fn recommended_confirmation(&self) -> u32 {
         1
     }
+
+    fn action_handlers(&self) -> &[Arc<ActionHandler>] {
+        &self.action_handlers
+    }
 }
 
 #[cfg(test)]
@@ -17,19 +17,28 @@
 use cjson;
 use ckey::Public;
 
+use super::super::reward::{FeeBurn, RewardSchedule};
+
 #[derive(Debug, PartialEq)]
 pub struct SimplePoAParams {
     /// Valid signatories.
     pub validators: Vec<Public>,
     /// base reward for a block.
-    pub block_reward: u64,
+    pub block_reward: RewardSchedule,
+    /// The portion of the block fee which is burned.
+    pub fee_burn: FeeBurn,
+    /// The validators vote to change the validators at the end of each epoch.
+    /// The validators are fixed if it's None.
+    pub epoch_length: Option<u64>,
 }
 
 impl From<cjson::scheme::SimplePoAParams> for SimplePoAParams {
     fn from(p: cjson::scheme::SimplePoAParams) -> Self {
         SimplePoAParams {
             validators: p.validators,
-            block_reward: p.block_reward.map_or_else(Default::default, Into::into),
+            block_reward: RewardSchedule::new(p.block_reward, p.reward_schedule),
+            fee_burn: FeeBurn::new(p.fee_burn_ratio),
+            epoch_length: p.epoch_length.map(Into::into).filter(|epoch_length: &u64| *epoch_length > 0),
         }
     }
 }
@@ -0,0 +1,149 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{btree_map, BTreeMap, BTreeSet};
+
+use ckey::{public_to_address, Address, Public};
+use cstate::{ActionDataKeyBuilder, ActionHandlerResult, TopLevelState, TopState, TopStateView};
+use primitives::H256;
+use rlp::{Encodable, RlpStream, UntrustedRlp};
+
+use super::actions::Action;
+use super::CUSTOM_ACTION_HANDLER_ID;
+
+lazy_static! {
+    pub static ref validators_key: H256 =
+        ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Validators").into_key();
+    pub static ref votes_key: H256 = ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Votes").into_key();
+}
+
+/// The validators of the blocks after the current epoch.
+#[derive(Debug, PartialEq)]
+pub struct Validators(Vec<Public>);
+
+impl Validators {
+    pub fn new(validators: Vec<Public>) -> Validators {
+        Validators(validators)
+    }
+
+    pub fn load_from_state(state: &TopLevelState) -> ActionHandlerResult<Validators> {
+        let action_data = state.action_data(&*validators_key)?;
+        let validators = match action_data {
+            Some(data) => UntrustedRlp::new(&data).as_list()?,
+            None => Vec::new(),
+        };
+        Ok(Validators(validators))
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> ActionHandlerResult<()> {
+        let mut rlp = RlpStream::new();
+        rlp.append_list(&self.0);
+        state.update_action_data(&*validators_key, rlp.drain().into_vec())?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn contains(&self, public: &Public) -> bool {
+        self.0.contains(public)
+    }
+
+    pub fn addresses(&self) -> BTreeSet<Address> {
+        self.0.iter().map(public_to_address).collect()
+    }
+
+    /// Returns whether the validator is added.
+    pub fn add(&mut self, public: Public) -> bool {
+        if self.contains(&public) {
+            return false
+        }
+        self.0.push(public);
+        true
+    }
+
+    /// Returns whether the validator is removed.
+    pub fn remove(&mut self, public: &Public) -> bool {
+        let len = self.0.len();
+        self.0.retain(|validator| validator != public);
+        self.0.len() != len
+    }
+}
+
+impl From<Validators> for Vec<Public> {
+    fn from(validators: Validators) -> Self {
+        validators.0
+    }
+}
+
+/// The voters of each vote cast in the current epoch.
+#[derive(Debug, Default, PartialEq)]
+pub struct Votes(BTreeMap<Action, BTreeSet<Address>>);
+
+impl Votes {
+    pub fn load_from_state(state: &TopLevelState) -> ActionHandlerResult<Votes> {
+        let action_data = state.action_data(&*votes_key)?;
+        let mut votes = BTreeMap::new();
+        if let Some(data) = action_data {
+            let rlp = UntrustedRlp::new(&data);
+            for i in 0..rlp.item_count()? {
+                let vote = rlp.at(i)?;
+                let voters: Vec<Address> = vote.list_at(1)?;
+                votes.insert(vote.val_at(0)?, voters.into_iter().collect());
+            }
+        }
+        Ok(Votes(votes))
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> ActionHandlerResult<()> {
+        state.update_action_data(&*votes_key, self.rlp_bytes().into_vec())?;
+        Ok(())
+    }
+
+    /// Returns whether the vote is newly cast by the voter.
+    pub fn insert(&mut self, vote: Action, voter: Address) -> bool {
+        self.0.entry(vote).or_insert_with(BTreeSet::new).insert(voter)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<Action, BTreeSet<Address>> {
+        self.0.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+impl Encodable for Votes {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(self.0.len());
+        for (vote, voters) in self.0.iter() {
+            s.begin_list(2).append(vote).begin_list(voters.len());
+            for voter in voters {
+                s.append(voter);
+            }
+        }
+    }
+}
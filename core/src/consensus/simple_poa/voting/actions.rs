@@ -0,0 +1,91 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::Public;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+const ACTION_TAG_ADD_VALIDATOR: u8 = 1;
+const ACTION_TAG_REMOVE_VALIDATOR: u8 = 2;
+
+/// A vote of a validator. The votes are also used as the keys of the tally.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Action {
+    AddValidator {
+        public: Public,
+    },
+    RemoveValidator {
+        public: Public,
+    },
+}
+
+impl Encodable for Action {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Action::AddValidator {
+                public,
+            } => s.begin_list(2).append(&ACTION_TAG_ADD_VALIDATOR).append(public),
+            Action::RemoveValidator {
+                public,
+            } => s.begin_list(2).append(&ACTION_TAG_REMOVE_VALIDATOR).append(public),
+        };
+    }
+}
+
+impl Decodable for Action {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let tag = rlp.val_at(0)?;
+        match tag {
+            ACTION_TAG_ADD_VALIDATOR => {
+                if rlp.item_count()? != 2 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::AddValidator {
+                    public: rlp.val_at(1)?,
+                })
+            }
+            ACTION_TAG_REMOVE_VALIDATOR => {
+                if rlp.item_count()? != 2 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::RemoveValidator {
+                    public: rlp.val_at(1)?,
+                })
+            }
+            _ => Err(DecoderError::Custom("Unexpected SimplePoA Voting Action Type")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_add_validator() {
+        rlp_encode_and_decode_test!(Action::AddValidator {
+            public: Public::random(),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_remove_validator() {
+        rlp_encode_and_decode_test!(Action::RemoveValidator {
+            public: Public::random(),
+        });
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod action_data;
+mod actions;
+
+use ckey::{Address, Public};
+use cstate::{ActionHandler, ActionHandlerResult, TopLevelState};
+use ctypes::invoice::Invoice;
+use ctypes::machine::Header;
+use ctypes::transaction::ParcelError;
+use rlp::UntrustedRlp;
+
+use self::action_data::{Validators, Votes};
+pub use self::actions::Action;
+
+const CUSTOM_ACTION_HANDLER_ID: u64 = 3;
+
+/// Changes the validators of SimplePoA by the votes of the current validators.
+/// The votes which the majority of the validators cast take effect from the next epoch.
+pub struct Voting {
+    genesis_validators: Vec<Public>,
+    epoch_length: u64,
+}
+
+impl Voting {
+    pub fn new(genesis_validators: Vec<Public>, epoch_length: u64) -> Self {
+        Self {
+            genesis_validators,
+            epoch_length,
+        }
+    }
+}
+
+impl ActionHandler for Voting {
+    fn name(&self) -> &'static str {
+        "voting"
+    }
+
+    fn handler_id(&self) -> u64 {
+        CUSTOM_ACTION_HANDLER_ID
+    }
+
+    fn init(&self, state: &mut TopLevelState) -> ActionHandlerResult<()> {
+        Validators::new(self.genesis_validators.clone()).save_to_state(state)
+    }
+
+    fn execute(&self, bytes: &[u8], state: &mut TopLevelState, sender: &Address) -> ActionHandlerResult<Invoice> {
+        let action = UntrustedRlp::new(bytes).as_val()?;
+        vote(state, sender, action)
+    }
+
+    fn verify(&self, bytes: &[u8]) -> ActionHandlerResult<()> {
+        UntrustedRlp::new(bytes).as_val::<Action>()?;
+        Ok(())
+    }
+
+    /// Applies the votes of the majority at the end of each epoch, and discards the others.
+    fn on_close_block(&self, state: &mut TopLevelState, header: &Header) -> ActionHandlerResult<()> {
+        if header.number() % self.epoch_length != 0 {
+            return Ok(())
+        }
+        let mut votes = Votes::load_from_state(state)?;
+        if votes.is_empty() {
+            return Ok(())
+        }
+        let mut validators = Validators::load_from_state(state)?;
+        let addresses = validators.addresses();
+        let num_of_validators = validators.len();
+        let passed: Vec<Action> = votes
+            .iter()
+            .filter(|(_, voters)| voters.intersection(&addresses).count() * 2 > num_of_validators)
+            .map(|(vote, _)| *vote)
+            .collect();
+        for vote in passed {
+            match vote {
+                Action::AddValidator {
+                    public,
+                } => {
+                    if validators.add(public) {
+                        cinfo!(
+                            ENGINE,
+                            "{:?} is added to the validators from the block {}",
+                            public,
+                            header.number() + 1
+                        );
+                    }
+                }
+                Action::RemoveValidator {
+                    public,
+                } => {
+                    // The last validator is never removed, which keeps the chain alive.
+                    if validators.len() == 1 && validators.contains(&public) {
+                        cwarn!(
+                            ENGINE,
+                            "The removal of {:?} is rejected because it is the last validator at the block {}",
+                            public,
+                            header.number()
+                        );
+                        continue
+                    }
+                    if validators.remove(&public) {
+                        cinfo!(
+                            ENGINE,
+                            "{:?} is removed from the validators from the block {}",
+                            public,
+                            header.number() + 1
+                        );
+                    }
+                }
+            }
+        }
+        votes.clear();
+        validators.save_to_state(state)?;
+        votes.save_to_state(state)
+    }
+}
+
+fn vote(state: &mut TopLevelState, sender: &Address, action: Action) -> ActionHandlerResult<Invoice> {
+    let validators = Validators::load_from_state(state)?;
+    if !validators.addresses().contains(sender) {
+        return Err(ParcelError::InvalidCustomAction(format!("{} is not a validator", sender)).into())
+    }
+    match action {
+        Action::AddValidator {
+            public,
+        } if validators.contains(&public) => {
+            return Err(ParcelError::InvalidCustomAction(format!("{:?} is already a validator", public)).into())
+        }
+        Action::RemoveValidator {
+            public,
+        } if !validators.contains(&public) => {
+            return Err(ParcelError::InvalidCustomAction(format!("{:?} is not a validator", public)).into())
+        }
+        _ => {}
+    }
+
+    let mut votes = Votes::load_from_state(state)?;
+    votes.insert(action, *sender);
+    votes.save_to_state(state)?;
+    Ok(Invoice::Success)
+}
+
+/// Returns the validators of the blocks after the given state.
+pub fn get_validators(state: &TopLevelState) -> ActionHandlerResult<Vec<Public>> {
+    Ok(Validators::load_from_state(state)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{public_to_address, Generator, Random};
+    use cstate::tests::helpers;
+    use rlp::Encodable;
+
+    use super::*;
+    use crate::header::Header as BlockHeader;
+
+    const EPOCH_LENGTH: u64 = 10;
+
+    fn random_publics(count: usize) -> Vec<Public> {
+        (0..count).map(|_| *Random.generate().unwrap().public()).collect()
+    }
+
+    fn init(validators: &[Public]) -> (Voting, TopLevelState) {
+        let voting = Voting::new(validators.to_vec(), EPOCH_LENGTH);
+        let mut state = helpers::get_temp_state();
+        voting.init(&mut state).unwrap();
+        (voting, state)
+    }
+
+    fn execute(voting: &Voting, state: &mut TopLevelState, voter: &Public, action: Action) -> bool {
+        voting.execute(&action.rlp_bytes(), state, &public_to_address(voter)).is_ok()
+    }
+
+    fn close_block(voting: &Voting, state: &mut TopLevelState, number: u64) {
+        let mut header = BlockHeader::default();
+        header.set_number(number);
+        voting.on_close_block(state, &header).unwrap();
+    }
+
+    #[test]
+    fn genesis_validators() {
+        let validators = random_publics(3);
+        let (_, state) = init(&validators);
+        assert_eq!(Ok(validators), get_validators(&state));
+    }
+
+    #[test]
+    fn majority_adds_a_validator_at_the_end_of_the_epoch() {
+        let validators = random_publics(3);
+        let (voting, mut state) = init(&validators);
+        let candidate = random_publics(1)[0];
+
+        assert!(execute(&voting, &mut state, &validators[0], Action::AddValidator {
+            public: candidate,
+        }));
+        assert!(execute(&voting, &mut state, &validators[1], Action::AddValidator {
+            public: candidate,
+        }));
+        close_block(&voting, &mut state, EPOCH_LENGTH - 1);
+        assert_eq!(Ok(validators.clone()), get_validators(&state));
+
+        close_block(&voting, &mut state, EPOCH_LENGTH);
+        let mut expected = validators.clone();
+        expected.push(candidate);
+        assert_eq!(Ok(expected), get_validators(&state));
+    }
+
+    #[test]
+    fn majority_removes_a_validator_at_the_end_of_the_epoch() {
+        let validators = random_publics(4);
+        let (voting, mut state) = init(&validators);
+
+        for voter in &validators[0..3] {
+            assert!(execute(&voting, &mut state, voter, Action::RemoveValidator {
+                public: validators[3],
+            }));
+        }
+        close_block(&voting, &mut state, EPOCH_LENGTH);
+        assert_eq!(Ok(validators[0..3].to_vec()), get_validators(&state));
+    }
+
+    #[test]
+    fn votes_without_majority_are_discarded_at_the_end_of_the_epoch() {
+        let validators = random_publics(4);
+        let (voting, mut state) = init(&validators);
+        let candidate = random_publics(1)[0];
+
+        for voter in &validators[0..2] {
+            assert!(execute(&voting, &mut state, voter, Action::AddValidator {
+                public: candidate,
+            }));
+        }
+        close_block(&voting, &mut state, EPOCH_LENGTH);
+        assert_eq!(Ok(validators.clone()), get_validators(&state));
+
+        // The votes of the last epoch are not counted.
+        assert!(execute(&voting, &mut state, &validators[2], Action::AddValidator {
+            public: candidate,
+        }));
+        close_block(&voting, &mut state, 2 * EPOCH_LENGTH);
+        assert_eq!(Ok(validators), get_validators(&state));
+    }
+
+    #[test]
+    fn only_validators_can_vote() {
+        let validators = random_publics(1);
+        let (voting, mut state) = init(&validators);
+        let outsider = random_publics(1)[0];
+
+        assert!(!execute(&voting, &mut state, &outsider, Action::AddValidator {
+            public: outsider,
+        }));
+        assert!(!execute(&voting, &mut state, &validators[0], Action::AddValidator {
+            public: validators[0],
+        }));
+        assert!(!execute(&voting, &mut state, &validators[0], Action::RemoveValidator {
+            public: outsider,
+        }));
+    }
+
+    #[test]
+    fn the_last_validator_is_not_removed() {
+        let validators = random_publics(1);
+        let (voting, mut state) = init(&validators);
+
+        assert!(execute(&voting, &mut state, &validators[0], Action::RemoveValidator {
+            public: validators[0],
+        }));
+        close_block(&voting, &mut state, EPOCH_LENGTH);
+        assert_eq!(Ok(validators), get_validators(&state));
+    }
+
+    #[test]
+    fn the_removals_which_empty_the_validators_are_rejected() {
+        let validators = random_publics(2);
+        let (voting, mut state) = init(&validators);
+
+        for voter in &validators {
+            for removed in &validators {
+                assert!(execute(&voting, &mut state, voter, Action::RemoveValidator {
+                    public: *removed,
+                }));
+            }
+        }
+        close_block(&voting, &mut state, EPOCH_LENGTH);
+        let remaining = get_validators(&state).unwrap();
+        assert_eq!(1, remaining.len());
+        assert!(validators.contains(&remaining[0]));
+
+        // The remaining validator cannot remove itself either.
+        assert!(execute(&voting, &mut state, &remaining[0], Action::RemoveValidator {
+            public: remaining[0],
+        }));
+        close_block(&voting, &mut state, 2 * EPOCH_LENGTH);
+        assert_eq!(Ok(remaining), get_validators(&state));
+    }
+}
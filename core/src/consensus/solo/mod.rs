@@ -23,6 +23,7 @@ use ctypes::machine::{Header, LiveBlock, Transactions, WithBalances};
 
 use self::params::SoloParams;
 use super::{ConsensusEngine, Seal};
+use crate::codechain_machine::WithActionHandlers;
 use crate::consensus::EngineType;
 use crate::SignedTransaction;
 
@@ -33,10 +34,10 @@ pub struct Solo<M> {
     action_handlers: Vec<Arc<ActionHandler>>,
 }
 
-impl<M> Solo<M> {
+impl<M: WithActionHandlers> Solo<M> {
     /// Returns new instance of Solo over the given state machine.
     pub fn new(params: SoloParams, machine: M) -> Self {
-        let mut action_handlers: Vec<Arc<ActionHandler>> = Vec::new();
+        let mut action_handlers: Vec<Arc<ActionHandler>> = machine.action_handlers().to_vec();
         if params.enable_hit_handler {
             action_handlers.push(Arc::new(HitHandler::new()));
         }
@@ -49,7 +50,7 @@ impl<M> Solo<M> {
     }
 }
 
-impl<M: WithBalances> ConsensusEngine<M> for Solo<M>
+impl<M: WithBalances + WithActionHandlers> ConsensusEngine<M> for Solo<M>
 where
     M::LiveBlock: Transactions<Transaction = SignedTransaction>,
 {
@@ -79,13 +80,22 @@ where
 
     fn on_close_block(&self, block: &mut M::LiveBlock) -> Result<(), M::Error> {
         let author = *LiveBlock::header(&*block).author();
-        let total_reward = self.block_reward(block.header().number())
-            + self.block_fee(Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
-        self.machine.add_balance(block, &author, total_reward)
+        let block_number = block.header().number();
+        let block_fee =
+            self.block_fee(block_number, Box::new(block.transactions().to_owned().into_iter().map(Into::into)));
+        let burned_fee = self.burned_fee(block_number, block_fee);
+        let total_reward = self.block_reward(block_number) + block_fee - burned_fee;
+        self.machine.add_balance(block, &author, total_reward)?;
+        self.machine.burn_fee(block, burned_fee)?;
+        self.machine.close_action_handlers(block, &self.action_handlers)
     }
 
-    fn block_reward(&self, _block_number: u64) -> u64 {
-        self.params.block_reward
+    fn block_reward(&self, block_number: u64) -> u64 {
+        self.params.block_reward.reward(block_number)
+    }
+
+    fn burned_fee(&self, _block_number: u64, block_fee: u64) -> u64 {
+        self.params.fee_burn.burned(block_fee)
     }
 
     fn recommended_confirmation(&self) -> u32 {
@@ -99,12 +109,20 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use ckey::Address;
+    use cstate::{ActionHandler, TopStateView};
     use primitives::H520;
 
+    use super::params::SoloParams;
+    use super::Solo;
     use crate::block::{IsBlock, OpenBlock};
+    use crate::codechain_machine::CodeChainMachine;
+    use crate::consensus::CodeChainEngine;
     use crate::header::Header;
     use crate::scheme::Scheme;
-    use crate::tests::helpers::get_temp_state_db;
+    use crate::tests::helpers::{get_temp_state_db, TestActionHandler};
 
     #[test]
     fn seal() {
@@ -132,4 +150,25 @@ mod tests {
 
         assert!(engine.verify_block_unordered(&header).is_ok());
     }
+
+    #[test]
+    fn action_handlers_are_closed_with_the_block() {
+        let scheme = Scheme::new_test_solo();
+        let address = Address::random();
+        let handler: Arc<ActionHandler> = Arc::new(TestActionHandler {
+            handler_id: 7,
+            closing_address: Some(address),
+        });
+        let machine = CodeChainMachine::new(scheme.params().clone(), vec![handler]);
+        let solo = Solo::new(SoloParams::default(), machine);
+        let engine: &CodeChainEngine = &solo;
+
+        let db = scheme.ensure_genesis_state(get_temp_state_db()).unwrap();
+        let genesis_header = scheme.genesis_header();
+        let b = OpenBlock::try_new(engine, db, &genesis_header, Default::default(), vec![], false).unwrap();
+        assert_eq!(Ok(0), b.block().state().balance(&address));
+
+        let b = b.close(*genesis_header.transactions_root(), *genesis_header.invoices_root()).unwrap();
+        assert_eq!(Ok(1), b.block().state().balance(&address));
+    }
 }
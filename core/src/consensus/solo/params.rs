@@ -16,18 +16,23 @@
 
 use cjson;
 
+use super::super::reward::{FeeBurn, RewardSchedule};
+
 /// Params for a null engine.
 #[derive(Clone, Default)]
 pub struct SoloParams {
     /// base reward for a block.
-    pub block_reward: u64,
+    pub block_reward: RewardSchedule,
+    /// The portion of the block fee which is burned.
+    pub fee_burn: FeeBurn,
     pub enable_hit_handler: bool,
 }
 
 impl From<cjson::scheme::SoloParams> for SoloParams {
     fn from(p: cjson::scheme::SoloParams) -> Self {
         SoloParams {
-            block_reward: p.block_reward.map_or_else(Default::default, Into::into),
+            block_reward: RewardSchedule::new(p.block_reward, p.reward_schedule),
+            fee_burn: FeeBurn::new(p.fee_burn_ratio),
             enable_hit_handler: p.action_handlers.hit.is_some(),
         }
     }
@@ -0,0 +1,498 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+//! Aggregation of the precommits which commit a block into a single Schnorr signature.
+//!
+//! The proposer of the next height requests the aggregation of the precommits it collected. Each signer of the
+//! precommits makes a random nonce for the round and broadcasts the commitment to its public part. Once a signer
+//! knows the commitments of all the signers, it reveals its public nonce. Once it knows the nonces of all the
+//! signers, which must match their commitments, it signs its precommit again as a part of the aggregated signature
+//! and broadcasts it. The proposer seals its block with the signer bitset and the signature combined from the parts.
+//! If the parts don't arrive in time, the proposer seals its block with the signatures of the precommits.
+//!
+//! Since the nonces are committed before any of them is revealed, no signer can choose its nonce after seeing
+//! the others. A signer joins only a round of its current height and view, at most one round in a view,
+//! and uses a nonce only once, so the same nonce is never combined with the different nonces of the others.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use ccrypto::blake256;
+use ckey::{aggregate_schnorr_publics, public_to_address, verify_schnorr, Private, Public, SchnorrSignature};
+use primitives::H256;
+use rlp::{Encodable, RlpStream};
+
+use super::super::EngineError;
+use super::message::VoteStep;
+use super::types::{BitSet, Height, View};
+use crate::error::{BlockError, Error};
+
+/// Identifies an aggregation of the precommits which commit a block.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, RlpDecodable, RlpEncodable)]
+pub struct AggregationRound {
+    /// The step of the precommits.
+    pub precommit_step: VoteStep,
+    /// The committed block.
+    pub block_hash: H256,
+    /// The view of the next height in which the proposer requested the aggregation.
+    pub proposer_view: View,
+    /// The validators whose precommits are aggregated.
+    pub signers: BitSet,
+}
+
+impl AggregationRound {
+    /// The hash signed by the proposer which requests the aggregation.
+    pub fn hash(&self) -> H256 {
+        blake256(self.rlp_bytes())
+    }
+
+    /// The hash signed by a signer which announces the commitment to its public nonce.
+    pub fn commitment_hash(&self, commitment: &H256) -> H256 {
+        let mut s = RlpStream::new_list(2);
+        s.append(self).append(commitment);
+        blake256(s.out())
+    }
+
+    /// The hash signed by a signer which reveals its public nonce.
+    pub fn nonce_hash(&self, nonce: &Public) -> H256 {
+        let mut s = RlpStream::new_list(2);
+        s.append(self).append(nonce);
+        blake256(s.out())
+    }
+
+    /// The hash signed by a signer which sends its part of the aggregated signature.
+    pub fn partial_hash(&self, partial: &SchnorrSignature) -> H256 {
+        let mut s = RlpStream::new_list(2);
+        s.append(self).append(partial);
+        blake256(s.out())
+    }
+}
+
+/// The commitment to a public nonce, which is revealed after the commitments of all the signers are known.
+pub fn nonce_commitment(nonce: &Public) -> H256 {
+    blake256(nonce)
+}
+
+/// Verifies the precommits of a seal against the public keys of the signers.
+/// The precommits are either the signatures of all the signers in their order or a single aggregated signature.
+/// The aggregated signature is accepted only if `allows_aggregated` is true.
+/// The signature of a single signer is also its aggregated signature, so both forms give the same verdict.
+pub fn verify_precommits(
+    signers: &[Public],
+    precommits: &[SchnorrSignature],
+    precommit_hash: &H256,
+    allows_aggregated: bool,
+) -> Result<(), Error> {
+    if precommits.len() == signers.len() {
+        for (public, signature) in signers.iter().zip(precommits) {
+            if !verify_schnorr(public, signature, precommit_hash)? {
+                return Err(EngineError::BlockNotAuthorized(public_to_address(public)).into())
+            }
+        }
+        return Ok(())
+    }
+    if !allows_aggregated || precommits.len() != 1 {
+        return Err(BlockError::InvalidSeal.into())
+    }
+    let aggregated = aggregate_schnorr_publics(signers)?;
+    if !verify_schnorr(&aggregated, &precommits[0], precommit_hash)? {
+        return Err(BlockError::InvalidSeal.into())
+    }
+    Ok(())
+}
+
+/// Signs the message with all the keys and combines the parts into the aggregated signature.
+#[cfg(test)]
+pub fn sign_aggregated(key_pairs: &[::ckey::KeyPair], message: &H256) -> SchnorrSignature {
+    use ckey::{combine_publics, combine_schnorr_signatures, sign_schnorr_aggregated_partial, Generator, Random};
+
+    let publics: Vec<_> = key_pairs.iter().map(|key_pair| *key_pair.public()).collect();
+    let nonces: Vec<_> = key_pairs.iter().map(|_| Random.generate().unwrap()).collect();
+    let partials: Vec<_> = key_pairs
+        .iter()
+        .enumerate()
+        .map(|(i, key_pair)| {
+            let other_nonces: Vec<_> =
+                nonces.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, nonce)| *nonce.public()).collect();
+            let other_nonces = combine_publics(&other_nonces).unwrap();
+            sign_schnorr_aggregated_partial(key_pair.private(), &publics, nonces[i].private(), &other_nonces, message)
+                .unwrap()
+        })
+        .collect();
+    combine_schnorr_signatures(&partials).unwrap()
+}
+
+#[derive(Default)]
+struct Session {
+    is_requested: bool,
+    commitments: BTreeMap<usize, H256>,
+    nonces: BTreeMap<usize, Public>,
+    partials: BTreeMap<usize, SchnorrSignature>,
+    /// The index and the secret nonce of this node. It's taken when this node signs its part.
+    secret_nonce: Option<(usize, Private)>,
+    /// The public nonce of this node. It's taken when this node reveals it.
+    public_nonce: Option<Public>,
+}
+
+impl Session {
+    /// Whether the nonces of all the signers are revealed and match their commitments.
+    fn has_all_nonces(&self, num_signers: usize) -> bool {
+        self.nonces.len() == num_signers
+            && self.commitments.len() == num_signers
+            && self.nonces.iter().all(|(index, nonce)| self.commitments.get(index) == Some(&nonce_commitment(nonce)))
+    }
+}
+
+/// The aggregation rounds seen by this node.
+#[derive(Default)]
+pub struct Aggregations {
+    rounds: HashMap<AggregationRound, Session>,
+    /// The (height, view) of the rounds this node joined as a signer.
+    joined: HashSet<(Height, View)>,
+    /// The (height, view) in which the proposer gave up the aggregation.
+    timed_out: HashSet<(Height, View)>,
+    /// The signers and the aggregated signature of the committed blocks.
+    completed: HashMap<(VoteStep, H256), (BitSet, SchnorrSignature)>,
+}
+
+impl Aggregations {
+    /// Marks the round requested. Returns false if it's already requested.
+    pub fn request(&mut self, round: AggregationRound) -> bool {
+        let session = self.rounds.entry(round).or_default();
+        let is_new = !session.is_requested;
+        session.is_requested = true;
+        is_new
+    }
+
+    pub fn is_requested(&self, round: &AggregationRound) -> bool {
+        self.rounds.get(round).map_or(false, |session| session.is_requested)
+    }
+
+    /// Joins the round as a signer.
+    /// Returns false if this node already joined a round requested in the same view.
+    pub fn join(
+        &mut self,
+        round: AggregationRound,
+        signer_index: usize,
+        secret_nonce: Private,
+        public_nonce: Public,
+    ) -> bool {
+        assert!(round.signers.is_set(signer_index));
+        if !self.joined.insert((round.precommit_step.height + 1, round.proposer_view)) {
+            return false
+        }
+        let session = self.rounds.entry(round).or_default();
+        session.secret_nonce = Some((signer_index, secret_nonce));
+        session.public_nonce = Some(public_nonce);
+        session.commitments.insert(signer_index, nonce_commitment(&public_nonce));
+        true
+    }
+
+    /// Returns false if the commitment is already known or the sender is not a signer of the round.
+    pub fn add_commitment(&mut self, round: AggregationRound, signer_index: usize, commitment: H256) -> bool {
+        if !round.signers.is_set(signer_index) {
+            return false
+        }
+        let session = self.rounds.entry(round).or_default();
+        if session.commitments.contains_key(&signer_index) {
+            return false
+        }
+        session.commitments.insert(signer_index, commitment);
+        true
+    }
+
+    /// Takes the index and the public nonce of this node to reveal them
+    /// once all the signers sent their commitments. It returns them only once in a round.
+    pub fn take_nonce_to_reveal(&mut self, round: &AggregationRound) -> Option<(usize, Public)> {
+        let session = self.rounds.get_mut(round)?;
+        if session.commitments.len() != round.signers.indices().len() {
+            return None
+        }
+        let signer_index = session.secret_nonce.as_ref()?.0;
+        let public_nonce = session.public_nonce.take()?;
+        session.nonces.insert(signer_index, public_nonce);
+        Some((signer_index, public_nonce))
+    }
+
+    /// Returns false if the nonce is already known, the sender is not a signer of the round,
+    /// or the nonce doesn't match the commitment of the sender.
+    /// A nonce which arrives before its commitment is checked when the nonces are taken.
+    pub fn add_nonce(&mut self, round: AggregationRound, signer_index: usize, nonce: Public) -> bool {
+        if !round.signers.is_set(signer_index) {
+            return false
+        }
+        let session = self.rounds.entry(round).or_default();
+        if session.nonces.contains_key(&signer_index) {
+            return false
+        }
+        match session.commitments.get(&signer_index) {
+            Some(commitment) if *commitment != nonce_commitment(&nonce) => return false,
+            _ => {}
+        }
+        session.nonces.insert(signer_index, nonce);
+        true
+    }
+
+    /// Takes the index and the secret nonce of this node with the public nonces of the other signers
+    /// once all the signers revealed their committed nonces. It returns them only once in a round.
+    pub fn take_nonces(&mut self, round: &AggregationRound) -> Option<(usize, Private, Vec<Public>)> {
+        let session = self.rounds.get_mut(round)?;
+        if !session.has_all_nonces(round.signers.indices().len()) {
+            return None
+        }
+        let (signer_index, secret_nonce) = session.secret_nonce.take()?;
+        let other_nonces =
+            session.nonces.iter().filter(|(index, _)| **index != signer_index).map(|(_, nonce)| *nonce).collect();
+        Some((signer_index, secret_nonce, other_nonces))
+    }
+
+    /// Returns false if the part is already known or the sender is not a signer of the round.
+    pub fn add_partial(&mut self, round: AggregationRound, signer_index: usize, partial: SchnorrSignature) -> bool {
+        if !round.signers.is_set(signer_index) {
+            return false
+        }
+        let session = self.rounds.entry(round).or_default();
+        if session.partials.contains_key(&signer_index) {
+            return false
+        }
+        session.partials.insert(signer_index, partial);
+        true
+    }
+
+    /// Returns the parts of all the signers in the order of their indices once all of them are received.
+    pub fn partials(&self, round: &AggregationRound) -> Option<Vec<SchnorrSignature>> {
+        let session = self.rounds.get(round)?;
+        if session.partials.len() != round.signers.indices().len() {
+            return None
+        }
+        Some(session.partials.values().cloned().collect())
+    }
+
+    /// Gives up the aggregations requested in the view. Returns false if they're already given up.
+    pub fn time_out(&mut self, height: Height, view: View) -> bool {
+        self.timed_out.insert((height, view))
+    }
+
+    pub fn is_timed_out(&self, height: Height, view: View) -> bool {
+        self.timed_out.contains(&(height, view))
+    }
+
+    pub fn complete(&mut self, round: &AggregationRound, signature: SchnorrSignature) {
+        self.completed.insert((round.precommit_step, round.block_hash), (round.signers, signature));
+    }
+
+    /// The signers and the aggregated signature of the precommits on the block.
+    pub fn aggregated(&self, precommit_step: &VoteStep, block_hash: &H256) -> Option<(BitSet, SchnorrSignature)> {
+        self.completed.get(&(*precommit_step, *block_hash)).cloned()
+    }
+
+    /// Throws out the rounds of the blocks committed below the height.
+    pub fn throw_out_old(&mut self, height: Height) {
+        self.rounds.retain(|round, _| round.precommit_step.height >= height);
+        self.joined.retain(|(next_height, _)| *next_height > height);
+        self.timed_out.retain(|(next_height, _)| *next_height > height);
+        self.completed.retain(|(precommit_step, _), _| precommit_step.height >= height);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{combine_publics, combine_schnorr_signatures, sign_schnorr, Generator, Random};
+
+    use super::super::types::Step;
+    use super::*;
+
+    fn round(signers: &[usize], proposer_view: View) -> AggregationRound {
+        AggregationRound {
+            precommit_step: VoteStep::new(3, 0, Step::Precommit),
+            block_hash: H256::random(),
+            proposer_view,
+            signers: BitSet::new_with_indices(signers),
+        }
+    }
+
+    #[test]
+    fn aggregated_precommits_are_equivalent_to_the_signatures() {
+        let key_pairs: Vec<_> = (0..4).map(|_| Random.generate().unwrap()).collect();
+        let hash = H256::random();
+        let other_hash = H256::random();
+        for subset in 1..16usize {
+            let signers: Vec<_> =
+                key_pairs.iter().enumerate().filter(|(i, _)| subset & (1 << i) != 0).map(|(_, k)| k.clone()).collect();
+            let publics: Vec<_> = signers.iter().map(|key_pair| *key_pair.public()).collect();
+            let signatures: Vec<_> =
+                signers.iter().map(|key_pair| sign_schnorr(key_pair.private(), &hash).unwrap()).collect();
+            let aggregated = sign_aggregated(&signers, &hash);
+
+            assert!(verify_precommits(&publics, &signatures, &hash, true).is_ok());
+            assert!(verify_precommits(&publics, &[aggregated], &hash, true).is_ok());
+            // The aggregated signature isn't accepted before the activation.
+            assert_eq!(publics.len() == 1, verify_precommits(&publics, &[aggregated], &hash, false).is_ok());
+
+            // Both forms reject the precommits on another message.
+            assert!(verify_precommits(&publics, &signatures, &other_hash, true).is_err());
+            assert!(verify_precommits(&publics, &[aggregated], &other_hash, true).is_err());
+
+            // Both forms reject the precommits if a signer is replaced or missing.
+            let outsider = Random.generate().unwrap();
+            let mut replaced = publics.clone();
+            replaced[0] = *outsider.public();
+            assert!(verify_precommits(&replaced, &signatures, &hash, true).is_err());
+            assert!(verify_precommits(&replaced, &[aggregated], &hash, true).is_err());
+            if publics.len() > 1 {
+                assert!(verify_precommits(&publics[1..], &signatures[1..], &hash, true).is_ok());
+                assert!(verify_precommits(&publics[1..], &[aggregated], &hash, true).is_err());
+                assert!(verify_precommits(&publics, &signatures[1..], &hash, true).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn aggregated_precommits_are_not_the_sum_of_the_keys() {
+        let key_pairs: Vec<_> = (0..2).map(|_| Random.generate().unwrap()).collect();
+        let publics: Vec<_> = key_pairs.iter().map(|key_pair| *key_pair.public()).collect();
+        let hash = H256::random();
+        let partials: Vec<_> = {
+            let nonces: Vec<_> = key_pairs.iter().map(|_| Random.generate().unwrap()).collect();
+            key_pairs
+                .iter()
+                .zip(&nonces)
+                .zip(nonces.iter().rev())
+                .map(|((key_pair, nonce), other)| {
+                    ::ckey::sign_schnorr_partial(key_pair.private(), nonce.private(), other.public(), &hash).unwrap()
+                })
+                .collect()
+        };
+        let summed = combine_schnorr_signatures(&partials).unwrap();
+        assert!(verify_schnorr(&combine_publics(&publics).unwrap(), &summed, &hash).unwrap());
+        assert!(verify_precommits(&publics, &[summed], &hash, true).is_err());
+    }
+
+    #[test]
+    fn join_one_round_in_a_view() {
+        let mut aggregations = Aggregations::default();
+        let first = round(&[0, 1], 0);
+        let second = round(&[0, 2], 0);
+        let next_view = round(&[0, 2], 1);
+        let nonce = Random.generate().unwrap();
+        assert!(aggregations.join(first, 0, *nonce.private(), *nonce.public()));
+        assert!(!aggregations.join(second, 0, *nonce.private(), *nonce.public()));
+        assert!(aggregations.join(next_view, 0, *nonce.private(), *nonce.public()));
+    }
+
+    #[test]
+    fn reveal_the_nonce_once_all_the_commitments_are_received() {
+        let mut aggregations = Aggregations::default();
+        let round = round(&[0, 1, 3], 0);
+        let nonces: Vec<_> = (0..4).map(|_| Random.generate().unwrap()).collect();
+        assert!(aggregations.join(round, 1, *nonces[1].private(), *nonces[1].public()));
+        assert_eq!(None, aggregations.take_nonce_to_reveal(&round));
+
+        assert!(aggregations.add_commitment(round, 0, nonce_commitment(nonces[0].public())));
+        assert!(!aggregations.add_commitment(round, 0, nonce_commitment(nonces[0].public())));
+        assert!(!aggregations.add_commitment(round, 2, nonce_commitment(nonces[2].public())));
+        assert_eq!(None, aggregations.take_nonce_to_reveal(&round));
+
+        assert!(aggregations.add_commitment(round, 3, nonce_commitment(nonces[3].public())));
+        assert_eq!(Some((1, *nonces[1].public())), aggregations.take_nonce_to_reveal(&round));
+        assert_eq!(None, aggregations.take_nonce_to_reveal(&round));
+    }
+
+    #[test]
+    fn nonces_must_match_the_commitments() {
+        let mut aggregations = Aggregations::default();
+        let round = round(&[0, 1], 0);
+        let nonces: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+        assert!(aggregations.join(round, 1, *nonces[1].private(), *nonces[1].public()));
+        assert!(aggregations.add_commitment(round, 0, nonce_commitment(nonces[0].public())));
+        assert!(aggregations.take_nonce_to_reveal(&round).is_some());
+
+        assert!(!aggregations.add_nonce(round, 0, *nonces[2].public()));
+        assert_eq!(None, aggregations.take_nonces(&round));
+        assert!(aggregations.add_nonce(round, 0, *nonces[0].public()));
+        assert!(aggregations.take_nonces(&round).is_some());
+
+        // A nonce which arrived before its commitment is checked when the nonces are taken.
+        let other_round = self::round(&[0, 1], 1);
+        assert!(aggregations.join(other_round, 1, *nonces[1].private(), *nonces[1].public()));
+        assert!(aggregations.add_nonce(other_round, 0, *nonces[2].public()));
+        assert!(aggregations.add_commitment(other_round, 0, nonce_commitment(nonces[0].public())));
+        assert!(aggregations.take_nonce_to_reveal(&other_round).is_some());
+        assert_eq!(None, aggregations.take_nonces(&other_round));
+    }
+
+    #[test]
+    fn take_nonces_once_all_the_nonces_are_received() {
+        let mut aggregations = Aggregations::default();
+        let round = round(&[0, 1, 3], 0);
+        let nonces: Vec<_> = (0..4).map(|_| Random.generate().unwrap()).collect();
+        assert!(aggregations.join(round, 1, *nonces[1].private(), *nonces[1].public()));
+        for index in &[0, 3] {
+            assert!(aggregations.add_commitment(round, *index, nonce_commitment(nonces[*index].public())));
+        }
+        assert!(aggregations.take_nonce_to_reveal(&round).is_some());
+        assert_eq!(None, aggregations.take_nonces(&round));
+
+        assert!(aggregations.add_nonce(round, 0, *nonces[0].public()));
+        assert!(!aggregations.add_nonce(round, 0, *nonces[0].public()));
+        assert!(!aggregations.add_nonce(round, 2, *nonces[2].public()));
+        assert_eq!(None, aggregations.take_nonces(&round));
+
+        assert!(aggregations.add_nonce(round, 3, *nonces[3].public()));
+        assert_eq!(
+            Some((1, *nonces[1].private(), vec![*nonces[0].public(), *nonces[3].public()])),
+            aggregations.take_nonces(&round)
+        );
+        assert_eq!(None, aggregations.take_nonces(&round));
+    }
+
+    #[test]
+    fn partials_are_returned_in_the_order_of_the_signers() {
+        let mut aggregations = Aggregations::default();
+        let round = round(&[0, 2], 0);
+        let first = SchnorrSignature::random();
+        let second = SchnorrSignature::random();
+        assert!(aggregations.add_partial(round, 2, second));
+        assert!(!aggregations.add_partial(round, 2, second));
+        assert!(!aggregations.add_partial(round, 1, first));
+        assert_eq!(None, aggregations.partials(&round));
+        assert!(aggregations.add_partial(round, 0, first));
+        assert_eq!(Some(vec![first, second]), aggregations.partials(&round));
+    }
+
+    #[test]
+    fn throw_out_old_rounds() {
+        let mut aggregations = Aggregations::default();
+        let round = round(&[0], 0);
+        let nonce = Random.generate().unwrap();
+        assert!(aggregations.request(round));
+        assert!(!aggregations.request(round));
+        assert!(aggregations.join(round, 0, *nonce.private(), *nonce.public()));
+        aggregations.complete(&round, SchnorrSignature::random());
+        let next_height = round.precommit_step.height + 1;
+        assert!(aggregations.time_out(next_height, 0));
+        assert!(!aggregations.time_out(next_height, 0));
+
+        aggregations.throw_out_old(round.precommit_step.height);
+        assert!(aggregations.is_requested(&round));
+        assert!(aggregations.aggregated(&round.precommit_step, &round.block_hash).is_some());
+        assert!(aggregations.is_timed_out(next_height, 0));
+
+        aggregations.throw_out_old(round.precommit_step.height + 1);
+        assert!(!aggregations.is_requested(&round));
+        assert!(!aggregations.is_timed_out(next_height, 0));
+        assert_eq!(None, aggregations.aggregated(&round.precommit_step, &round.block_hash));
+        assert!(aggregations.join(round, 0, *nonce.private(), *nonce.public()));
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::{Public, SchnorrSignature};
+use ctypes::util::unexpected::{Mismatch, OutOfBounds};
+use ctypes::BlockNumber;
+use primitives::Bytes;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use super::super::EngineError;
+use super::aggregation::verify_precommits;
+use super::message::{message_hash, VoteStep};
+use super::stake;
+use super::types::{BitSet, Height, Step, View};
+use crate::error::{BlockError, Error};
+use crate::header::Header;
+
+/// The proof that a block is committed by Tendermint.
+/// The precommits of a block are stored in the seal of its child,
+/// so the certificate gathers them with the validators which signed them.
+/// It also proves the validators of the next block against the state root of the committed block.
+/// It can be verified without the database.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommitCertificate {
+    /// The committed block.
+    pub header: Header,
+    /// The validators of the committed block with their voting power.
+    pub validators: Vec<(Public, u64)>,
+    /// The view in which the block is committed.
+    pub view: View,
+    /// The indices of the validators which signed the precommits.
+    pub precommit_bitset: BitSet,
+    /// The precommit signatures in the order of the indices, or their aggregated signature.
+    pub precommits: Vec<SchnorrSignature>,
+    /// The nodes of the state trie proving the validators elected in the committed block.
+    pub next_validators_proof: Vec<Bytes>,
+}
+
+impl CommitCertificate {
+    /// Checks that the validators holding more than 2/3 of the voting power precommitted the block.
+    /// The precommits may be aggregated if the child of the block, which has them in its seal,
+    /// is after `aggregated_seal_activation`.
+    pub fn verify(&self, aggregated_seal_activation: Option<BlockNumber>) -> Result<(), Error> {
+        let allows_aggregated =
+            aggregated_seal_activation.map_or(false, |activation| self.header.number() + 1 >= activation);
+        let signers = self.signers(allows_aggregated)?;
+        check_above_threshold(&self.validators, &signers)
+    }
+
+    /// Returns the validators of the next block, which are proved against the state root of the committed block.
+    pub fn next_validators(&self) -> Result<Vec<(Public, u64)>, Error> {
+        let elected = stake::verify_validators_proof(self.header.state_root(), &self.next_validators_proof)?;
+        if elected.is_empty() {
+            // Nobody has been elected yet, so the validators don't change.
+            return Ok(self.validators.clone())
+        }
+        Ok(elected)
+    }
+
+    /// Returns the validators which signed the precommits.
+    fn signers(&self, allows_aggregated: bool) -> Result<Vec<Public>, Error> {
+        let step = VoteStep::new(self.header.number() as Height, self.view, Step::Precommit);
+        let precommit_hash = message_hash(step, self.header.hash());
+
+        let indices = self.precommit_bitset.indices();
+        // Every bit must point a validator.
+        if indices.iter().any(|index| *index >= self.validators.len()) {
+            return Err(BlockError::InvalidSeal.into())
+        }
+        let signers: Vec<Public> = indices.into_iter().map(|index| self.validators[index].0).collect();
+        verify_precommits(&signers, &self.precommits, &precommit_hash, allows_aggregated)?;
+        Ok(signers)
+    }
+}
+
+impl Encodable for CommitCertificate {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(6);
+        s.append(&self.header);
+        s.begin_list(self.validators.len());
+        for (public, weight) in self.validators.iter() {
+            s.begin_list(2).append(public).append(weight);
+        }
+        s.append(&self.view);
+        s.append(&self.precommit_bitset);
+        s.append_list(&self.precommits);
+        s.append_list(&self.next_validators_proof);
+    }
+}
+
+impl Decodable for CommitCertificate {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 6 {
+            return Err(DecoderError::RlpIncorrectListLen)
+        }
+        let mut validators = Vec::new();
+        for validator in rlp.at(1)?.iter() {
+            validators.push((validator.val_at(0)?, validator.val_at(1)?));
+        }
+        Ok(CommitCertificate {
+            header: rlp.val_at(0)?,
+            validators,
+            view: rlp.val_at(2)?,
+            precommit_bitset: rlp.val_at(3)?,
+            precommits: rlp.list_at(4)?,
+            next_validators_proof: rlp.list_at(5)?,
+        })
+    }
+}
+
+/// Verifies the certificates in the ascending order of the block numbers, beginning with the validators of the first
+/// block. Each certificate must be signed by the trusted validators, and the validators of the next block proved by it
+/// are trusted after it. So the certificates must include every block whose child has new validators.
+/// Returns the validators of the block after the last certificate.
+pub fn verify_certificate_chain(
+    trusted_validators: Vec<(Public, u64)>,
+    certificates: &[CommitCertificate],
+    aggregated_seal_activation: Option<BlockNumber>,
+) -> Result<Vec<(Public, u64)>, Error> {
+    let mut trusted_validators = trusted_validators;
+    let mut last_number = None;
+    for certificate in certificates {
+        let number = certificate.header.number();
+        if let Some(last_number) = last_number {
+            if number <= last_number {
+                return Err(BlockError::InvalidNumber(Mismatch {
+                    expected: last_number + 1,
+                    found: number,
+                })
+                .into())
+            }
+        }
+        if certificate.validators != trusted_validators {
+            return Err(EngineError::UntrustedValidators(number).into())
+        }
+        certificate.verify(aggregated_seal_activation)?;
+        trusted_validators = certificate.next_validators()?;
+        last_number = Some(number);
+    }
+    Ok(trusted_validators)
+}
+
+fn check_above_threshold(validators: &[(Public, u64)], signers: &[Public]) -> Result<(), Error> {
+    let total_power: u64 = validators.iter().map(|(_, weight)| weight).sum();
+    let power: u64 = validators.iter().filter(|(public, _)| signers.contains(public)).map(|(_, weight)| weight).sum();
+    let threshold = total_power * 2 / 3;
+    if power > threshold {
+        Ok(())
+    } else {
+        Err(EngineError::InsufficientVotingPower(OutOfBounds {
+            min: Some(threshold + 1),
+            max: None,
+            found: power,
+        })
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ccrypto::blake256;
+    use ckey::{sign_schnorr, Generator, KeyPair, Random};
+    use cstate::tests::helpers;
+    use cstate::StateWithCache;
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::super::aggregation::sign_aggregated;
+    use super::super::message::message_info_rlp;
+    use super::*;
+
+    fn certificate(number: u64, validators: &[(KeyPair, u64)], signers: &[usize]) -> CommitCertificate {
+        certificate_electing(number, validators, signers, &[])
+    }
+
+    /// Makes the certificate of the block in which the next validators are elected.
+    fn certificate_electing(
+        number: u64,
+        validators: &[(KeyPair, u64)],
+        signers: &[usize],
+        next_validators: &[(KeyPair, u64)],
+    ) -> CommitCertificate {
+        let mut state = helpers::get_temp_state();
+        if !next_validators.is_empty() {
+            stake::set_validators(&mut state, publics(next_validators)).unwrap();
+        }
+        let state_root = state.commit().unwrap();
+
+        let mut header = Header::new();
+        header.set_number(number);
+        header.set_state_root(state_root);
+        let step = VoteStep::new(number as Height, 0, Step::Precommit);
+        let precommit_hash = blake256(message_info_rlp(step, Some(header.hash())));
+        let precommits = signers
+            .iter()
+            .map(|index| sign_schnorr(validators[*index].0.private(), &precommit_hash).unwrap())
+            .collect();
+        CommitCertificate {
+            header,
+            validators: publics(validators),
+            view: 0,
+            precommit_bitset: BitSet::new_with_indices(signers),
+            precommits,
+            next_validators_proof: stake::validators_proof(&state).unwrap(),
+        }
+    }
+
+    fn publics(validators: &[(KeyPair, u64)]) -> Vec<(Public, u64)> {
+        validators.iter().map(|(key_pair, weight)| (*key_pair.public(), *weight)).collect()
+    }
+
+    fn key_pairs(weights: &[u64]) -> Vec<(KeyPair, u64)> {
+        weights.iter().map(|weight| (Random.generate().unwrap(), *weight)).collect()
+    }
+
+    #[test]
+    fn encode_and_decode_certificate() {
+        let validators = key_pairs(&[1, 2, 3]);
+        rlp_encode_and_decode_test!(certificate(3, &validators, &[0, 2]));
+    }
+
+    #[test]
+    fn certificate_needs_more_than_two_thirds() {
+        let validators = key_pairs(&[1, 1, 1, 3]);
+        assert!(certificate(3, &validators, &[0, 1, 2]).verify(None).is_err());
+        assert!(certificate(3, &validators, &[0, 3]).verify(None).is_ok());
+    }
+
+    #[test]
+    fn certificate_with_wrong_signature_fails() {
+        let validators = key_pairs(&[1, 1, 1]);
+        let mut certificate = certificate(3, &validators, &[0, 1, 2]);
+        certificate.precommits.swap(0, 1);
+        assert!(certificate.verify(None).is_err());
+    }
+
+    #[test]
+    fn certificate_with_missing_signature_fails() {
+        let validators = key_pairs(&[1, 1, 1]);
+        let mut certificate = certificate(3, &validators, &[0, 1, 2]);
+        certificate.precommits.pop();
+        assert!(certificate.verify(None).is_err());
+    }
+
+    #[test]
+    fn certificate_with_aggregated_signature() {
+        let validators = key_pairs(&[1, 1, 1, 1]);
+        let mut certificate = certificate(3, &validators, &[0, 1, 3]);
+        let step = VoteStep::new(3, 0, Step::Precommit);
+        let precommit_hash = message_hash(step, certificate.header.hash());
+        let signers: Vec<_> = [0, 1, 3].iter().map(|index| validators[*index].0.clone()).collect();
+        certificate.precommits = vec![sign_aggregated(&signers, &precommit_hash)];
+        assert!(certificate.verify(Some(4)).is_ok());
+
+        // The precommits are in the seal of block 4, which is before the activation.
+        assert!(certificate.verify(Some(5)).is_err());
+        assert!(certificate.verify(None).is_err());
+
+        certificate.precommit_bitset = BitSet::new_with_indices(&[0, 1, 2]);
+        assert!(certificate.verify(Some(4)).is_err());
+    }
+
+    #[test]
+    fn chain_follows_validator_set_changes() {
+        let old_validators = key_pairs(&[1, 1, 1, 1]);
+        let mut new_validators: Vec<_> = old_validators.iter().take(3).cloned().collect();
+        new_validators.extend(key_pairs(&[1]));
+        let trusted = publics(&old_validators);
+
+        // The old validators committed the block electing the new validators.
+        let certificates = vec![
+            certificate(1, &old_validators, &[0, 1, 2]),
+            certificate_electing(2, &old_validators, &[0, 1, 2], &new_validators),
+            certificate(3, &new_validators, &[0, 1, 2, 3]),
+        ];
+        assert_eq!(publics(&new_validators), verify_certificate_chain(trusted.clone(), &certificates, None).unwrap());
+
+        // The validators which are not proved cannot sign the certificate.
+        let unknown_validators = key_pairs(&[1, 1, 1]);
+        let certificates = vec![certificate(2, &unknown_validators, &[0, 1, 2])];
+        assert!(verify_certificate_chain(trusted.clone(), &certificates, None).is_err());
+
+        // The proof must be against the state root of the committed block.
+        let mut electing = certificate_electing(1, &old_validators, &[0, 1, 2], &new_validators);
+        electing.next_validators_proof =
+            certificate_electing(1, &old_validators, &[0, 1, 2], &unknown_validators).next_validators_proof;
+        assert!(verify_certificate_chain(trusted.clone(), &[electing], None).is_err());
+
+        // The certificates must be in the ascending order.
+        let certificates = vec![certificate(2, &old_validators, &[0, 1, 2]), certificate(1, &old_validators, &[0, 1])];
+        assert!(verify_certificate_chain(trusted, &certificates, None).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use ckey::Address;
+use primitives::H256;
+
+use super::types::{BitSet, Height, Step, View};
+
+/// A snapshot of the round which the Tendermint engine is in.
+/// It's used to inspect a stalled network from outside.
+#[derive(Clone, Debug)]
+pub struct ConsensusState {
+    pub height: Height,
+    pub view: View,
+    pub step: Step,
+    /// The validator which should propose in the current view.
+    pub proposer: Address,
+    /// The view and the block which this node precommitted and is locked on.
+    pub locked: Option<(View, H256)>,
+    /// The view and the block which got the latest prevotes of more than 2/3 of the voting power.
+    pub valid: Option<(View, H256)>,
+    /// The proposal of the current view.
+    pub proposal: Option<H256>,
+    /// The votes collected in the current view.
+    pub votes: Vec<StepVotes>,
+    /// The time left until the current step times out.
+    pub timeout: Option<Duration>,
+}
+
+/// The votes of a step, with the validators which signed them.
+#[derive(Clone, Debug)]
+pub struct StepVotes {
+    pub step: Step,
+    pub bitset: BitSet,
+    pub signers: Vec<Address>,
+}
+
+/// The transitions of the Tendermint engine which are streamed to the listeners.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConsensusEvent {
+    /// The engine moved to the step.
+    StepChanged {
+        height: Height,
+        view: View,
+        step: Step,
+    },
+    /// The step expired before the engine got enough votes.
+    Timeout {
+        height: Height,
+        view: View,
+        step: Step,
+    },
+}
+
+pub type ConsensusListener = Box<Fn(&ConsensusEvent) + Send + Sync>;
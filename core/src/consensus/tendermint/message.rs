@@ -17,11 +17,12 @@
 use std::cmp;
 
 use ccrypto::blake256;
-use ckey::{verify_schnorr, Public, SchnorrSignature};
+use ckey::{recover_schnorr, verify_schnorr, Error as KeyError, Public, SchnorrSignature};
 use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
 use super::super::vote_collector::Message;
+use super::aggregation::AggregationRound;
 use super::types::BitSet;
 use super::{BlockHash, Height, Step, View};
 use crate::error::Error;
@@ -82,6 +83,10 @@ const MESSAGE_ID_PROPOSAL_BLOCK: u8 = 0x02;
 const MESSAGE_ID_STEP_STATE: u8 = 0x03;
 const MESSAGE_ID_REQUEST_MESSAGE: u8 = 0x04;
 const MESSAGE_ID_REQUEST_PROPOSAL: u8 = 0x05;
+const MESSAGE_ID_AGGREGATION_REQUEST: u8 = 0x06;
+const MESSAGE_ID_AGGREGATION_COMMITMENT: u8 = 0x07;
+const MESSAGE_ID_AGGREGATION_NONCE: u8 = 0x08;
+const MESSAGE_ID_AGGREGATION_PARTIAL: u8 = 0x09;
 
 #[derive(Debug, PartialEq)]
 pub enum TendermintMessage {
@@ -100,6 +105,28 @@ pub enum TendermintMessage {
         height: Height,
         view: View,
     },
+    AggregationRequest {
+        round: AggregationRound,
+        signature: SchnorrSignature,
+    },
+    AggregationCommitment {
+        round: AggregationRound,
+        signer_index: usize,
+        commitment: H256,
+        signature: SchnorrSignature,
+    },
+    AggregationNonce {
+        round: AggregationRound,
+        signer_index: usize,
+        nonce: Public,
+        signature: SchnorrSignature,
+    },
+    AggregationPartial {
+        round: AggregationRound,
+        signer_index: usize,
+        partial: SchnorrSignature,
+        signature: SchnorrSignature,
+    },
 }
 
 impl Encodable for TendermintMessage {
@@ -146,6 +173,54 @@ impl Encodable for TendermintMessage {
                 s.append(height);
                 s.append(view);
             }
+            TendermintMessage::AggregationRequest {
+                round,
+                signature,
+            } => {
+                s.begin_list(3);
+                s.append(&MESSAGE_ID_AGGREGATION_REQUEST);
+                s.append(round);
+                s.append(signature);
+            }
+            TendermintMessage::AggregationCommitment {
+                round,
+                signer_index,
+                commitment,
+                signature,
+            } => {
+                s.begin_list(5);
+                s.append(&MESSAGE_ID_AGGREGATION_COMMITMENT);
+                s.append(round);
+                s.append(signer_index);
+                s.append(commitment);
+                s.append(signature);
+            }
+            TendermintMessage::AggregationNonce {
+                round,
+                signer_index,
+                nonce,
+                signature,
+            } => {
+                s.begin_list(5);
+                s.append(&MESSAGE_ID_AGGREGATION_NONCE);
+                s.append(round);
+                s.append(signer_index);
+                s.append(nonce);
+                s.append(signature);
+            }
+            TendermintMessage::AggregationPartial {
+                round,
+                signer_index,
+                partial,
+                signature,
+            } => {
+                s.begin_list(5);
+                s.append(&MESSAGE_ID_AGGREGATION_PARTIAL);
+                s.append(round);
+                s.append(signer_index);
+                s.append(partial);
+                s.append(signature);
+            }
         }
     }
 }
@@ -205,6 +280,62 @@ impl Decodable for TendermintMessage {
                     view,
                 }
             }
+            MESSAGE_ID_AGGREGATION_REQUEST => {
+                if rlp.item_count()? != 3 {
+                    return Err(DecoderError::RlpIncorrectListLen)
+                }
+                let round = rlp.at(1)?.as_val()?;
+                let signature = rlp.at(2)?.as_val()?;
+                TendermintMessage::AggregationRequest {
+                    round,
+                    signature,
+                }
+            }
+            MESSAGE_ID_AGGREGATION_COMMITMENT => {
+                if rlp.item_count()? != 5 {
+                    return Err(DecoderError::RlpIncorrectListLen)
+                }
+                let round = rlp.at(1)?.as_val()?;
+                let signer_index = rlp.at(2)?.as_val()?;
+                let commitment = rlp.at(3)?.as_val()?;
+                let signature = rlp.at(4)?.as_val()?;
+                TendermintMessage::AggregationCommitment {
+                    round,
+                    signer_index,
+                    commitment,
+                    signature,
+                }
+            }
+            MESSAGE_ID_AGGREGATION_NONCE => {
+                if rlp.item_count()? != 5 {
+                    return Err(DecoderError::RlpIncorrectListLen)
+                }
+                let round = rlp.at(1)?.as_val()?;
+                let signer_index = rlp.at(2)?.as_val()?;
+                let nonce = rlp.at(3)?.as_val()?;
+                let signature = rlp.at(4)?.as_val()?;
+                TendermintMessage::AggregationNonce {
+                    round,
+                    signer_index,
+                    nonce,
+                    signature,
+                }
+            }
+            MESSAGE_ID_AGGREGATION_PARTIAL => {
+                if rlp.item_count()? != 5 {
+                    return Err(DecoderError::RlpIncorrectListLen)
+                }
+                let round = rlp.at(1)?.as_val()?;
+                let signer_index = rlp.at(2)?.as_val()?;
+                let partial = rlp.at(3)?.as_val()?;
+                let signature = rlp.at(4)?.as_val()?;
+                TendermintMessage::AggregationPartial {
+                    round,
+                    signer_index,
+                    partial,
+                    signature,
+                }
+            }
             _ => return Err(DecoderError::Custom("Unknown message id detected")),
         })
     }
@@ -266,6 +397,12 @@ impl ConsensusMessage {
         let vote_info = message_info_rlp(self.on.step, self.on.block_hash);
         Ok(verify_schnorr(signer_public, &self.signature, &blake256(vote_info))?)
     }
+
+    /// Recovers the public key of the signer from the signature.
+    pub fn recover_public(&self) -> Result<Public, KeyError> {
+        let vote_info = message_info_rlp(self.on.step, self.on.block_hash);
+        recover_schnorr(&self.signature, &blake256(vote_info))
+    }
 }
 
 /// Header consensus view.
@@ -376,6 +513,53 @@ mod tests {
         });
     }
 
+    fn aggregation_round() -> AggregationRound {
+        AggregationRound {
+            precommit_step: VoteStep::new(10, 123, Step::Precommit),
+            block_hash: H256::random(),
+            proposer_view: 2,
+            signers: BitSet::new_with_indices(&[0, 2, 3]),
+        }
+    }
+
+    #[test]
+    fn encode_and_decode_tendermint_message_6() {
+        rlp_encode_and_decode_test!(TendermintMessage::AggregationRequest {
+            round: aggregation_round(),
+            signature: SchnorrSignature::random(),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_tendermint_message_7() {
+        rlp_encode_and_decode_test!(TendermintMessage::AggregationCommitment {
+            round: aggregation_round(),
+            signer_index: 2,
+            commitment: H256::random(),
+            signature: SchnorrSignature::random(),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_tendermint_message_8() {
+        rlp_encode_and_decode_test!(TendermintMessage::AggregationNonce {
+            round: aggregation_round(),
+            signer_index: 2,
+            nonce: Public::random(),
+            signature: SchnorrSignature::random(),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_tendermint_message_9() {
+        rlp_encode_and_decode_test!(TendermintMessage::AggregationPartial {
+            round: aggregation_round(),
+            signer_index: 3,
+            partial: SchnorrSignature::random(),
+            signature: SchnorrSignature::random(),
+        });
+    }
+
     #[test]
     fn encode_and_decode_consensus_message_1() {
         let message = ConsensusMessage::default();
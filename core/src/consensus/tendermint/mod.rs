@@ -14,53 +14,74 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-mod backup;
+mod aggregation;
+mod certificate;
+mod consensus_state;
 mod message;
 mod params;
-mod stake;
+mod proposer;
+#[cfg(test)]
+mod simulation;
+pub mod stake;
 pub mod types;
+mod wal;
 
 use std::cmp;
 use std::collections::{HashMap, HashSet};
 use std::iter::Iterator;
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
 use ccrypto::blake256;
-use ckey::{public_to_address, recover_schnorr, verify_schnorr, Address, Message, Password, Public, SchnorrSignature};
+use ckey::{
+    combine_publics, combine_schnorr_signatures, public_to_address, recover_schnorr, verify_schnorr, Address,
+    Generator, Message, Password, Public, Random, SchnorrSignature,
+};
 use cnetwork::{Api, NetworkExtension, NetworkService, NodeId};
 use cstate::ActionHandler;
 use ctimer::{TimeoutHandler, TimerToken};
 use ctypes::machine::WithBalances;
+use ctypes::transaction::Transaction;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::BlockNumber;
+use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::{Mutex, ReentrantMutex, RwLock};
 use primitives::{u256_from_u128, Bytes, H256, U256};
 use rand::prelude::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
 use rlp::{Encodable, UntrustedRlp};
 
-use self::backup::{backup, restore, BackupView};
+use self::aggregation::{nonce_commitment, verify_precommits, AggregationRound, Aggregations};
+pub use self::certificate::{verify_certificate_chain, CommitCertificate};
+pub use self::consensus_state::{ConsensusEvent, ConsensusListener, ConsensusState, StepVotes};
 use self::message::*;
-pub use self::params::{TendermintParams, TimeoutParams};
+pub use self::params::{DowntimeParams, ElectionParams, TendermintParams, TimeoutParams};
+use self::proposer::ProposerSchedule;
 use self::types::{BitSet, Height, PeerState, Step, View};
+use self::wal::{replay, Wal, WalEntry};
+use super::reward::{FeeBurn, RewardSchedule};
 use super::signer::EngineSigner;
 use super::validator_set::validator_list::ValidatorList;
 use super::validator_set::ValidatorSet;
-use super::vote_collector::VoteCollector;
+use super::vote_collector::{DoubleVote, VoteCollector};
 use super::{ConsensusEngine, ConstructedVerifier, EngineError, EpochChange, Seal};
 use crate::account_provider::AccountProvider;
 use crate::block::*;
 use crate::client::{Client, EngineClient};
-use crate::codechain_machine::CodeChainMachine;
+use crate::codechain_machine::{CodeChainMachine, WithActionHandlers};
 use crate::consensus::EngineType;
 use crate::encoded;
 use crate::error::{BlockError, Error};
 use crate::header::Header;
+use crate::transaction::{SignedTransaction, UnverifiedTransaction};
 use crate::views::{BlockView, HeaderView};
 use crate::BlockId;
 use ChainNotify;
 
+/// Timer token representing the timeout of the aggregation of the precommits.
+pub const ENGINE_TIMEOUT_AGGREGATION: TimerToken = 22;
 /// Timer token representing the consensus step timeouts.
 pub const ENGINE_TIMEOUT_TOKEN_NONCE_BASE: TimerToken = 23;
 
@@ -98,14 +119,32 @@ struct TendermintInner {
     last_confirmed_view: RwLock<(H256, View)>,
     /// Set used to determine the current validators.
     validators: Box<ValidatorSet>,
+    /// Picks the proposers in proportion to the voting power.
+    proposer_schedule: ProposerSchedule,
+    /// The height from which the proposer is picked in proportion to the voting power.
+    weighted_proposer_activation: Option<BlockNumber>,
+    /// The number of blocks in a term, at the end of which the validators are elected.
+    term_length: Option<u64>,
     /// Reward per block, in base units.
-    block_reward: u64,
+    block_reward: RewardSchedule,
+    /// The portion of the block fee which is burned.
+    fee_burn: FeeBurn,
+    /// Jails the validators which missed too many precommits if it's given.
+    downtime: Option<DowntimeParams>,
+    /// The block number from which the precommits in a seal may be aggregated into a single signature.
+    aggregated_seal_activation: Option<BlockNumber>,
+    /// The aggregations of the precommits in which this node takes part.
+    aggregations: Mutex<Aggregations>,
     /// Network extension,
     extension: Arc<TendermintExtension>,
     /// codechain machine descriptor
     machine: Arc<CodeChainMachine>,
     /// Chain notify
     chain_notify: Arc<TendermintChainNotify>,
+    /// Write-ahead log of the consensus. It's opened when the client is registered.
+    wal: RwLock<Option<Wal>>,
+    /// Listeners of the step transitions and the timeouts.
+    consensus_listeners: RwLock<Vec<ConsensusListener>>,
     /// Lock for changing step. We use ReentrantMutex because handle_valid_message called recursively.
     pub step_change_lock: ReentrantMutex<()>,
 }
@@ -114,8 +153,13 @@ impl Tendermint {
     #![cfg_attr(feature = "cargo-clippy", allow(clippy::new_ret_no_self))]
     /// Create a new instance of Tendermint engine
     pub fn new(our_params: TendermintParams, machine: CodeChainMachine) -> Arc<Self> {
-        let action_handlers: Vec<Arc<ActionHandler>> =
-            vec![Arc::new(stake::Stake::new(our_params.genesis_stakes.clone()))];
+        let stake = stake::Stake::new(
+            our_params.genesis_stakes.clone(),
+            our_params.election,
+            our_params.initial_validators.clone(),
+        );
+        let mut action_handlers: Vec<Arc<ActionHandler>> = vec![Arc::new(stake)];
+        action_handlers.extend(machine.action_handlers().iter().cloned());
         let machine = Arc::new(machine);
         let inner = TendermintInner::new(our_params, machine.clone());
 
@@ -150,9 +194,18 @@ impl TendermintInner {
             proposal: RwLock::new(None),
             last_confirmed_view: RwLock::new((Default::default(), 0)),
             validators: our_params.validators,
+            proposer_schedule: Default::default(),
+            weighted_proposer_activation: our_params.weighted_proposer_activation,
+            term_length: our_params.election.map(|election| election.term_length),
             block_reward: our_params.block_reward,
+            fee_burn: our_params.fee_burn,
+            downtime: our_params.downtime,
+            aggregated_seal_activation: our_params.aggregated_seal_activation,
+            aggregations: Default::default(),
             extension: Arc::new(extension),
             chain_notify: Arc::new(chain_notify),
+            wal: RwLock::new(None),
+            consensus_listeners: RwLock::new(vec![]),
             machine,
             step_change_lock: ReentrantMutex::new(()),
             votes_received: RwLock::new(BitSet::new()),
@@ -180,9 +233,31 @@ impl TendermintInner {
 
     /// Find the designated for the given view.
     fn view_proposer(&self, bh: &H256, height: Height, view: View) -> Address {
-        let proposer_nonce = height + view;
-        ctrace!(ENGINE, "Proposer nonce: {}", proposer_nonce);
-        self.validators.get_address(bh, proposer_nonce)
+        if !self.is_weighted_proposer_active(height) {
+            let proposer_nonce = height + view;
+            ctrace!(ENGINE, "Proposer nonce: {}", proposer_nonce);
+            return self.validators.get_address(bh, proposer_nonce)
+        }
+        let weights: Vec<u64> = (0..self.validators.count(bh)).map(|index| self.validators.weight(bh, index)).collect();
+        let number = height.saturating_sub(1) as BlockNumber;
+        let parent = || self.client().block_header(&BlockId::Hash(*bh)).map(|header| header.parent_hash());
+        let priority = self.proposer_schedule.priority(bh, parent, number, self.proposer_epoch_start(number), &weights);
+        let proposer_index = priority.proposer(&weights, view as u64);
+        ctrace!(ENGINE, "Proposer of the view {} at the height {}: {}", view, height, proposer_index);
+        self.validators.get_address(bh, proposer_index)
+    }
+
+    /// Whether the proposer of the height is picked in proportion to the voting power.
+    fn is_weighted_proposer_active(&self, height: Height) -> bool {
+        self.weighted_proposer_activation.map_or(false, |activation| height as BlockNumber >= activation)
+    }
+
+    /// Returns the block after which the proposer priorities restart from zero.
+    /// They restart before the activation and at the beginning of every term, in which the validators don't change.
+    fn proposer_epoch_start(&self, number: BlockNumber) -> BlockNumber {
+        let activation = self.weighted_proposer_activation.map_or(0, |activation| activation.saturating_sub(1));
+        let term_start = self.term_length.map_or(0, |term_length| number - number % term_length);
+        cmp::max(activation, term_start)
     }
 
     pub fn height(&self) -> Height {
@@ -275,22 +350,27 @@ impl TendermintInner {
         self.validators.contains_address(&prev_hash, address)
     }
 
-    fn check_above_threshold(&self, n: usize) -> Result<(), EngineError> {
-        let threshold = self.validators.count(&self.prev_block_hash()) * 2 / 3;
-        if n > threshold {
+    /// Checks if the voting power is more than 2/3 of the validators of the block whose parent is `parent`.
+    fn check_above_threshold(&self, parent: &H256, power: u64) -> Result<(), EngineError> {
+        let threshold = self.validators.total_weight(parent) * 2 / 3;
+        if power > threshold {
             Ok(())
         } else {
             Err(EngineError::BadSealFieldSize(OutOfBounds {
-                min: Some(threshold),
+                min: Some(threshold as usize),
                 max: None,
-                found: n,
+                found: power as usize,
             }))
         }
     }
 
     fn has_enough_any_votes(&self) -> bool {
-        let step_votes = self.votes.count_round_votes(&VoteStep::new(self.height(), self.view(), *self.step.read()));
-        self.check_above_threshold(step_votes).is_ok()
+        let prev_block_hash = self.prev_block_hash();
+        let step_votes =
+            self.votes.round_votes_power(&VoteStep::new(self.height(), self.view(), *self.step.read()), |index| {
+                self.validators.weight(&prev_block_hash, index)
+            });
+        self.check_above_threshold(&prev_block_hash, step_votes).is_ok()
     }
 
     fn has_all_votes(&self, vote_step: &VoteStep) -> bool {
@@ -299,14 +379,19 @@ impl TendermintInner {
     }
 
     fn has_enough_aligned_votes(&self, message: &ConsensusMessage) -> bool {
-        let aligned_count = self.votes.count_aligned_votes(&message);
-        self.check_above_threshold(aligned_count).is_ok()
+        let prev_block_hash = self.prev_block_hash();
+        let aligned_power =
+            self.votes.aligned_votes_power(&message, |index| self.validators.weight(&prev_block_hash, index));
+        self.check_above_threshold(&prev_block_hash, aligned_power).is_ok()
     }
 
     fn has_enough_precommit_votes(&self, block_hash: H256) -> bool {
+        let prev_block_hash = self.prev_block_hash();
         let vote_step = VoteStep::new(self.height(), self.view(), Step::Precommit);
-        let count = self.votes.count_block_round_votes(&vote_step, &Some(block_hash));
-        self.check_above_threshold(count).is_ok()
+        let power = self.votes.block_round_votes_power(&vote_step, &Some(block_hash), |index| {
+            self.validators.weight(&prev_block_hash, index)
+        });
+        self.check_above_threshold(&prev_block_hash, power).is_ok()
     }
 
     fn broadcast_message(&self, message: Bytes) {
@@ -330,9 +415,29 @@ impl TendermintInner {
     }
 
     fn save_last_confirmed_view(&self, block_hash: H256, view: View) {
+        self.log(WalEntry::Confirmed {
+            height: self.height(),
+            view,
+            block_hash,
+        });
         *self.last_confirmed_view.write() = (block_hash, view);
     }
 
+    fn set_proposal(&self, block_hash: H256) {
+        self.log(WalEntry::Proposal {
+            height: self.height(),
+            view: self.view(),
+            block_hash,
+        });
+        *self.proposal.write() = Some(block_hash);
+    }
+
+    fn log(&self, entry: WalEntry) {
+        if let Some(wal) = self.wal.read().as_ref() {
+            wal.append(&entry);
+        }
+    }
+
     fn increment_view(&self, n: View) {
         ctrace!(ENGINE, "increment_view: New view.");
         self.view.fetch_add(n, AtomicOrdering::SeqCst);
@@ -344,6 +449,18 @@ impl TendermintInner {
         self.last_lock.load(AtomicOrdering::SeqCst) < lock_change_view && lock_change_view < self.view()
     }
 
+    /// The view and the block of the precommit which this node sent when it locked.
+    fn locked(&self) -> Option<(View, BlockHash)> {
+        let signer_index = self.signer_index(&self.prev_block_hash())?;
+        let last_lock = self.last_lock.load(AtomicOrdering::SeqCst);
+        let vote_step = VoteStep::new(self.height(), last_lock, Step::Precommit);
+        self.votes
+            .get_all_votes_and_indices_in_round(&vote_step)
+            .into_iter()
+            .find(|(index, _)| *index == signer_index)
+            .and_then(|(_, message)| Some((last_lock, message.on.block_hash?)))
+    }
+
     fn to_next_height(&self, height: Height) {
         assert!(height >= self.height(), "{} < {}", height, self.height());
         let new_height = height + 1;
@@ -354,15 +471,25 @@ impl TendermintInner {
         *self.lock_change.write() = None;
         *self.proposal.write() = None;
         *self.votes_received.write() = BitSet::new();
+        // The votes of the committed height are kept to seal the next block.
+        if let Some(wal) = self.wal.read().as_ref() {
+            wal.truncate(height);
+        }
+        self.aggregations.lock().throw_out_old(height);
     }
 
     fn to_step(&self, step: Step) {
         let prev_step = self.get_step();
+        let vote_step = VoteStep::new(self.height(), self.view(), step);
+        self.log(WalEntry::Step(vote_step));
         // Should be protected by step_change_lock
         self.extension.set_timer_step(step, self.view());
         *self.step.write() = step;
-        self.backup();
-        let vote_step = VoteStep::new(self.height(), self.view(), step);
+        self.notify_listeners(ConsensusEvent::StepChanged {
+            height: vote_step.height,
+            view: vote_step.view,
+            step,
+        });
 
         // If there are not enough pre-votes or pre-commits,
         // to_step could be called with same step
@@ -376,7 +503,7 @@ impl TendermintInner {
             Step::Propose => {
                 if let Some(hash) = self.votes.get_block_hashes(&vote_step).first() {
                     if self.client().block_header(&BlockId::Hash(*hash)).is_some() {
-                        *self.proposal.write() = Some(*hash);
+                        self.set_proposal(*hash);
                         self.to_step(Step::Prevote);
                     } else {
                         ctrace!(ENGINE, "Proposal is received but not imported");
@@ -403,6 +530,10 @@ impl TendermintInner {
                 let block_hash = match *self.lock_change.read() {
                     Some(ref m) if self.is_view(m) && m.on.block_hash.is_some() => {
                         ctrace!(ENGINE, "Setting last lock: {}", m.on.step.view);
+                        self.log(WalEntry::Lock {
+                            height: self.height(),
+                            view: m.on.step.view,
+                        });
                         self.last_lock.store(m.on.step.view, AtomicOrdering::SeqCst);
                         m.on.block_hash
                     }
@@ -418,7 +549,6 @@ impl TendermintInner {
 
     fn generate_and_broadcast_message(&self, block_hash: Option<BlockHash>) {
         if let Some(message) = self.generate_message(block_hash) {
-            self.backup();
             self.broadcast_message(message);
         }
     }
@@ -441,6 +571,7 @@ impl TendermintInner {
                 };
                 let message_rlp = message.rlp_bytes().into_vec();
                 self.votes.vote(message.clone());
+                self.log(WalEntry::Message(message.clone()));
                 cdebug!(ENGINE, "Generated {:?} as {}th validator.", message, signer_index);
                 self.handle_valid_message(&message);
 
@@ -532,65 +663,77 @@ impl TendermintInner {
         let height = proposal.number() as Height;
         let view = consensus_view(proposal).expect("Imported block is already verified");
         if current_height == height && self.view() == view {
-            *self.proposal.write() = Some(proposal.hash());
+            self.set_proposal(proposal.hash());
             if *self.step.read() == Step::Propose {
                 self.to_step(Step::Prevote);
             }
         } else if current_height < height {
             self.to_next_height(height - 1);
-            *self.proposal.write() = Some(proposal.hash());
+            self.set_proposal(proposal.hash());
             self.to_step(Step::Prevote);
         }
     }
 
-    fn backup(&self) {
-        backup(
-            self.client().get_kvdb().as_ref(),
-            BackupView {
-                height: &self.height(),
-                view: &self.view(),
-                step: &*self.step.read(),
-                votes: &self.votes.get_all(),
-                last_confirmed_view: &self.last_confirmed_view.read(),
-            },
-        );
-    }
-
+    /// Replays the write-ahead log to restore the state before the last shutdown.
     fn restore(&self) {
         let client = self.client();
-        let backup = restore(client.get_kvdb().as_ref());
-        if let Some(backup) = backup {
-            *self.step.write() = backup.step;
-            self.height.store(backup.height, AtomicOrdering::SeqCst);
-            self.view.store(backup.view, AtomicOrdering::SeqCst);
-            *self.last_confirmed_view.write() = backup.last_confirmed_view;
-            if let Some(proposal) = backup.proposal {
-                if client.block_header(&BlockId::Hash(proposal)).is_some() {
-                    *self.proposal.write() = Some(proposal);
-                }
+        let (wal, entries) = Wal::open(client.get_kvdb());
+        *self.wal.write() = Some(wal);
+
+        let state = replay(&entries);
+        let vote_step = match state.vote_step {
+            Some(vote_step) => vote_step,
+            None => return,
+        };
+        cinfo!(ENGINE, "Restoring the consensus state {:?} from {} WAL entries", vote_step, entries.len());
+        *self.step.write() = vote_step.step;
+        self.height.store(vote_step.height, AtomicOrdering::SeqCst);
+        self.view.store(vote_step.view, AtomicOrdering::SeqCst);
+        self.last_lock.store(state.last_lock(), AtomicOrdering::SeqCst);
+        if let Some(last_confirmed_view) = state.last_confirmed_view {
+            *self.last_confirmed_view.write() = last_confirmed_view;
+        }
+        if let Some(proposal) = state.proposal() {
+            if client.block_header(&BlockId::Hash(proposal)).is_some() {
+                *self.proposal.write() = Some(proposal);
             }
+        }
 
-            let mut to_next_height = None;
-            for vote in backup.votes {
-                if let Some(committed) = self.load_vote_from_backup(&vote, backup.height) {
-                    to_next_height = Some(committed);
+        // The proposals which were not imported before the crash are imported again.
+        for block in state.proposal_blocks {
+            let hash = BlockView::new(&block).header_view().hash();
+            if client.block_header(&BlockId::Hash(hash)).is_none() {
+                if let Err(err) = client.import_block(block) {
+                    cdebug!(ENGINE, "Failed to import the proposal {} in the WAL: {:?}", hash, err);
                 }
             }
+        }
+
+        let mut to_next_height = None;
+        for message in &state.messages {
+            if let Some(committed) = self.load_vote_from_wal(message, vote_step.height) {
+                to_next_height = Some(committed);
+            }
+        }
 
-            if let Some(committed) = to_next_height {
-                if client.block(&BlockId::Hash(committed)).is_some() {
-                    self.to_next_height(backup.height);
-                    return
-                } else {
-                    cwarn!(ENGINE, "Cannot find a proposal which committed");
-                }
+        if let Some(committed) = to_next_height {
+            if client.block(&BlockId::Hash(committed)).is_some() {
+                self.to_next_height(vote_step.height);
+                self.to_step(Step::Commit);
+                return
+            } else {
+                cwarn!(ENGINE, "Cannot find a proposal which committed");
             }
+        }
 
-            self.extension.set_timer_step(backup.step, backup.view);
+        if state.timed_out {
+            self.handle_timeout();
+        } else {
+            self.extension.set_timer_step(vote_step.step, vote_step.view);
         }
     }
 
-    fn load_vote_from_backup(&self, vote: &ConsensusMessage, height: Height) -> Option<H256> {
+    fn load_vote_from_wal(&self, vote: &ConsensusMessage, height: Height) -> Option<H256> {
         self.votes.vote(vote.clone());
 
         if vote.on.step.height != height {
@@ -643,17 +786,363 @@ impl TendermintInner {
 
         let (last_block_hash, last_block_view) = &*self.last_confirmed_view.read();
         assert_eq!(last_block_hash, &parent.hash());
-        let (precommits, precommit_indices) = self.votes.round_signatures_and_indices(
-            &VoteStep::new(height - 1, *last_block_view, Step::Precommit),
-            &last_block_hash,
-        );
+        let precommit_step = VoteStep::new(height - 1, *last_block_view, Step::Precommit);
+        let (precommits, precommit_indices) =
+            self.votes.round_signatures_and_indices(&precommit_step, &last_block_hash);
         ctrace!(ENGINE, "Collected seal: {:?}({:?})", precommits, precommit_indices);
         let precommit_bitset = BitSet::new_with_indices(&precommit_indices);
-        Seal::Tendermint {
-            prev_view: *last_block_view,
-            cur_view: view,
-            precommits: precommits.clone(),
-            precommit_bitset,
+        if !self.is_aggregated_seal_active(header.number()) || precommits.len() < 2 {
+            return Seal::Tendermint {
+                prev_view: *last_block_view,
+                cur_view: view,
+                precommits,
+                precommit_bitset,
+            }
+        }
+
+        let aggregated = self.aggregations.lock().aggregated(&precommit_step, &last_block_hash);
+        match aggregated {
+            Some((signers, signature)) => Seal::Tendermint {
+                prev_view: *last_block_view,
+                cur_view: view,
+                precommits: vec![signature],
+                precommit_bitset: signers,
+            },
+            // The block is sealed after the signers complete the aggregation or it times out.
+            None if view == 0 && !self.aggregations.lock().is_timed_out(height, view) => {
+                self.request_aggregation(AggregationRound {
+                    precommit_step,
+                    block_hash: *last_block_hash,
+                    proposer_view: view,
+                    signers: precommit_bitset,
+                });
+                Seal::None
+            }
+            // The signers didn't complete the aggregation in time, so the precommits are not aggregated.
+            None => Seal::Tendermint {
+                prev_view: *last_block_view,
+                cur_view: view,
+                precommits,
+                precommit_bitset,
+            },
+        }
+    }
+
+    /// Requests the signers of the precommits to aggregate them.
+    fn request_aggregation(&self, round: AggregationRound) {
+        if !self.aggregations.lock().request(round) {
+            return
+        }
+        let signature = match self.sign(round.hash()) {
+            Ok(signature) => signature,
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot sign the aggregation request {:?}: {:?}", round, err);
+                return
+            }
+        };
+        cdebug!(ENGINE, "Request the aggregation of the precommits {:?}", round);
+        self.extension.broadcast_aggregation_message(TendermintMessage::AggregationRequest {
+            round,
+            signature,
+        });
+        self.extension.set_timer_aggregation();
+        self.join_aggregation(round);
+    }
+
+    /// Whether the precommits in the seal of the block may be aggregated.
+    fn is_aggregated_seal_active(&self, block_number: BlockNumber) -> bool {
+        self.aggregated_seal_activation.map_or(false, |activation| block_number >= activation)
+    }
+
+    /// Gives up the aggregation requested in the current view, so the proposer seals the block with the signatures.
+    fn on_aggregation_timeout(&self) {
+        let _guard = self.step_change_lock.lock();
+        let (height, view) = (self.height(), self.view());
+        if !self.aggregations.lock().time_out(height, view) {
+            return
+        }
+        cdebug!(ENGINE, "The aggregation of the precommits timed out at {}-{}", height, view);
+        if *self.step.read() == Step::Propose {
+            self.update_sealing();
+        }
+    }
+
+    /// Returns the hash of the block which decides the signers of the round with their public keys.
+    fn aggregation_signers(&self, round: &AggregationRound) -> Option<(H256, Vec<Public>)> {
+        let height = round.precommit_step.height;
+        if round.precommit_step.step != Step::Precommit || height + 1 < self.height() {
+            return None
+        }
+        // The precommits are signed by the validators of the committed block, which are decided by its parent.
+        let header = self.client().block_header(&BlockId::Hash(round.block_hash))?;
+        if header.number() as Height != height {
+            return None
+        }
+        let validators_hash = header.parent_hash();
+        let indices = round.signers.indices();
+        let count = self.validators.count(&validators_hash);
+        if indices.len() < 2 || indices.iter().any(|index| *index >= count) {
+            return None
+        }
+        let signers = indices.into_iter().map(|index| self.validators.get(&validators_hash, index)).collect();
+        Some((validators_hash, signers))
+    }
+
+    fn on_aggregation_request(&self, round: AggregationRound, signature: SchnorrSignature) {
+        if self.aggregation_signers(&round).is_none() {
+            return
+        }
+        let proposer = self.view_proposer(&round.block_hash, round.precommit_step.height + 1, round.proposer_view);
+        match recover_schnorr(&signature, &round.hash()) {
+            Ok(public) if public_to_address(&public) == proposer => {}
+            _ => {
+                cinfo!(ENGINE, "The aggregation request {:?} is not signed by the proposer", round);
+                return
+            }
+        }
+        if !self.aggregations.lock().request(round) {
+            return
+        }
+        self.extension.broadcast_aggregation_message(TendermintMessage::AggregationRequest {
+            round,
+            signature,
+        });
+        self.join_aggregation(round);
+    }
+
+    /// Joins the round with a new nonce if this node signed one of the precommits.
+    /// Only the round requested in the current height and view is joined.
+    fn join_aggregation(&self, round: AggregationRound) {
+        if round.precommit_step.height + 1 != self.height() || round.proposer_view != self.view() {
+            return
+        }
+        let validators_hash = match self.aggregation_signers(&round) {
+            Some((validators_hash, _)) => validators_hash,
+            None => return,
+        };
+        let signer_index = match self.signer_index(&validators_hash) {
+            Some(signer_index) if round.signers.is_set(signer_index) => signer_index,
+            _ => return,
+        };
+        // Never sign a precommit which this node didn't cast.
+        let (_, precommitted) = self.votes.round_signatures_and_indices(&round.precommit_step, &round.block_hash);
+        if !precommitted.contains(&signer_index) {
+            return
+        }
+        let nonce = match Random.generate() {
+            Ok(nonce) => nonce,
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot generate a nonce for the aggregation {:?}: {:?}", round, err);
+                return
+            }
+        };
+        if !self.aggregations.lock().join(round, signer_index, *nonce.private(), *nonce.public()) {
+            cdebug!(ENGINE, "Already joined an aggregation requested in the view of {:?}", round);
+            return
+        }
+        let commitment = nonce_commitment(nonce.public());
+        let signature = match self.sign(round.commitment_hash(&commitment)) {
+            Ok(signature) => signature,
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot sign the nonce commitment of the aggregation {:?}: {:?}", round, err);
+                return
+            }
+        };
+        self.extension.broadcast_aggregation_message(TendermintMessage::AggregationCommitment {
+            round,
+            signer_index,
+            commitment,
+            signature,
+        });
+        self.reveal_aggregation_nonce(round);
+    }
+
+    fn on_aggregation_commitment(
+        &self,
+        round: AggregationRound,
+        signer_index: usize,
+        commitment: H256,
+        signature: SchnorrSignature,
+    ) {
+        let validators_hash = match self.aggregation_signers(&round) {
+            Some((validators_hash, _)) => validators_hash,
+            None => return,
+        };
+        if !round.signers.is_set(signer_index) {
+            return
+        }
+        let public = self.validators.get(&validators_hash, signer_index);
+        if !verify_schnorr(&public, &signature, &round.commitment_hash(&commitment)).unwrap_or(false) {
+            cinfo!(
+                ENGINE,
+                "The nonce commitment of {} in the aggregation {:?} has an invalid signature",
+                signer_index,
+                round
+            );
+            return
+        }
+        if !self.aggregations.lock().add_commitment(round, signer_index, commitment) {
+            return
+        }
+        self.extension.broadcast_aggregation_message(TendermintMessage::AggregationCommitment {
+            round,
+            signer_index,
+            commitment,
+            signature,
+        });
+        self.reveal_aggregation_nonce(round);
+    }
+
+    /// Reveals the nonce of this node once the commitments of all the signers are received.
+    fn reveal_aggregation_nonce(&self, round: AggregationRound) {
+        let (signer_index, nonce) = match self.aggregations.lock().take_nonce_to_reveal(&round) {
+            Some(nonce) => nonce,
+            None => return,
+        };
+        let signature = match self.sign(round.nonce_hash(&nonce)) {
+            Ok(signature) => signature,
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot sign the nonce of the aggregation {:?}: {:?}", round, err);
+                return
+            }
+        };
+        self.extension.broadcast_aggregation_message(TendermintMessage::AggregationNonce {
+            round,
+            signer_index,
+            nonce,
+            signature,
+        });
+        self.sign_aggregation_partial(round);
+    }
+
+    fn on_aggregation_nonce(
+        &self,
+        round: AggregationRound,
+        signer_index: usize,
+        nonce: Public,
+        signature: SchnorrSignature,
+    ) {
+        let validators_hash = match self.aggregation_signers(&round) {
+            Some((validators_hash, _)) => validators_hash,
+            None => return,
+        };
+        if !round.signers.is_set(signer_index) {
+            return
+        }
+        let public = self.validators.get(&validators_hash, signer_index);
+        if !verify_schnorr(&public, &signature, &round.nonce_hash(&nonce)).unwrap_or(false) {
+            cinfo!(ENGINE, "The nonce of {} in the aggregation {:?} has an invalid signature", signer_index, round);
+            return
+        }
+        if !self.aggregations.lock().add_nonce(round, signer_index, nonce) {
+            return
+        }
+        self.extension.broadcast_aggregation_message(TendermintMessage::AggregationNonce {
+            round,
+            signer_index,
+            nonce,
+            signature,
+        });
+        self.sign_aggregation_partial(round);
+    }
+
+    /// Signs the part of the aggregated signature once the nonces of all the signers are received.
+    fn sign_aggregation_partial(&self, round: AggregationRound) {
+        let (signer_index, secret_nonce, other_nonces) = match self.aggregations.lock().take_nonces(&round) {
+            Some(nonces) => nonces,
+            None => return,
+        };
+        let signers = match self.aggregation_signers(&round) {
+            Some((_, signers)) => signers,
+            None => return,
+        };
+        let precommit_hash = message_hash(round.precommit_step, round.block_hash);
+        let signed = combine_publics(&other_nonces).map_err(Error::from).and_then(|other_nonces| {
+            let partial =
+                self.signer.read().sign_aggregated_partial(&signers, &secret_nonce, &other_nonces, precommit_hash)?;
+            Ok((partial, self.sign(round.partial_hash(&partial))?))
+        });
+        let (partial, signature) = match signed {
+            Ok(signed) => signed,
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot sign the part of the aggregation {:?}: {:?}", round, err);
+                return
+            }
+        };
+        self.aggregations.lock().add_partial(round, signer_index, partial);
+        self.extension.broadcast_aggregation_message(TendermintMessage::AggregationPartial {
+            round,
+            signer_index,
+            partial,
+            signature,
+        });
+        self.complete_aggregation(round);
+    }
+
+    fn on_aggregation_partial(
+        &self,
+        round: AggregationRound,
+        signer_index: usize,
+        partial: SchnorrSignature,
+        signature: SchnorrSignature,
+    ) {
+        let validators_hash = match self.aggregation_signers(&round) {
+            Some((validators_hash, _)) => validators_hash,
+            None => return,
+        };
+        if !round.signers.is_set(signer_index) {
+            return
+        }
+        let public = self.validators.get(&validators_hash, signer_index);
+        if !verify_schnorr(&public, &signature, &round.partial_hash(&partial)).unwrap_or(false) {
+            cinfo!(ENGINE, "The part of {} in the aggregation {:?} has an invalid signature", signer_index, round);
+            return
+        }
+        if !self.aggregations.lock().add_partial(round, signer_index, partial) {
+            return
+        }
+        self.extension.broadcast_aggregation_message(TendermintMessage::AggregationPartial {
+            round,
+            signer_index,
+            partial,
+            signature,
+        });
+        self.complete_aggregation(round);
+    }
+
+    /// Combines the parts into the aggregated signature once all of them are received
+    /// if this node is the proposer which requested the round.
+    fn complete_aggregation(&self, round: AggregationRound) {
+        let partials = match self.aggregations.lock().partials(&round) {
+            Some(partials) => partials,
+            None => return,
+        };
+        let next_height = round.precommit_step.height + 1;
+        let proposer = self.view_proposer(&round.block_hash, next_height, round.proposer_view);
+        if !self.signer.read().is_address(&proposer) {
+            return
+        }
+        let signers = match self.aggregation_signers(&round) {
+            Some((_, signers)) => signers,
+            None => return,
+        };
+        let precommit_hash = message_hash(round.precommit_step, round.block_hash);
+        let verified = combine_schnorr_signatures(&partials)
+            .map_err(Error::from)
+            .and_then(|signature| verify_precommits(&signers, &[signature], &precommit_hash, true).map(|_| signature));
+        let signature = match verified {
+            Ok(signature) => signature,
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot aggregate the precommits {:?}: {:?}", round, err);
+                return
+            }
+        };
+        cdebug!(ENGINE, "Aggregated the precommits {:?}", round);
+        self.aggregations.lock().complete(&round, signature);
+
+        let _guard = self.step_change_lock.lock();
+        if self.height() == next_height && self.view() == round.proposer_view && *self.step.read() == Step::Propose {
+            self.update_sealing();
         }
     }
 
@@ -666,9 +1155,14 @@ impl TendermintInner {
         let vote_info = message_info_rlp(vote_step, Some(hash));
         let signer_index = self.signer_index(&self.prev_block_hash()).expect("I am proposer");
         let signature = self.sign(blake256(&vote_info)).expect("I am proposer");
-        self.votes.vote(ConsensusMessage::new_proposal(signature, signer_index, header).expect("I am proposer"));
+        let message = ConsensusMessage::new_proposal(signature, signer_index, header).expect("I am proposer");
+        self.votes.vote(message.clone());
+        self.log(WalEntry::ProposalBlock {
+            message,
+            block: sealed_block.rlp_bytes(),
+        });
 
-        *self.proposal.write() = Some(hash);
+        self.set_proposal(hash);
         cdebug!(
             ENGINE,
             "Submitting proposal {} at height {}-{} view {}-{}.\n{:?}",
@@ -696,6 +1190,11 @@ impl TendermintInner {
         }
     }
 
+    /// Blocks before a transition to Tendermint are sealed by another engine.
+    fn is_sealed_by_tendermint(&self, header: &Header) -> bool {
+        header.seal().len() == self.seal_fields(header)
+    }
+
     fn verify_block_external(&self, header: &Header) -> Result<(), Error> {
         let height = header.number() as usize;
         let view = consensus_view(header).unwrap();
@@ -709,58 +1208,52 @@ impl TendermintInner {
 
         let previous_block_view = previous_block_view(header)?;
         let step = VoteStep::new((header.number() - 1) as usize, previous_block_view, Step::Precommit);
+        // The precommits are signed by the validators of the parent block,
+        // which are decided by the grandparent block.
+        let precommit_validators_hash = if header.number() > 1 {
+            self.client()
+                .block_header(&BlockId::Hash(*header.parent_hash()))
+                .map_or(*header.parent_hash(), |parent| parent.parent_hash())
+        } else {
+            *header.parent_hash()
+        };
         let precommit_hash = message_hash(step, *header.parent_hash());
-        let precommits_field =
-            &header.seal().get(2).expect("block went through verify_block_basic; block has .seal_fields() fields; qed");
-        let mut precommit_bitset: BitSet = UntrustedRlp::new(
+        let precommits: Vec<SchnorrSignature> = UntrustedRlp::new(
+            &header.seal().get(2).expect("block went through verify_block_basic; block has .seal_fields() fields; qed"),
+        )
+        .as_list()?;
+        let precommit_bitset: BitSet = UntrustedRlp::new(
             &header.seal().get(3).expect("block went through verify_block_basic; block has .seal_fields() fields; qed"),
         )
         .as_val()?;
-        let mut bitset_index = 0;
-        let mut counter = 0;
-        for rlp in UntrustedRlp::new(precommits_field).iter() {
-            let signature = rlp.as_val()?;
-            if precommit_bitset.is_empty() {
-                cwarn!(
-                    ENGINE,
-                    "verify_block_external: Precommit bitset got empty while dealing with validators of block hash {} on the seal.",
-                    header.parent_hash()
-                );
-                return Err(BlockError::InvalidSeal.into())
-            }
-            while !precommit_bitset.is_set(bitset_index) {
-                bitset_index += 1;
-            }
-            precommit_bitset.reset(bitset_index);
-
-            if bitset_index >= self.validators.count(header.parent_hash()) {
-                cwarn!(
-                    ENGINE,
-                    "verify_block_external: Signer index {} is out of bound from validators of block hash {}",
-                    bitset_index,
-                    header.parent_hash()
-                );
-                return Err(BlockError::InvalidSeal.into())
-            }
-
-            let public = self.validators.get(header.parent_hash(), bitset_index);
-            if !verify_schnorr(&public, &signature, &precommit_hash)? {
-                let address = public_to_address(&public);
-                return Err(EngineError::BlockNotAuthorized(address.to_owned()).into())
-            }
-            counter += 1;
-        }
-
-        if !precommit_bitset.is_empty() {
-            cwarn!(ENGINE, "verify_block_external: Precommit bitset is not empty after checking precommit signatures.",);
+        let precommit_indices = precommit_bitset.indices();
+        let validator_count = self.validators.count(&precommit_validators_hash);
+        if let Some(index) = precommit_indices.iter().find(|index| **index >= validator_count) {
+            cwarn!(
+                ENGINE,
+                "verify_block_external: Signer index {} is out of bound from validators of block hash {}",
+                index,
+                precommit_validators_hash
+            );
             return Err(BlockError::InvalidSeal.into())
         }
+        let signers: Vec<Public> =
+            precommit_indices.iter().map(|index| self.validators.get(&precommit_validators_hash, *index)).collect();
+        // The precommits are either the signatures of the signers or their aggregated signature after the activation.
+        verify_precommits(&signers, &precommits, &precommit_hash, self.is_aggregated_seal_active(header.number()))?;
+        let power: u64 =
+            precommit_indices.iter().map(|index| self.validators.weight(&precommit_validators_hash, *index)).sum();
 
         // Genesisblock does not have signatures
         if header.number() == 1 {
             return Ok(())
         }
-        self.check_above_threshold(counter).map_err(Into::into)
+        // Neither does the last block sealed by the engine which preceded Tendermint.
+        let parent = self.client().block_header(&BlockId::Hash(*header.parent_hash()));
+        if parent.map_or(false, |parent| parent.seal().len() != self.seal_fields(header)) {
+            return Ok(())
+        }
+        self.check_above_threshold(&precommit_validators_hash, power).map_err(Into::into)
     }
 
     fn signals_epoch_end(&self, header: &Header) -> EpochChange {
@@ -825,7 +1318,64 @@ impl TendermintInner {
         if self.extension.is_expired_timeout_token(token) {
             return
         }
+        let vote_step = self.vote_step();
+        self.log(WalEntry::Timeout(vote_step));
+        self.notify_listeners(ConsensusEvent::Timeout {
+            height: vote_step.height,
+            view: vote_step.view,
+            step: vote_step.step,
+        });
+        self.handle_timeout();
+    }
+
+    fn notify_listeners(&self, event: ConsensusEvent) {
+        for listener in &*self.consensus_listeners.read() {
+            listener(&event);
+        }
+    }
+
+    fn add_consensus_listener(&self, listener: ConsensusListener) {
+        self.consensus_listeners.write().push(listener);
+    }
+
+    fn consensus_state(&self) -> ConsensusState {
+        let height = self.height();
+        let view = self.view();
+        let prev_block_hash = self.prev_block_hash();
+        let valid = self.lock_change.read().as_ref().and_then(|m| Some((m.on.step.view, m.on.block_hash?)));
+        let votes = [Step::Propose, Step::Prevote, Step::Precommit]
+            .iter()
+            .map(|&step| {
+                let indices: Vec<usize> = self
+                    .votes
+                    .get_all_votes_and_indices_in_round(&VoteStep::new(height, view, step))
+                    .into_iter()
+                    .map(|(index, _)| index)
+                    .collect();
+                StepVotes {
+                    step,
+                    bitset: BitSet::new_with_indices(&indices),
+                    signers: indices
+                        .iter()
+                        .map(|&index| self.validators.get_address(&prev_block_hash, index))
+                        .collect(),
+                }
+            })
+            .collect();
+        ConsensusState {
+            height,
+            view,
+            step: self.get_step(),
+            proposer: self.view_proposer(&prev_block_hash, height, view),
+            locked: self.locked(),
+            valid,
+            proposal: self.proposal(),
+            votes,
+            timeout: self.extension.remaining_timeout(),
+        }
+    }
 
+    fn handle_timeout(&self) {
         let next_step = match *self.step.read() {
             Step::Propose => {
                 ctrace!(ENGINE, "Propose timeout.");
@@ -876,25 +1426,101 @@ impl TendermintInner {
 
     fn on_close_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
         let author = *block.header().author();
+        let number = block.header().number();
         let transactions = block.transactions().to_owned().into_iter();
         let fee = transactions.map(|tx| tx.fee).sum();
+        let burned_fee = self.fee_burn.burned(fee);
         let stakes = stake::get_stakes(block.state()).expect("Cannot get Stake status");
-        for (address, share) in stake::fee_distribute(&author, fee, &stakes) {
+        // The block reward goes to the proposer, and the fee is shared with the stakeholders.
+        self.machine.add_balance(block, &author, self.block_reward.reward(number))?;
+        for (address, share) in stake::fee_distribute(&author, fee - burned_fee, &stakes) {
             self.machine.add_balance(block, &address, share)?
         }
+        self.machine.burn_fee(block, burned_fee)?;
+        if let Some(downtime) = self.downtime {
+            let precommits = self.parent_precommits(block.header().parent_hash());
+            stake::track_downtime(block.state_mut(), number, &downtime, &precommits)?;
+        }
         Ok(())
     }
 
+    /// Returns whether each validator signed the precommit in the seal of the given block.
+    /// The seal of the block being closed is not available yet, so the parent's seal is used.
+    fn parent_precommits(&self, parent_hash: &H256) -> Vec<(Address, bool)> {
+        let client = self.client();
+        let parent = match client.block_header(&BlockId::Hash(*parent_hash)) {
+            Some(parent) => parent,
+            None => return Vec::new(),
+        };
+        // The genesis block has no precommits.
+        if parent.number() < 2 {
+            return Vec::new()
+        }
+        // The precommits are signed by the validators of the grandparent block,
+        // which are decided by the great-grandparent block.
+        let validators_hash = match client.block_header(&BlockId::Hash(parent.parent_hash())) {
+            Some(grandparent) => grandparent.parent_hash(),
+            None => return Vec::new(),
+        };
+        let seal = parent.seal();
+        let precommit_bitset: BitSet = match seal.get(3).map(|bitset| UntrustedRlp::new(bitset).as_val()) {
+            Some(Ok(bitset)) => bitset,
+            _ => {
+                cwarn!(ENGINE, "Cannot read the precommit bitset of {}", parent_hash);
+                return Vec::new()
+            }
+        };
+        (0..self.validators.count(&validators_hash))
+            .map(|index| (self.validators.get_address(&validators_hash, index), precommit_bitset.is_set(index)))
+            .collect()
+    }
+
     fn register_client(&self, client: Weak<EngineClient>) {
         if let Some(c) = client.upgrade() {
             self.height.store(c.chain_info().best_block_number as usize + 1, AtomicOrdering::SeqCst);
             *self.last_confirmed_view.write() = (c.best_block_header().hash(), 0);
         }
         *self.client.write() = Some(Weak::clone(&client));
-        self.restore();
         self.extension.register_client(Weak::clone(&client));
         self.validators.register_client(Weak::clone(&client));
         self.chain_notify.register_client(client);
+        self.restore();
+    }
+
+    /// Submits the evidence of the double vote to the stake handler with a transaction signed by the engine signer.
+    fn report_double_vote(&self, double: &DoubleVote<ConsensusMessage>) {
+        let (message1, message2) = double.votes();
+        if message1.on.block_hash == message2.on.block_hash {
+            // The messages only differ in their signatures, so they are not the evidence.
+            return
+        }
+        let signer = self.signer.read();
+        let public = match signer.public() {
+            Some(public) => *public,
+            None => return,
+        };
+        let client = self.client();
+        // The signer may have queued transactions which are not in the latest block yet.
+        let seq = client.next_seq(&public);
+        let params = self.machine.params();
+        let transaction = Transaction {
+            seq,
+            fee: params.min_custom_transaction_cost,
+            network_id: params.network_id,
+            action: stake::double_vote_report_action(message1, message2),
+        };
+        let signature = match signer.sign_ecdsa(transaction.hash()) {
+            Ok(signature) => signature,
+            Err(err) => {
+                cwarn!(ENGINE, "Cannot sign the report of the double vote: {:?}", err);
+                return
+            }
+        };
+        drop(signer);
+        let signed = SignedTransaction::try_new(UnverifiedTransaction::new(transaction, signature))
+            .expect("The transaction is signed by the engine signer; qed");
+        cinfo!(ENGINE, "Reporting the double vote of the validator {}", message1.signer_index);
+        client.queue_own_transaction(signed);
     }
 
     fn handle_message(&self, rlp: &[u8]) -> Result<(), EngineError> {
@@ -908,7 +1534,7 @@ impl TendermintInner {
             let signer_index = message.signer_index;
             let prev_height = (message.on.step.height - 1) as u64;
             let sender_public = if let Some(prev_block_hash) =
-                self.client().block_header(&BlockId::Number(prev_height)).map(|header| header.parent_hash())
+                self.client().block_header(&BlockId::Number(prev_height)).map(|header| header.hash())
             {
                 if signer_index >= self.validators.count(&prev_block_hash) {
                     return Err(EngineError::ValidatorNotExist {
@@ -951,8 +1577,10 @@ impl TendermintInner {
             if let Some(double) = self.votes.vote(message.clone()) {
                 let height = message.on.step.height as BlockNumber;
                 self.validators.report_malicious(&sender, height, height, ::rlp::encode(&double).into_vec());
+                self.report_double_vote(&double);
                 return Err(EngineError::DoubleVote(sender))
             }
+            self.log(WalEntry::Message(message.clone()));
             ctrace!(ENGINE, "Handling a valid {:?} from {}.", message, sender);
             self.handle_valid_message(&message);
             self.broadcast_state(&self.vote_step(), self.proposal(), self.get_current_votes());
@@ -962,7 +1590,7 @@ impl TendermintInner {
 
     fn is_proposal(&self, header: &Header) -> bool {
         let number = header.number();
-        if self.height() > number as usize {
+        if self.height() > number as usize || !self.is_sealed_by_tendermint(header) {
             return false
         }
 
@@ -1013,20 +1641,29 @@ impl TendermintInner {
         self.signer.read().public().cloned()
     }
 
+    fn validators(&self, parent: &Header) -> Option<Vec<Public>> {
+        let hash = parent.hash();
+        Some((0..self.validators.count(&hash)).map(|index| self.validators.get(&hash, index)).collect())
+    }
+
     fn signer_index(&self, bh: &H256) -> Option<usize> {
         // FIXME: More effecient way to find index
         self.signer.read().public().and_then(|public| self.validators.get_index(bh, public))
     }
 
-    fn register_network_extension_to_service(&self, service: &NetworkService) {
+    fn register_network_extension_to_service(&self, service: &Arc<NetworkService>) {
         service.register_extension(Arc::clone(&self.extension));
     }
 
-    fn block_reward(&self, _block_number: u64) -> u64 {
-        self.block_reward
+    fn block_reward(&self, block_number: u64) -> u64 {
+        self.block_reward.reward(block_number)
     }
 
-    fn register_chain_notify(&self, client: &Client) {
+    fn burned_fee(&self, _block_number: u64, block_fee: u64) -> u64 {
+        self.fee_burn.burned(block_fee)
+    }
+
+    fn register_chain_notify(&self, client: &Arc<Client>) {
         client.add_notify(Arc::downgrade(&self.chain_notify) as Weak<ChainNotify>);
     }
 
@@ -1034,6 +1671,35 @@ impl TendermintInner {
         let (hash, _) = &*self.last_confirmed_view.read();
         *hash
     }
+
+    fn commit_certificate(&self, block_number: BlockNumber) -> Option<CommitCertificate> {
+        // The genesis block is not committed by the validators.
+        if block_number == 0 {
+            return None
+        }
+        let client = self.client();
+        let header = client.block_header(&BlockId::Number(block_number))?.decode();
+        // The precommits are in the seal of the child block.
+        let child = client.block_header(&BlockId::Number(block_number + 1))?.decode();
+        let parent_hash = header.parent_hash();
+        let validators = (0..self.validators.count(parent_hash))
+            .map(|index| (self.validators.get(parent_hash, index), self.validators.weight(parent_hash, index)))
+            .collect();
+        let view = previous_block_view(&child).ok()?;
+        let precommit_bitset = UntrustedRlp::new(child.seal().get(3)?).as_val().ok()?;
+        let precommits = UntrustedRlp::new(child.seal().get(2)?).as_list().ok()?;
+        // The certificate cannot be made once the state of the block is pruned.
+        let state = client.state_at(BlockId::Hash(header.hash()))?;
+        let next_validators_proof = stake::validators_proof(&state).ok()?;
+        Some(CommitCertificate {
+            header,
+            validators,
+            view,
+            precommit_bitset,
+            precommits,
+            next_validators_proof,
+        })
+    }
 }
 
 impl ConsensusEngine<CodeChainMachine> for Tendermint {
@@ -1120,7 +1786,8 @@ impl ConsensusEngine<CodeChainMachine> for Tendermint {
     }
 
     fn on_close_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
-        self.inner.lock().on_close_block(block)
+        self.inner.lock().on_close_block(block)?;
+        self.machine.close_action_handlers(block, &self.action_handlers)
     }
 
     fn register_client(&self, client: Weak<EngineClient>) {
@@ -1151,19 +1818,27 @@ impl ConsensusEngine<CodeChainMachine> for Tendermint {
         self.inner.lock().signer_public()
     }
 
-    fn register_network_extension_to_service(&self, service: &NetworkService) {
+    fn validators(&self, parent: &Header) -> Option<Vec<Public>> {
+        self.inner.lock().validators(parent)
+    }
+
+    fn register_network_extension_to_service(&self, service: &Arc<NetworkService>) {
         self.inner.lock().register_network_extension_to_service(service)
     }
 
-    fn block_reward(&self, _block_number: u64) -> u64 {
-        self.inner.lock().block_reward(_block_number)
+    fn block_reward(&self, block_number: u64) -> u64 {
+        self.inner.lock().block_reward(block_number)
+    }
+
+    fn burned_fee(&self, block_number: u64, block_fee: u64) -> u64 {
+        self.inner.lock().burned_fee(block_number, block_fee)
     }
 
     fn recommended_confirmation(&self) -> u32 {
         1
     }
 
-    fn register_chain_notify(&self, client: &Client) {
+    fn register_chain_notify(&self, client: &Arc<Client>) {
         self.inner.lock().register_chain_notify(client);
     }
 
@@ -1178,6 +1853,22 @@ impl ConsensusEngine<CodeChainMachine> for Tendermint {
     fn action_handlers(&self) -> &[Arc<ActionHandler>] {
         &self.action_handlers
     }
+
+    fn commit_certificate(&self, block_number: BlockNumber) -> Option<CommitCertificate> {
+        self.inner.lock().commit_certificate(block_number)
+    }
+
+    fn consensus_state(&self) -> Option<ConsensusState> {
+        Some(self.inner.lock().consensus_state())
+    }
+
+    fn rewind_consensus_state(&self, db: &KeyValueDB, batch: &mut DBTransaction, best_block_number: BlockNumber) {
+        wal::rewind(db, batch, best_block_number as Height)
+    }
+
+    fn add_consensus_listener(&self, listener: ConsensusListener) {
+        self.inner.lock().add_consensus_listener(listener)
+    }
 }
 
 struct TendermintChainNotify {
@@ -1276,15 +1967,15 @@ where
             addresses.insert(address);
         }
 
-        let n = addresses.len();
-        let threshold = self.subchain_validators.len() * 2 / 3;
-        if n > threshold {
+        let power: u64 = addresses.iter().map(|address| self.subchain_validators.weight_of_address(address)).sum();
+        let threshold = self.subchain_validators.total_weight(header.parent_hash()) * 2 / 3;
+        if power > threshold {
             Ok(())
         } else {
             Err(EngineError::BadSealFieldSize(OutOfBounds {
-                min: Some(threshold),
+                min: Some(threshold as usize),
                 max: None,
-                found: n,
+                found: power as usize,
             })
             .into())
         }
@@ -1307,6 +1998,11 @@ fn destructure_proofs(combined: &[u8]) -> Result<(BlockNumber, &[u8], &[u8]), Er
     Ok((rlp.at(0)?.as_val()?, rlp.at(1)?.data()?, rlp.at(2)?.data()?))
 }
 
+/// Extracts the validators from the combined proof of a transition.
+fn set_proof(combined: &[u8]) -> Result<&[u8], Error> {
+    Ok(destructure_proofs(combined)?.1)
+}
+
 struct TendermintExtension {
     tendermint: RwLock<Option<Weak<Tendermint>>>,
     client: RwLock<Option<Weak<EngineClient>>>,
@@ -1314,6 +2010,10 @@ struct TendermintExtension {
     api: Mutex<Option<Arc<Api>>>,
     timeouts: TimeoutParams,
     timeout_token_nonce: AtomicUsize,
+    /// When the timer of the current step expires.
+    timeout_deadline: RwLock<Option<Instant>>,
+    /// Picks the peers to gossip.
+    rng: Mutex<XorShiftRng>,
 }
 
 const MIN_PEERS_PROPAGATION: usize = 4;
@@ -1328,9 +2028,16 @@ impl TendermintExtension {
             api: Mutex::new(None),
             timeouts,
             timeout_token_nonce: AtomicUsize::new(ENGINE_TIMEOUT_TOKEN_NONCE_BASE),
+            timeout_deadline: RwLock::new(None),
+            rng: Mutex::new(XorShiftRng::from_rng(thread_rng()).expect("The thread RNG never fails")),
         }
     }
 
+    #[cfg(test)]
+    fn set_rng(&self, rng: XorShiftRng) {
+        *self.rng.lock() = rng;
+    }
+
     fn register_client(&self, client: Weak<EngineClient>) {
         *self.client.write() = Some(client);
     }
@@ -1348,11 +2055,13 @@ impl TendermintExtension {
     }
 
     fn select_random_peers(&self) -> Vec<NodeId> {
-        let mut peers: Vec<NodeId> = self.peers.write().keys().cloned().collect();
+        let mut peers: Vec<NodeId> = self.peers.read().keys().cloned().collect();
+        // The order of the keys differs by the process, so the peers are sorted before they are shuffled.
+        peers.sort();
         let mut count = (peers.len() as f64).powf(0.5).round() as usize;
         count = cmp::min(count, MAX_PEERS_PROPAGATION);
         count = cmp::max(count, MIN_PEERS_PROPAGATION);
-        peers.shuffle(&mut thread_rng());
+        peers.shuffle(&mut *self.rng.lock());
         peers.truncate(count);
         peers
     }
@@ -1409,6 +2118,16 @@ impl TendermintExtension {
         };
     }
 
+    /// Sends the messages of the aggregations to all the peers, since every signer must receive them.
+    fn broadcast_aggregation_message(&self, message: TendermintMessage) {
+        let message = message.rlp_bytes().into_vec();
+        if let Some(api) = self.api.lock().as_ref() {
+            for token in self.peers.read().keys() {
+                api.send(&token, &message);
+            }
+        };
+    }
+
     fn request_proposal_to_any(&self, height: Height, view: View) {
         let peers_guard = self.peers.read();
         for (token, peer) in peers_guard.iter() {
@@ -1475,10 +2194,29 @@ impl TendermintExtension {
             let expired_token_nonce = self.timeout_token_nonce.fetch_add(1, AtomicOrdering::SeqCst);
 
             api.clear_timer(expired_token_nonce).expect("Timer clear succeeds");
-            api.set_timer_once(expired_token_nonce + 1, self.timeouts.timeout(step, view)).expect("Timer set succeeds");
+            let timeout = self.timeouts.timeout(step, view);
+            api.set_timer_once(expired_token_nonce + 1, timeout).expect("Timer set succeeds");
+            *self.timeout_deadline.write() = timeout.to_std().ok().map(|timeout| Instant::now() + timeout);
+        };
+    }
+
+    fn set_timer_aggregation(&self) {
+        if let Some(api) = self.api.lock().as_ref() {
+            api.clear_timer(ENGINE_TIMEOUT_AGGREGATION).expect("Timer clear succeeds");
+            api.set_timer_once(ENGINE_TIMEOUT_AGGREGATION, self.timeouts.aggregation).expect("Timer set succeeds");
         };
     }
 
+    fn remaining_timeout(&self) -> Option<Duration> {
+        let deadline = (*self.timeout_deadline.read())?;
+        let now = Instant::now();
+        if deadline > now {
+            Some(deadline - now)
+        } else {
+            Some(Duration::from_secs(0))
+        }
+    }
+
     fn register_tendermint(&self, tendermint: Weak<Tendermint>) {
         *self.tendermint.write() = Some(tendermint);
     }
@@ -1564,7 +2302,11 @@ impl TendermintExtension {
                 return
             }
 
-            tendermint.votes.vote(message);
+            tendermint.votes.vote(message.clone());
+            tendermint.log(WalEntry::ProposalBlock {
+                message,
+                block: bytes.clone(),
+            });
         }
 
         if let Err(e) = c.import_block(bytes) {
@@ -1726,6 +2468,36 @@ impl NetworkExtension for TendermintExtension {
                     self.send_message(token, vote.rlp_bytes().into_vec());
                 }
             }
+            Ok(TendermintMessage::AggregationRequest {
+                round,
+                signature,
+            }) => {
+                t.on_aggregation_request(round, signature);
+            }
+            Ok(TendermintMessage::AggregationCommitment {
+                round,
+                signer_index,
+                commitment,
+                signature,
+            }) => {
+                t.on_aggregation_commitment(round, signer_index, commitment, signature);
+            }
+            Ok(TendermintMessage::AggregationNonce {
+                round,
+                signer_index,
+                nonce,
+                signature,
+            }) => {
+                t.on_aggregation_nonce(round, signer_index, nonce, signature);
+            }
+            Ok(TendermintMessage::AggregationPartial {
+                round,
+                signer_index,
+                partial,
+                signature,
+            }) => {
+                t.on_aggregation_partial(round, signer_index, partial, signature);
+            }
             _ => cinfo!(ENGINE, "Invalid message from peer {}", token),
         }
     }
@@ -1733,10 +2505,14 @@ impl NetworkExtension for TendermintExtension {
 
 impl TimeoutHandler for TendermintExtension {
     fn on_timeout(&self, token: TimerToken) {
-        debug_assert!(token >= ENGINE_TIMEOUT_TOKEN_NONCE_BASE);
+        debug_assert!(token == ENGINE_TIMEOUT_AGGREGATION || token >= ENGINE_TIMEOUT_TOKEN_NONCE_BASE);
         if let Some(ref weak) = *self.tendermint.read() {
             if let Some(c) = weak.upgrade() {
-                c.on_timeout(token);
+                if token == ENGINE_TIMEOUT_AGGREGATION {
+                    c.inner.lock().on_aggregation_timeout();
+                } else {
+                    c.on_timeout(token);
+                }
             }
         }
     }
@@ -1744,14 +2520,18 @@ impl TimeoutHandler for TendermintExtension {
 
 #[cfg(test)]
 mod tests {
+    use ckey::{sign_schnorr, KeyPair, Private};
+    use cstate::TopStateView;
+    use ctypes::transaction::Action;
+
+    use super::aggregation::sign_aggregated;
+    use super::*;
     use crate::block::{ClosedBlock, IsBlock, OpenBlock};
-    use crate::client::TestBlockChainClient;
+    use crate::client::{BlockChainClient, TestBlockChainClient};
     use crate::consensus::CodeChainEngine;
     use crate::scheme::Scheme;
     use crate::tests::helpers::get_temp_state_db;
 
-    use super::*;
-
     /// Accounts inserted with "0" and "1" are validators. First proposer is "0".
     fn setup() -> (Scheme, Arc<AccountProvider>, Arc<EngineClient>) {
         let tap = AccountProvider::transient_provider();
@@ -1821,6 +2601,34 @@ mod tests {
         assert!(b.lock().try_seal(scheme.engine.as_ref(), seal).is_ok());
     }
 
+    #[test]
+    fn listeners_get_the_timeout_and_the_step_transition() {
+        let (scheme, _tap, _c) = setup();
+        let engine = scheme.engine;
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_clone = Arc::clone(&events);
+        engine.add_consensus_listener(Box::new(move |event| events_clone.lock().push(*event)));
+
+        engine.on_timeout(ENGINE_TIMEOUT_TOKEN_NONCE_BASE);
+
+        assert_eq!(*events.lock(), vec![
+            ConsensusEvent::Timeout {
+                height: 1,
+                view: 0,
+                step: Step::Propose,
+            },
+            ConsensusEvent::StepChanged {
+                height: 1,
+                view: 0,
+                step: Step::Prevote,
+            },
+        ]);
+        let state = engine.consensus_state().unwrap();
+        assert_eq!((state.height, state.view, state.step), (1, 0, Step::Prevote));
+        assert_eq!(state.proposal, None);
+        assert_eq!(state.locked, None);
+    }
+
     #[test]
     fn seal_signatures_checking() {
         let (spec, tap, _c) = setup();
@@ -1888,4 +2696,253 @@ mod tests {
         };
         engine.stop();
     }
+
+    #[test]
+    fn double_vote_is_reported_with_the_next_seq_of_the_signer() {
+        let tap = AccountProvider::transient_provider();
+        let scheme = Scheme::new_test_tendermint();
+        let client = Arc::new(TestBlockChainClient::new_with_scheme(Scheme::new_test_tendermint()));
+        scheme.engine.register_client(Arc::downgrade(&client) as Weak<EngineClient>);
+        let engine = scheme.engine;
+        let reporter = insert_and_register(&tap, engine.as_ref(), "0");
+        client.set_balance(reporter, 1000);
+        client.set_seq(reporter, 3);
+        // Moves to the prevote step of the first height.
+        engine.on_timeout(ENGINE_TIMEOUT_TOKEN_NONCE_BASE);
+
+        let offender = KeyPair::from_private(Private::from(blake256("1"))).unwrap();
+        let prevote = |block_hash| {
+            let on = VoteOn {
+                step: VoteStep::new(1, 0, Step::Prevote),
+                block_hash: Some(block_hash),
+            };
+            let signature =
+                sign_schnorr(offender.private(), &blake256(message_info_rlp(on.step, on.block_hash))).unwrap();
+            ConsensusMessage {
+                on,
+                signature,
+                signer_index: 1,
+            }
+            .rlp_bytes()
+            .into_vec()
+        };
+        assert!(engine.handle_message(&prevote(H256::random())).is_ok());
+        match engine.handle_message(&prevote(H256::random())) {
+            Err(EngineError::DoubleVote(address)) => assert_eq!(offender.address(), address),
+            _ => panic!(),
+        }
+
+        let reports = client.ready_transactions();
+        assert_eq!(1, reports.len());
+        assert_eq!(reporter, public_to_address(&reports[0].signer_public()));
+        assert_eq!(3, reports[0].seq);
+        match reports[0].action {
+            Action::Custom {
+                handler_id,
+                ..
+            } => assert_eq!(handler_id, engine.action_handlers()[0].handler_id()),
+            _ => panic!(),
+        }
+        engine.stop();
+    }
+
+    #[test]
+    fn block_reward_is_paid_to_the_proposer() {
+        let scheme = Scheme::new_test_tendermint();
+        let json = ::cjson::scheme::Scheme::load(include_bytes!("../../../res/tendermint.json") as &[u8]).unwrap();
+        let machine = CodeChainMachine::new(json.params.into(), Vec::new());
+        let mut params: TendermintParams = match json.engine {
+            ::cjson::scheme::Engine::Tendermint(tendermint) => tendermint.params.into(),
+            _ => panic!(),
+        };
+        params.block_reward = RewardSchedule::constant(7);
+        let engine = Tendermint::new(params, machine);
+        let client: Arc<EngineClient> = Arc::new(TestBlockChainClient::new_with_scheme(Scheme::new_test_tendermint()));
+        engine.register_client(Arc::downgrade(&client));
+
+        let proposer = Address::random();
+        let db = scheme.ensure_genesis_state(get_temp_state_db()).unwrap();
+        let genesis_header = scheme.genesis_header();
+        let b = OpenBlock::try_new(engine.as_ref(), db, &genesis_header, proposer, vec![], false).unwrap();
+        let b = b.close(*genesis_header.transactions_root(), *genesis_header.invoices_root()).unwrap();
+        assert_eq!(Ok(7), b.block().state().balance(&proposer));
+        engine.stop();
+    }
+
+    #[test]
+    fn restore_replays_the_wal() {
+        let client = Arc::new(TestBlockChainClient::new_with_scheme(Scheme::new_test_tendermint()));
+        let genesis_hash = client.genesis_hash;
+        let validator = KeyPair::from_private(Private::from(blake256("0"))).unwrap();
+        let on = VoteOn {
+            step: VoteStep::new(1, 1, Step::Prevote),
+            block_hash: None,
+        };
+        let signature = sign_schnorr(validator.private(), &blake256(message_info_rlp(on.step, on.block_hash))).unwrap();
+        let prevote = ConsensusMessage {
+            on,
+            signature,
+            signer_index: 0,
+        };
+        {
+            let (wal, _) = Wal::open(client.get_kvdb());
+            wal.append(&WalEntry::Step(VoteStep::new(1, 0, Step::Propose)));
+            wal.append(&WalEntry::Step(VoteStep::new(1, 1, Step::Prevote)));
+            wal.append(&WalEntry::Message(prevote));
+            wal.append(&WalEntry::Confirmed {
+                height: 0,
+                view: 0,
+                block_hash: genesis_hash,
+            });
+            // The node crashes here.
+        }
+
+        let json = ::cjson::scheme::Scheme::load(include_bytes!("../../../res/tendermint.json") as &[u8]).unwrap();
+        let params = match json.engine {
+            ::cjson::scheme::Engine::Tendermint(tendermint) => tendermint.params,
+            _ => unreachable!(),
+        };
+        let engine = Tendermint::new(params.into(), CodeChainMachine::new(json.params.into(), Vec::new()));
+        engine.register_client(Arc::downgrade(&client) as Weak<EngineClient>);
+        {
+            let inner = engine.inner.lock();
+            assert_eq!((inner.height(), inner.view(), *inner.step.read()), (1, 1, Step::Prevote));
+            assert_eq!(inner.proposal(), None);
+            assert_eq!(1, inner.votes.count_round_votes(&VoteStep::new(1, 1, Step::Prevote)));
+        }
+        engine.stop();
+    }
+
+    fn set_precommits(header: &mut Header, precommits: Vec<SchnorrSignature>, signers: &[usize]) {
+        let seal = Seal::Tendermint {
+            prev_view: 0,
+            cur_view: 0,
+            precommits,
+            precommit_bitset: BitSet::new_with_indices(signers),
+        }
+        .seal_fields()
+        .unwrap();
+        header.set_seal(seal);
+    }
+
+    /// The engine of the test scheme whose params are modified.
+    fn engine_with_params<F>(client: &Arc<EngineClient>, modify: F) -> Arc<Tendermint>
+    where
+        F: FnOnce(&mut ::cjson::scheme::TendermintParams), {
+        let json = ::cjson::scheme::Scheme::load(include_bytes!("../../../res/tendermint.json") as &[u8]).unwrap();
+        let mut params = match json.engine {
+            ::cjson::scheme::Engine::Tendermint(tendermint) => tendermint.params,
+            _ => unreachable!(),
+        };
+        modify(&mut params);
+        let engine = Tendermint::new(params.into(), CodeChainMachine::new(json.params.into(), Vec::new()));
+        engine.register_client(Arc::downgrade(client));
+        engine
+    }
+
+    /// The engine of the test scheme whose precommits may be aggregated from the activation.
+    fn engine_with_aggregated_seal(activation: BlockNumber, client: &Arc<EngineClient>) -> Arc<Tendermint> {
+        engine_with_params(client, |params| params.aggregated_seal_activation = Some(activation.into()))
+    }
+
+    #[test]
+    fn proposer_is_weighted_from_the_activation() {
+        let (_scheme, _tap, client) = setup();
+        let engine = engine_with_params(&client, |params| params.weighted_proposer_activation = Some(3.into()));
+        {
+            let inner = engine.inner.lock();
+            let validator = |index| inner.validators.get_address(&Default::default(), index);
+            let parent = |height: Height| H256::from(height as u64);
+
+            // The validators take turns by the height before the activation.
+            assert_eq!(validator(1), inner.view_proposer(&parent(1), 1, 0));
+            assert_eq!(validator(3), inner.view_proposer(&parent(2), 2, 1));
+            // The priorities restart from zero after the block before the activation and advance once a height.
+            assert_eq!(validator(0), inner.view_proposer(&parent(3), 3, 0));
+            assert_eq!(validator(1), inner.view_proposer(&parent(3), 3, 1));
+            assert_eq!(validator(1), inner.view_proposer(&parent(4), 4, 0));
+            assert_eq!(validator(0), inner.view_proposer(&parent(5), 5, 2));
+        }
+        engine.stop();
+    }
+
+    #[test]
+    fn aggregated_seal_is_verified_like_the_signatures() {
+        let (_scheme, tap, client) = setup();
+        let engine = engine_with_aggregated_seal(4, &client);
+
+        let mut header = Header::default();
+        header.set_number(4);
+        header.set_author(insert_and_unlock(&tap, "0"));
+        header.set_parent_hash(Default::default());
+
+        let precommit_hash =
+            blake256(message_info_rlp(VoteStep::new(3, 0, Step::Precommit), Some(*header.parent_hash())));
+        let key_pairs: Vec<_> =
+            (0..4).map(|i| KeyPair::from_private(Private::from(blake256(i.to_string()))).unwrap()).collect();
+
+        // Every set of the signers gets the same verdict from both forms of the seal.
+        for subset in 1..16usize {
+            let indices: Vec<_> = (0..4).filter(|i| subset & (1 << i) != 0).collect();
+            let signers: Vec<_> = indices.iter().map(|i| key_pairs[*i].clone()).collect();
+
+            let signatures =
+                signers.iter().map(|key_pair| sign_schnorr(key_pair.private(), &precommit_hash).unwrap()).collect();
+            set_precommits(&mut header, signatures, &indices);
+            let result = engine.verify_block_external(&header);
+            assert_eq!(indices.len() >= 3, result.is_ok());
+
+            set_precommits(&mut header, vec![sign_aggregated(&signers, &precommit_hash)], &indices);
+            assert_eq!(format!("{:?}", result), format!("{:?}", engine.verify_block_external(&header)));
+        }
+
+        // The signers must match the bitset.
+        let signers = &key_pairs[..3];
+        set_precommits(&mut header, vec![sign_aggregated(signers, &precommit_hash)], &[0, 1, 3]);
+        match engine.verify_block_external(&header) {
+            Err(Error::Block(BlockError::InvalidSeal)) => {}
+            _ => panic!(),
+        }
+
+        // The precommits must be on the parent.
+        let other_hash = blake256(message_info_rlp(VoteStep::new(3, 1, Step::Precommit), Some(*header.parent_hash())));
+        set_precommits(&mut header, vec![sign_aggregated(signers, &other_hash)], &[0, 1, 2]);
+        match engine.verify_block_external(&header) {
+            Err(Error::Block(BlockError::InvalidSeal)) => {}
+            _ => panic!(),
+        }
+
+        set_precommits(&mut header, vec![sign_aggregated(signers, &precommit_hash)], &[0, 1, 2]);
+        assert!(engine.verify_block_external(&header).is_ok());
+        engine.stop();
+    }
+
+    #[test]
+    fn aggregated_seal_is_rejected_before_the_activation() {
+        let (scheme, tap, client) = setup();
+
+        let mut header = Header::default();
+        header.set_number(4);
+        header.set_author(insert_and_unlock(&tap, "0"));
+        header.set_parent_hash(Default::default());
+
+        let precommit_hash =
+            blake256(message_info_rlp(VoteStep::new(3, 0, Step::Precommit), Some(*header.parent_hash())));
+        let signers: Vec<_> =
+            (0..3).map(|i| KeyPair::from_private(Private::from(blake256(i.to_string()))).unwrap()).collect();
+        set_precommits(&mut header, vec![sign_aggregated(&signers, &precommit_hash)], &[0, 1, 2]);
+
+        match scheme.engine.verify_block_external(&header) {
+            Err(Error::Block(BlockError::InvalidSeal)) => {}
+            _ => panic!(),
+        }
+        let engine = engine_with_aggregated_seal(5, &client);
+        match engine.verify_block_external(&header) {
+            Err(Error::Block(BlockError::InvalidSeal)) => {}
+            _ => panic!(),
+        }
+        let engine = engine_with_aggregated_seal(4, &client);
+        assert!(engine.verify_block_external(&header).is_ok());
+        scheme.engine.stop();
+    }
 }
@@ -15,31 +15,102 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use cjson;
-use ckey::{Address, PlatformAddress};
+use ckey::{public_to_address, Address, PlatformAddress};
+use ctypes::BlockNumber;
 use std::collections::HashMap;
 use time::Duration;
 
-use super::super::validator_set::{new_validator_set, ValidatorSet};
+use super::super::reward::{FeeBurn, RewardSchedule};
+use super::super::validator_set::{new_dynamic_validator_set, new_validator_set, ValidatorSet};
 use super::types::View;
-use super::Step;
+use super::{set_proof, Step};
+use crate::error::SchemeError;
 
 /// `Tendermint` params.
 pub struct TendermintParams {
     /// List of validators.
     pub validators: Box<ValidatorSet>,
+    /// The addresses of the validators in the scheme, which validate the blocks until the first election.
+    pub initial_validators: Vec<Address>,
     /// Timeout durations for different steps.
     pub timeouts: TimeoutParams,
-    /// Reward per block in base units.
-    pub block_reward: u64,
+    /// Reward per block in base units, which may change as the chain grows.
+    pub block_reward: RewardSchedule,
+    /// The portion of the block fee which is burned.
+    pub fee_burn: FeeBurn,
     /// Tokens distributed at genesis.
     pub genesis_stakes: HashMap<Address, u64>,
+    /// The validator election. The validators are fixed if it's None.
+    pub election: Option<ElectionParams>,
+    /// The downtime tracking. The scheme cannot enable it without the election.
+    pub downtime: Option<DowntimeParams>,
+    /// The height from which the proposer is picked in proportion to the voting power.
+    pub weighted_proposer_activation: Option<BlockNumber>,
+    /// The block number from which the precommits in a seal may be aggregated into a single signature.
+    pub aggregated_seal_activation: Option<BlockNumber>,
+}
+
+/// Params of the validator election by the delegated stakes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElectionParams {
+    /// The number of blocks in a term.
+    pub term_length: u64,
+    /// The maximum number of the elected validators.
+    pub max_num_of_validators: usize,
+}
+
+/// Params of jailing the validators which missed too many precommits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DowntimeParams {
+    /// The number of blocks in which the missed precommits are counted.
+    pub window: u64,
+    /// The validators which missed more precommits than this in a window are jailed.
+    pub max_missed_blocks: u64,
+}
+
+impl TendermintParams {
+    /// Checks the params in the scheme which cannot make a working engine.
+    pub fn verify_scheme(p: &cjson::scheme::TendermintParams) -> Result<(), SchemeError> {
+        let term_length: u64 = p.term_length.map_or(0, Into::into);
+        if term_length > 0 && p.max_num_of_validators.map_or(0, Into::<usize>::into) == 0 {
+            return Err(SchemeError::InvalidEngineParams("termLength requires a positive maxNumOfValidators"))
+        }
+        let downtime_window: u64 = p.downtime_window.map_or(0, Into::into);
+        if (downtime_window > 0) != p.max_missed_blocks.is_some() {
+            return Err(SchemeError::InvalidEngineParams("downtimeWindow and maxMissedBlocks must be given together"))
+        }
+        // Only the elected validators are jailed.
+        if downtime_window > 0 && term_length == 0 {
+            return Err(SchemeError::InvalidEngineParams("downtimeWindow requires termLength"))
+        }
+        Ok(())
+    }
 }
 
 impl From<cjson::scheme::TendermintParams> for TendermintParams {
     fn from(p: cjson::scheme::TendermintParams) -> Self {
         let dt = TimeoutParams::default();
+        let term_length = p.term_length.map(Into::into).filter(|term_length: &u64| *term_length > 0);
+        let election = term_length.map(|term_length| ElectionParams {
+            term_length,
+            max_num_of_validators: p.max_num_of_validators.expect("Verified by verify_scheme").into(),
+        });
+        let downtime_window = p.downtime_window.map(Into::into).filter(|window: &u64| *window > 0);
+        let downtime = match (election, downtime_window, p.max_missed_blocks) {
+            (Some(_), Some(window), Some(max_missed_blocks)) => Some(DowntimeParams {
+                window,
+                max_missed_blocks: max_missed_blocks.into(),
+            }),
+            _ => None,
+        };
+        let initial_validators = p.validators.iter().map(public_to_address).collect();
+        let validators = match election {
+            Some(election) => new_dynamic_validator_set(p.validators, election.term_length, set_proof),
+            None => new_validator_set(p.validators),
+        };
         TendermintParams {
-            validators: new_validator_set(p.validators),
+            validators,
+            initial_validators,
             timeouts: TimeoutParams {
                 propose: p.timeout_propose.map_or(dt.propose, to_duration),
                 propose_delta: p.timeout_propose_delta.map_or(dt.propose_delta, to_duration),
@@ -48,14 +119,20 @@ impl From<cjson::scheme::TendermintParams> for TendermintParams {
                 precommit: p.timeout_precommit.map_or(dt.precommit, to_duration),
                 precommit_delta: p.timeout_precommit_delta.map_or(dt.precommit_delta, to_duration),
                 commit: p.timeout_commit.map_or(dt.commit, to_duration),
+                aggregation: p.timeout_aggregation.map_or(dt.aggregation, to_duration),
             },
-            block_reward: p.block_reward.map_or(0, Into::into),
+            block_reward: RewardSchedule::new(p.block_reward, p.reward_schedule),
+            fee_burn: FeeBurn::new(p.fee_burn_ratio),
             genesis_stakes: p
                 .genesis_stakes
                 .unwrap_or_default()
                 .into_iter()
                 .map(|(pa, amount)| (PlatformAddress::into_address(pa), amount))
                 .collect(),
+            election,
+            downtime,
+            weighted_proposer_activation: p.weighted_proposer_activation.map(Into::into),
+            aggregated_seal_activation: p.aggregated_seal_activation.map(Into::into),
         }
     }
 }
@@ -75,6 +152,7 @@ pub struct TimeoutParams {
     pub precommit: Duration,
     pub precommit_delta: Duration,
     pub commit: Duration,
+    pub aggregation: Duration,
 }
 
 impl Default for TimeoutParams {
@@ -87,6 +165,7 @@ impl Default for TimeoutParams {
             precommit: Duration::milliseconds(1000),
             precommit_delta: Duration::milliseconds(500),
             commit: Duration::milliseconds(1000),
+            aggregation: Duration::milliseconds(500),
         }
     }
 }
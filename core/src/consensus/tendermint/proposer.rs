@@ -0,0 +1,303 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ctypes::BlockNumber;
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use primitives::H256;
+
+/// The number of blocks whose proposer priorities are kept in memory.
+const PROPOSER_PRIORITY_CACHE_SIZE: usize = 128;
+
+/// The priorities of the validators in the weighted round-robin.
+///
+/// Every round, each validator gains its voting power as the priority and the validator with the highest priority
+/// proposes, paying back the total voting power. Within every `total weight` rounds, each validator proposes as many
+/// times as its voting power, evenly spread out, and the priorities go back to zero.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProposerPriority {
+    priorities: Vec<i128>,
+}
+
+impl ProposerPriority {
+    pub fn new(count: usize) -> Self {
+        Self {
+            priorities: vec![0; count],
+        }
+    }
+
+    /// Returns the index of the proposer of the next round.
+    pub fn next(&mut self, weights: &[u64]) -> usize {
+        if self.priorities.len() != weights.len() {
+            // The priorities of an unknown validator set start from zero.
+            *self = Self::new(weights.len());
+        }
+        if weights.is_empty() {
+            return 0
+        }
+        // The validators take turns in order if nobody has voting power.
+        let is_unweighted = weights.iter().all(|weight| *weight == 0);
+        let weight = |weight: u64| {
+            if is_unweighted {
+                1
+            } else {
+                i128::from(weight)
+            }
+        };
+        let total_weight: i128 = weights.iter().map(|w| weight(*w)).sum();
+        for (priority, w) in self.priorities.iter_mut().zip(weights) {
+            *priority += weight(*w);
+        }
+        // The first validator wins a tie, so the validators of the same voting power take turns in order.
+        let (proposer, _) =
+            self.priorities.iter().enumerate().fold((0, i128::min_value()), |(max_index, max), (index, priority)| {
+                if *priority > max {
+                    (index, *priority)
+                } else {
+                    (max_index, max)
+                }
+            });
+        self.priorities[proposer] -= total_weight;
+        proposer
+    }
+
+    /// Advances the priorities by the given number of rounds.
+    pub fn advance(&mut self, weights: &[u64], rounds: u64) {
+        let mut rounds = u128::from(rounds);
+        if self.priorities.iter().all(|priority| *priority == 0) {
+            // The schedule starting from zero repeats itself every `total weight` rounds.
+            let total_weight: u128 = weights.iter().map(|weight| u128::from(*weight)).sum();
+            let period = if total_weight == 0 {
+                weights.len() as u128
+            } else {
+                total_weight
+            };
+            if period != 0 {
+                rounds %= period;
+            }
+        }
+        for _ in 0..rounds {
+            self.next(weights);
+        }
+    }
+
+    /// Returns the index of the proposer of the given view of the next height.
+    pub fn proposer(&self, weights: &[u64], view: u64) -> usize {
+        let mut priority = self.clone();
+        priority.advance(weights, view);
+        priority.next(weights)
+    }
+}
+
+/// Picks the proposer of each view in proportion to the voting power.
+///
+/// The priorities restart from zero at the start of every epoch, in which the validators don't change, and every
+/// block advances them once. So the priorities after a block depend only on the voting power of the validators of its
+/// children and the number of blocks since the start of the epoch, which are on the chain. The priorities of the
+/// recent blocks are cached, so the priorities of a new block are derived from its parent's in a single round.
+#[derive(Default)]
+pub struct ProposerSchedule {
+    /// The voting power of the validators of the children of a block and the priorities after the block.
+    cache: Mutex<LinkedHashMap<H256, (Vec<u64>, ProposerPriority)>>,
+}
+
+impl ProposerSchedule {
+    /// Returns the priorities after the block at `number`, whose children are validated with `weights`.
+    /// The priorities after the block at `epoch_start` are zero. `parent` is called only if the block is not cached.
+    pub fn priority<F>(
+        &self,
+        hash: &H256,
+        parent: F,
+        number: BlockNumber,
+        epoch_start: BlockNumber,
+        weights: &[u64],
+    ) -> ProposerPriority
+    where
+        F: FnOnce() -> Option<H256>, {
+        debug_assert!(epoch_start <= number);
+        let mut cache = self.cache.lock();
+        if let Some((cached_weights, priority)) = cache.get_refresh(hash) {
+            if cached_weights[..] == *weights {
+                return priority.clone()
+            }
+        }
+        // The parent's priorities are advanced only if they are in the same epoch with the same validators.
+        let parent = if number > epoch_start {
+            parent()
+        } else {
+            None
+        };
+        let from_parent = parent
+            .and_then(|parent| cache.get_refresh(&parent).cloned())
+            .filter(|(parent_weights, _)| parent_weights[..] == *weights)
+            .map(|(_, mut priority)| {
+                priority.next(weights);
+                priority
+            });
+        let priority = from_parent.unwrap_or_else(|| {
+            let mut priority = ProposerPriority::new(weights.len());
+            priority.advance(weights, number - epoch_start);
+            priority
+        });
+        cache.insert(*hash, (weights.to_vec(), priority.clone()));
+        while cache.len() > PROPOSER_PRIORITY_CACHE_SIZE {
+            cache.pop_front();
+        }
+        priority
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposers(weights: &[u64], rounds: u64) -> Vec<usize> {
+        let mut priority = ProposerPriority::new(weights.len());
+        (0..rounds).map(|_| priority.next(weights)).collect()
+    }
+
+    #[test]
+    fn same_weights_take_turns_in_order() {
+        assert_eq!(proposers(&[1, 1, 1, 1], 10), vec![0, 1, 2, 3, 0, 1, 2, 3, 0, 1]);
+        assert_eq!(proposers(&[7, 7, 7], 7), vec![0, 1, 2, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn proposes_in_proportion_to_weight() {
+        let weights = [5, 1, 3, 10, 1];
+        let total_weight = weights.iter().sum::<u64>();
+        for cycles in 1..=10 {
+            let proposers = proposers(&weights, total_weight * cycles);
+            for (index, weight) in weights.iter().enumerate() {
+                let count = proposers.iter().filter(|proposer| **proposer == index).count() as u64;
+                assert_eq!(weight * cycles, count);
+            }
+        }
+    }
+
+    #[test]
+    fn proposals_are_spread_out() {
+        let weights = [5, 1, 3, 10, 1];
+        let total_weight = weights.iter().sum::<u64>();
+        let proposers = proposers(&weights, total_weight * 10);
+        for (index, weight) in weights.iter().enumerate() {
+            let rounds: Vec<_> = (0..proposers.len()).filter(|round| proposers[*round] == index).collect();
+            for gap in rounds.windows(2).map(|rounds| (rounds[1] - rounds[0]) as u64) {
+                assert!(gap * weight < 2 * total_weight, "validator {} waited {} rounds", index, gap);
+            }
+        }
+    }
+
+    #[test]
+    fn heavy_validator_is_not_picked_all_the_time() {
+        assert_eq!(proposers(&[3, 1], 8), vec![0, 0, 1, 0, 0, 0, 1, 0]);
+        assert_eq!(proposers(&[1, 3], 8), vec![1, 0, 1, 1, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn advancing_from_zero_matches_sequential_rounds() {
+        let weights = [4, 2, 9, 1];
+        let expected = proposers(&weights, 100);
+
+        for rounds in [57, 3, 99, 16, 0, 42].iter() {
+            let mut priority = ProposerPriority::new(weights.len());
+            priority.advance(&weights, *rounds);
+            assert_eq!(expected[*rounds as usize], priority.next(&weights));
+        }
+    }
+
+    #[test]
+    fn views_advance_a_copy_of_the_priorities() {
+        let weights = [4, 2, 9, 1];
+        let expected = proposers(&weights, 20);
+
+        let mut priority = ProposerPriority::new(weights.len());
+        priority.advance(&weights, 5);
+        let before = priority.clone();
+        for view in 0..15 {
+            assert_eq!(expected[5 + view as usize], priority.proposer(&weights, view));
+        }
+        assert_eq!(before, priority);
+    }
+
+    #[test]
+    fn unknown_validators_start_from_zero() {
+        let mut priority = ProposerPriority {
+            priorities: vec![3, -3],
+        };
+        assert_eq!(proposers(&[1, 1, 1], 1)[0], priority.next(&[1, 1, 1]));
+    }
+
+    /// A chain whose validators change at the beginning of every epoch of `EPOCH_LENGTH` blocks.
+    const EPOCH_LENGTH: u64 = 10;
+
+    fn block_hash(number: BlockNumber) -> H256 {
+        H256::from(number + 1)
+    }
+
+    fn weights_after(number: BlockNumber) -> Vec<u64> {
+        match number / EPOCH_LENGTH {
+            0 => vec![1, 1, 1, 1],
+            1 => vec![5, 1, 3, 10, 1],
+            _ => vec![2, 7, 4],
+        }
+    }
+
+    fn priority_after(schedule: &ProposerSchedule, number: BlockNumber) -> ProposerPriority {
+        let parent = move || number.checked_sub(1).map(block_hash);
+        let epoch_start = number - number % EPOCH_LENGTH;
+        schedule.priority(&block_hash(number), parent, number, epoch_start, &weights_after(number))
+    }
+
+    #[test]
+    fn cold_cache_matches_warm_cache_across_validator_changes() {
+        let warm = ProposerSchedule::default();
+        let warm_priorities: Vec<_> = (0..3 * EPOCH_LENGTH).map(|number| priority_after(&warm, number)).collect();
+
+        for (number, warm_priority) in warm_priorities.iter().enumerate() {
+            let cold = ProposerSchedule::default();
+            assert_eq!(*warm_priority, priority_after(&cold, number as BlockNumber), "block {}", number);
+        }
+
+        // The priorities restart from zero whenever the validators change.
+        for number in (0..3).map(|epoch| epoch * EPOCH_LENGTH) {
+            assert_eq!(ProposerPriority::new(weights_after(number).len()), warm_priorities[number as usize]);
+        }
+        // They advance once in every block of an epoch.
+        let weights = weights_after(EPOCH_LENGTH);
+        let expected = proposers(&weights, EPOCH_LENGTH);
+        for number in EPOCH_LENGTH..2 * EPOCH_LENGTH {
+            let rounds = (number - EPOCH_LENGTH) as usize;
+            assert_eq!(expected[rounds], warm_priorities[number as usize].proposer(&weights, 0));
+        }
+    }
+
+    #[test]
+    fn cached_priorities_are_not_used_for_other_validators() {
+        let schedule = ProposerSchedule::default();
+        let weights = [1, 2, 3];
+        let hash = block_hash(5);
+        let before = schedule.priority(&hash, || None, 5, 0, &weights);
+        let mut expected = ProposerPriority::new(weights.len());
+        expected.advance(&weights, 5);
+        assert_eq!(expected, before);
+
+        let other_weights = [3, 2, 1];
+        let mut expected = ProposerPriority::new(other_weights.len());
+        expected.advance(&other_weights, 5);
+        assert_eq!(expected, schedule.priority(&hash, || None, 5, 0, &other_weights));
+    }
+}
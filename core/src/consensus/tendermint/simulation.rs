@@ -0,0 +1,988 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Deterministic simulation of the Tendermint validators.
+//!
+//! The engines exchange messages over an in-memory bus and their timers run on a virtual clock,
+//! so a schedule, including the delayed, dropped and partitioned messages, is decided only by the seed.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Weak};
+
+use byteorder::{ByteOrder, LittleEndian};
+use ccrypto::blake256;
+use ckey::{public_to_address, Address, KeyPair, Password, PlatformAddress, Private, Public};
+use cnetwork::{Api, NetworkExtension, NetworkExtensionResult, NodeId};
+use cstate::{StateDB, TopLevelState};
+use ctimer::{TimeoutHandler, TimerToken};
+use ctypes::BlockNumber;
+use kvdb::KeyValueDB;
+use kvdb_memorydb;
+use parking_lot::{Mutex, RwLock};
+use primitives::{Bytes, H256, U256};
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use time::Duration;
+
+use super::super::validator_set::new_validator_set;
+use super::{Tendermint, TendermintChainNotify, TendermintExtension, TendermintParams, TimeoutParams};
+use crate::account_provider::AccountProvider;
+use crate::block::{IsBlock, OpenBlock};
+use crate::blockchain_info::BlockChainInfo;
+use crate::client::{BlockInfo, ChainInfo, EngineClient, ImportBlock, StateInfo};
+use crate::codechain_machine::CodeChainMachine;
+use crate::consensus::epoch::Transition as EpochTransition;
+use crate::consensus::ConsensusEngine;
+use crate::db::NUM_COLUMNS;
+use crate::encoded;
+use crate::error::{BlockError, BlockImportError, ImportError};
+use crate::header::Header;
+use crate::scheme::Scheme;
+use crate::transaction::SignedTransaction;
+use crate::views::BlockView;
+use crate::BlockId;
+use ChainNotify;
+
+/// The interval of the block synchronization between the connected nodes in ms.
+const SYNC_INTERVAL: u64 = 2_000;
+
+/// How the bus delivers the messages.
+#[derive(Clone, Copy, Debug)]
+pub struct NetworkConditions {
+    /// The minimum delay of a message in ms.
+    pub min_delay: u64,
+    /// The maximum delay of a message in ms.
+    pub max_delay: u64,
+    /// The probability to drop a message in per mille.
+    pub drop_rate: u32,
+}
+
+impl Default for NetworkConditions {
+    fn default() -> Self {
+        NetworkConditions {
+            min_delay: 10,
+            max_delay: 100,
+            drop_rate: 0,
+        }
+    }
+}
+
+/// The nodes in the group cannot communicate with the others in [from, until).
+struct Partition {
+    from: u64,
+    until: u64,
+    group: HashSet<usize>,
+}
+
+enum Request {
+    Send {
+        from: usize,
+        to: NodeId,
+        message: Bytes,
+    },
+    SetTimer {
+        node: usize,
+        token: TimerToken,
+        duration: Duration,
+        repeat: bool,
+    },
+    ClearTimer {
+        node: usize,
+        token: TimerToken,
+    },
+}
+
+/// The network API given to an engine. The requests are handled by the simulation after the engine returns.
+struct SimulatedApi {
+    node: usize,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Api for SimulatedApi {
+    fn send(&self, node: &NodeId, message: &[u8]) {
+        self.requests.lock().push(Request::Send {
+            from: self.node,
+            to: *node,
+            message: message.to_vec(),
+        });
+    }
+
+    fn set_timer(&self, token: TimerToken, duration: Duration) -> NetworkExtensionResult<()> {
+        self.requests.lock().push(Request::SetTimer {
+            node: self.node,
+            token,
+            duration,
+            repeat: true,
+        });
+        Ok(())
+    }
+
+    fn set_timer_once(&self, token: TimerToken, duration: Duration) -> NetworkExtensionResult<()> {
+        self.requests.lock().push(Request::SetTimer {
+            node: self.node,
+            token,
+            duration,
+            repeat: false,
+        });
+        Ok(())
+    }
+
+    fn clear_timer(&self, token: TimerToken) -> NetworkExtensionResult<()> {
+        self.requests.lock().push(Request::ClearTimer {
+            node: self.node,
+            token,
+        });
+        Ok(())
+    }
+}
+
+/// A block chain which keeps the blocks in memory and doesn't execute the transactions.
+///
+/// Like the real block chain, the best block is the parent of the proposal with the highest score.
+struct SimulatedClient {
+    engine: Arc<Tendermint>,
+    author: Address,
+    genesis_hash: H256,
+    state_db: RwLock<StateDB>,
+    kvdb: Arc<KeyValueDB>,
+    blocks: RwLock<HashMap<H256, Bytes>>,
+    numbers: RwLock<HashMap<BlockNumber, H256>>,
+    highest: RwLock<H256>,
+    /// The blocks imported after the last notification.
+    imported: Mutex<Vec<H256>>,
+}
+
+impl SimulatedClient {
+    fn new(engine: Arc<Tendermint>, author: Address, scheme: &Scheme) -> Self {
+        let genesis_hash = scheme.genesis_header().hash();
+        let state_db = scheme.ensure_genesis_state(StateDB::new_with_memorydb()).expect("Genesis state is valid");
+        let mut blocks = HashMap::new();
+        blocks.insert(genesis_hash, scheme.genesis_block());
+        let mut numbers = HashMap::new();
+        numbers.insert(0, genesis_hash);
+        SimulatedClient {
+            engine,
+            author,
+            genesis_hash,
+            state_db: RwLock::new(state_db),
+            kvdb: Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0))),
+            blocks: RwLock::new(blocks),
+            numbers: RwLock::new(numbers),
+            highest: RwLock::new(genesis_hash),
+            imported: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn header(&self, hash: &H256) -> Option<Header> {
+        self.blocks.read().get(hash).map(|bytes| BlockView::new(bytes).header())
+    }
+
+    fn best_block_hash(&self) -> H256 {
+        let highest = *self.highest.read();
+        if highest == self.genesis_hash {
+            return highest
+        }
+        *self.header(&highest).expect("The highest block is imported").parent_hash()
+    }
+
+    fn best_block_number(&self) -> BlockNumber {
+        self.header(&self.best_block_hash()).expect("The best block is imported").number()
+    }
+
+    fn block_hash(&self, id: &BlockId) -> Option<H256> {
+        match id {
+            BlockId::Hash(hash) => Some(*hash),
+            BlockId::Number(number) => self.numbers.read().get(number).cloned(),
+            BlockId::Earliest => Some(self.genesis_hash),
+            BlockId::Latest => Some(self.best_block_hash()),
+        }
+    }
+
+    /// Makes the chain ending with the block canonical.
+    fn canonize(&self, header: &Header) {
+        let mut numbers = self.numbers.write();
+        numbers.retain(|number, _| *number <= header.number());
+        let mut number = header.number();
+        let mut hash = header.hash();
+        while numbers.get(&number) != Some(&hash) {
+            numbers.insert(number, hash);
+            let parent = BlockView::new(&self.blocks.read()[&hash]).header();
+            number -= 1;
+            hash = *parent.parent_hash();
+        }
+    }
+
+    fn take_imported(&self) -> Vec<H256> {
+        ::std::mem::replace(&mut *self.imported.lock(), Vec::new())
+    }
+}
+
+impl ChainInfo for SimulatedClient {
+    fn chain_info(&self) -> BlockChainInfo {
+        let best_block = self.header(&self.best_block_hash()).expect("The best block is imported");
+        let highest = self.header(&*self.highest.read()).expect("The highest block is imported");
+        BlockChainInfo {
+            best_score: *best_block.score(),
+            highest_score: *highest.score(),
+            pending_total_score: *highest.score(),
+            genesis_hash: self.genesis_hash,
+            best_block_hash: best_block.hash(),
+            best_block_number: best_block.number(),
+            best_block_timestamp: best_block.timestamp(),
+        }
+    }
+
+    fn genesis_accounts(&self) -> Vec<PlatformAddress> {
+        Vec::new()
+    }
+}
+
+impl BlockInfo for SimulatedClient {
+    fn block_header(&self, id: &BlockId) -> Option<encoded::Header> {
+        let hash = self.block_hash(id)?;
+        self.block(&BlockId::Hash(hash)).map(|block| block.header())
+    }
+
+    fn best_block_header(&self) -> encoded::Header {
+        self.block_header(&BlockId::Latest).expect("The best block is imported")
+    }
+
+    fn best_header(&self) -> encoded::Header {
+        self.block_header(&BlockId::Hash(*self.highest.read())).expect("The highest block is imported")
+    }
+
+    fn block(&self, id: &BlockId) -> Option<encoded::Block> {
+        let hash = self.block_hash(id)?;
+        self.blocks.read().get(&hash).cloned().map(encoded::Block::new)
+    }
+}
+
+impl ImportBlock for SimulatedClient {
+    fn import_block(&self, bytes: Bytes) -> Result<H256, BlockImportError> {
+        let header = BlockView::new(&bytes).header();
+        let hash = header.hash();
+        if self.blocks.read().contains_key(&hash) {
+            return Err(BlockImportError::Import(ImportError::AlreadyInChain))
+        }
+        if !self.blocks.read().contains_key(header.parent_hash()) {
+            return Err(BlockImportError::Block(BlockError::UnknownParent(*header.parent_hash())))
+        }
+        self.engine.verify_block_basic(&header)?;
+        self.engine.verify_block_external(&header)?;
+
+        self.blocks.write().insert(hash, bytes);
+        let highest = self.header(&*self.highest.read()).expect("The highest block is imported");
+        if header.score() > highest.score() {
+            *self.highest.write() = hash;
+            self.canonize(&header);
+        }
+        self.imported.lock().push(hash);
+        Ok(hash)
+    }
+
+    fn import_header(&self, bytes: Bytes) -> Result<H256, BlockImportError> {
+        let hash = encoded::Header::new(bytes).hash();
+        if self.blocks.read().contains_key(&hash) {
+            return Err(BlockImportError::Import(ImportError::AlreadyInChain))
+        }
+        Err(BlockImportError::Other("The simulated chain imports only the whole blocks".to_string()))
+    }
+}
+
+impl StateInfo for SimulatedClient {
+    fn state_at(&self, id: BlockId) -> Option<TopLevelState> {
+        self.block_header(&id).and_then(|header| {
+            let root = header.state_root();
+            TopLevelState::from_existing(self.state_db.read().clone(&root), root).ok()
+        })
+    }
+}
+
+impl EngineClient for SimulatedClient {
+    /// Makes a proposal like the miner does. The transactions are not included.
+    fn update_sealing(&self, _allow_empty_block: bool) {
+        let parent_hash = self.engine.get_block_hash_to_mine_on(self.best_block_hash());
+        let parent = match self.header(&parent_hash) {
+            Some(parent) => parent,
+            None => return,
+        };
+        let db = self.state_db.read().clone(parent.state_root());
+        let mut block = OpenBlock::try_new(&*self.engine, db, &parent, self.author, Vec::new(), false)
+            .expect("Cannot open a block on the simulated chain");
+        // The wall clock must not affect the simulation.
+        block.set_timestamp(parent.timestamp() + 1);
+        let block = block
+            .close(*parent.transactions_root(), *parent.invoices_root())
+            .expect("Cannot close a block on the simulated chain");
+        let seal = match self.engine.generate_seal(block.block(), &parent).seal_fields() {
+            Some(seal) => seal,
+            None => return,
+        };
+        if !self.engine.is_proposal(block.header()) {
+            return
+        }
+        let sealed = block.lock().seal(&*self.engine, seal).expect("The seal is generated by the engine");
+        self.engine.proposal_generated(&sealed);
+        if let Err(err) = self.import_block(sealed.rlp_bytes()) {
+            cwarn!(ENGINE, "Cannot import the own proposal: {:?}", err);
+        }
+        self.engine.broadcast_proposal_block(encoded::Block::new(sealed.rlp_bytes()));
+    }
+
+    /// The proposals are sealed when they are made, so there is no block waiting for the seal.
+    fn submit_seal(&self, block_hash: H256, _seal: Vec<Bytes>) {
+        cwarn!(ENGINE, "The simulated chain has no block {} to seal", block_hash);
+    }
+
+    fn score_to_target(&self, _score: &U256, _block_number: BlockNumber) -> U256 {
+        U256::zero()
+    }
+
+    fn get_kvdb(&self) -> Arc<KeyValueDB> {
+        Arc::clone(&self.kvdb)
+    }
+
+    fn queue_own_transaction(&self, _transaction: SignedTransaction) {}
+
+    fn next_seq(&self, _public: &Public) -> u64 {
+        0
+    }
+
+    fn epoch_transition_for(&self, _parent_hash: H256) -> Option<EpochTransition> {
+        None
+    }
+}
+
+struct Node {
+    node_id: NodeId,
+    engine: Arc<Tendermint>,
+    client: Arc<SimulatedClient>,
+    extension: Arc<TendermintExtension>,
+    chain_notify: Arc<TendermintChainNotify>,
+    /// The blocks committed by the node.
+    committed: BTreeMap<BlockNumber, H256>,
+}
+
+enum Event {
+    Deliver {
+        from: usize,
+        to: usize,
+        message: Bytes,
+    },
+    Timeout {
+        node: usize,
+        token: TimerToken,
+        generation: u64,
+    },
+    Sync,
+}
+
+struct Scheduled {
+    time: u64,
+    /// Orders the events scheduled at the same time.
+    sequence: u64,
+    event: Event,
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        (self.time, self.sequence) == (other.time, other.sequence)
+    }
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.time, self.sequence).cmp(&(other.time, other.sequence))
+    }
+}
+
+/// Runs the Tendermint validators on a virtual clock.
+pub struct Simulation {
+    seed: u64,
+    rng: XorShiftRng,
+    conditions: NetworkConditions,
+    partitions: Vec<Partition>,
+    nodes: Vec<Node>,
+    requests: Arc<Mutex<Vec<Request>>>,
+    queue: BinaryHeap<Reverse<Scheduled>>,
+    /// The virtual time in ms.
+    now: u64,
+    sequence: u64,
+    /// The generations of the timers which are not cleared.
+    timers: HashMap<(usize, TimerToken), (u64, Option<Duration>)>,
+    generation: u64,
+    /// The blocks committed by any node.
+    committed: BTreeMap<BlockNumber, (H256, usize)>,
+    /// (time, node, height) of every commit, in the order they happened.
+    trace: Vec<(u64, usize, BlockNumber)>,
+}
+
+impl Simulation {
+    pub fn new(num_validators: usize, conditions: NetworkConditions, seed: u64) -> Self {
+        Self::with_aggregated_seal(num_validators, conditions, seed, None)
+    }
+
+    pub fn with_aggregated_seal(
+        num_validators: usize,
+        conditions: NetworkConditions,
+        seed: u64,
+        aggregated_seal_activation: Option<BlockNumber>,
+    ) -> Self {
+        assert!(conditions.min_delay <= conditions.max_delay);
+
+        let mut seed_bytes = [0u8; 16];
+        LittleEndian::write_u64(&mut seed_bytes[..8], seed);
+        LittleEndian::write_u64(&mut seed_bytes[8..], !seed);
+        let mut rng = XorShiftRng::from_seed(seed_bytes);
+
+        let key_pairs: Vec<KeyPair> = (0..num_validators)
+            .map(|i| KeyPair::from_private(Private::from(blake256(format!("simulation-{}", i)))).unwrap())
+            .collect();
+        let validators: Vec<_> = key_pairs.iter().map(|key_pair| *key_pair.public()).collect();
+        let node_ids: Vec<NodeId> = (0..num_validators)
+            .map(|i| NodeId::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 3485 + i as u16))
+            .collect();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let nodes = key_pairs
+            .iter()
+            .enumerate()
+            .map(|(index, key_pair)| {
+                let scheme = Scheme::new_test_tendermint();
+                let json = ::cjson::scheme::Scheme::load(include_bytes!("../../../res/tendermint.json") as &[u8])
+                    .expect("Tendermint scheme is valid");
+                let machine = CodeChainMachine::new(json.params.into(), Vec::new());
+                let params = TendermintParams {
+                    validators: new_validator_set(validators.clone()),
+                    initial_validators: validators.iter().map(public_to_address).collect(),
+                    timeouts: TimeoutParams::default(),
+                    block_reward: Default::default(),
+                    fee_burn: Default::default(),
+                    genesis_stakes: HashMap::new(),
+                    election: None,
+                    downtime: None,
+                    weighted_proposer_activation: None,
+                    aggregated_seal_activation,
+                };
+                let engine = Tendermint::new(params, machine);
+
+                let client = Arc::new(SimulatedClient::new(Arc::clone(&engine), key_pair.address(), &scheme));
+                let weak_client = Arc::downgrade(&client) as Weak<EngineClient>;
+                engine.register_client(weak_client);
+
+                let tap = AccountProvider::transient_provider();
+                let password = Password::from("simulation");
+                let address = tap.insert_account(key_pair.private().clone(), &password).unwrap();
+                engine.set_signer(tap, address, Some(password));
+
+                let (extension, chain_notify) = {
+                    let inner = engine.inner.lock();
+                    (Arc::clone(&inner.extension), Arc::clone(&inner.chain_notify))
+                };
+                // The peers to gossip are picked by the seed too.
+                extension.set_rng(XorShiftRng::from_rng(&mut rng).expect("XorShiftRng never fails"));
+                extension.on_initialize(Arc::new(SimulatedApi {
+                    node: index,
+                    requests: Arc::clone(&requests),
+                }));
+                for (peer, peer_id) in node_ids.iter().enumerate() {
+                    if peer != index {
+                        extension.on_node_added(peer_id, 0);
+                    }
+                }
+
+                Node {
+                    node_id: node_ids[index],
+                    engine,
+                    client,
+                    extension,
+                    chain_notify,
+                    committed: BTreeMap::new(),
+                }
+            })
+            .collect();
+
+        let mut simulation = Simulation {
+            seed,
+            rng,
+            conditions,
+            partitions: Vec::new(),
+            nodes,
+            requests,
+            queue: BinaryHeap::new(),
+            now: 0,
+            sequence: 0,
+            timers: HashMap::new(),
+            generation: 0,
+            committed: BTreeMap::new(),
+            trace: Vec::new(),
+        };
+        // The miner of a real node tries to seal a block when it starts.
+        for node in &simulation.nodes {
+            node.client.update_sealing(true);
+        }
+        simulation.handle_requests();
+        simulation.schedule(SYNC_INTERVAL, Event::Sync);
+        simulation
+    }
+
+    /// Isolates the group from the other nodes in [from, until).
+    pub fn partition(&mut self, from: u64, until: u64, group: &[usize]) {
+        self.partitions.push(Partition {
+            from,
+            until,
+            group: group.iter().cloned().collect(),
+        });
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// The height of the last block committed by the node.
+    pub fn committed_height(&self, node: usize) -> BlockNumber {
+        self.nodes[node].committed.keys().next_back().cloned().unwrap_or(0)
+    }
+
+    pub fn trace(&self) -> &[(u64, usize, BlockNumber)] {
+        &self.trace
+    }
+
+    /// Runs the events until the condition holds. Returns false if the deadline comes first.
+    pub fn run_until<F>(&mut self, deadline: u64, condition: F) -> bool
+    where
+        F: Fn(&Simulation) -> bool, {
+        while !condition(self) {
+            let next_time = match self.queue.peek() {
+                Some(Reverse(scheduled)) => scheduled.time,
+                None => return false,
+            };
+            if next_time > deadline {
+                self.now = deadline;
+                return false
+            }
+            self.step();
+        }
+        true
+    }
+
+    /// Runs the events until the time.
+    pub fn run_for(&mut self, until: u64) {
+        self.run_until(until, |_| false);
+    }
+
+    /// Checks that all the nodes commit the block of the height before the deadline.
+    pub fn assert_liveness(&mut self, height: BlockNumber, deadline: u64) {
+        let num_nodes = self.nodes.len();
+        let reached = self
+            .run_until(deadline, |simulation| (0..num_nodes).all(|node| simulation.committed_height(node) >= height));
+        if !reached {
+            let heights: Vec<_> = (0..num_nodes).map(|node| self.committed_height(node)).collect();
+            self.fail(&format!("the nodes committed {:?} until {}ms, expected {}", heights, deadline, height));
+        }
+    }
+
+    fn fail(&self, reason: &str) -> ! {
+        panic!("Simulation with seed {} failed at {}ms: {}", self.seed, self.now, reason)
+    }
+
+    fn schedule(&mut self, delay: u64, event: Event) {
+        self.sequence += 1;
+        self.queue.push(Reverse(Scheduled {
+            time: self.now + delay,
+            sequence: self.sequence,
+            event,
+        }));
+    }
+
+    fn is_connected(&self, a: usize, b: usize) -> bool {
+        self.partitions.iter().all(|partition| {
+            let active = partition.from <= self.now && self.now < partition.until;
+            !active || partition.group.contains(&a) == partition.group.contains(&b)
+        })
+    }
+
+    fn step(&mut self) {
+        let Reverse(scheduled) = self.queue.pop().expect("The caller checked the queue");
+        self.now = scheduled.time;
+        match scheduled.event {
+            Event::Deliver {
+                from,
+                to,
+                message,
+            } => {
+                let sender = self.nodes[from].node_id;
+                self.nodes[to].extension.on_message(&sender, &message);
+            }
+            Event::Timeout {
+                node,
+                token,
+                generation,
+            } => match self.timers.get(&(node, token)).cloned() {
+                Some((current, repeat)) if current == generation => {
+                    match repeat {
+                        Some(duration) => self.schedule(
+                            to_millis(duration),
+                            Event::Timeout {
+                                node,
+                                token,
+                                generation,
+                            },
+                        ),
+                        None => {
+                            self.timers.remove(&(node, token));
+                        }
+                    }
+                    self.nodes[node].extension.on_timeout(token);
+                }
+                _ => {}
+            },
+            Event::Sync => {
+                self.sync();
+                self.schedule(SYNC_INTERVAL, Event::Sync);
+            }
+        }
+        self.handle_requests();
+        self.check_safety();
+    }
+
+    /// Imports the blocks committed by the connected nodes, which the block sync extension does in a real node.
+    fn sync(&mut self) {
+        for to in 0..self.nodes.len() {
+            for from in 0..self.nodes.len() {
+                if from == to || !self.is_connected(from, to) {
+                    continue
+                }
+                let start = self.nodes[to].client.best_block_number() + 1;
+                let end = self.nodes[from].client.best_block_number();
+                for number in start..=end {
+                    let block = self.nodes[from]
+                        .client
+                        .block(&BlockId::Number(number))
+                        .expect("The best chain of the node is imported");
+                    // The proposal might be imported already.
+                    let _ = self.nodes[to].client.import_block(block.into_inner());
+                }
+            }
+        }
+    }
+
+    /// Notifies the imported blocks and handles the requests of the engines until they settle down.
+    fn handle_requests(&mut self) {
+        loop {
+            let mut notified = false;
+            for node in &self.nodes {
+                let imported = node.client.take_imported();
+                if !imported.is_empty() {
+                    node.chain_notify.new_blocks(imported, Vec::new(), Vec::new(), Vec::new(), Vec::new(), 0);
+                    notified = true;
+                }
+            }
+
+            let requests = ::std::mem::replace(&mut *self.requests.lock(), Vec::new());
+            if requests.is_empty() && !notified {
+                return
+            }
+
+            let mut sends = Vec::new();
+            for request in requests {
+                match request {
+                    Request::Send {
+                        from,
+                        to,
+                        message,
+                    } => {
+                        if let Some(to) = self.nodes.iter().position(|node| node.node_id == to) {
+                            sends.push((from, to, message));
+                        }
+                    }
+                    Request::SetTimer {
+                        node,
+                        token,
+                        duration,
+                        repeat,
+                    } => {
+                        self.generation += 1;
+                        let generation = self.generation;
+                        self.timers.insert(
+                            (node, token),
+                            (
+                                generation,
+                                if repeat {
+                                    Some(duration)
+                                } else {
+                                    None
+                                },
+                            ),
+                        );
+                        self.schedule(
+                            to_millis(duration),
+                            Event::Timeout {
+                                node,
+                                token,
+                                generation,
+                            },
+                        );
+                    }
+                    Request::ClearTimer {
+                        node,
+                        token,
+                    } => {
+                        self.timers.remove(&(node, token));
+                    }
+                }
+            }
+
+            // The engines visit the peers in a random order. The messages to each peer keep their order.
+            sends.sort_by_key(|(from, to, _)| (*from, *to));
+            for (from, to, message) in sends {
+                if !self.is_connected(from, to) || self.rng.gen_range(0, 1000) < self.conditions.drop_rate {
+                    continue
+                }
+                let delay = self.rng.gen_range(self.conditions.min_delay, self.conditions.max_delay + 1);
+                self.schedule(
+                    delay,
+                    Event::Deliver {
+                        from,
+                        to,
+                        message,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Records the new commits of every node and checks that no two nodes committed different blocks at a height.
+    fn check_safety(&mut self) {
+        for index in 0..self.nodes.len() {
+            let confirmed = self.nodes[index].engine.inner.lock().last_confirmed_view.read().0;
+            let best_block = self.nodes[index].client.best_block_hash();
+            for tip in &[confirmed, best_block] {
+                self.record_commits(index, *tip);
+            }
+        }
+    }
+
+    fn record_commits(&mut self, index: usize, tip: H256) {
+        let mut new_commits = Vec::new();
+        {
+            let node = &self.nodes[index];
+            let mut header = node.client.header(&tip).expect("The committed block is imported");
+            while header.number() > 0 && !node.committed.contains_key(&header.number()) {
+                new_commits.push((header.number(), header.hash()));
+                header = node.client.header(header.parent_hash()).expect("The parent is imported");
+            }
+            if let Some(committed) = node.committed.get(&header.number()) {
+                if header.number() > 0 && *committed != header.hash() {
+                    self.fail(&format!(
+                        "node {} committed {} and {} at {}",
+                        index,
+                        committed,
+                        header.hash(),
+                        header.number()
+                    ));
+                }
+            }
+        }
+
+        for (number, hash) in new_commits.into_iter().rev() {
+            match self.committed.get(&number).cloned() {
+                Some((other_hash, other)) if other_hash != hash => self.fail(&format!(
+                    "node {} committed {} but node {} committed {} at {}",
+                    other, other_hash, index, hash, number
+                )),
+                Some(_) => {}
+                None => {
+                    self.committed.insert(number, (hash, index));
+                }
+            }
+            self.nodes[index].committed.insert(number, hash);
+            self.trace.push((self.now, index, number));
+        }
+    }
+}
+
+fn to_millis(duration: Duration) -> u64 {
+    duration.num_milliseconds() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::UntrustedRlp;
+
+    use super::super::message::consensus_view;
+    use super::super::types::BitSet;
+    use super::*;
+
+    const NUM_VALIDATORS: usize = 4;
+
+    #[test]
+    fn commits_on_reliable_network() {
+        let mut simulation = Simulation::new(NUM_VALIDATORS, NetworkConditions::default(), 0);
+        simulation.assert_liveness(5, 60_000);
+    }
+
+    #[test]
+    fn stays_safe_and_live_with_delayed_and_dropped_messages() {
+        // Some messages arrive after the timeouts.
+        let conditions = NetworkConditions {
+            min_delay: 0,
+            max_delay: 2_000,
+            drop_rate: 100,
+        };
+        for seed in 0..10 {
+            let mut simulation = Simulation::new(NUM_VALIDATORS, conditions, seed);
+            simulation.assert_liveness(3, 600_000);
+        }
+    }
+
+    #[test]
+    fn isolated_validator_catches_up_after_partition() {
+        let mut simulation = Simulation::new(NUM_VALIDATORS, NetworkConditions::default(), 1);
+        simulation.partition(0, 30_000, &[0]);
+        simulation.run_for(30_000);
+        assert_eq!(0, simulation.committed_height(0));
+        assert!((1..NUM_VALIDATORS).all(|node| simulation.committed_height(node) > 0));
+
+        let height = (1..NUM_VALIDATORS).map(|node| simulation.committed_height(node)).max().unwrap() + 2;
+        let deadline = simulation.now() + 60_000;
+        simulation.assert_liveness(height, deadline);
+    }
+
+    #[test]
+    fn nothing_is_committed_without_quorum() {
+        let mut simulation = Simulation::new(NUM_VALIDATORS, NetworkConditions::default(), 2);
+        simulation.partition(0, 30_000, &[0, 1]);
+        simulation.run_for(30_000);
+        assert!((0..NUM_VALIDATORS).all(|node| simulation.committed_height(node) == 0));
+
+        simulation.assert_liveness(3, 120_000);
+    }
+
+    #[test]
+    fn commits_with_aggregated_seal() {
+        let mut simulation = Simulation::with_aggregated_seal(NUM_VALIDATORS, NetworkConditions::default(), 3, Some(2));
+        simulation.assert_liveness(5, 60_000);
+
+        // The proposers of the first views seal the blocks with the aggregated precommits of their parents.
+        let node = &simulation.nodes[0];
+        for number in 2..=5 {
+            let header = node.client.header(&node.committed[&number]).unwrap();
+            if consensus_view(&header).unwrap() != 0 {
+                continue
+            }
+            let precommits = UntrustedRlp::new(&header.seal()[2]).item_count().unwrap();
+            let signers: BitSet = UntrustedRlp::new(&header.seal()[3]).as_val().unwrap();
+            assert_eq!(1, precommits, "block {}", number);
+            assert!(signers.indices().len() * 3 > NUM_VALIDATORS * 2, "block {}", number);
+        }
+    }
+
+    #[test]
+    fn aggregation_times_out_to_the_signatures() {
+        // Each of the four rounds of the aggregation takes longer than a quarter of its timeout.
+        let conditions = NetworkConditions {
+            min_delay: 200,
+            max_delay: 300,
+            drop_rate: 0,
+        };
+        let mut simulation = Simulation::with_aggregated_seal(NUM_VALIDATORS, conditions, 5, Some(2));
+        simulation.assert_liveness(4, 120_000);
+
+        let node = &simulation.nodes[0];
+        for number in 2..=4 {
+            let header = node.client.header(&node.committed[&number]).unwrap();
+            if consensus_view(&header).unwrap() != 0 {
+                continue
+            }
+            let precommits = UntrustedRlp::new(&header.seal()[2]).item_count().unwrap();
+            let signers: BitSet = UntrustedRlp::new(&header.seal()[3]).as_val().unwrap();
+            assert_eq!(signers.indices().len(), precommits, "block {}", number);
+        }
+    }
+
+    #[test]
+    fn consensus_state_has_the_signers_of_the_votes() {
+        let mut simulation = Simulation::new(NUM_VALIDATORS, NetworkConditions::default(), 4);
+        // Node 0 gets the votes of node 1 only.
+        simulation.partition(0, 30_000, &[0, 1]);
+        let addresses: Vec<Address> = (0..NUM_VALIDATORS)
+            .map(|i| KeyPair::from_private(Private::from(blake256(format!("simulation-{}", i)))).unwrap().address())
+            .collect();
+
+        let mut has_votes = false;
+        while simulation.now() < 20_000 {
+            let deadline = simulation.now() + 500;
+            simulation.run_for(deadline);
+            let state = simulation.nodes[0].engine.consensus_state().unwrap();
+            for votes in &state.votes {
+                let signers: Vec<Address> = votes.bitset.indices().into_iter().map(|index| addresses[index]).collect();
+                assert_eq!(signers, votes.signers, "{:?} votes", votes.step);
+                assert!(votes.signers.iter().all(|signer| addresses[..2].contains(signer)));
+                has_votes |= !votes.signers.is_empty();
+            }
+        }
+        assert!(has_votes);
+    }
+
+    #[test]
+    fn consensus_state_has_the_lock_of_the_node() {
+        let mut simulation = Simulation::new(NUM_VALIDATORS, NetworkConditions::default(), 4);
+
+        let mut has_lock = false;
+        while simulation.now() < 20_000 {
+            let deadline = simulation.now() + 100;
+            simulation.run_for(deadline);
+            for node in &simulation.nodes {
+                let state = node.engine.consensus_state().unwrap();
+                if let Some((locked_view, _)) = state.locked {
+                    // The node locks on a PoLC, so it has seen the PoLC of the locked view or a later one.
+                    let (valid_view, _) = state.valid.expect("The locked node has a valid block");
+                    assert!(locked_view <= valid_view);
+                    assert!(locked_view <= state.view);
+                    has_lock = true;
+                }
+            }
+        }
+        assert!(has_lock);
+    }
+
+    #[test]
+    fn same_seed_reproduces_the_schedule() {
+        let conditions = NetworkConditions {
+            min_delay: 0,
+            max_delay: 1_500,
+            drop_rate: 50,
+        };
+        let mut first = Simulation::new(NUM_VALIDATORS, conditions, 7);
+        first.run_for(60_000);
+        let mut second = Simulation::new(NUM_VALIDATORS, conditions, 7);
+        second.run_for(60_000);
+
+        assert!(!first.trace().is_empty());
+        assert_eq!(first.trace(), second.trace());
+    }
+}
@@ -14,13 +14,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::collections::{btree_set, BTreeSet};
+use std::collections::{btree_map, btree_set, BTreeMap, BTreeSet};
 
-use ckey::Address;
+use ckey::{public_to_address, Address, Public};
 use cstate::{ActionDataKeyBuilder, TopLevelState, TopState, TopStateView};
 use ctypes::transaction::ParcelError;
 use primitives::H256;
-use rlp::{RlpStream, UntrustedRlp};
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
 use super::{StakeResult, CUSTOM_ACTION_HANDLER_ID};
 
@@ -28,12 +28,26 @@ fn get_account_key(address: &Address) -> H256 {
     ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(address).into_key()
 }
 
+fn get_delegation_key(address: &Address) -> H256 {
+    ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 2).append(&"Delegation").append(address).into_key()
+}
+
 lazy_static! {
     pub static ref stakeholder_addresses_key: H256 =
         ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"StakeholderAddresses").into_key();
+    pub static ref candidates_key: H256 =
+        ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Candidates").into_key();
+    pub static ref validators_key: H256 =
+        ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Validators").into_key();
+    pub static ref validator_history_key: H256 =
+        ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"ValidatorHistory").into_key();
+    pub static ref jail_key: H256 = ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Jail").into_key();
+    pub static ref liveness_key: H256 =
+        ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Liveness").into_key();
 }
 
 pub type StakeBalance = u64;
+pub type StakeQuantity = u64;
 
 pub struct StakeAccount<'a> {
     pub address: &'a Address,
@@ -113,10 +127,16 @@ impl Stakeholders {
         self.0.contains(address)
     }
 
-    pub fn update(&mut self, account: &StakeAccount) {
+    pub fn update_by_increased_balance(&mut self, account: &StakeAccount) {
         if account.balance > 0 {
             self.0.insert(*account.address);
-        } else {
+        }
+    }
+
+    /// Delegators remain stakeholders until they revoke all of their delegations.
+    pub fn update_by_decreased_balance(&mut self, account: &StakeAccount, delegation: &Delegation) {
+        debug_assert_eq!(account.address, delegation.delegator);
+        if account.balance == 0 && delegation.sum() == 0 {
             self.0.remove(account.address);
         }
     }
@@ -126,6 +146,410 @@ impl Stakeholders {
     }
 }
 
+pub struct Delegation<'a> {
+    pub delegator: &'a Address,
+    delegatees: BTreeMap<Address, StakeQuantity>,
+}
+
+impl<'a> Delegation<'a> {
+    pub fn load_from_state(state: &TopLevelState, delegator: &'a Address) -> StakeResult<Delegation<'a>> {
+        let key = get_delegation_key(delegator);
+        let action_data = state.action_data(&key)?;
+
+        let mut delegatees = BTreeMap::new();
+        if let Some(rlp) = action_data.as_ref().map(|x| UntrustedRlp::new(x)) {
+            for i in 0..rlp.item_count()? {
+                let pair = rlp.at(i)?;
+                delegatees.insert(pair.val_at(0)?, pair.val_at(1)?);
+            }
+        }
+
+        Ok(Delegation {
+            delegator,
+            delegatees,
+        })
+    }
+
+    /// The delegation without any delegatee is removed from the state.
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StakeResult<()> {
+        let key = get_delegation_key(self.delegator);
+        if self.delegatees.is_empty() {
+            state.remove_action_data(&key);
+            return Ok(())
+        }
+        let mut rlp = RlpStream::new();
+        rlp.begin_list(self.delegatees.len());
+        for (delegatee, quantity) in self.delegatees.iter() {
+            rlp.begin_list(2).append(delegatee).append(quantity);
+        }
+        state.update_action_data(&key, rlp.drain().into_vec())?;
+        Ok(())
+    }
+
+    pub fn add_quantity(&mut self, delegatee: Address, quantity: StakeQuantity) -> Result<(), ParcelError> {
+        if quantity == 0 {
+            return Ok(())
+        }
+        *self.delegatees.entry(delegatee).or_insert(0) += quantity;
+        Ok(())
+    }
+
+    pub fn subtract_quantity(&mut self, delegatee: Address, quantity: StakeQuantity) -> Result<(), ParcelError> {
+        let delegated = self.get_quantity(&delegatee);
+        if delegated < quantity {
+            return Err(ParcelError::InvalidCustomAction("Cannot revoke more than delegated".to_string()))
+        }
+        if delegated == quantity {
+            self.delegatees.remove(&delegatee);
+        } else {
+            self.delegatees.insert(delegatee, delegated - quantity);
+        }
+        Ok(())
+    }
+
+    pub fn get_quantity(&self, delegatee: &Address) -> StakeQuantity {
+        self.delegatees.get(delegatee).cloned().unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<Address, StakeQuantity> {
+        self.delegatees.iter()
+    }
+
+    pub fn sum(&self) -> StakeQuantity {
+        self.delegatees.values().sum()
+    }
+
+    /// Removes all the delegations and returns the sum of them.
+    pub fn clear(&mut self) -> StakeQuantity {
+        let sum = self.sum();
+        self.delegatees.clear();
+        sum
+    }
+}
+
+/// The accounts which nominated themselves as validators, with their public keys.
+pub struct Candidates(BTreeMap<Address, Public>);
+
+impl Candidates {
+    pub fn load_from_state(state: &TopLevelState) -> StakeResult<Candidates> {
+        let action_data = state.action_data(&*candidates_key)?;
+
+        let mut candidates = BTreeMap::new();
+        if let Some(rlp) = action_data.as_ref().map(|x| UntrustedRlp::new(x)) {
+            for i in 0..rlp.item_count()? {
+                let public: Public = rlp.val_at(i)?;
+                candidates.insert(public_to_address(&public), public);
+            }
+        }
+
+        Ok(Candidates(candidates))
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StakeResult<()> {
+        let mut rlp = RlpStream::new();
+        rlp.begin_list(self.0.len());
+        for public in self.0.values() {
+            rlp.append(public);
+        }
+        state.update_action_data(&*candidates_key, rlp.drain().into_vec())?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, public: Public) {
+        self.0.insert(public_to_address(&public), public);
+    }
+
+    pub fn remove(&mut self, address: &Address) -> Option<Public> {
+        self.0.remove(address)
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.0.contains_key(address)
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<Address, Public> {
+        self.0.iter()
+    }
+}
+
+/// The validators elected at the end of the last term, with their delegated stakes.
+pub struct Validators(Vec<(Public, StakeQuantity)>);
+
+impl Validators {
+    pub fn new(validators: Vec<(Public, StakeQuantity)>) -> Validators {
+        Validators(validators)
+    }
+
+    pub fn load_from_state(state: &TopLevelState) -> StakeResult<Validators> {
+        let action_data = state.action_data(&*validators_key)?;
+
+        match action_data {
+            Some(action_data) => Ok(Validators::from_action_data(&action_data)?),
+            None => Ok(Validators(Vec::new())),
+        }
+    }
+
+    pub fn from_action_data(action_data: &[u8]) -> Result<Validators, DecoderError> {
+        let rlp = UntrustedRlp::new(action_data);
+        let mut validators = Vec::new();
+        for i in 0..rlp.item_count()? {
+            let validator = rlp.at(i)?;
+            validators.push((validator.val_at(0)?, validator.val_at(1)?));
+        }
+        Ok(Validators(validators))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.0.iter().map(|(public, _)| public_to_address(public)).collect()
+    }
+
+    /// Removes the validator unless it's the last one, which keeps the chain alive.
+    /// Returns whether the validator is removed.
+    pub fn remove(&mut self, address: &Address) -> bool {
+        if self.0.len() <= 1 {
+            return false
+        }
+        let len = self.0.len();
+        self.0.retain(|(public, _)| public_to_address(public) != *address);
+        self.0.len() != len
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StakeResult<()> {
+        let mut rlp = RlpStream::new();
+        rlp.begin_list(self.0.len());
+        for (public, weight) in self.0.iter() {
+            rlp.begin_list(2).append(public).append(weight);
+        }
+        state.update_action_data(&*validators_key, rlp.drain().into_vec())?;
+        Ok(())
+    }
+}
+
+impl From<Validators> for Vec<(Public, StakeQuantity)> {
+    fn from(validators: Validators) -> Self {
+        validators.0
+    }
+}
+
+/// The validators of the recent blocks, which the votes in the double vote evidence are checked against.
+/// Each set is kept with the first block number it validates, until it cannot be reported anymore.
+pub struct ValidatorHistory {
+    last_block_number: u64,
+    sets: Vec<(u64, Vec<Address>)>,
+}
+
+impl ValidatorHistory {
+    pub fn load_from_state(state: &TopLevelState) -> StakeResult<ValidatorHistory> {
+        let action_data = state.action_data(&*validator_history_key)?;
+
+        let mut last_block_number = 0;
+        let mut sets = Vec::new();
+        if let Some(rlp) = action_data.as_ref().map(|x| UntrustedRlp::new(x)) {
+            last_block_number = rlp.val_at(0)?;
+            let sets_rlp = rlp.at(1)?;
+            for i in 0..sets_rlp.item_count()? {
+                let set = sets_rlp.at(i)?;
+                sets.push((set.val_at(0)?, set.list_at(1)?));
+            }
+        }
+
+        Ok(ValidatorHistory {
+            last_block_number,
+            sets,
+        })
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StakeResult<()> {
+        let mut rlp = RlpStream::new();
+        rlp.begin_list(2).append(&self.last_block_number).begin_list(self.sets.len());
+        for (first_block_number, validators) in self.sets.iter() {
+            rlp.begin_list(2).append(first_block_number).append_list(validators);
+        }
+        state.update_action_data(&*validator_history_key, rlp.drain().into_vec())?;
+        Ok(())
+    }
+
+    /// Records the validators of the block after the closed one,
+    /// and forgets the ones which validated only the blocks older than `max_age`.
+    pub fn record(&mut self, closed_block_number: u64, validators: Vec<Address>, max_age: u64) {
+        self.last_block_number = closed_block_number;
+        let next = closed_block_number + 1;
+        if self.sets.last().map_or(true, |(_, last)| *last != validators) {
+            self.sets.push((next, validators));
+        }
+        let oldest = next.saturating_sub(max_age);
+        while self.sets.len() > 1 && self.sets[1].0 <= oldest {
+            self.sets.remove(0);
+        }
+    }
+
+    /// The number of the block being made on top of the state.
+    pub fn current_block_number(&self) -> u64 {
+        self.last_block_number + 1
+    }
+
+    pub fn validators_at(&self, block_number: u64) -> Option<&[Address]> {
+        self.sets.iter().rev().find(|(first, _)| *first <= block_number).map(|(_, validators)| validators.as_slice())
+    }
+}
+
+/// Why the account is in jail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JailReason {
+    /// Jailed for good. The stakes are slashed as well.
+    DoubleVote,
+    /// Jailed until the account sends `Unjail`.
+    Downtime,
+}
+
+const JAIL_REASON_DOUBLE_VOTE: u8 = 1;
+const JAIL_REASON_DOWNTIME: u8 = 2;
+
+impl Encodable for JailReason {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            JailReason::DoubleVote => s.append(&JAIL_REASON_DOUBLE_VOTE),
+            JailReason::Downtime => s.append(&JAIL_REASON_DOWNTIME),
+        };
+    }
+}
+
+impl Decodable for JailReason {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        match rlp.as_val()? {
+            JAIL_REASON_DOUBLE_VOTE => Ok(JailReason::DoubleVote),
+            JAIL_REASON_DOWNTIME => Ok(JailReason::Downtime),
+            _ => Err(DecoderError::Custom("Unexpected jail reason")),
+        }
+    }
+}
+
+/// The accounts which are punished for misbehaving as validators.
+/// They cannot be elected while they are in jail.
+pub struct Jail(BTreeMap<Address, JailReason>);
+
+impl Jail {
+    pub fn load_from_state(state: &TopLevelState) -> StakeResult<Jail> {
+        let action_data = state.action_data(&*jail_key)?;
+
+        let mut prisoners = BTreeMap::new();
+        if let Some(rlp) = action_data.as_ref().map(|x| UntrustedRlp::new(x)) {
+            for i in 0..rlp.item_count()? {
+                let prisoner = rlp.at(i)?;
+                prisoners.insert(prisoner.val_at(0)?, prisoner.val_at(1)?);
+            }
+        }
+
+        Ok(Jail(prisoners))
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StakeResult<()> {
+        let mut rlp = RlpStream::new();
+        rlp.begin_list(self.0.len());
+        for (address, reason) in self.0.iter() {
+            rlp.begin_list(2).append(address).append(reason);
+        }
+        state.update_action_data(&*jail_key, rlp.drain().into_vec())?;
+        Ok(())
+    }
+
+    /// The double vote overrides the other reasons.
+    pub fn add(&mut self, address: Address, reason: JailReason) {
+        let current = self.0.entry(address).or_insert(reason);
+        if reason == JailReason::DoubleVote {
+            *current = reason;
+        }
+    }
+
+    pub fn remove(&mut self, address: &Address) -> Option<JailReason> {
+        self.0.remove(address)
+    }
+
+    pub fn get(&self, address: &Address) -> Option<JailReason> {
+        self.0.get(address).cloned()
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.0.contains_key(address)
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<Address, JailReason> {
+        self.0.iter()
+    }
+}
+
+/// How many precommits a validator signed and missed in the current downtime window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LivenessCounter {
+    pub signed: u64,
+    pub missed: u64,
+}
+
+/// The liveness counters of the validators in the current downtime window.
+pub struct Liveness(BTreeMap<Address, LivenessCounter>);
+
+impl Liveness {
+    pub fn load_from_state(state: &TopLevelState) -> StakeResult<Liveness> {
+        let action_data = state.action_data(&*liveness_key)?;
+
+        let mut counters = BTreeMap::new();
+        if let Some(rlp) = action_data.as_ref().map(|x| UntrustedRlp::new(x)) {
+            for i in 0..rlp.item_count()? {
+                let counter = rlp.at(i)?;
+                let address = counter.val_at(0)?;
+                let signed = counter.val_at(1)?;
+                let missed = counter.val_at(2)?;
+                counters.insert(
+                    address,
+                    LivenessCounter {
+                        signed,
+                        missed,
+                    },
+                );
+            }
+        }
+
+        Ok(Liveness(counters))
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StakeResult<()> {
+        let mut rlp = RlpStream::new();
+        rlp.begin_list(self.0.len());
+        for (address, counter) in self.0.iter() {
+            rlp.begin_list(3).append(address).append(&counter.signed).append(&counter.missed);
+        }
+        state.update_action_data(&*liveness_key, rlp.drain().into_vec())?;
+        Ok(())
+    }
+
+    /// Counts the precommit of the validator and returns the number of the missed ones.
+    pub fn record(&mut self, address: Address, signed: bool) -> u64 {
+        let counter = self.0.entry(address).or_default();
+        if signed {
+            counter.signed += 1;
+        } else {
+            counter.missed += 1;
+        }
+        counter.missed
+    }
+
+    pub fn reset(&mut self, address: &Address) {
+        self.0.remove(address);
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<Address, LivenessCounter> {
+        self.0.iter()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,7 +644,7 @@ mod tests {
 
         let mut stakeholders = Stakeholders::load_from_state(&state).unwrap();
         for account in &accounts {
-            stakeholders.update(account);
+            stakeholders.update_by_increased_balance(account);
         }
         stakeholders.save_to_state(&mut state).unwrap();
 
@@ -243,7 +667,7 @@ mod tests {
 
         let mut stakeholders = Stakeholders::load_from_state(&state).unwrap();
         for account in &accounts {
-            stakeholders.update(account);
+            stakeholders.update_by_increased_balance(account);
         }
         stakeholders.save_to_state(&mut state).unwrap();
 
@@ -254,7 +678,8 @@ mod tests {
         }
         let mut stakeholders = Stakeholders::load_from_state(&state).unwrap();
         for account in &accounts {
-            stakeholders.update(account);
+            let delegation = Delegation::load_from_state(&state, account.address).unwrap();
+            stakeholders.update_by_decreased_balance(account, &delegation);
         }
         stakeholders.save_to_state(&mut state).unwrap();
 
@@ -265,4 +690,88 @@ mod tests {
             assert!(tracked && has_balance || !tracked && !has_balance);
         }
     }
+
+    #[test]
+    fn stakeholders_doesnt_untrack_if_delegation_exists() {
+        let mut state = helpers::get_temp_state();
+        let delegator = Address::random();
+        let delegatee = Address::random();
+
+        let account = StakeAccount {
+            address: &delegator,
+            balance: 0,
+        };
+        let mut delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+        delegation.add_quantity(delegatee, 100).unwrap();
+        delegation.save_to_state(&mut state).unwrap();
+
+        let mut stakeholders = Stakeholders::load_from_state(&state).unwrap();
+        stakeholders.update_by_increased_balance(&StakeAccount {
+            address: &delegator,
+            balance: 100,
+        });
+        stakeholders.update_by_decreased_balance(&account, &delegation);
+        stakeholders.save_to_state(&mut state).unwrap();
+
+        let stakeholders = Stakeholders::load_from_state(&state).unwrap();
+        assert!(stakeholders.contains(&delegator));
+    }
+
+    #[test]
+    fn delegation_add_and_subtract() {
+        let mut state = helpers::get_temp_state();
+        let delegator = Address::random();
+        let delegatee1 = Address::random();
+        let delegatee2 = Address::random();
+        {
+            let mut delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+            delegation.add_quantity(delegatee1, 100).unwrap();
+            delegation.add_quantity(delegatee2, 50).unwrap();
+            delegation.save_to_state(&mut state).unwrap();
+        }
+        {
+            let mut delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+            assert_eq!(delegation.get_quantity(&delegatee1), 100);
+            assert_eq!(delegation.get_quantity(&delegatee2), 50);
+            assert_eq!(delegation.sum(), 150);
+
+            delegation.subtract_quantity(delegatee1, 30).unwrap();
+            delegation.subtract_quantity(delegatee2, 50).unwrap();
+            delegation.save_to_state(&mut state).unwrap();
+        }
+        let delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+        assert_eq!(delegation.get_quantity(&delegatee1), 70);
+        assert_eq!(delegation.get_quantity(&delegatee2), 0);
+        assert_eq!(delegation.iter().count(), 1);
+    }
+
+    #[test]
+    fn delegation_subtract_error_on_low() {
+        let state = helpers::get_temp_state();
+        let delegator = Address::random();
+        let delegatee = Address::random();
+
+        let mut delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+        delegation.add_quantity(delegatee, 100).unwrap();
+        assert!(delegation.subtract_quantity(delegatee, 110).is_err());
+        assert_eq!(delegation.get_quantity(&delegatee), 100);
+    }
+
+    #[test]
+    fn delegation_without_delegatee_is_removed() {
+        let mut state = helpers::get_temp_state();
+        let delegator = Address::random();
+        let delegatee = Address::random();
+        {
+            let mut delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+            delegation.add_quantity(delegatee, 100).unwrap();
+            delegation.save_to_state(&mut state).unwrap();
+        }
+        assert_ne!(Ok(None), state.action_data(&get_delegation_key(&delegator)));
+
+        let mut delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+        delegation.subtract_quantity(delegatee, 100).unwrap();
+        delegation.save_to_state(&mut state).unwrap();
+        assert_eq!(Ok(None), state.action_data(&get_delegation_key(&delegator)));
+    }
 }
@@ -14,10 +14,18 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use ckey::Address;
+use ckey::{Address, Public};
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
+use super::super::message::ConsensusMessage;
+
 const ACTION_TAG_TRANSFER_CCS: u8 = 1;
+const ACTION_TAG_DELEGATE_CCS: u8 = 2;
+const ACTION_TAG_REVOKE_DELEGATION: u8 = 3;
+const ACTION_TAG_SELF_NOMINATE: u8 = 4;
+const ACTION_TAG_REPORT_DOUBLE_VOTE: u8 = 5;
+const ACTION_TAG_UNJAIL: u8 = 6;
+const ACTION_TAG_UNNOMINATE: u8 = 7;
 
 #[derive(Debug)]
 pub enum Action {
@@ -25,6 +33,23 @@ pub enum Action {
         address: Address,
         amount: u64,
     },
+    DelegateCCS {
+        address: Address,
+        amount: u64,
+    },
+    RevokeDelegation {
+        address: Address,
+        amount: u64,
+    },
+    SelfNominate {
+        public: Public,
+    },
+    Unnominate,
+    ReportDoubleVote {
+        message1: ConsensusMessage,
+        message2: ConsensusMessage,
+    },
+    Unjail,
 }
 
 impl Encodable for Action {
@@ -34,6 +59,23 @@ impl Encodable for Action {
                 address,
                 amount,
             } => s.begin_list(3).append(&ACTION_TAG_TRANSFER_CCS).append(address).append(amount),
+            Action::DelegateCCS {
+                address,
+                amount,
+            } => s.begin_list(3).append(&ACTION_TAG_DELEGATE_CCS).append(address).append(amount),
+            Action::RevokeDelegation {
+                address,
+                amount,
+            } => s.begin_list(3).append(&ACTION_TAG_REVOKE_DELEGATION).append(address).append(amount),
+            Action::SelfNominate {
+                public,
+            } => s.begin_list(2).append(&ACTION_TAG_SELF_NOMINATE).append(public),
+            Action::Unnominate => s.begin_list(1).append(&ACTION_TAG_UNNOMINATE),
+            Action::ReportDoubleVote {
+                message1,
+                message2,
+            } => s.begin_list(3).append(&ACTION_TAG_REPORT_DOUBLE_VOTE).append(message1).append(message2),
+            Action::Unjail => s.begin_list(1).append(&ACTION_TAG_UNJAIL),
         };
     }
 }
@@ -51,6 +93,53 @@ impl Decodable for Action {
                     amount: rlp.val_at(2)?,
                 })
             }
+            ACTION_TAG_DELEGATE_CCS => {
+                if rlp.item_count()? != 3 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::DelegateCCS {
+                    address: rlp.val_at(1)?,
+                    amount: rlp.val_at(2)?,
+                })
+            }
+            ACTION_TAG_REVOKE_DELEGATION => {
+                if rlp.item_count()? != 3 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::RevokeDelegation {
+                    address: rlp.val_at(1)?,
+                    amount: rlp.val_at(2)?,
+                })
+            }
+            ACTION_TAG_SELF_NOMINATE => {
+                if rlp.item_count()? != 2 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::SelfNominate {
+                    public: rlp.val_at(1)?,
+                })
+            }
+            ACTION_TAG_UNNOMINATE => {
+                if rlp.item_count()? != 1 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::Unnominate)
+            }
+            ACTION_TAG_REPORT_DOUBLE_VOTE => {
+                if rlp.item_count()? != 3 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::ReportDoubleVote {
+                    message1: rlp.val_at(1)?,
+                    message2: rlp.val_at(2)?,
+                })
+            }
+            ACTION_TAG_UNJAIL => {
+                if rlp.item_count()? != 1 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::Unjail)
+            }
             _ => Err(DecoderError::Custom("Unexpected Tendermint Stake Action Type")),
         }
     }
@@ -18,34 +18,72 @@ mod action_data;
 mod actions;
 mod distribute;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use ckey::Address;
-use cstate::{ActionHandler, ActionHandlerResult, TopLevelState};
+use ckey::{public_to_address, Address, Public};
+use cmerkle::verify_proof;
+use cstate::{ActionHandler, ActionHandlerResult, StateError, TopLevelState};
 use ctypes::invoice::Invoice;
-use rlp::UntrustedRlp;
+use ctypes::machine::Header;
+use ctypes::transaction::{Action as TransactionAction, ParcelError};
+use primitives::{Bytes, H256};
+use rlp::{Encodable, UntrustedRlp};
 
-use self::action_data::{StakeAccount, Stakeholders};
+use self::action_data::{
+    validators_key, Candidates, Delegation, Jail, Liveness, StakeAccount, Stakeholders, ValidatorHistory, Validators,
+};
+pub use self::action_data::{JailReason, LivenessCounter};
 use self::actions::Action;
 pub use self::distribute::fee_distribute;
+use super::message::ConsensusMessage;
+use super::{DowntimeParams, ElectionParams};
+use crate::error::Error;
 
 const CUSTOM_ACTION_HANDLER_ID: u64 = 2;
+/// The double vote older than this number of blocks cannot be reported.
+const MAX_EVIDENCE_AGE: u64 = 128;
 
 pub type StakeResult<T> = ActionHandlerResult<T>;
 
 pub struct Stake {
     genesis_stakes: HashMap<Address, u64>,
+    election: Option<ElectionParams>,
+    /// The validators until the first election.
+    initial_validators: Vec<Address>,
 }
 
 impl Stake {
-    pub fn new(genesis_stakes: HashMap<Address, u64>) -> Stake {
+    pub fn new(
+        genesis_stakes: HashMap<Address, u64>,
+        election: Option<ElectionParams>,
+        initial_validators: Vec<Address>,
+    ) -> Stake {
         Stake {
             genesis_stakes,
+            election,
+            initial_validators,
         }
     }
+
+    /// Records the validators of the next block, which the double votes on it are checked against.
+    fn record_validators(&self, state: &mut TopLevelState, closed_block_number: u64) -> StakeResult<()> {
+        let elected = Validators::load_from_state(state)?;
+        let validators = if elected.is_empty() {
+            self.initial_validators.clone()
+        } else {
+            elected.addresses()
+        };
+        let mut history = ValidatorHistory::load_from_state(state)?;
+        history.record(closed_block_number, validators, MAX_EVIDENCE_AGE);
+        history.save_to_state(state)
+    }
 }
 
 impl ActionHandler for Stake {
+    fn name(&self) -> &'static str {
+        "stake"
+    }
+
     fn handler_id(&self) -> u64 {
         CUSTOM_ACTION_HANDLER_ID
     }
@@ -58,12 +96,12 @@ impl ActionHandler for Stake {
                     address,
                     balance: *amount,
                 };
-                stakeholders.update(&account);
+                stakeholders.update_by_increased_balance(&account);
                 account.save_to_state(state)?;
             }
             stakeholders.save_to_state(state)?;
         }
-        Ok(())
+        self.record_validators(state, 0)
     }
 
     fn execute(&self, bytes: &[u8], state: &mut TopLevelState, sender: &Address) -> ActionHandlerResult<Invoice> {
@@ -73,20 +111,59 @@ impl ActionHandler for Stake {
                 address,
                 amount,
             } => transfer_ccs(state, sender, &address, amount),
+            Action::DelegateCCS {
+                address,
+                amount,
+            } => delegate_ccs(state, sender, &address, amount),
+            Action::RevokeDelegation {
+                address,
+                amount,
+            } => revoke_delegation(state, sender, &address, amount),
+            Action::SelfNominate {
+                public,
+            } => self_nominate(state, sender, public),
+            Action::Unnominate => unnominate(state, sender),
+            Action::ReportDoubleVote {
+                message1,
+                message2,
+            } => report_double_vote(state, &message1, &message2),
+            Action::Unjail => unjail(state, sender),
         }
     }
+
+    fn verify(&self, bytes: &[u8]) -> ActionHandlerResult<()> {
+        let action = UntrustedRlp::new(bytes).as_val()?;
+        if let Action::ReportDoubleVote {
+            message1,
+            message2,
+        } = action
+        {
+            verify_double_vote(&message1, &message2)?;
+        }
+        Ok(())
+    }
+
+    fn on_close_block(&self, state: &mut TopLevelState, header: &Header) -> ActionHandlerResult<()> {
+        if let Some(election) = self.election {
+            if header.number() % election.term_length == 0 {
+                elect_validators(state, election.max_num_of_validators)?;
+            }
+        }
+        self.record_validators(state, header.number())
+    }
 }
 
 fn transfer_ccs(state: &mut TopLevelState, sender: &Address, receiver: &Address, amount: u64) -> StakeResult<Invoice> {
     let mut stakeholders = Stakeholders::load_from_state(state)?;
     let mut sender_account = StakeAccount::load_from_state(state, sender)?;
     let mut receiver_account = StakeAccount::load_from_state(state, receiver)?;
+    let sender_delegation = Delegation::load_from_state(state, sender)?;
 
     sender_account.subtract_balance(amount)?;
     receiver_account.add_balance(amount)?;
 
-    stakeholders.update(&sender_account);
-    stakeholders.update(&receiver_account);
+    stakeholders.update_by_decreased_balance(&sender_account, &sender_delegation);
+    stakeholders.update_by_increased_balance(&receiver_account);
 
     stakeholders.save_to_state(state)?;
     sender_account.save_to_state(state)?;
@@ -95,20 +172,354 @@ fn transfer_ccs(state: &mut TopLevelState, sender: &Address, receiver: &Address,
     Ok(Invoice::Success)
 }
 
+fn delegate_ccs(
+    state: &mut TopLevelState,
+    delegator: &Address,
+    delegatee: &Address,
+    amount: u64,
+) -> StakeResult<Invoice> {
+    if !Candidates::load_from_state(state)?.contains(delegatee) {
+        return Err(ParcelError::InvalidCustomAction("The delegatee is not a candidate".to_string()).into())
+    }
+    let mut delegator_account = StakeAccount::load_from_state(state, delegator)?;
+    let mut delegation = Delegation::load_from_state(state, delegator)?;
+
+    delegator_account.subtract_balance(amount)?;
+    delegation.add_quantity(*delegatee, amount)?;
+    // The delegation keeps the delegator as a stakeholder, so the stakeholders don't change.
+
+    delegator_account.save_to_state(state)?;
+    delegation.save_to_state(state)?;
+
+    Ok(Invoice::Success)
+}
+
+fn revoke_delegation(
+    state: &mut TopLevelState,
+    delegator: &Address,
+    delegatee: &Address,
+    amount: u64,
+) -> StakeResult<Invoice> {
+    let mut stakeholders = Stakeholders::load_from_state(state)?;
+    let mut delegator_account = StakeAccount::load_from_state(state, delegator)?;
+    let mut delegation = Delegation::load_from_state(state, delegator)?;
+
+    delegation.subtract_quantity(*delegatee, amount)?;
+    delegator_account.add_balance(amount)?;
+
+    stakeholders.update_by_increased_balance(&delegator_account);
+
+    stakeholders.save_to_state(state)?;
+    delegator_account.save_to_state(state)?;
+    delegation.save_to_state(state)?;
+
+    Ok(Invoice::Success)
+}
+
+fn self_nominate(state: &mut TopLevelState, sender: &Address, public: Public) -> StakeResult<Invoice> {
+    if public_to_address(&public) != *sender {
+        return Err(ParcelError::InvalidCustomAction("The public key is not the sender's".to_string()).into())
+    }
+    if Jail::load_from_state(state)?.contains(sender) {
+        return Err(ParcelError::InvalidCustomAction("The jailed account cannot be a candidate".to_string()).into())
+    }
+    let mut candidates = Candidates::load_from_state(state)?;
+    candidates.add(public);
+    candidates.save_to_state(state)?;
+
+    Ok(Invoice::Success)
+}
+
+/// Withdraws the candidacy. The delegations to the account remain until the delegators revoke them,
+/// but it's not elected from the next term.
+fn unnominate(state: &mut TopLevelState, sender: &Address) -> StakeResult<Invoice> {
+    let mut candidates = Candidates::load_from_state(state)?;
+    if candidates.remove(sender).is_none() {
+        return Err(ParcelError::InvalidCustomAction("The account is not a candidate".to_string()).into())
+    }
+    candidates.save_to_state(state)?;
+
+    Ok(Invoice::Success)
+}
+
+/// Checks that the two messages are the conflicting votes of the same signer and returns the signer.
+/// The signer is recovered from the signatures, so the evidence doesn't depend on the validator set.
+fn verify_double_vote(message1: &ConsensusMessage, message2: &ConsensusMessage) -> Result<Address, ParcelError> {
+    if message1.on.step != message2.on.step {
+        return Err(ParcelError::InvalidCustomAction("The votes are not on the same step".to_string()))
+    }
+    if message1.on.block_hash == message2.on.block_hash {
+        return Err(ParcelError::InvalidCustomAction("The votes are not conflicting".to_string()))
+    }
+    if message1.signer_index != message2.signer_index {
+        return Err(ParcelError::InvalidCustomAction("The votes are not from the same validator".to_string()))
+    }
+    let invalid_signature = |_| ParcelError::InvalidCustomAction("The vote has an invalid signature".to_string());
+    let signer1 = message1.recover_public().map_err(invalid_signature)?;
+    let signer2 = message2.recover_public().map_err(invalid_signature)?;
+    if signer1 != signer2 {
+        return Err(ParcelError::InvalidCustomAction("The votes are not signed by the same key".to_string()))
+    }
+    Ok(public_to_address(&signer1))
+}
+
+/// Slashes all the stakes of the double voter and the stakes delegated to it, and puts it in jail.
+/// Nobody gets the slashed stakes, so the reporter cannot profit from reporting itself.
+fn report_double_vote(
+    state: &mut TopLevelState,
+    message1: &ConsensusMessage,
+    message2: &ConsensusMessage,
+) -> StakeResult<Invoice> {
+    let offender = verify_double_vote(message1, message2)?;
+
+    let height = message1.on.step.height as u64;
+    let history = ValidatorHistory::load_from_state(state)?;
+    let current = history.current_block_number();
+    if height > current {
+        return Err(ParcelError::InvalidCustomAction("The votes are on a future block".to_string()).into())
+    }
+    if current - height > MAX_EVIDENCE_AGE {
+        return Err(ParcelError::InvalidCustomAction("The evidence is too old".to_string()).into())
+    }
+    if !history.validators_at(height).map_or(false, |validators| validators.contains(&offender)) {
+        return Err(ParcelError::InvalidCustomAction("The signer was not a validator".to_string()).into())
+    }
+
+    let mut stakeholders = Stakeholders::load_from_state(state)?;
+
+    let delegators: Vec<Address> = stakeholders.iter().filter(|address| **address != offender).cloned().collect();
+    let mut slashed_delegations = 0;
+    for delegator in delegators.iter() {
+        let mut delegation = Delegation::load_from_state(state, delegator)?;
+        let quantity = delegation.get_quantity(&offender);
+        if quantity == 0 {
+            continue
+        }
+        delegation.subtract_quantity(offender, quantity)?;
+        let account = StakeAccount::load_from_state(state, delegator)?;
+        stakeholders.update_by_decreased_balance(&account, &delegation);
+        delegation.save_to_state(state)?;
+        slashed_delegations += quantity;
+    }
+
+    let mut candidates = Candidates::load_from_state(state)?;
+    let mut jail = Jail::load_from_state(state)?;
+    let mut account = StakeAccount::load_from_state(state, &offender)?;
+    let mut delegation = Delegation::load_from_state(state, &offender)?;
+
+    let mut validators = Validators::load_from_state(state)?;
+
+    let balance = account.balance;
+    account.subtract_balance(balance)?;
+    let delegated = delegation.clear();
+    stakeholders.update_by_decreased_balance(&account, &delegation);
+    candidates.remove(&offender);
+    validators.remove(&offender);
+    jail.add(offender, JailReason::DoubleVote);
+    cinfo!(
+        ENGINE,
+        "{} is jailed for the double vote and {} CCS is slashed",
+        offender,
+        balance + delegated + slashed_delegations
+    );
+
+    stakeholders.save_to_state(state)?;
+    candidates.save_to_state(state)?;
+    validators.save_to_state(state)?;
+    jail.save_to_state(state)?;
+    account.save_to_state(state)?;
+    delegation.save_to_state(state)?;
+
+    Ok(Invoice::Success)
+}
+
+/// Releases the validator jailed for the downtime. It can be elected again from the next term.
+fn unjail(state: &mut TopLevelState, sender: &Address) -> StakeResult<Invoice> {
+    let mut jail = Jail::load_from_state(state)?;
+    match jail.get(sender) {
+        Some(JailReason::Downtime) => {}
+        Some(JailReason::DoubleVote) => {
+            return Err(ParcelError::InvalidCustomAction("The double voter cannot be released".to_string()).into())
+        }
+        None => return Err(ParcelError::InvalidCustomAction("The account is not jailed".to_string()).into()),
+    }
+    let mut liveness = Liveness::load_from_state(state)?;
+    jail.remove(sender);
+    liveness.reset(sender);
+
+    jail.save_to_state(state)?;
+    liveness.save_to_state(state)?;
+
+    Ok(Invoice::Success)
+}
+
+/// Counts the precommits of the validators, and jails the ones which missed too many of them in the window.
+/// The jailed validators are removed from the active validators immediately.
+pub fn track_downtime(
+    state: &mut TopLevelState,
+    block_number: u64,
+    params: &DowntimeParams,
+    precommits: &[(Address, bool)],
+) -> StakeResult<()> {
+    let mut liveness = Liveness::load_from_state(state)?;
+    let mut jail = Jail::load_from_state(state)?;
+    let mut validators = Validators::load_from_state(state)?;
+
+    if block_number % params.window == 0 {
+        liveness.clear();
+    }
+    for (address, signed) in precommits {
+        if jail.contains(address) {
+            continue
+        }
+        let missed = liveness.record(*address, *signed);
+        if missed > params.max_missed_blocks {
+            cinfo!(ENGINE, "{} is jailed for missing {} precommits", address, missed);
+            validators.remove(address);
+            jail.add(*address, JailReason::Downtime);
+        }
+    }
+
+    liveness.save_to_state(state)?;
+    jail.save_to_state(state)?;
+    validators.save_to_state(state)?;
+    Ok(())
+}
+
+/// Makes the transaction action reporting the double vote to the stake handler.
+pub fn double_vote_report_action(message1: &ConsensusMessage, message2: &ConsensusMessage) -> TransactionAction {
+    let action = Action::ReportDoubleVote {
+        message1: message1.clone(),
+        message2: message2.clone(),
+    };
+    TransactionAction::Custom {
+        handler_id: CUSTOM_ACTION_HANDLER_ID,
+        bytes: action.rlp_bytes().into_vec(),
+    }
+}
+
+/// Elects the candidates with the most delegated stakes.
+/// The validators of the last term are kept if there's no candidate with delegations.
+fn elect_validators(state: &mut TopLevelState, max_num_of_validators: usize) -> StakeResult<()> {
+    let candidates = Candidates::load_from_state(state)?;
+    let delegators = get_delegators(state)?;
+
+    let jail = Jail::load_from_state(state)?;
+
+    let mut elected: Vec<(Address, Public, u64)> = candidates
+        .iter()
+        .filter(|(address, _)| !jail.contains(address))
+        .filter_map(|(address, public)| {
+            let delegated: u64 = delegators.get(address)?.values().sum();
+            if delegated > 0 {
+                Some((*address, *public, delegated))
+            } else {
+                None
+            }
+        })
+        .collect();
+    if elected.is_empty() {
+        return Ok(())
+    }
+    // The ties are broken by the addresses to make the election deterministic.
+    elected.sort_by(|(address1, _, delegated1), (address2, _, delegated2)| {
+        delegated2.cmp(delegated1).then_with(|| address1.cmp(address2))
+    });
+    elected.truncate(max_num_of_validators);
+
+    let validators = Validators::new(elected.into_iter().map(|(_, public, delegated)| (public, delegated)).collect());
+    validators.save_to_state(state)?;
+    Ok(())
+}
+
+/// Returns the validators elected at the end of the last term with their voting power.
+/// It's empty until the first election.
+pub fn get_validators(state: &TopLevelState) -> StakeResult<Vec<(Public, u64)>> {
+    Ok(Validators::load_from_state(state)?.into())
+}
+
+/// Returns the nodes proving the elected validators against the state root.
+/// The validators in the state of a block are the validators of its child.
+pub fn validators_proof(state: &TopLevelState) -> StakeResult<Vec<Bytes>> {
+    Ok(state.action_data_proof(&*validators_key)?)
+}
+
+/// Returns the validators proved by `validators_proof` against the state root.
+/// It's empty until the first election.
+pub fn verify_validators_proof(state_root: &H256, proof: &[Bytes]) -> Result<Vec<(Public, u64)>, Error> {
+    let value = match verify_proof(state_root, &*validators_key, proof).map_err(StateError::Trie)? {
+        Some(value) => value,
+        None => return Ok(Vec::new()),
+    };
+    let action_data: Bytes = UntrustedRlp::new(&value).as_val()?;
+    Ok(Validators::from_action_data(&action_data)?.into())
+}
+
+#[cfg(test)]
+pub fn set_validators(state: &mut TopLevelState, validators: Vec<(Public, u64)>) -> StakeResult<()> {
+    Validators::new(validators).save_to_state(state)
+}
+
+/// Returns the jailed accounts with the reasons.
+pub fn get_jailed(state: &TopLevelState) -> StakeResult<Vec<(Address, JailReason)>> {
+    let jail = Jail::load_from_state(state)?;
+    Ok(jail.iter().map(|(address, reason)| (*address, *reason)).collect())
+}
+
+/// Returns the liveness counters of the validators in the current downtime window.
+pub fn get_liveness(state: &TopLevelState) -> StakeResult<Vec<(Address, LivenessCounter)>> {
+    let liveness = Liveness::load_from_state(state)?;
+    Ok(liveness.iter().map(|(address, counter)| (*address, *counter)).collect())
+}
+
+/// Returns the stakes which the fees are distributed by.
+/// The delegated CCS is counted for the delegatee.
 pub fn get_stakes(state: &TopLevelState) -> StakeResult<HashMap<Address, u64>> {
     let stakeholders = Stakeholders::load_from_state(state)?;
     let mut result = HashMap::new();
     for stakeholder in stakeholders.iter() {
         let account = StakeAccount::load_from_state(state, stakeholder)?;
-        result.insert(*stakeholder, account.balance);
+        let delegation = Delegation::load_from_state(state, stakeholder)?;
+        if account.balance > 0 {
+            *result.entry(*stakeholder).or_insert(0) += account.balance;
+        }
+        for (delegatee, quantity) in delegation.iter() {
+            *result.entry(*delegatee).or_insert(0) += *quantity;
+        }
+    }
+    Ok(result)
+}
+
+/// Returns the delegators of each delegatee with the delegated quantities.
+pub fn get_delegators(state: &TopLevelState) -> StakeResult<BTreeMap<Address, BTreeMap<Address, u64>>> {
+    let stakeholders = Stakeholders::load_from_state(state)?;
+    let mut result: BTreeMap<Address, BTreeMap<Address, u64>> = BTreeMap::new();
+    for stakeholder in stakeholders.iter() {
+        let delegation = Delegation::load_from_state(state, stakeholder)?;
+        for (delegatee, quantity) in delegation.iter() {
+            result.entry(*delegatee).or_default().insert(*stakeholder, *quantity);
+        }
     }
     Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::message::{message_info_rlp, VoteOn, VoteStep};
+    use super::super::types::Step;
     use super::*;
+    use ccrypto::blake256;
+    use ckey::{sign_schnorr, Generator, KeyPair, Random};
     use cstate::tests::helpers;
+    use primitives::H256;
+    use rlp::Encodable;
+
+    /// Nominates a new candidate and returns its address.
+    fn nominate_candidate(state: &mut TopLevelState) -> Address {
+        let candidate = Random.generate().unwrap();
+        self_nominate(state, &candidate.address(), *candidate.public()).unwrap();
+        candidate.address()
+    }
 
     #[test]
     fn genesis_stakes() {
@@ -119,7 +530,7 @@ mod tests {
         let stake = {
             let mut genesis_stakes = HashMap::new();
             genesis_stakes.insert(address1, 100);
-            Stake::new(genesis_stakes)
+            Stake::new(genesis_stakes, None, Vec::new())
         };
         assert_eq!(Ok(()), stake.init(&mut state));
 
@@ -141,7 +552,7 @@ mod tests {
         let stake = {
             let mut genesis_stakes = HashMap::new();
             genesis_stakes.insert(address1, 100);
-            Stake::new(genesis_stakes)
+            Stake::new(genesis_stakes, None, Vec::new())
         };
         assert_eq!(Ok(()), stake.init(&mut state));
 
@@ -166,7 +577,7 @@ mod tests {
         let stake = {
             let mut genesis_stakes = HashMap::new();
             genesis_stakes.insert(address1, 100);
-            Stake::new(genesis_stakes)
+            Stake::new(genesis_stakes, None, Vec::new())
         };
         assert_eq!(Ok(()), stake.init(&mut state));
 
@@ -180,4 +591,449 @@ mod tests {
         assert!(!stakeholders.contains(&address1));
         assert!(stakeholders.contains(&address2));
     }
+
+    #[test]
+    fn delegate_ccs_partially() {
+        let delegator = Address::random();
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(genesis_stakes, None, Vec::new())
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+        let delegatee = nominate_candidate(&mut state);
+
+        let action = Action::DelegateCCS {
+            address: delegatee,
+            amount: 40,
+        };
+        let result = stake.execute(&action.rlp_bytes(), &mut state, &delegator);
+        assert_eq!(Ok(Invoice::Success), result);
+
+        let delegator_account = StakeAccount::load_from_state(&state, &delegator).unwrap();
+        let delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+        assert_eq!(delegator_account.balance, 60);
+        assert_eq!(delegation.get_quantity(&delegatee), 40);
+
+        let stakes = get_stakes(&state).unwrap();
+        assert_eq!(stakes.get(&delegator), Some(&60));
+        assert_eq!(stakes.get(&delegatee), Some(&40));
+
+        let delegators = get_delegators(&state).unwrap();
+        assert_eq!(delegators.len(), 1);
+        assert_eq!(delegators[&delegatee].get(&delegator), Some(&40));
+    }
+
+    #[test]
+    fn delegate_all_ccs_keeps_stakeholder() {
+        let delegator = Address::random();
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(genesis_stakes, None, Vec::new())
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+        let delegatee = nominate_candidate(&mut state);
+
+        assert_eq!(Ok(Invoice::Success), delegate_ccs(&mut state, &delegator, &delegatee, 100));
+
+        let stakeholders = Stakeholders::load_from_state(&state).unwrap();
+        assert!(stakeholders.contains(&delegator));
+        let stakes = get_stakes(&state).unwrap();
+        assert_eq!(stakes.get(&delegator), None);
+        assert_eq!(stakes.get(&delegatee), Some(&100));
+    }
+
+    #[test]
+    fn delegate_more_than_balance() {
+        let delegator = Address::random();
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(genesis_stakes, None, Vec::new())
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+        let delegatee = nominate_candidate(&mut state);
+
+        assert!(delegate_ccs(&mut state, &delegator, &delegatee, 101).is_err());
+    }
+
+    #[test]
+    fn revoke_delegation_partially() {
+        let delegator = Address::random();
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(genesis_stakes, None, Vec::new())
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+        let delegatee = nominate_candidate(&mut state);
+        delegate_ccs(&mut state, &delegator, &delegatee, 50).unwrap();
+
+        let action = Action::RevokeDelegation {
+            address: delegatee,
+            amount: 20,
+        };
+        let result = stake.execute(&action.rlp_bytes(), &mut state, &delegator);
+        assert_eq!(Ok(Invoice::Success), result);
+
+        let delegator_account = StakeAccount::load_from_state(&state, &delegator).unwrap();
+        let delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+        assert_eq!(delegator_account.balance, 70);
+        assert_eq!(delegation.get_quantity(&delegatee), 30);
+    }
+
+    #[test]
+    fn revoke_more_than_delegated() {
+        let delegator = Address::random();
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(genesis_stakes, None, Vec::new())
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+        let delegatee = nominate_candidate(&mut state);
+        delegate_ccs(&mut state, &delegator, &delegatee, 50).unwrap();
+
+        assert!(revoke_delegation(&mut state, &delegator, &delegatee, 51).is_err());
+        let delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+        assert_eq!(delegation.get_quantity(&delegatee), 50);
+    }
+
+    #[test]
+    fn delegate_to_non_candidate_fails() {
+        let delegator = Address::random();
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(genesis_stakes, None, Vec::new())
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+
+        assert!(delegate_ccs(&mut state, &delegator, &Address::random(), 10).is_err());
+        let delegatee = nominate_candidate(&mut state);
+        assert_eq!(Ok(Invoice::Success), unnominate(&mut state, &delegatee));
+        assert!(delegate_ccs(&mut state, &delegator, &delegatee, 10).is_err());
+        assert_eq!(StakeAccount::load_from_state(&state, &delegator).unwrap().balance, 100);
+    }
+
+    #[test]
+    fn self_nominate_with_other_public_fails() {
+        let mut state = helpers::get_temp_state();
+        let key_pair = Random.generate().unwrap();
+        let other = Address::random();
+
+        assert!(self_nominate(&mut state, &other, *key_pair.public()).is_err());
+        assert_eq!(
+            Ok(Invoice::Success),
+            self_nominate(&mut state, &key_pair.address(), *key_pair.public())
+        );
+    }
+
+    #[test]
+    fn elect_candidates_with_most_delegations() {
+        let delegator = Address::random();
+        let candidates: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(
+                genesis_stakes,
+                Some(ElectionParams {
+                    term_length: 10,
+                    max_num_of_validators: 2,
+                }),
+                Vec::new(),
+            )
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+
+        for (candidate, amount) in candidates.iter().zip(&[10, 30, 20]) {
+            self_nominate(&mut state, &candidate.address(), *candidate.public()).unwrap();
+            delegate_ccs(&mut state, &delegator, &candidate.address(), *amount).unwrap();
+        }
+
+        let mut header = crate::header::Header::new();
+        header.set_number(9);
+        stake.on_close_block(&mut state, &header).unwrap();
+        assert_eq!(Ok(vec![]), get_validators(&state));
+
+        header.set_number(10);
+        stake.on_close_block(&mut state, &header).unwrap();
+        assert_eq!(
+            Ok(vec![(*candidates[1].public(), 30), (*candidates[2].public(), 20)]),
+            get_validators(&state)
+        );
+    }
+
+    #[test]
+    fn unnominated_candidate_is_not_elected() {
+        let delegator = Address::random();
+        let candidates: Vec<_> = (0..2).map(|_| Random.generate().unwrap()).collect();
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(genesis_stakes, None, Vec::new())
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+
+        for candidate in &candidates {
+            self_nominate(&mut state, &candidate.address(), *candidate.public()).unwrap();
+            delegate_ccs(&mut state, &delegator, &candidate.address(), 10).unwrap();
+        }
+        let action = Action::Unnominate;
+        let result = stake.execute(&action.rlp_bytes(), &mut state, &candidates[0].address());
+        assert_eq!(Ok(Invoice::Success), result);
+        assert!(unnominate(&mut state, &candidates[0].address()).is_err());
+
+        elect_validators(&mut state, 10).unwrap();
+        assert_eq!(Ok(vec![(*candidates[1].public(), 10)]), get_validators(&state));
+    }
+
+    #[test]
+    fn candidates_without_delegation_are_not_elected() {
+        let candidate = Random.generate().unwrap();
+
+        let mut state = helpers::get_temp_state();
+        self_nominate(&mut state, &candidate.address(), *candidate.public()).unwrap();
+        elect_validators(&mut state, 10).unwrap();
+
+        assert_eq!(Ok(vec![]), get_validators(&state));
+    }
+
+    fn precommit(key_pair: &KeyPair, signer_index: usize, block_hash: Option<H256>) -> ConsensusMessage {
+        let step = VoteStep::new(3, 0, Step::Precommit);
+        let signature = sign_schnorr(key_pair.private(), &blake256(message_info_rlp(step, block_hash))).unwrap();
+        ConsensusMessage {
+            on: VoteOn {
+                step,
+                block_hash,
+            },
+            signature,
+            signer_index,
+        }
+    }
+
+    #[test]
+    fn double_voter_is_slashed_and_jailed() {
+        let offender = Random.generate().unwrap();
+        let delegator = Address::random();
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(offender.address(), 100);
+            genesis_stakes.insert(delegator, 50);
+            Stake::new(genesis_stakes, None, vec![offender.address()])
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+        self_nominate(&mut state, &offender.address(), *offender.public()).unwrap();
+        let delegatee = nominate_candidate(&mut state);
+        delegate_ccs(&mut state, &offender.address(), &delegatee, 40).unwrap();
+        delegate_ccs(&mut state, &delegator, &offender.address(), 30).unwrap();
+        stake.record_validators(&mut state, 2).unwrap();
+
+        let message1 = precommit(&offender, 1, Some(H256::random()));
+        let message2 = precommit(&offender, 1, Some(H256::random()));
+        let reporter = Address::random();
+        let action = Action::ReportDoubleVote {
+            message1,
+            message2,
+        };
+        assert_eq!(Ok(()), stake.verify(&action.rlp_bytes()));
+        assert_eq!(Ok(Invoice::Success), stake.execute(&action.rlp_bytes(), &mut state, &reporter));
+
+        let account = StakeAccount::load_from_state(&state, &offender.address()).unwrap();
+        assert_eq!(account.balance, 0);
+        let delegation = Delegation::load_from_state(&state, &offender.address()).unwrap();
+        assert_eq!(delegation.sum(), 0);
+        let stakeholders = Stakeholders::load_from_state(&state).unwrap();
+        assert!(!stakeholders.contains(&offender.address()));
+        assert!(Jail::load_from_state(&state).unwrap().contains(&offender.address()));
+        assert!(!Candidates::load_from_state(&state).unwrap().contains(&offender.address()));
+
+        let delegator_account = StakeAccount::load_from_state(&state, &delegator).unwrap();
+        assert_eq!(delegator_account.balance, 20);
+        let delegator_delegation = Delegation::load_from_state(&state, &delegator).unwrap();
+        assert_eq!(delegator_delegation.sum(), 0);
+        assert_eq!(None, get_stakes(&state).unwrap().get(&offender.address()));
+
+        assert!(self_nominate(&mut state, &offender.address(), *offender.public()).is_err());
+    }
+
+    #[test]
+    fn double_vote_of_non_validator_is_rejected() {
+        let offender = Random.generate().unwrap();
+        let validator = Address::random();
+
+        let mut state = helpers::get_temp_state();
+        let stake = Stake::new(HashMap::new(), None, vec![validator]);
+        assert_eq!(Ok(()), stake.init(&mut state));
+        stake.record_validators(&mut state, 2).unwrap();
+
+        let message1 = precommit(&offender, 0, Some(H256::random()));
+        let message2 = precommit(&offender, 0, Some(H256::random()));
+        assert!(report_double_vote(&mut state, &message1, &message2).is_err());
+        assert_eq!(Ok(vec![]), get_jailed(&state));
+    }
+
+    #[test]
+    fn too_old_double_vote_is_rejected() {
+        let offender = Random.generate().unwrap();
+
+        let mut state = helpers::get_temp_state();
+        let stake = Stake::new(HashMap::new(), None, vec![offender.address()]);
+        assert_eq!(Ok(()), stake.init(&mut state));
+        // The votes are on the block 3, which is older than the evidence can be.
+        stake.record_validators(&mut state, 3 + MAX_EVIDENCE_AGE).unwrap();
+
+        let message1 = precommit(&offender, 0, Some(H256::random()));
+        let message2 = precommit(&offender, 0, Some(H256::random()));
+        assert!(report_double_vote(&mut state, &message1, &message2).is_err());
+        assert_eq!(Ok(vec![]), get_jailed(&state));
+    }
+
+    #[test]
+    fn votes_on_the_same_block_are_not_double_vote() {
+        let key_pair = Random.generate().unwrap();
+        let block_hash = Some(H256::random());
+
+        let message1 = precommit(&key_pair, 1, block_hash);
+        let message2 = precommit(&key_pair, 1, block_hash);
+        assert!(verify_double_vote(&message1, &message2).is_err());
+    }
+
+    #[test]
+    fn votes_of_different_signers_are_not_double_vote() {
+        let key_pair1 = Random.generate().unwrap();
+        let key_pair2 = Random.generate().unwrap();
+
+        let message1 = precommit(&key_pair1, 1, Some(H256::random()));
+        let message2 = precommit(&key_pair2, 1, Some(H256::random()));
+        assert!(verify_double_vote(&message1, &message2).is_err());
+
+        let message3 = precommit(&key_pair1, 2, Some(H256::random()));
+        assert!(verify_double_vote(&message1, &message3).is_err());
+    }
+
+    #[test]
+    fn double_vote_is_verified_by_signer() {
+        let key_pair = Random.generate().unwrap();
+
+        let message1 = precommit(&key_pair, 1, Some(H256::random()));
+        let message2 = precommit(&key_pair, 1, None);
+        assert_eq!(Ok(key_pair.address()), verify_double_vote(&message1, &message2));
+    }
+
+    #[test]
+    fn validator_missing_too_many_precommits_is_jailed() {
+        let validators: Vec<_> = (0..2).map(|_| Random.generate().unwrap()).collect();
+        let lazy = validators[1].address();
+        let params = DowntimeParams {
+            window: 10,
+            max_missed_blocks: 2,
+        };
+
+        let mut state = helpers::get_temp_state();
+        Validators::new(validators.iter().map(|validator| (*validator.public(), 10)).collect())
+            .save_to_state(&mut state)
+            .unwrap();
+
+        let precommits = vec![(validators[0].address(), true), (lazy, false)];
+        for number in 1..3 {
+            track_downtime(&mut state, number, &params, &precommits).unwrap();
+        }
+        assert_eq!(None, Jail::load_from_state(&state).unwrap().get(&lazy));
+        let liveness: BTreeMap<_, _> = get_liveness(&state).unwrap().into_iter().collect();
+        assert_eq!(2, liveness[&validators[0].address()].signed);
+        assert_eq!(2, liveness[&lazy].missed);
+
+        track_downtime(&mut state, 3, &params, &precommits).unwrap();
+        assert_eq!(Some(JailReason::Downtime), Jail::load_from_state(&state).unwrap().get(&lazy));
+        assert_eq!(Ok(vec![(*validators[0].public(), 10)]), get_validators(&state));
+    }
+
+    #[test]
+    fn missed_precommits_are_reset_in_new_window() {
+        let lazy = Address::random();
+        let params = DowntimeParams {
+            window: 10,
+            max_missed_blocks: 2,
+        };
+
+        let mut state = helpers::get_temp_state();
+        for number in 8..12 {
+            track_downtime(&mut state, number, &params, &[(lazy, false)]).unwrap();
+        }
+        assert!(!Jail::load_from_state(&state).unwrap().contains(&lazy));
+        let liveness = get_liveness(&state).unwrap();
+        assert_eq!(vec![lazy], liveness.iter().map(|(address, _)| *address).collect::<Vec<_>>());
+        assert_eq!(2, liveness[0].1.missed);
+    }
+
+    #[test]
+    fn jailed_validator_is_released_by_unjail() {
+        let candidate = Random.generate().unwrap();
+        let delegator = Address::random();
+        let params = DowntimeParams {
+            window: 10,
+            max_missed_blocks: 0,
+        };
+
+        let mut state = helpers::get_temp_state();
+        let stake = {
+            let mut genesis_stakes = HashMap::new();
+            genesis_stakes.insert(delegator, 100);
+            Stake::new(genesis_stakes, None, Vec::new())
+        };
+        assert_eq!(Ok(()), stake.init(&mut state));
+        self_nominate(&mut state, &candidate.address(), *candidate.public()).unwrap();
+        delegate_ccs(&mut state, &delegator, &candidate.address(), 50).unwrap();
+
+        assert!(unjail(&mut state, &candidate.address()).is_err());
+        track_downtime(&mut state, 1, &params, &[(candidate.address(), false)]).unwrap();
+        assert_eq!(Ok(vec![(candidate.address(), JailReason::Downtime)]), get_jailed(&state));
+
+        elect_validators(&mut state, 10).unwrap();
+        assert_eq!(Ok(vec![]), get_validators(&state));
+
+        assert_eq!(Ok(Invoice::Success), unjail(&mut state, &candidate.address()));
+        assert_eq!(Ok(vec![]), get_jailed(&state));
+        assert_eq!(Ok(vec![]), get_liveness(&state));
+
+        elect_validators(&mut state, 10).unwrap();
+        assert_eq!(Ok(vec![(*candidate.public(), 50)]), get_validators(&state));
+    }
+
+    #[test]
+    fn double_voter_cannot_be_released() {
+        let offender = Random.generate().unwrap();
+
+        let mut state = helpers::get_temp_state();
+        let stake = Stake::new(HashMap::new(), None, vec![offender.address()]);
+        assert_eq!(Ok(()), stake.init(&mut state));
+        stake.record_validators(&mut state, 2).unwrap();
+        let message1 = precommit(&offender, 0, Some(H256::random()));
+        let message2 = precommit(&offender, 0, Some(H256::random()));
+        report_double_vote(&mut state, &message1, &message2).unwrap();
+
+        assert_eq!(Ok(vec![(offender.address(), JailReason::DoubleVote)]), get_jailed(&state));
+        assert!(unjail(&mut state, &offender.address()).is_err());
+    }
 }
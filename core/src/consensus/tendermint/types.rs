@@ -16,6 +16,7 @@
 
 use std::cmp::PartialEq;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Sub;
 
 use primitives::H256;
@@ -120,12 +121,21 @@ impl BitSet {
         self.0[array_index] |= 1u8 << bit_index;
     }
 
+    pub fn as_slice(&self) -> &[u8] {
+        &self.0
+    }
+
     pub fn reset(&mut self, index: usize) {
         let array_index = index / 8;
         let bit_index = index % 8;
 
         self.0[array_index] &= 0b1111_1111 ^ (1 << bit_index);
     }
+
+    /// Returns the set indices in the ascending order.
+    pub fn indices(&self) -> Vec<usize> {
+        (0..MAX_VALIDATOR_SIZE).filter(|index| self.is_set(*index)).collect()
+    }
 }
 
 impl Default for BitSet {
@@ -150,6 +160,14 @@ impl PartialEq for BitSet {
     }
 }
 
+impl Eq for BitSet {}
+
+impl Hash for BitSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0[..].hash(state)
+    }
+}
+
 impl Encodable for BitSet {
     fn rlp_append(&self, s: &mut RlpStream) {
         let slice: &[u8] = &self.0;
@@ -0,0 +1,767 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use byteorder::{BigEndian, ByteOrder};
+use kvdb::{DBTransaction, KeyValueDB};
+use parking_lot::Mutex;
+use primitives::{Bytes, H256};
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use super::message::{ConsensusMessage, VoteStep};
+use super::types::{Height, Step, View};
+use crate::db;
+
+const WAL_PREFIX: &[u8] = b"tendermint-wal-";
+/// The key of the snapshot of the consensus state, which was used before the WAL.
+const LEGACY_BACKUP_KEY: &[u8] = b"tendermint-backup";
+
+const ENTRY_TAG_STEP: u8 = 1;
+const ENTRY_TAG_MESSAGE: u8 = 2;
+const ENTRY_TAG_PROPOSAL_BLOCK: u8 = 3;
+const ENTRY_TAG_PROPOSAL: u8 = 4;
+const ENTRY_TAG_LOCK: u8 = 5;
+const ENTRY_TAG_CONFIRMED: u8 = 6;
+const ENTRY_TAG_TIMEOUT: u8 = 7;
+
+/// A record of the write-ahead log of the consensus.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WalEntry {
+    /// The engine moved to the step.
+    Step(VoteStep),
+    /// A vote, which is generated by this node or received from a peer, is collected.
+    Message(ConsensusMessage),
+    /// A proposal and the body of its block. The block may not be imported yet.
+    ProposalBlock {
+        message: ConsensusMessage,
+        block: Bytes,
+    },
+    /// The engine accepted the block as the proposal of the view.
+    Proposal {
+        height: Height,
+        view: View,
+        block_hash: H256,
+    },
+    /// The engine precommitted the locked block of the view.
+    Lock {
+        height: Height,
+        view: View,
+    },
+    /// The block of the height is committed at the view.
+    Confirmed {
+        height: Height,
+        view: View,
+        block_hash: H256,
+    },
+    /// The timer of the step expired.
+    Timeout(VoteStep),
+}
+
+impl WalEntry {
+    /// The height that the entry belongs to.
+    fn height(&self) -> Height {
+        match self {
+            WalEntry::Step(vote_step) | WalEntry::Timeout(vote_step) => vote_step.height,
+            WalEntry::Message(message)
+            | WalEntry::ProposalBlock {
+                message,
+                ..
+            } => message.on.step.height,
+            WalEntry::Proposal {
+                height,
+                ..
+            }
+            | WalEntry::Lock {
+                height,
+                ..
+            }
+            | WalEntry::Confirmed {
+                height,
+                ..
+            } => *height,
+        }
+    }
+}
+
+impl Encodable for WalEntry {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            WalEntry::Step(vote_step) => s.begin_list(2).append(&ENTRY_TAG_STEP).append(vote_step),
+            WalEntry::Message(message) => s.begin_list(2).append(&ENTRY_TAG_MESSAGE).append(message),
+            WalEntry::ProposalBlock {
+                message,
+                block,
+            } => s.begin_list(3).append(&ENTRY_TAG_PROPOSAL_BLOCK).append(message).append(block),
+            WalEntry::Proposal {
+                height,
+                view,
+                block_hash,
+            } => s.begin_list(4).append(&ENTRY_TAG_PROPOSAL).append(height).append(view).append(block_hash),
+            WalEntry::Lock {
+                height,
+                view,
+            } => s.begin_list(3).append(&ENTRY_TAG_LOCK).append(height).append(view),
+            WalEntry::Confirmed {
+                height,
+                view,
+                block_hash,
+            } => s.begin_list(4).append(&ENTRY_TAG_CONFIRMED).append(height).append(view).append(block_hash),
+            WalEntry::Timeout(vote_step) => s.begin_list(2).append(&ENTRY_TAG_TIMEOUT).append(vote_step),
+        };
+    }
+}
+
+impl Decodable for WalEntry {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let tag = rlp.val_at(0)?;
+        let expected_item_count = match tag {
+            ENTRY_TAG_STEP | ENTRY_TAG_MESSAGE | ENTRY_TAG_TIMEOUT => 2,
+            ENTRY_TAG_PROPOSAL_BLOCK | ENTRY_TAG_LOCK => 3,
+            ENTRY_TAG_PROPOSAL | ENTRY_TAG_CONFIRMED => 4,
+            _ => return Err(DecoderError::Custom("Unexpected Tendermint WAL Entry Type")),
+        };
+        if rlp.item_count()? != expected_item_count {
+            return Err(DecoderError::RlpInvalidLength)
+        }
+        Ok(match tag {
+            ENTRY_TAG_STEP => WalEntry::Step(rlp.val_at(1)?),
+            ENTRY_TAG_MESSAGE => WalEntry::Message(rlp.val_at(1)?),
+            ENTRY_TAG_PROPOSAL_BLOCK => WalEntry::ProposalBlock {
+                message: rlp.val_at(1)?,
+                block: rlp.val_at(2)?,
+            },
+            ENTRY_TAG_PROPOSAL => WalEntry::Proposal {
+                height: rlp.val_at(1)?,
+                view: rlp.val_at(2)?,
+                block_hash: rlp.val_at(3)?,
+            },
+            ENTRY_TAG_LOCK => WalEntry::Lock {
+                height: rlp.val_at(1)?,
+                view: rlp.val_at(2)?,
+            },
+            ENTRY_TAG_CONFIRMED => WalEntry::Confirmed {
+                height: rlp.val_at(1)?,
+                view: rlp.val_at(2)?,
+                block_hash: rlp.val_at(3)?,
+            },
+            ENTRY_TAG_TIMEOUT => WalEntry::Timeout(rlp.val_at(1)?),
+            _ => unreachable!("The tag is checked above"),
+        })
+    }
+}
+
+/// Append-only log of the consensus events.
+///
+/// Every entry is written to the disk before the engine acts on it,
+/// so replaying the log after a crash reconstructs the state that the engine had right before the crash.
+pub struct Wal {
+    db: Arc<KeyValueDB>,
+    indices: Mutex<WalIndices>,
+}
+
+/// The indices of the entries in the log, grouped by the heights they belong to.
+#[derive(Default)]
+struct WalIndices {
+    next_index: u64,
+    by_height: BTreeMap<Height, Vec<u64>>,
+}
+
+impl WalIndices {
+    fn insert(&mut self, height: Height) -> u64 {
+        let index = self.next_index;
+        self.by_height.entry(height).or_insert_with(Vec::new).push(index);
+        self.next_index += 1;
+        index
+    }
+}
+
+impl Wal {
+    /// Opens the log and returns the entries written before the last shutdown.
+    /// A broken entry, which can be left by a crash in the middle of a write, and the entries after it are discarded.
+    pub fn open(db: Arc<KeyValueDB>) -> (Self, Vec<WalEntry>) {
+        let mut entries = Vec::new();
+        let mut indices = WalIndices::default();
+        let mut broken_keys = Vec::new();
+        for (key, value) in
+            db.iter_from_prefix(db::COL_EXTRA, WAL_PREFIX).take_while(|(key, _)| key.starts_with(WAL_PREFIX))
+        {
+            if !broken_keys.is_empty() {
+                broken_keys.push(key);
+                continue
+            }
+            let index = decode_index(&key);
+            match UntrustedRlp::new(&value).as_val::<WalEntry>() {
+                Ok(entry) => {
+                    indices.by_height.entry(entry.height()).or_insert_with(Vec::new).push(index);
+                    indices.next_index = index + 1;
+                    entries.push(entry);
+                }
+                Err(err) => {
+                    cwarn!(ENGINE, "The consensus WAL is broken at #{}: {:?}", index, err);
+                    broken_keys.push(key);
+                }
+            }
+        }
+        if !broken_keys.is_empty() {
+            let mut batch = DBTransaction::new();
+            for key in broken_keys {
+                batch.delete(db::COL_EXTRA, &key);
+            }
+            db.write_sync(batch).expect("Low level database error. Some issue with disk?");
+        }
+        if entries.is_empty() {
+            entries = migrate_legacy_backup(&*db);
+            for entry in &entries {
+                indices.insert(entry.height());
+            }
+        }
+
+        let wal = Wal {
+            db,
+            indices: Mutex::new(indices),
+        };
+        (wal, entries)
+    }
+
+    /// Appends the entry and returns after the entry reaches the disk.
+    pub fn append(&self, entry: &WalEntry) {
+        let mut indices = self.indices.lock();
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_EXTRA, &encode_index(indices.next_index), &entry.rlp_bytes());
+        self.db.write_sync(batch).expect("Low level database error. Some issue with disk?");
+        indices.insert(entry.height());
+    }
+
+    /// Removes the entries of the heights lower than `min_height`.
+    pub fn truncate(&self, min_height: Height) {
+        let mut indices = self.indices.lock();
+        let retained = indices.by_height.split_off(&min_height);
+        let removed = ::std::mem::replace(&mut indices.by_height, retained);
+        if removed.is_empty() {
+            return
+        }
+        let mut batch = DBTransaction::new();
+        for index in removed.values().flatten() {
+            batch.delete(db::COL_EXTRA, &encode_index(*index));
+        }
+        self.db.write_sync(batch).expect("Low level database error. Some issue with disk?");
+    }
+}
+
+/// Removes the entries of the heights higher than `max_height`, and the legacy backup if it is higher.
+/// It's used to rewind the chain while the engine is not running.
+pub fn rewind(db: &KeyValueDB, batch: &mut DBTransaction, max_height: Height) {
+    for (key, value) in
+        db.iter_from_prefix(db::COL_EXTRA, WAL_PREFIX).take_while(|(key, _)| key.starts_with(WAL_PREFIX))
+    {
+        match UntrustedRlp::new(&value).as_val::<WalEntry>() {
+            Ok(entry) => {
+                if entry.height() > max_height {
+                    batch.delete(db::COL_EXTRA, &key);
+                }
+            }
+            // The broken entry and the entries after it are discarded when the WAL is opened.
+            Err(_) => break,
+        }
+    }
+
+    let backup = db.get(db::COL_EXTRA, LEGACY_BACKUP_KEY).expect("Low level database error. Some issue with disk?");
+    if let Some(backup) = backup {
+        let height: Result<Height, _> = UntrustedRlp::new(&backup).val_at(0);
+        if height.map(|height| height > max_height).unwrap_or(false) {
+            batch.delete(db::COL_EXTRA, LEGACY_BACKUP_KEY);
+        }
+    }
+}
+
+/// Converts the snapshot written by the older versions into the entries of the WAL.
+/// The entries replace the snapshot atomically, so the migration runs only once.
+fn migrate_legacy_backup(db: &KeyValueDB) -> Vec<WalEntry> {
+    let backup = db.get(db::COL_EXTRA, LEGACY_BACKUP_KEY).expect("Low level database error. Some issue with disk?");
+    let backup = match backup {
+        Some(backup) => backup,
+        None => return Vec::new(),
+    };
+    let entries = match decode_legacy_backup(&backup) {
+        Ok(entries) => entries,
+        Err(err) => {
+            // The backup is kept so that it can be recovered by hand.
+            cwarn!(ENGINE, "Cannot migrate the Tendermint backup: {:?}", err);
+            return Vec::new()
+        }
+    };
+
+    let mut batch = DBTransaction::new();
+    for (index, entry) in entries.iter().enumerate() {
+        batch.put(db::COL_EXTRA, &encode_index(index as u64), &entry.rlp_bytes());
+    }
+    batch.delete(db::COL_EXTRA, LEGACY_BACKUP_KEY);
+    db.write_sync(batch).expect("Low level database error. Some issue with disk?");
+    cinfo!(ENGINE, "Migrated the Tendermint backup into {} WAL entries", entries.len());
+    entries
+}
+
+fn decode_legacy_backup(backup: &[u8]) -> Result<Vec<WalEntry>, DecoderError> {
+    let rlp = UntrustedRlp::new(backup);
+    if rlp.item_count()? != 6 {
+        return Err(DecoderError::RlpInvalidLength)
+    }
+    let height: Height = rlp.val_at(0)?;
+    let view: View = rlp.val_at(1)?;
+    let step: Step = rlp.val_at(2)?;
+    let votes: Vec<ConsensusMessage> = rlp.list_at(3)?;
+    let last_confirmed_view: (H256, View) = (rlp.val_at(4)?, rlp.val_at(5)?);
+
+    let proposal = votes
+        .iter()
+        .rev()
+        .map(|vote| &vote.on)
+        .find(|vote_on| vote_on.step == VoteStep::new(height, view, Step::Propose))
+        .and_then(|vote_on| vote_on.block_hash);
+
+    let mut entries = vec![WalEntry::Step(VoteStep::new(height, view, step))];
+    entries.extend(votes.into_iter().map(WalEntry::Message));
+    if let Some(block_hash) = proposal {
+        entries.push(WalEntry::Proposal {
+            height,
+            view,
+            block_hash,
+        });
+    }
+    // The last confirmed block is the one committed at the previous height.
+    entries.push(WalEntry::Confirmed {
+        height: height.saturating_sub(1),
+        view: last_confirmed_view.1,
+        block_hash: last_confirmed_view.0,
+    });
+    Ok(entries)
+}
+
+fn encode_index(index: u64) -> Vec<u8> {
+    let mut key = WAL_PREFIX.to_vec();
+    let mut index_bytes = [0u8; 8];
+    BigEndian::write_u64(&mut index_bytes, index);
+    key.extend_from_slice(&index_bytes);
+    key
+}
+
+fn decode_index(key: &[u8]) -> u64 {
+    BigEndian::read_u64(&key[WAL_PREFIX.len()..])
+}
+
+/// The state of the engine reconstructed from the WAL.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WalState {
+    pub vote_step: Option<VoteStep>,
+    /// Collected votes and proposals in the order they were collected.
+    pub messages: Vec<ConsensusMessage>,
+    /// Bodies of the proposal blocks.
+    pub proposal_blocks: Vec<Bytes>,
+    pub last_confirmed_view: Option<(H256, View)>,
+    /// Whether the timer of the current step had expired.
+    pub timed_out: bool,
+    proposal: Option<(Height, View, H256)>,
+    last_lock: Option<(Height, View)>,
+}
+
+impl WalState {
+    fn apply(&mut self, entry: &WalEntry) {
+        match entry {
+            WalEntry::Step(vote_step) => {
+                self.vote_step = Some(*vote_step);
+                self.timed_out = false;
+            }
+            WalEntry::Message(message) => self.messages.push(message.clone()),
+            WalEntry::ProposalBlock {
+                message,
+                block,
+            } => {
+                self.messages.push(message.clone());
+                self.proposal_blocks.push(block.clone());
+            }
+            WalEntry::Proposal {
+                height,
+                view,
+                block_hash,
+            } => self.proposal = Some((*height, *view, *block_hash)),
+            WalEntry::Lock {
+                height,
+                view,
+            } => self.last_lock = Some((*height, *view)),
+            WalEntry::Confirmed {
+                view,
+                block_hash,
+                ..
+            } => self.last_confirmed_view = Some((*block_hash, *view)),
+            WalEntry::Timeout(vote_step) => {
+                if self.vote_step == Some(*vote_step) {
+                    self.timed_out = true;
+                }
+            }
+        }
+    }
+
+    /// The proposal of the current view.
+    pub fn proposal(&self) -> Option<H256> {
+        let vote_step = self.vote_step?;
+        match self.proposal {
+            Some((height, view, block_hash)) if height == vote_step.height && view == vote_step.view => {
+                Some(block_hash)
+            }
+            _ => None,
+        }
+    }
+
+    /// The last lock view of the current height.
+    pub fn last_lock(&self) -> View {
+        match (self.vote_step, self.last_lock) {
+            (Some(vote_step), Some((height, view))) if height == vote_step.height => view,
+            _ => 0,
+        }
+    }
+}
+
+pub fn replay(entries: &[WalEntry]) -> WalState {
+    let mut state = WalState::default();
+    for entry in entries {
+        state.apply(entry);
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::SchnorrSignature;
+    use kvdb_memorydb;
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::super::message::VoteOn;
+    use super::super::types::Step;
+    use super::*;
+
+    enum Operation {
+        Append(WalEntry),
+        Truncate(Height),
+    }
+
+    fn vote(height: Height, view: View, step: Step, block_hash: Option<H256>) -> ConsensusMessage {
+        ConsensusMessage {
+            signature: SchnorrSignature::random(),
+            signer_index: 0,
+            on: VoteOn {
+                step: VoteStep::new(height, view, step),
+                block_hash,
+            },
+        }
+    }
+
+    fn new_db() -> Arc<KeyValueDB> {
+        Arc::new(kvdb_memorydb::create(db::NUM_COLUMNS.unwrap_or(0)))
+    }
+
+    /// Operations of a node which commits the block 1 at the view 1 and moves to the height 2.
+    fn scenario() -> Vec<Operation> {
+        let block1 = H256::random();
+        let block2 = H256::random();
+        vec![
+            Operation::Append(WalEntry::Step(VoteStep::new(1, 0, Step::Propose))),
+            Operation::Append(WalEntry::Timeout(VoteStep::new(1, 0, Step::Propose))),
+            Operation::Append(WalEntry::Step(VoteStep::new(1, 0, Step::Prevote))),
+            Operation::Append(WalEntry::Message(vote(1, 0, Step::Prevote, None))),
+            Operation::Append(WalEntry::Step(VoteStep::new(1, 1, Step::Propose))),
+            Operation::Append(WalEntry::ProposalBlock {
+                message: vote(1, 1, Step::Propose, Some(block1)),
+                block: vec![0xc0, 0x01],
+            }),
+            Operation::Append(WalEntry::Proposal {
+                height: 1,
+                view: 1,
+                block_hash: block1,
+            }),
+            Operation::Append(WalEntry::Step(VoteStep::new(1, 1, Step::Prevote))),
+            Operation::Append(WalEntry::Message(vote(1, 1, Step::Prevote, Some(block1)))),
+            Operation::Append(WalEntry::Lock {
+                height: 1,
+                view: 1,
+            }),
+            Operation::Append(WalEntry::Step(VoteStep::new(1, 1, Step::Precommit))),
+            Operation::Append(WalEntry::Message(vote(1, 1, Step::Precommit, Some(block1)))),
+            Operation::Append(WalEntry::Confirmed {
+                height: 1,
+                view: 1,
+                block_hash: block1,
+            }),
+            Operation::Truncate(1),
+            Operation::Append(WalEntry::Step(VoteStep::new(2, 0, Step::Commit))),
+            Operation::Append(WalEntry::Step(VoteStep::new(2, 0, Step::Propose))),
+            Operation::Append(WalEntry::ProposalBlock {
+                message: vote(2, 0, Step::Propose, Some(block2)),
+                block: vec![0xc0, 0x02],
+            }),
+            Operation::Truncate(2),
+            Operation::Append(WalEntry::Proposal {
+                height: 2,
+                view: 0,
+                block_hash: block2,
+            }),
+            Operation::Append(WalEntry::Timeout(VoteStep::new(2, 0, Step::Propose))),
+        ]
+    }
+
+    /// Runs the operations on the WAL and on the list of the entries that should survive.
+    fn run(wal: &Wal, survived: &mut Vec<WalEntry>, operations: &[Operation]) {
+        for operation in operations {
+            match operation {
+                Operation::Append(entry) => {
+                    wal.append(entry);
+                    survived.push(entry.clone());
+                }
+                Operation::Truncate(min_height) => {
+                    wal.truncate(*min_height);
+                    survived.retain(|entry| entry.height() >= *min_height);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn encode_and_decode_entries() {
+        for operation in scenario() {
+            if let Operation::Append(entry) = operation {
+                rlp_encode_and_decode_test!(entry);
+            }
+        }
+    }
+
+    #[test]
+    fn replay_reaches_the_state_before_crash_at_any_point() {
+        let operations = scenario();
+        for crash_point in 0..=operations.len() {
+            let db = new_db();
+            let mut survived = Vec::new();
+            {
+                let (wal, entries) = Wal::open(Arc::clone(&db));
+                assert_eq!(Vec::<WalEntry>::new(), entries);
+                run(&wal, &mut survived, &operations[..crash_point]);
+                // The node crashes here.
+            }
+
+            let (_wal, entries) = Wal::open(db);
+            assert_eq!(survived, entries, "crashed after {} operations", crash_point);
+            assert_eq!(replay(&survived), replay(&entries), "crashed after {} operations", crash_point);
+        }
+    }
+
+    #[test]
+    fn replay_restores_the_proposal_and_the_lock() {
+        let operations = scenario();
+        let db = new_db();
+        let (wal, _) = Wal::open(Arc::clone(&db));
+        let mut survived = Vec::new();
+        // Crash right after the precommit of the height 1.
+        run(&wal, &mut survived, &operations[..12]);
+        drop(wal);
+
+        let (_, entries) = Wal::open(db);
+        let state = replay(&entries);
+        assert_eq!(Some(VoteStep::new(1, 1, Step::Precommit)), state.vote_step);
+        assert!(state.proposal().is_some());
+        assert_eq!(1, state.last_lock());
+        assert_eq!(None, state.last_confirmed_view);
+        assert!(!state.timed_out);
+        assert_eq!(vec![vec![0xc0, 0x01]], state.proposal_blocks);
+        assert_eq!(4, state.messages.len());
+    }
+
+    #[test]
+    fn replay_resets_the_state_of_the_previous_height() {
+        let operations = scenario();
+        let db = new_db();
+        let (wal, _) = Wal::open(Arc::clone(&db));
+        let mut survived = Vec::new();
+        // Crash right after moving to the height 2.
+        run(&wal, &mut survived, &operations[..15]);
+        drop(wal);
+
+        let (_, entries) = Wal::open(db);
+        let state = replay(&entries);
+        assert_eq!(Some(VoteStep::new(2, 0, Step::Commit)), state.vote_step);
+        assert_eq!(None, state.proposal());
+        assert_eq!(0, state.last_lock());
+        assert!(state.last_confirmed_view.is_some());
+        // The precommits of the height 1 are kept to seal the block of the height 2.
+        assert!(state.messages.iter().any(|message| message.on.step == VoteStep::new(1, 1, Step::Precommit)));
+    }
+
+    #[test]
+    fn replay_remembers_the_expired_timer() {
+        let operations = scenario();
+        let db = new_db();
+        let (wal, _) = Wal::open(Arc::clone(&db));
+        let mut survived = Vec::new();
+        run(&wal, &mut survived, &operations);
+        drop(wal);
+
+        let (_, entries) = Wal::open(Arc::clone(&db));
+        let state = replay(&entries);
+        assert_eq!(Some(VoteStep::new(2, 0, Step::Propose)), state.vote_step);
+        assert!(state.timed_out);
+        assert!(state.proposal().is_some());
+        assert!(entries.iter().all(|entry| entry.height() == 2));
+
+        let (wal, _) = Wal::open(Arc::clone(&db));
+        wal.append(&WalEntry::Step(VoteStep::new(2, 0, Step::Prevote)));
+        drop(wal);
+        let (_, entries) = Wal::open(db);
+        assert!(!replay(&entries).timed_out);
+    }
+
+    #[test]
+    fn broken_entry_is_discarded() {
+        let db = new_db();
+        let (wal, _) = Wal::open(Arc::clone(&db));
+        let step = WalEntry::Step(VoteStep::new(1, 0, Step::Propose));
+        wal.append(&step);
+        drop(wal);
+
+        // Crash in the middle of writing the second entry.
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_EXTRA, &encode_index(1), &[0xc3, 0x07]);
+        db.write(batch).unwrap();
+
+        let (wal, entries) = Wal::open(Arc::clone(&db));
+        assert_eq!(vec![step.clone()], entries);
+
+        let timeout = WalEntry::Timeout(VoteStep::new(1, 0, Step::Propose));
+        wal.append(&timeout);
+        drop(wal);
+        let (_, entries) = Wal::open(db);
+        assert_eq!(vec![step, timeout], entries);
+    }
+
+    #[test]
+    fn index_is_not_reused_after_truncation() {
+        let db = new_db();
+        let (wal, _) = Wal::open(Arc::clone(&db));
+        wal.append(&WalEntry::Step(VoteStep::new(1, 0, Step::Propose)));
+        wal.append(&WalEntry::Step(VoteStep::new(2, 0, Step::Propose)));
+        wal.truncate(2);
+        drop(wal);
+
+        let (wal, entries) = Wal::open(Arc::clone(&db));
+        assert_eq!(vec![WalEntry::Step(VoteStep::new(2, 0, Step::Propose))], entries);
+        wal.append(&WalEntry::Step(VoteStep::new(2, 0, Step::Prevote)));
+        drop(wal);
+
+        assert!(db.get(db::COL_EXTRA, &encode_index(0)).unwrap().is_none());
+        assert!(db.get(db::COL_EXTRA, &encode_index(2)).unwrap().is_some());
+        let (_, entries) = Wal::open(db);
+        assert_eq!(Some(VoteStep::new(2, 0, Step::Prevote)), replay(&entries).vote_step);
+    }
+
+    #[test]
+    fn entries_written_before_reopen_are_truncated() {
+        let db = new_db();
+        let (wal, _) = Wal::open(Arc::clone(&db));
+        wal.append(&WalEntry::Step(VoteStep::new(1, 0, Step::Propose)));
+        wal.append(&WalEntry::Step(VoteStep::new(2, 0, Step::Propose)));
+        drop(wal);
+
+        let (wal, _) = Wal::open(Arc::clone(&db));
+        wal.append(&WalEntry::Step(VoteStep::new(3, 0, Step::Propose)));
+        wal.truncate(3);
+        drop(wal);
+
+        let (_, entries) = Wal::open(db);
+        assert_eq!(vec![WalEntry::Step(VoteStep::new(3, 0, Step::Propose))], entries);
+    }
+
+    #[test]
+    fn legacy_backup_is_migrated_once() {
+        let db = new_db();
+        let proposal = H256::random();
+        let last_confirmed = H256::random();
+        let votes = vec![vote(3, 1, Step::Propose, Some(proposal)), vote(3, 1, Step::Prevote, Some(proposal))];
+        let mut s = RlpStream::new_list(6);
+        s.append(&3u64).append(&1u64).append(&Step::Prevote).append_list(&votes);
+        s.append(&last_confirmed).append(&0u64);
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_EXTRA, LEGACY_BACKUP_KEY, &s.out());
+        db.write(batch).unwrap();
+
+        let (wal, entries) = Wal::open(Arc::clone(&db));
+        let state = replay(&entries);
+        assert_eq!(Some(VoteStep::new(3, 1, Step::Prevote)), state.vote_step);
+        assert_eq!(votes, state.messages);
+        assert_eq!(Some(proposal), state.proposal());
+        assert_eq!(Some((last_confirmed, 0)), state.last_confirmed_view);
+        assert!(entries.contains(&WalEntry::Confirmed {
+            height: 2,
+            view: 0,
+            block_hash: last_confirmed,
+        }));
+        assert!(db.get(db::COL_EXTRA, LEGACY_BACKUP_KEY).unwrap().is_none());
+
+        let timeout = WalEntry::Timeout(VoteStep::new(3, 1, Step::Prevote));
+        wal.append(&timeout);
+        drop(wal);
+        let (_, reopened) = Wal::open(db);
+        assert_eq!(entries.len() + 1, reopened.len());
+        assert_eq!(entries[..], reopened[..entries.len()]);
+        assert_eq!(Some(&timeout), reopened.last());
+    }
+
+    #[test]
+    fn broken_legacy_backup_is_kept() {
+        let db = new_db();
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_EXTRA, LEGACY_BACKUP_KEY, &[0xc1, 0x80]);
+        db.write(batch).unwrap();
+
+        let (_, entries) = Wal::open(Arc::clone(&db));
+        assert_eq!(Vec::<WalEntry>::new(), entries);
+        assert!(db.get(db::COL_EXTRA, LEGACY_BACKUP_KEY).unwrap().is_some());
+    }
+
+    #[test]
+    fn rewind_removes_the_entries_above_the_height() {
+        let operations = scenario();
+        let db = new_db();
+        let (wal, _) = Wal::open(Arc::clone(&db));
+        let mut survived = Vec::new();
+        // The node stops at the height 2 before truncating the height 1.
+        run(&wal, &mut survived, &operations[..15]);
+        drop(wal);
+        let mut s = RlpStream::new_list(6);
+        s.append(&2u64).append(&0u64).append(&Step::Propose).append_list::<ConsensusMessage, _>(&[]);
+        s.append(&H256::random()).append(&0u64);
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_EXTRA, LEGACY_BACKUP_KEY, &s.out());
+        db.write(batch).unwrap();
+
+        let mut batch = DBTransaction::new();
+        rewind(&*db, &mut batch, 1);
+        db.write(batch).unwrap();
+
+        let (_, entries) = Wal::open(Arc::clone(&db));
+        survived.retain(|entry| entry.height() <= 1);
+        assert_eq!(survived, entries);
+        assert_eq!(Some(VoteStep::new(1, 1, Step::Precommit)), replay(&entries).vote_step);
+        assert!(db.get(db::COL_EXTRA, LEGACY_BACKUP_KEY).unwrap().is_none());
+    }
+}
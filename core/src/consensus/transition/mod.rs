@@ -0,0 +1,473 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Weak};
+
+use ckey::{Address, Password, Public, SchnorrSignature};
+use cnetwork::NetworkService;
+use cstate::ActionHandler;
+use ctypes::BlockNumber;
+use parking_lot::{Mutex, RwLock};
+use primitives::{Bytes, H256, U256};
+
+use super::{
+    CodeChainEngine, CommitCertificate, ConsensusEngine, ConsensusListener, ConsensusState, ConstructedVerifier,
+    EngineError, EngineType, EpochChange, Headers, PendingTransitionStore, Seal,
+};
+use crate::account_provider::AccountProvider;
+use crate::block::{ExecutedBlock, IsBlock, SealedBlock};
+use crate::client::{BlockInfo, ChainInfo, ChainNotify, Client, EngineClient};
+use crate::codechain_machine::CodeChainMachine;
+use crate::encoded;
+use crate::error::Error;
+use crate::header::Header;
+use crate::transaction::UnverifiedTransaction;
+use crate::views::HeaderView;
+use crate::BlockId;
+
+/// An engine which delegates each block to the engine scheduled for its number.
+/// It lets a chain switch its consensus engine at a fork height without starting over.
+pub struct TransitionEngine {
+    /// Engines and their activation blocks in increasing order. The first one is activated at the genesis block.
+    engines: Vec<(BlockNumber, Arc<CodeChainEngine>)>,
+    /// The action handlers of all the engines.
+    /// A transaction can use only the handlers of the engine which handles its block.
+    action_handlers: Vec<Arc<ActionHandler>>,
+    /// The action handlers which are initialized in each activation block.
+    /// They are the handlers of the activated engine that the engines before it don't have.
+    new_action_handlers: Vec<(BlockNumber, Vec<Arc<ActionHandler>>)>,
+    client: RwLock<Option<Weak<EngineClient>>>,
+    activation: Arc<Activation>,
+}
+
+impl TransitionEngine {
+    pub fn new(engines: Vec<(BlockNumber, Arc<CodeChainEngine>)>) -> Self {
+        assert_eq!(Some(0), engines.first().map(|(activation, _)| *activation), "No engine for the genesis block");
+        assert!(engines.windows(2).all(|pair| pair[0].0 < pair[1].0), "Activation blocks are not increasing");
+
+        let mut handler_ids = HashSet::new();
+        let mut action_handlers = Vec::new();
+        let mut new_action_handlers = Vec::new();
+        for (activation, engine) in &engines {
+            let handlers: Vec<Arc<ActionHandler>> = engine
+                .action_handlers()
+                .iter()
+                .filter(|handler| !handler_ids.contains(&handler.handler_id()))
+                .cloned()
+                .collect();
+            handler_ids.extend(handlers.iter().map(|handler| handler.handler_id()));
+            action_handlers.extend(handlers.iter().cloned());
+            if *activation != 0 && !handlers.is_empty() {
+                new_action_handlers.push((*activation, handlers));
+            }
+        }
+
+        Self {
+            engines: engines.clone(),
+            action_handlers,
+            new_action_handlers,
+            client: Default::default(),
+            activation: Arc::new(Activation::new(engines)),
+        }
+    }
+
+    /// Returns the engine which handles the block of the given number.
+    fn engine_at(&self, number: BlockNumber) -> &CodeChainEngine {
+        let (_, engine) = self
+            .engines
+            .iter()
+            .rev()
+            .find(|(activation, _)| *activation <= number)
+            .expect("The first engine is activated at the genesis block");
+        engine.as_ref()
+    }
+
+    /// Returns the engine which handles the children of the given block.
+    fn engine_after(&self, parent_hash: &H256) -> Option<&CodeChainEngine> {
+        let client = self.client.read().as_ref().and_then(Weak::upgrade)?;
+        let parent = client.block_header(&BlockId::Hash(*parent_hash))?;
+        Some(self.engine_at(parent.number() + 1))
+    }
+
+    /// Returns the engine which handles the block on top of the best block.
+    fn current(&self) -> &CodeChainEngine {
+        let best_block_number = self
+            .client
+            .read()
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .map_or(0, |client| client.chain_info().best_block_number);
+        self.engine_at(best_block_number + 1)
+    }
+}
+
+impl ConsensusEngine<CodeChainMachine> for TransitionEngine {
+    fn name(&self) -> &str {
+        self.current().name()
+    }
+
+    fn machine(&self) -> &CodeChainMachine {
+        // All the engines are built on the machines of the same params and action handlers.
+        self.engine_at(0).machine()
+    }
+
+    fn seal_fields(&self, header: &Header) -> usize {
+        self.engine_at(header.number()).seal_fields(header)
+    }
+
+    fn seals_internally(&self) -> Option<bool> {
+        self.current().seals_internally()
+    }
+
+    fn engine_type(&self) -> EngineType {
+        self.current().engine_type()
+    }
+
+    fn generate_seal(&self, block: &ExecutedBlock, parent: &Header) -> Seal {
+        self.engine_at(parent.number() + 1).generate_seal(block, parent)
+    }
+
+    fn proposal_generated(&self, sealed_block: &SealedBlock) {
+        self.engine_at(sealed_block.header().number()).proposal_generated(sealed_block)
+    }
+
+    fn verify_local_seal(&self, header: &Header) -> Result<(), Error> {
+        self.engine_at(header.number()).verify_local_seal(header)
+    }
+
+    fn verify_block_basic(&self, header: &Header) -> Result<(), Error> {
+        self.engine_at(header.number()).verify_block_basic(header)
+    }
+
+    fn verify_block_unordered(&self, header: &Header) -> Result<(), Error> {
+        self.engine_at(header.number()).verify_block_unordered(header)
+    }
+
+    fn verify_block_family(&self, header: &Header, parent: &Header) -> Result<(), Error> {
+        self.engine_at(header.number()).verify_block_family(header, parent)
+    }
+
+    fn verify_block_external(&self, header: &Header) -> Result<(), Error> {
+        self.engine_at(header.number()).verify_block_external(header)
+    }
+
+    fn genesis_epoch_data(&self, header: &Header) -> Result<Vec<u8>, String> {
+        self.engine_at(0).genesis_epoch_data(header)
+    }
+
+    fn signals_epoch_end(&self, header: &Header) -> EpochChange {
+        self.engine_at(header.number()).signals_epoch_end(header)
+    }
+
+    fn is_epoch_end(
+        &self,
+        chain_head: &Header,
+        chain: &Headers<Header>,
+        transition_store: &PendingTransitionStore,
+    ) -> Option<Vec<u8>> {
+        self.engine_at(chain_head.number()).is_epoch_end(chain_head, chain, transition_store)
+    }
+
+    fn epoch_verifier<'a>(&self, header: &Header, proof: &'a [u8]) -> ConstructedVerifier<'a, CodeChainMachine> {
+        self.engine_at(header.number()).epoch_verifier(header, proof)
+    }
+
+    fn populate_from_parent(&self, header: &mut Header, parent: &Header) {
+        self.engine_at(parent.number() + 1).populate_from_parent(header, parent)
+    }
+
+    fn on_timeout(&self, token: usize) {
+        self.current().on_timeout(token)
+    }
+
+    fn stop(&self) {
+        for (_, engine) in &self.engines {
+            engine.stop();
+        }
+    }
+
+    fn on_new_block(&self, block: &mut ExecutedBlock, epoch_begin: bool) -> Result<(), Error> {
+        let number = block.header().number();
+        if let Some((_, handlers)) = self.new_action_handlers.iter().find(|(activation, _)| *activation == number) {
+            for handler in handlers {
+                handler.init(block.state_mut())?;
+            }
+        }
+        self.engine_at(number).on_new_block(block, epoch_begin)
+    }
+
+    fn on_close_block(&self, block: &mut ExecutedBlock) -> Result<(), Error> {
+        let number = block.header().number();
+        self.engine_at(number).on_close_block(block)
+    }
+
+    fn register_client(&self, client: Weak<EngineClient>) {
+        for (_, engine) in &self.engines {
+            engine.register_client(Weak::clone(&client));
+        }
+        *self.client.write() = Some(client);
+    }
+
+    fn handle_message(&self, message: &[u8]) -> Result<(), EngineError> {
+        self.current().handle_message(message)
+    }
+
+    fn is_proposal(&self, verified_header: &Header) -> bool {
+        self.engine_at(verified_header.number()).is_proposal(verified_header)
+    }
+
+    fn on_verified_proposal(&self, verified_block_data: encoded::Block) {
+        let number = verified_block_data.number();
+        self.engine_at(number).on_verified_proposal(verified_block_data)
+    }
+
+    fn broadcast_proposal_block(&self, block: encoded::Block) {
+        let number = block.number();
+        self.engine_at(number).broadcast_proposal_block(block)
+    }
+
+    fn set_signer(&self, ap: Arc<AccountProvider>, address: Address, password: Option<Password>) {
+        for (_, engine) in &self.engines {
+            engine.set_signer(Arc::clone(&ap), address, password.clone());
+        }
+    }
+
+    fn sign(&self, hash: H256) -> Result<SchnorrSignature, Error> {
+        // Every engine has the same signer, but only the engines with validators can sign.
+        let (_, last) = self.engines.last().expect("The first engine is activated at the genesis block");
+        let engine =
+            self.engines.iter().rev().map(|(_, engine)| engine).find(|engine| engine.signer_public().is_some());
+        engine.unwrap_or(last).sign(hash)
+    }
+
+    fn signer_public(&self) -> Option<Public> {
+        self.current().signer_public()
+    }
+
+    fn signer_index(&self, bh: &H256) -> Option<usize> {
+        self.engine_after(bh)?.signer_index(bh)
+    }
+
+    fn validators(&self, parent: &Header) -> Option<Vec<Public>> {
+        self.engine_at(parent.number() + 1).validators(parent)
+    }
+
+    fn register_network_extension_to_service(&self, service: &Arc<NetworkService>) {
+        self.activation.register_network(service);
+    }
+
+    fn score_to_target(&self, score: &U256, block_number: u64) -> U256 {
+        self.engine_at(block_number).score_to_target(score, block_number)
+    }
+
+    fn pow_seal(&self, header: &Header, nonce: u64) -> Option<Vec<Bytes>> {
+        self.engine_at(header.number()).pow_seal(header, nonce)
+    }
+
+    fn block_reward(&self, block_number: u64) -> u64 {
+        self.engine_at(block_number).block_reward(block_number)
+    }
+
+    fn block_fee(&self, block_number: u64, transactions: Box<Iterator<Item = UnverifiedTransaction>>) -> u64 {
+        self.engine_at(block_number).block_fee(block_number, transactions)
+    }
+
+    fn burned_fee(&self, block_number: u64, block_fee: u64) -> u64 {
+        self.engine_at(block_number).burned_fee(block_number, block_fee)
+    }
+
+    fn recommended_confirmation(&self) -> u32 {
+        self.current().recommended_confirmation()
+    }
+
+    fn register_chain_notify(&self, client: &Arc<Client>) {
+        self.activation.register_client(client);
+        client.add_notify(Arc::downgrade(&self.activation) as Weak<ChainNotify>);
+    }
+
+    fn get_best_block_from_highest_score_header(&self, header: &HeaderView) -> H256 {
+        self.engine_at(header.number()).get_best_block_from_highest_score_header(header)
+    }
+
+    fn get_block_hash_to_mine_on(&self, best_block_hash: H256) -> H256 {
+        self.current().get_block_hash_to_mine_on(best_block_hash)
+    }
+
+    fn action_handlers(&self) -> &[Arc<ActionHandler>] {
+        &self.action_handlers
+    }
+
+    fn action_handlers_at(&self, block_number: BlockNumber) -> &[Arc<ActionHandler>] {
+        self.engine_at(block_number).action_handlers()
+    }
+
+    fn genesis_action_handlers(&self) -> &[Arc<ActionHandler>] {
+        self.engine_at(0).action_handlers()
+    }
+
+    fn commit_certificate(&self, block_number: BlockNumber) -> Option<CommitCertificate> {
+        self.engine_at(block_number).commit_certificate(block_number)
+    }
+
+    fn consensus_state(&self) -> Option<ConsensusState> {
+        self.current().consensus_state()
+    }
+
+    fn add_consensus_listener(&self, listener: ConsensusListener) {
+        // Every engine gets the listener because the current one changes at the activation blocks.
+        let listener = Arc::new(listener);
+        for (_, engine) in &self.engines {
+            let listener = Arc::clone(&listener);
+            engine.add_consensus_listener(Box::new(move |event| listener(event)));
+        }
+    }
+}
+
+/// Connects the engines to the client and the network when the chain reaches their activation blocks.
+/// An engine which is connected before its activation would act on the blocks of the previous engine.
+struct Activation {
+    engines: Vec<(BlockNumber, Arc<CodeChainEngine>)>,
+    /// The number of the connected engines.
+    activated: Mutex<usize>,
+    client: RwLock<Option<Weak<Client>>>,
+    network: RwLock<Option<Weak<NetworkService>>>,
+}
+
+impl Activation {
+    fn new(engines: Vec<(BlockNumber, Arc<CodeChainEngine>)>) -> Self {
+        Self {
+            engines,
+            activated: Mutex::new(0),
+            client: Default::default(),
+            network: Default::default(),
+        }
+    }
+
+    fn register_client(&self, client: &Arc<Client>) {
+        *self.client.write() = Some(Arc::downgrade(client));
+        let mut activated = self.activated.lock();
+        let next_block_number = client.chain_info().best_block_number + 1;
+        while *activated < self.engines.len() && self.engines[*activated].0 <= next_block_number {
+            self.engines[*activated].1.register_chain_notify(client);
+            *activated += 1;
+        }
+    }
+
+    fn register_network(&self, service: &Arc<NetworkService>) {
+        *self.network.write() = Some(Arc::downgrade(service));
+        let activated = self.activated.lock();
+        for (_, engine) in &self.engines[..*activated] {
+            engine.register_network_extension_to_service(service);
+        }
+    }
+
+    /// Connects the engines which handle the block on top of the best block.
+    fn activate(&self, client: &Arc<Client>) {
+        let network = self.network.read().as_ref().and_then(Weak::upgrade);
+        let mut activated = self.activated.lock();
+        let next_block_number = client.chain_info().best_block_number + 1;
+        while *activated < self.engines.len() && self.engines[*activated].0 <= next_block_number {
+            let (activation, engine) = &self.engines[*activated];
+            cinfo!(ENGINE, "The engine {} is activated at the block {}", engine.name(), activation);
+            // The engine starts from the best block.
+            engine.register_client(Arc::downgrade(client) as Weak<EngineClient>);
+            engine.register_chain_notify(client);
+            if let Some(network) = &network {
+                engine.register_network_extension_to_service(network);
+            }
+            *activated += 1;
+        }
+    }
+}
+
+impl ChainNotify for Activation {
+    fn new_blocks(
+        &self,
+        imported: Vec<H256>,
+        _invalid: Vec<H256>,
+        _enacted: Vec<H256>,
+        _retracted: Vec<H256>,
+        _sealed: Vec<H256>,
+        _duration: u64,
+    ) {
+        if imported.is_empty() {
+            return
+        }
+        if let Some(client) = self.client.read().as_ref().and_then(Weak::upgrade) {
+            self.activate(&client);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::OpenBlock;
+    use crate::consensus::stake;
+    use crate::scheme::Scheme;
+    use crate::tests::helpers::get_temp_state_db;
+
+    const ACTIVATION: BlockNumber = 1;
+
+    /// A chain which starts with Solo and switches to Tendermint at the activation block.
+    fn solo_to_tendermint() -> Scheme {
+        let json = include_str!("../../../res/solo.json");
+        let end = json.rfind('}').unwrap();
+        let json = format!(
+            r#"{}, "engineTransitions": [{{ "activation": "{:#x}", "engine": {{ "tendermint": {{ "params": {{
+                "validators": [
+                    "0xd8c897324f1663639479d96a038263c47d76a5847a1e991695d29568856b41afe789df7665146f94a6a081938d10ecbb368e4b01f6fab0fa353d6cb62363327f"
+                ],
+                "genesisStakes": {{ "tccq8qlwpt7xcs9lec3c8tyt3kqxlgsus8q4qp3m6ft": 100 }}
+            }} }} }} }}]}}"#,
+            &json[..end],
+            ACTIVATION
+        );
+        Scheme::load(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn engine_handlers_are_initialized_in_the_activation_block() {
+        let scheme = solo_to_tendermint();
+        let engine = &*scheme.engine;
+        let genesis_handlers: Vec<_> = engine.genesis_action_handlers().iter().map(|handler| handler.name()).collect();
+        let handlers: Vec<_> = engine.action_handlers().iter().map(|handler| handler.name()).collect();
+        assert_eq!(vec!["hit"], genesis_handlers);
+        assert_eq!(vec!["hit", "stake"], handlers);
+        let active_handlers: Vec<_> =
+            engine.action_handlers_at(ACTIVATION).iter().map(|handler| handler.name()).collect();
+        assert!(!active_handlers.contains(&"hit"));
+        assert!(active_handlers.contains(&"stake"));
+
+        let db = scheme.ensure_genesis_state(get_temp_state_db()).unwrap();
+        let genesis_header = scheme.genesis_header();
+        let b = OpenBlock::try_new(engine, db, &genesis_header, Default::default(), vec![], false).unwrap();
+        assert_eq!(ACTIVATION, b.block().header().number());
+        let stakes = stake::get_stakes(b.block().state()).unwrap();
+        assert_eq!(vec![100], stakes.values().cloned().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn each_block_is_handled_by_its_engine() {
+        let scheme = solo_to_tendermint();
+        let engine = &*scheme.engine;
+        let mut header = Header::default();
+        assert_eq!(0, engine.seal_fields(&header));
+        header.set_number(ACTIVATION);
+        assert_eq!(4, engine.seal_fields(&header));
+    }
+}
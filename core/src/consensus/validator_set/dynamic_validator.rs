@@ -0,0 +1,301 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Weak};
+
+use ckey::{Address, Public};
+use cstate::{ActionHandlerResult, TopLevelState};
+use ctypes::BlockNumber;
+use linked_hash_map::LinkedHashMap;
+use parking_lot::{Mutex, RwLock};
+use primitives::H256;
+use rlp::{Encodable, UntrustedRlp};
+
+use super::super::{EngineError, EpochChange};
+use super::validator_list::ValidatorList;
+use super::ValidatorSet;
+use crate::client::EngineClient;
+use crate::codechain_machine::CodeChainMachine;
+use crate::error::Error;
+use crate::header::Header;
+use crate::BlockId;
+
+/// Reads the validators and their voting power from the state.
+pub type LoadValidators = fn(&TopLevelState) -> ActionHandlerResult<Vec<(Public, u64)>>;
+
+/// Extracts the encoded validators from the proof of an epoch transition.
+pub type SetProof = for<'a> fn(&'a [u8]) -> Result<&'a [u8], Error>;
+
+/// The number of the blocks whose children's validators are cached.
+const CACHE_SIZE: usize = 128;
+
+/// Validator set which is stored in the state and changes at the end of each term.
+/// The initial validators are used until the first change.
+pub struct DynamicValidator {
+    initial_list: ValidatorList,
+    term_length: u64,
+    load: LoadValidators,
+    set_proof: SetProof,
+    client: RwLock<Option<Weak<EngineClient>>>,
+    /// The validators of the recently looked up blocks.
+    cache: Mutex<LinkedHashMap<H256, Arc<ValidatorList>>>,
+}
+
+impl DynamicValidator {
+    pub fn new(initial_validators: Vec<Public>, term_length: u64, load: LoadValidators, set_proof: SetProof) -> Self {
+        DynamicValidator {
+            initial_list: ValidatorList::new(initial_validators),
+            term_length,
+            load,
+            set_proof,
+            client: Default::default(),
+            cache: Default::default(),
+        }
+    }
+
+    /// Returns the validators of the blocks whose parent is the given block.
+    fn validators(&self, parent: &H256) -> Result<Arc<ValidatorList>, Error> {
+        if let Some(validators) = self.cache.lock().get_refresh(parent) {
+            return Ok(Arc::clone(validators))
+        }
+
+        let validators = Arc::new(self.load_validators(parent)?);
+        let mut cache = self.cache.lock();
+        cache.insert(*parent, Arc::clone(&validators));
+        while cache.len() > CACHE_SIZE {
+            cache.pop_front();
+        }
+        Ok(validators)
+    }
+
+    /// Returns the validators of the given block, or no validators if they are unknown.
+    /// Nobody can seal or verify a block without the validators.
+    fn validators_or_none(&self, parent: &H256) -> Arc<ValidatorList> {
+        self.validators(parent).unwrap_or_else(|err| {
+            cerror!(ENGINE, "Cannot read the validators of the children of {}: {}", parent, err);
+            Default::default()
+        })
+    }
+
+    fn load_validators(&self, hash: &H256) -> Result<ValidatorList, Error> {
+        let client = match self.client.read().as_ref().and_then(Weak::upgrade) {
+            Some(client) => client,
+            None => return Err(EngineError::UnknownValidators(*hash).into()),
+        };
+        if let Some(state) = client.state_at(BlockId::Hash(*hash)) {
+            let validators = (self.load)(&state)?;
+            if validators.is_empty() {
+                // Nobody has been elected yet.
+                return Ok(self.initial_list.clone())
+            }
+            return Ok(ValidatorList::new_with_weights(validators))
+        }
+
+        // The state is pruned. The proof of the last transition has the elected validators.
+        if client.block_header(&BlockId::Hash(*hash)).is_none() {
+            return Err(EngineError::UnknownValidators(*hash).into())
+        }
+        match client.epoch_transition_for(*hash) {
+            Some(transition) => Ok(UntrustedRlp::new((self.set_proof)(&transition.proof)?).as_val()?),
+            None => Ok(self.initial_list.clone()),
+        }
+    }
+}
+
+impl ValidatorSet for DynamicValidator {
+    fn contains(&self, parent: &H256, public: &Public) -> bool {
+        self.validators_or_none(parent).contains(parent, public)
+    }
+
+    fn contains_address(&self, parent: &H256, address: &Address) -> bool {
+        self.validators_or_none(parent).contains_address(parent, address)
+    }
+
+    fn get(&self, parent: &H256, nonce: usize) -> Public {
+        let validators = self.validators_or_none(parent);
+        if validators.is_empty() {
+            return Default::default()
+        }
+        validators.get(parent, nonce)
+    }
+
+    fn get_address(&self, parent: &H256, nonce: usize) -> Address {
+        let validators = self.validators_or_none(parent);
+        if validators.is_empty() {
+            return Default::default()
+        }
+        validators.get_address(parent, nonce)
+    }
+
+    fn get_index(&self, parent: &H256, public: &Public) -> Option<usize> {
+        self.validators_or_none(parent).get_index(parent, public)
+    }
+
+    fn count(&self, parent: &H256) -> usize {
+        self.validators_or_none(parent).count(parent)
+    }
+
+    fn weight(&self, parent: &H256, index: usize) -> u64 {
+        self.validators_or_none(parent).weight(parent, index)
+    }
+
+    fn total_weight(&self, parent: &H256) -> u64 {
+        self.validators_or_none(parent).total_weight(parent)
+    }
+
+    fn is_epoch_end(&self, first: bool, chain_head: &Header) -> Option<Vec<u8>> {
+        if first {
+            return Some(self.initial_list.rlp_bytes().into_vec())
+        }
+        let hash = chain_head.hash();
+        let result = self.validators(chain_head.parent_hash()).and_then(|previous| {
+            let next = self.validators(&hash)?;
+            Ok((previous, next))
+        });
+        let (previous, next) = match result {
+            Ok(validators) => validators,
+            Err(err) => {
+                cerror!(ENGINE, "Cannot check the end of the epoch at {}: {}", hash, err);
+                return None
+            }
+        };
+        // The validators change at the end of each term, but the jailed ones are removed at any block.
+        let changed = chain_head.number() % self.term_length == 0 || *previous != *next;
+        if !changed {
+            return None
+        }
+        // The proof of the transition is the new validators.
+        Some(next.rlp_bytes().into_vec())
+    }
+
+    fn signals_epoch_end(&self, _first: bool, _header: &Header) -> EpochChange {
+        EpochChange::No
+    }
+
+    fn epoch_set(
+        &self,
+        _first: bool,
+        _machine: &CodeChainMachine,
+        _number: BlockNumber,
+        proof: &[u8],
+    ) -> Result<(ValidatorList, Option<H256>), Error> {
+        let validators = UntrustedRlp::new(proof).as_val()?;
+        Ok((validators, None))
+    }
+
+    fn register_client(&self, client: Weak<EngineClient>) {
+        *self.client.write() = Some(client);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{Generator, Random};
+    use cstate::tests::helpers;
+    use cstate::ActionHandler;
+
+    use super::super::new_voted_validator_set;
+    use super::*;
+    use crate::client::TestBlockChainClient;
+    use crate::consensus::simple_poa::voting::Voting;
+
+    const TERM_LENGTH: u64 = 3;
+
+    fn random_publics(count: usize) -> Vec<Public> {
+        (0..count).map(|_| *Random.generate().unwrap().public()).collect()
+    }
+
+    fn state_with(validators: &[Public]) -> TopLevelState {
+        let mut state = helpers::get_temp_state();
+        Voting::new(validators.to_vec(), TERM_LENGTH).init(&mut state).unwrap();
+        state
+    }
+
+    fn header_of(client: &TestBlockChainClient, number: BlockNumber) -> Header {
+        client.block_header(&BlockId::Number(number)).unwrap().decode()
+    }
+
+    fn init(initial: &[Public], blocks: usize) -> (Arc<TestBlockChainClient>, Box<ValidatorSet>) {
+        let client = Arc::new(TestBlockChainClient::new());
+        client.add_blocks(blocks, 0);
+        let validators = new_voted_validator_set(initial.to_vec(), TERM_LENGTH);
+        validators.register_client(Arc::downgrade(&client) as _);
+        (client, validators)
+    }
+
+    #[test]
+    fn validators_change_at_the_end_of_the_term() {
+        let initial = random_publics(3);
+        let elected = random_publics(4);
+        let (client, validators) = init(&initial, TERM_LENGTH as usize + 1);
+        for number in 0..TERM_LENGTH {
+            client.set_state(header_of(&client, number).hash(), state_with(&initial));
+        }
+        let term_end = header_of(&client, TERM_LENGTH);
+        client.set_state(term_end.hash(), state_with(&elected));
+
+        assert_eq!(None, validators.is_epoch_end(false, &header_of(&client, TERM_LENGTH - 1)));
+        let proof = validators.is_epoch_end(false, &term_end).unwrap();
+        assert_eq!(ValidatorList::new(elected.clone()), UntrustedRlp::new(&proof).as_val::<ValidatorList>().unwrap());
+
+        let last_of_the_term = header_of(&client, TERM_LENGTH - 1).hash();
+        assert_eq!(initial.len(), validators.count(&last_of_the_term));
+        assert!(validators.contains(&last_of_the_term, &initial[0]));
+        assert!(!validators.contains(&last_of_the_term, &elected[0]));
+
+        assert_eq!(elected.len(), validators.count(&term_end.hash()));
+        assert!(validators.contains(&term_end.hash(), &elected[0]));
+        assert!(!validators.contains(&term_end.hash(), &initial[0]));
+    }
+
+    #[test]
+    fn initial_validators_are_used_before_the_first_transition() {
+        let initial = random_publics(3);
+        let (client, validators) = init(&initial, TERM_LENGTH as usize - 1);
+
+        let parent = header_of(&client, TERM_LENGTH - 1).hash();
+        assert_eq!(initial.len(), validators.count(&parent));
+        assert!(validators.contains(&parent, &initial[0]));
+    }
+
+    #[test]
+    fn elected_validators_are_read_from_the_transition_without_the_state() {
+        let initial = random_publics(3);
+        let elected = random_publics(4);
+        let (client, validators) = init(&initial, TERM_LENGTH as usize + 1);
+        let term_end = header_of(&client, TERM_LENGTH).hash();
+        client.insert_epoch_transition(term_end, ValidatorList::new(elected.clone()).rlp_bytes().to_vec());
+
+        let parent = header_of(&client, TERM_LENGTH + 1).hash();
+        assert_eq!(elected.len(), validators.count(&parent));
+        assert!(validators.contains(&parent, &elected[0]));
+        assert!(!validators.contains(&parent, &initial[0]));
+
+        let before_the_transition = header_of(&client, TERM_LENGTH - 1).hash();
+        assert!(validators.contains(&before_the_transition, &initial[0]));
+    }
+
+    #[test]
+    fn validators_of_an_unknown_block_are_empty() {
+        let initial = random_publics(3);
+        let (_client, validators) = init(&initial, 1);
+
+        let unknown = H256::random();
+        assert_eq!(0, validators.count(&unknown));
+        assert!(!validators.contains(&unknown, &initial[0]));
+        assert_eq!(Public::default(), validators.get(&unknown, 0));
+    }
+}
@@ -20,13 +20,17 @@ use ckey::{Address, Public};
 use ctypes::BlockNumber;
 use primitives::{Bytes, H256};
 
+use self::dynamic_validator::{DynamicValidator, SetProof};
 use self::validator_list::ValidatorList;
+use super::simple_poa::voting;
+use super::stake;
 use super::EpochChange;
 use crate::client::EngineClient;
 use crate::codechain_machine::CodeChainMachine;
 use crate::error::Error;
 use crate::header::Header;
 
+mod dynamic_validator;
 pub mod validator_list;
 
 /// Creates a validator set from validator public keys.
@@ -34,6 +38,32 @@ pub fn new_validator_set(validators: Vec<Public>) -> Box<ValidatorSet> {
     Box::new(ValidatorList::new(validators))
 }
 
+/// Creates a validator set which is elected at the end of each term.
+/// The given validators are used until the first election.
+/// The engine stores the elected validators in the transition proofs, and `set_proof` extracts them.
+pub fn new_dynamic_validator_set(
+    initial_validators: Vec<Public>,
+    term_length: u64,
+    set_proof: SetProof,
+) -> Box<ValidatorSet> {
+    Box::new(DynamicValidator::new(initial_validators, term_length, stake::get_validators, set_proof))
+}
+
+/// Creates a validator set which the validators change by voting at the end of each epoch.
+pub fn new_voted_validator_set(initial_validators: Vec<Public>, epoch_length: u64) -> Box<ValidatorSet> {
+    Box::new(DynamicValidator::new(
+        initial_validators,
+        epoch_length,
+        |state| Ok(voting::get_validators(state)?.into_iter().map(|public| (public, 1)).collect()),
+        raw_set_proof,
+    ))
+}
+
+/// SimplePoA stores the validators as the proof itself.
+fn raw_set_proof(proof: &[u8]) -> Result<&[u8], Error> {
+    Ok(proof)
+}
+
 /// A validator set.
 pub trait ValidatorSet: Send + Sync {
     /// Checks if a given public key is a validator,
@@ -55,6 +85,16 @@ pub trait ValidatorSet: Send + Sync {
     /// Returns the current number of validators.
     fn count(&self, parent: &H256) -> usize;
 
+    /// Returns the voting power of the validator at the index.
+    fn weight(&self, _parent: &H256, _index: usize) -> u64 {
+        1
+    }
+
+    /// Returns the sum of the voting power of the current validators.
+    fn total_weight(&self, parent: &H256) -> u64 {
+        self.count(parent) as u64
+    }
+
     /// Signalling that a new epoch has begun.
     ///
     /// The caller provided here may not generate proofs.
@@ -20,6 +20,7 @@ use std::collections::HashSet;
 use ckey::{public_to_address, Address, Public};
 use ctypes::BlockNumber;
 use primitives::H256;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
 use super::super::EpochChange;
 use super::ValidatorSet;
@@ -31,17 +32,40 @@ use crate::header::Header;
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct ValidatorList {
     validators: Vec<Public>,
+    /// The voting power of each validator.
+    weights: Vec<u64>,
     addresses: HashSet<Address>,
 }
 
 impl ValidatorList {
+    /// Creates a validator list in which every validator has the same voting power.
     pub fn new(validators: Vec<Public>) -> Self {
+        let weights = vec![1; validators.len()];
         let addresses = validators.iter().map(|public| public_to_address(public)).collect();
         ValidatorList {
             validators,
+            weights,
             addresses,
         }
     }
+
+    pub fn new_with_weights(validators: Vec<(Public, u64)>) -> Self {
+        let (validators, weights): (Vec<_>, Vec<_>) = validators.into_iter().unzip();
+        let addresses = validators.iter().map(|public| public_to_address(public)).collect();
+        ValidatorList {
+            validators,
+            weights,
+            addresses,
+        }
+    }
+
+    /// Returns the voting power of the validator, or 0 if it is not a validator.
+    pub fn weight_of_address(&self, address: &Address) -> u64 {
+        self.validators
+            .iter()
+            .position(|public| public_to_address(public) == *address)
+            .map_or(0, |index| self.weights[index])
+    }
 }
 
 impl ::std::ops::Deref for ValidatorList {
@@ -54,17 +78,37 @@ impl ::std::ops::Deref for ValidatorList {
 
 impl From<Vec<Public>> for ValidatorList {
     fn from(validators: Vec<Public>) -> Self {
-        let addresses = validators.iter().map(|public| public_to_address(public)).collect();
-        ValidatorList {
-            validators,
-            addresses,
-        }
+        Self::new(validators)
     }
 }
 
 impl HeapSizeOf for ValidatorList {
     fn heap_size_of_children(&self) -> usize {
-        self.validators.heap_size_of_children()
+        self.validators.heap_size_of_children() + self.weights.heap_size_of_children()
+    }
+}
+
+impl Encodable for ValidatorList {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(self.validators.len());
+        for (public, weight) in self.validators.iter().zip(self.weights.iter()) {
+            s.begin_list(2).append(public).append(weight);
+        }
+    }
+}
+
+impl Decodable for ValidatorList {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let validators = rlp
+            .iter()
+            .map(|validator| {
+                if validator.item_count()? != 2 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok((validator.val_at(0)?, validator.val_at(1)?))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new_with_weights(validators))
     }
 }
 
@@ -99,6 +143,14 @@ impl ValidatorSet for ValidatorList {
         self.validators.len()
     }
 
+    fn weight(&self, _bh: &H256, index: usize) -> u64 {
+        self.weights.get(index).cloned().unwrap_or(0)
+    }
+
+    fn total_weight(&self, _bh: &H256) -> u64 {
+        self.weights.iter().sum()
+    }
+
     fn is_epoch_end(&self, first: bool, _chain_head: &Header) -> Option<Vec<u8>> {
         if first {
             Some(Vec::new()) // allow transition to fixed list, and instantly
@@ -140,5 +192,19 @@ mod tests {
         assert_eq!(set.get(&Default::default(), 0), a1);
         assert_eq!(set.get(&Default::default(), 1), a2);
         assert_eq!(set.get(&Default::default(), 2), a1);
+        assert_eq!(set.total_weight(&Default::default()), 2);
+    }
+
+    #[test]
+    fn weighted_validator_list_rlp() {
+        let a1 = Public::from_str("34959b60d54703e9dfe36afb1e9950a4abe34d666cbb64c92969013bc9cc74063f9e4680d9d48c4597ee623bd4b507a1b2f43a9c5766a06463f85b73a94c51d1").unwrap();
+        let a2 = Public::from_str("8c5a25bfafceea03073e2775cfb233a46648a088c12a1ca18a5865534887ccf60e1670be65b5f8e29643f463fdf84b1cbadd6027e71d8d04496570cb6b04885d").unwrap();
+        let set = ValidatorList::new_with_weights(vec![(a1, 30), (a2, 10)]);
+        assert_eq!(set.weight(&Default::default(), 0), 30);
+        assert_eq!(set.weight(&Default::default(), 1), 10);
+        assert_eq!(set.total_weight(&Default::default()), 40);
+
+        let decoded: ValidatorList = ::rlp::decode(&::rlp::encode(&set));
+        assert_eq!(set, decoded);
     }
 }
@@ -58,6 +58,12 @@ pub struct DoubleVote<M: Message> {
     vote_two: M,
 }
 
+impl<M: Message> DoubleVote<M> {
+    pub fn votes(&self) -> (&M, &M) {
+        (&self.vote_one, &self.vote_two)
+    }
+}
+
 impl<M: Message> Encodable for DoubleVote<M> {
     fn rlp_append(&self, s: &mut RlpStream) {
         s.begin_list(2).append(&self.vote_one).append(&self.vote_two);
@@ -86,15 +92,20 @@ impl<M: Message> StepCollector<M> {
         None
     }
 
-    /// Count all votes for the given block hash at this round.
-    fn count_block(&self, block_hash: &Option<H256>) -> usize {
-        self.block_votes.get(block_hash).map_or(0, BTreeMap::len)
+    /// Sums the voting power of the votes for the given block hash at this round.
+    fn power_block<F: Fn(usize) -> u64>(&self, block_hash: &Option<H256>, weight: F) -> u64 {
+        self.block_votes.get(block_hash).map_or(0, |votes| votes.keys().map(|index| weight(*index)).sum())
     }
 
     /// Count all votes collected for the given round.
     fn count(&self) -> usize {
         self.block_votes.values().map(BTreeMap::len).sum()
     }
+
+    /// Sums the voting power of all votes collected for the given round.
+    fn power<F: Fn(usize) -> u64>(&self, weight: F) -> u64 {
+        self.block_votes.values().flat_map(BTreeMap::keys).map(|index| weight(*index)).sum()
+    }
 }
 
 impl<M: Message + Default> Default for VoteCollector<M> {
@@ -169,13 +180,19 @@ impl<M: Message + Default + Encodable + Debug> VoteCollector<M> {
             .and_then(|votes| votes.iter().next().map(|(k, v)| (*v, *k)))
     }
 
-    /// Count votes which agree with the given message.
-    pub fn count_aligned_votes(&self, message: &M) -> usize {
-        self.votes.read().get(&message.round()).map_or(0, |m| m.count_block(&message.block_hash()))
+    /// Sums the voting power of the votes which agree with the given message.
+    /// `weight` returns the voting power of the signer of the given index.
+    pub fn aligned_votes_power<F: Fn(usize) -> u64>(&self, message: &M, weight: F) -> u64 {
+        self.votes.read().get(&message.round()).map_or(0, |m| m.power_block(&message.block_hash(), weight))
     }
 
-    pub fn count_block_round_votes(&self, round: &M::Round, block_hash: &Option<H256>) -> usize {
-        self.votes.read().get(round).map_or(0, |m| m.count_block(block_hash))
+    pub fn block_round_votes_power<F: Fn(usize) -> u64>(
+        &self,
+        round: &M::Round,
+        block_hash: &Option<H256>,
+        weight: F,
+    ) -> u64 {
+        self.votes.read().get(round).map_or(0, |m| m.power_block(block_hash, weight))
     }
 
     /// Count all votes collected for a given round.
@@ -183,6 +200,11 @@ impl<M: Message + Default + Encodable + Debug> VoteCollector<M> {
         self.votes.read().get(vote_round).map_or(0, StepCollector::count)
     }
 
+    /// Sums the voting power of all votes collected for a given round.
+    pub fn round_votes_power<F: Fn(usize) -> u64>(&self, vote_round: &M::Round, weight: F) -> u64 {
+        self.votes.read().get(vote_round).map_or(0, |m| m.power(weight))
+    }
+
     pub fn get_block_hashes(&self, round: &M::Round) -> Vec<H256> {
         let guard = self.votes.read();
         guard.get(round).map(|c| c.block_votes.keys().cloned().filter_map(|x| x).collect()).unwrap_or_else(Vec::new)
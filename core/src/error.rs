@@ -18,7 +18,7 @@ use std::fmt;
 
 use cio::IoError;
 use ckey::{Address, Error as KeyError};
-use cstate::StateError;
+use cstate::{ActionHandlerError, StateError};
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::BlockNumber;
 use primitives::{H256, U256};
@@ -113,6 +113,8 @@ pub enum BlockError {
     TooManyTransactions(Address),
     /// Parent given is unknown.
     UnknownParent(H256),
+    /// An ancestor which the score calculation needs is unknown.
+    UnknownAncestor(H256),
     /// Body size limit is exceeded.
     BodySizeIsTooBig,
 }
@@ -121,6 +123,10 @@ pub enum BlockError {
 pub enum SchemeError {
     InvalidCommonParams,
     InvalidState,
+    DuplicatedActionHandler(u64),
+    InvalidEngineTransition(u64),
+    InvalidEngineParams(&'static str),
+    InvalidActionHandlerParams(&'static str),
 }
 
 impl fmt::Display for SchemeError {
@@ -129,6 +135,12 @@ impl fmt::Display for SchemeError {
         let msg: String = match self {
             InvalidCommonParams => "Common params are not matched with gensis block".into(),
             InvalidState => "Genesis state is not same with spec".into(),
+            DuplicatedActionHandler(handler_id) => format!("Action handler {} is enabled more than once", handler_id),
+            InvalidEngineTransition(activation) => {
+                format!("The engine transition at block {} is not after the previous one", activation)
+            }
+            InvalidEngineParams(reason) => format!("Invalid engine params: {}", reason),
+            InvalidActionHandlerParams(reason) => format!("Invalid action handler params: {}", reason),
         };
         f.write_fmt(format_args!("Scheme file error ({})", msg))
     }
@@ -155,6 +167,7 @@ impl fmt::Display for BlockError {
             InvalidNumber(mis) => format!("Invalid number in header: {}", mis),
             RidiculousNumber(oob) => format!("Implausible block number. {}", oob),
             UnknownParent(hash) => format!("Unknown parent: {}", hash),
+            UnknownAncestor(hash) => format!("Unknown ancestor: {}", hash),
             TooManyTransactions(address) => format!("Too many transactions from: {}", address),
             BodySizeIsTooBig => "Block's body size is too big".to_string(),
         };
@@ -284,3 +297,12 @@ impl From<StateError> for Error {
         Error::State(err)
     }
 }
+
+impl From<ActionHandlerError> for Error {
+    fn from(err: ActionHandlerError) -> Self {
+        match err {
+            ActionHandlerError::DecoderError(err) => err.into(),
+            ActionHandlerError::StateError(err) => Error::State(err),
+        }
+    }
+}
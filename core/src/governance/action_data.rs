@@ -0,0 +1,292 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ckey::Address;
+use cstate::{ActionDataKeyBuilder, ActionHandlerResult, TopLevelState, TopState, TopStateView};
+use ctypes::BlockNumber;
+use primitives::H256;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use crate::scheme::CommonParams;
+
+fn get_proposals_key(handler_id: u64) -> H256 {
+    ActionDataKeyBuilder::new(handler_id, 1).append(&"Proposals").into_key()
+}
+
+fn get_scheduled_params_key(handler_id: u64) -> H256 {
+    ActionDataKeyBuilder::new(handler_id, 1).append(&"ScheduledParams").into_key()
+}
+
+fn get_active_params_key(handler_id: u64) -> H256 {
+    ActionDataKeyBuilder::new(handler_id, 1).append(&"ActiveParams").into_key()
+}
+
+fn get_closed_block_number_key(handler_id: u64) -> H256 {
+    ActionDataKeyBuilder::new(handler_id, 1).append(&"ClosedBlockNumber").into_key()
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Proposal {
+    pub params: CommonParams,
+    pub activation: BlockNumber,
+    pub approvals: BTreeSet<Address>,
+    /// The majority approved the proposal. It's scheduled when the block is closed.
+    pub approved: bool,
+}
+
+impl Encodable for Proposal {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4).append(&self.params).append(&self.activation);
+        s.begin_list(self.approvals.len());
+        for address in self.approvals.iter() {
+            s.append(address);
+        }
+        s.append(&self.approved);
+    }
+}
+
+impl Decodable for Proposal {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 4 {
+            return Err(DecoderError::RlpInvalidLength)
+        }
+        let approvals: Vec<Address> = rlp.list_at(2)?;
+        Ok(Proposal {
+            params: rlp.val_at(0)?,
+            activation: rlp.val_at(1)?,
+            approvals: approvals.into_iter().collect(),
+            approved: rlp.val_at(3)?,
+        })
+    }
+}
+
+pub struct Proposals {
+    handler_id: u64,
+    next_id: u64,
+    proposals: BTreeMap<u64, Proposal>,
+}
+
+impl Proposals {
+    pub fn load_from_state(state: &TopLevelState, handler_id: u64) -> ActionHandlerResult<Proposals> {
+        let action_data = state.action_data(&get_proposals_key(handler_id))?;
+
+        let mut next_id = 0;
+        let mut proposals = BTreeMap::new();
+        if let Some(rlp) = action_data.as_ref().map(|x| UntrustedRlp::new(x)) {
+            next_id = rlp.val_at(0)?;
+            for pair in rlp.at(1)?.iter() {
+                proposals.insert(pair.val_at(0)?, pair.val_at(1)?);
+            }
+        }
+
+        Ok(Proposals {
+            handler_id,
+            next_id,
+            proposals,
+        })
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> ActionHandlerResult<()> {
+        let mut rlp = RlpStream::new_list(2);
+        rlp.append(&self.next_id);
+        rlp.begin_list(self.proposals.len());
+        for (id, proposal) in self.proposals.iter() {
+            rlp.begin_list(2).append(id).append(proposal);
+        }
+        state.update_action_data(&get_proposals_key(self.handler_id), rlp.drain().into_vec())?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proposals.is_empty()
+    }
+
+    /// Returns the id of the added proposal.
+    pub fn add(&mut self, proposal: Proposal) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.proposals.insert(id, proposal);
+        id
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut Proposal> {
+        self.proposals.get_mut(&id)
+    }
+
+    /// Removes the approved proposals and the ones which cannot activate after the given block anymore.
+    /// Returns the approved ones.
+    pub fn remove_finished(&mut self, block_number: BlockNumber) -> Vec<Proposal> {
+        let finished: Vec<u64> = self
+            .proposals
+            .iter()
+            .filter(|(_, proposal)| proposal.approved || proposal.activation <= block_number + 1)
+            .map(|(id, _)| *id)
+            .collect();
+        finished.into_iter().filter_map(|id| self.proposals.remove(&id)).filter(|proposal| proposal.approved).collect()
+    }
+}
+
+pub struct ScheduledParams {
+    handler_id: u64,
+    params: BTreeMap<BlockNumber, CommonParams>,
+}
+
+impl ScheduledParams {
+    pub fn load_from_state(state: &TopLevelState, handler_id: u64) -> ActionHandlerResult<ScheduledParams> {
+        let action_data = state.action_data(&get_scheduled_params_key(handler_id))?;
+
+        let mut params = BTreeMap::new();
+        if let Some(rlp) = action_data.as_ref().map(|x| UntrustedRlp::new(x)) {
+            for pair in rlp.iter() {
+                params.insert(pair.val_at(0)?, pair.val_at(1)?);
+            }
+        }
+
+        Ok(ScheduledParams {
+            handler_id,
+            params,
+        })
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> ActionHandlerResult<()> {
+        let mut rlp = RlpStream::new_list(self.params.len());
+        for (activation, params) in self.params.iter() {
+            rlp.begin_list(2).append(activation).append(params);
+        }
+        state.update_action_data(&get_scheduled_params_key(self.handler_id), rlp.drain().into_vec())?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    /// The later approval wins if two changes are scheduled at the same block.
+    pub fn insert(&mut self, activation: BlockNumber, params: CommonParams) {
+        self.params.insert(activation, params);
+    }
+
+    /// Removes the changes which activate until the given block and returns the last one.
+    pub fn activate(&mut self, block_number: BlockNumber) -> Option<CommonParams> {
+        let later = self.params.split_off(&(block_number + 1));
+        let activated = ::std::mem::replace(&mut self.params, later);
+        activated.into_iter().last().map(|(_, params)| params)
+    }
+}
+
+pub fn load_active_params(state: &TopLevelState, handler_id: u64) -> ActionHandlerResult<Option<CommonParams>> {
+    let action_data = state.action_data(&get_active_params_key(handler_id))?;
+    match action_data {
+        Some(data) => Ok(Some(UntrustedRlp::new(&data).as_val()?)),
+        None => Ok(None),
+    }
+}
+
+pub fn save_active_params(
+    state: &mut TopLevelState,
+    handler_id: u64,
+    params: &CommonParams,
+) -> ActionHandlerResult<()> {
+    state.update_action_data(&get_active_params_key(handler_id), rlp::encode(params).into_vec())?;
+    Ok(())
+}
+
+/// Returns the number of the last closed block. It's 0 before the first block is closed.
+pub fn load_closed_block_number(state: &TopLevelState, handler_id: u64) -> ActionHandlerResult<BlockNumber> {
+    let action_data = state.action_data(&get_closed_block_number_key(handler_id))?;
+    match action_data {
+        Some(data) => Ok(UntrustedRlp::new(&data).as_val()?),
+        None => Ok(0),
+    }
+}
+
+pub fn save_closed_block_number(
+    state: &mut TopLevelState,
+    handler_id: u64,
+    block_number: BlockNumber,
+) -> ActionHandlerResult<()> {
+    state.update_action_data(&get_closed_block_number_key(handler_id), rlp::encode(&block_number).into_vec())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cstate::tests::helpers;
+
+    use super::*;
+
+    fn proposal(activation: BlockNumber, approved: bool) -> Proposal {
+        Proposal {
+            params: CommonParams::default(),
+            activation,
+            approvals: BTreeSet::new(),
+            approved,
+        }
+    }
+
+    #[test]
+    fn encode_and_decode_proposal() {
+        let mut proposal = proposal(10, true);
+        proposal.approvals.insert(Address::random());
+        proposal.approvals.insert(Address::random());
+        assert_eq!(proposal, rlp::decode(&rlp::encode(&proposal)));
+    }
+
+    #[test]
+    fn remove_finished_returns_only_approved_proposals() {
+        let mut state = helpers::get_temp_state();
+        let mut proposals = Proposals::load_from_state(&state, 3).unwrap();
+        let approved = proposals.add(proposal(10, true));
+        let expired = proposals.add(proposal(6, false));
+        let pending = proposals.add(proposal(10, false));
+        assert_eq!((0, 1, 2), (approved, expired, pending));
+
+        let finished = proposals.remove_finished(5);
+        assert_eq!(vec![proposal(10, true)], finished);
+        proposals.save_to_state(&mut state).unwrap();
+
+        let mut proposals = Proposals::load_from_state(&state, 3).unwrap();
+        assert!(proposals.get_mut(approved).is_none());
+        assert!(proposals.get_mut(expired).is_none());
+        assert!(proposals.get_mut(pending).is_some());
+        assert_eq!(3, proposals.add(proposal(20, false)));
+    }
+
+    #[test]
+    fn activate_returns_the_last_due_change() {
+        let mut scheduled = ScheduledParams {
+            handler_id: 3,
+            params: BTreeMap::new(),
+        };
+        let mut first = CommonParams::default();
+        first.max_body_size = 1;
+        let mut second = CommonParams::default();
+        second.max_body_size = 2;
+        let mut third = CommonParams::default();
+        third.max_body_size = 3;
+        scheduled.insert(5, first);
+        scheduled.insert(6, second.clone());
+        scheduled.insert(8, third);
+
+        assert_eq!(None, scheduled.activate(4));
+        assert_eq!(Some(second), scheduled.activate(7));
+        assert!(!scheduled.is_empty());
+        assert_eq!(Some(3), scheduled.activate(8).map(|params| params.max_body_size));
+        assert!(scheduled.is_empty());
+    }
+}
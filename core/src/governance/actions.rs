@@ -0,0 +1,96 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ctypes::BlockNumber;
+use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
+
+use crate::scheme::CommonParams;
+
+const ACTION_TAG_PROPOSE_PARAMS: u8 = 1;
+const ACTION_TAG_APPROVE_PARAMS: u8 = 2;
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    ProposeParams {
+        params: CommonParams,
+        activation: BlockNumber,
+    },
+    ApproveParams {
+        id: u64,
+    },
+}
+
+impl Encodable for Action {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Action::ProposeParams {
+                params,
+                activation,
+            } => s.begin_list(3).append(&ACTION_TAG_PROPOSE_PARAMS).append(params).append(activation),
+            Action::ApproveParams {
+                id,
+            } => s.begin_list(2).append(&ACTION_TAG_APPROVE_PARAMS).append(id),
+        };
+    }
+}
+
+impl Decodable for Action {
+    fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
+        let tag = rlp.val_at(0)?;
+        match tag {
+            ACTION_TAG_PROPOSE_PARAMS => {
+                if rlp.item_count()? != 3 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::ProposeParams {
+                    params: rlp.val_at(1)?,
+                    activation: rlp.val_at(2)?,
+                })
+            }
+            ACTION_TAG_APPROVE_PARAMS => {
+                if rlp.item_count()? != 2 {
+                    return Err(DecoderError::RlpInvalidLength)
+                }
+                Ok(Action::ApproveParams {
+                    id: rlp.val_at(1)?,
+                })
+            }
+            _ => Err(DecoderError::Custom("Unexpected Governance Action Type")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_propose_params() {
+        rlp_encode_and_decode_test!(Action::ProposeParams {
+            params: CommonParams::default(),
+            activation: 100,
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_approve_params() {
+        rlp_encode_and_decode_test!(Action::ApproveParams {
+            id: 3,
+        });
+    }
+}
@@ -0,0 +1,384 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod action_data;
+mod actions;
+
+use std::collections::BTreeSet;
+
+use ckey::{Address, NetworkId};
+use cstate::{ActionHandler, ActionHandlerResult, TopLevelState};
+use ctypes::invoice::Invoice;
+use ctypes::machine::Header;
+use ctypes::transaction::ParcelError;
+use ctypes::BlockNumber;
+use rlp::UntrustedRlp;
+
+use self::action_data::{
+    load_active_params, load_closed_block_number, save_active_params, save_closed_block_number, Proposal, Proposals,
+    ScheduledParams,
+};
+use self::actions::Action;
+use crate::consensus::stake;
+use crate::scheme::CommonParams;
+
+const HANDLER_NAME: &str = "governance";
+/// The latest block which a proposal can activate at, counted from the block which includes the proposal.
+const MAX_ACTIVATION_DELAY: BlockNumber = 1_000_000;
+/// The smallest body size which can include the transactions to change the params again.
+const MIN_MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Changes the common params by the vote of the voters.
+/// An approved change activates at the block which the proposal specified.
+pub struct Governance {
+    handler_id: u64,
+    network_id: NetworkId,
+    /// The stakeholders vote with their stakes if it's `None`.
+    voters: Option<BTreeSet<Address>>,
+}
+
+impl Governance {
+    pub fn new(handler_id: u64, network_id: NetworkId, voters: Option<Vec<Address>>) -> Self {
+        Self {
+            handler_id,
+            network_id,
+            voters: voters.map(|voters| voters.into_iter().collect()),
+        }
+    }
+
+    /// Returns the voting power of the approvals and the total voting power.
+    fn voting_power(&self, state: &TopLevelState, approvals: &BTreeSet<Address>) -> ActionHandlerResult<(u64, u64)> {
+        match &self.voters {
+            Some(voters) => Ok((approvals.intersection(voters).count() as u64, voters.len() as u64)),
+            None => {
+                let stakes = stake::get_stakes(state)?;
+                let approved = approvals.iter().filter_map(|address| stakes.get(address)).sum();
+                Ok((approved, stakes.values().sum()))
+            }
+        }
+    }
+
+    fn check_voter(&self, state: &TopLevelState, sender: &Address) -> ActionHandlerResult<()> {
+        let is_voter = match &self.voters {
+            Some(voters) => voters.contains(sender),
+            None => stake::get_stakes(state)?.get(sender).map_or(false, |stake| *stake > 0),
+        };
+        if !is_voter {
+            return Err(ParcelError::InvalidCustomAction(format!("{} cannot vote on the params", sender)).into())
+        }
+        Ok(())
+    }
+
+    fn approve(&self, state: &TopLevelState, proposal: &mut Proposal, sender: Address) -> ActionHandlerResult<()> {
+        proposal.approvals.insert(sender);
+        let (approved, total) = self.voting_power(state, &proposal.approvals)?;
+        proposal.approved = approved * 2 > total;
+        Ok(())
+    }
+
+    fn propose_params(
+        &self,
+        state: &mut TopLevelState,
+        sender: &Address,
+        params: CommonParams,
+        activation: BlockNumber,
+    ) -> ActionHandlerResult<Invoice> {
+        self.check_voter(state, sender)?;
+        // The proposal is removed when the block is closed if it cannot be approved before the activation.
+        let block_number = load_closed_block_number(state, self.handler_id)? + 1;
+        if activation <= block_number + 1 || activation > block_number + MAX_ACTIVATION_DELAY {
+            return Err(ParcelError::InvalidCustomAction(format!(
+                "The params proposed at {} cannot activate at {}",
+                block_number, activation
+            ))
+            .into())
+        }
+        let mut proposals = Proposals::load_from_state(state, self.handler_id)?;
+        let mut proposal = Proposal {
+            params,
+            activation,
+            approvals: BTreeSet::new(),
+            approved: false,
+        };
+        self.approve(state, &mut proposal, *sender)?;
+        let id = proposals.add(proposal);
+        cinfo!(ENGINE, "{} proposed the params #{} which activate at {}", sender, id, activation);
+        proposals.save_to_state(state)?;
+
+        Ok(Invoice::Success)
+    }
+
+    fn approve_params(&self, state: &mut TopLevelState, sender: &Address, id: u64) -> ActionHandlerResult<Invoice> {
+        self.check_voter(state, sender)?;
+        let mut proposals = Proposals::load_from_state(state, self.handler_id)?;
+        {
+            let proposal = proposals
+                .get_mut(id)
+                .ok_or_else(|| ParcelError::InvalidCustomAction(format!("The params #{} are not proposed", id)))?;
+            self.approve(state, proposal, *sender)?;
+        }
+        proposals.save_to_state(state)?;
+
+        Ok(Invoice::Success)
+    }
+
+    /// Rejects the params which would stop the chain or cannot take effect.
+    fn verify_params(&self, params: &CommonParams) -> Result<(), ParcelError> {
+        let reason = if params.network_id != self.network_id {
+            "The network id cannot be changed"
+        } else if params.supply_tracking_activation.is_some() {
+            "The supply tracking cannot be changed"
+        } else if params.snapshot_period == 0 {
+            "The snapshot period must be positive"
+        } else if params.max_body_size < MIN_MAX_BODY_SIZE {
+            "The body size is too small to include the transactions"
+        } else {
+            return Ok(())
+        };
+        Err(ParcelError::InvalidCustomAction(reason.to_string()))
+    }
+}
+
+impl ActionHandler for Governance {
+    fn name(&self) -> &'static str {
+        HANDLER_NAME
+    }
+
+    fn handler_id(&self) -> u64 {
+        self.handler_id
+    }
+
+    fn init(&self, _state: &mut TopLevelState) -> ActionHandlerResult<()> {
+        Ok(())
+    }
+
+    fn execute(&self, bytes: &[u8], state: &mut TopLevelState, sender: &Address) -> ActionHandlerResult<Invoice> {
+        let action = UntrustedRlp::new(bytes).as_val()?;
+        match action {
+            Action::ProposeParams {
+                params,
+                activation,
+            } => {
+                self.verify_params(&params)?;
+                self.propose_params(state, sender, params, activation)
+            }
+            Action::ApproveParams {
+                id,
+            } => self.approve_params(state, sender, id),
+        }
+    }
+
+    fn verify(&self, bytes: &[u8]) -> ActionHandlerResult<()> {
+        let action = UntrustedRlp::new(bytes).as_val()?;
+        if let Action::ProposeParams {
+            params,
+            ..
+        } = action
+        {
+            self.verify_params(&params)?;
+        }
+        Ok(())
+    }
+
+    /// Schedules the approved changes, and activates the ones which the next block follows.
+    fn on_close_block(&self, state: &mut TopLevelState, header: &Header) -> ActionHandlerResult<()> {
+        let number = header.number();
+        save_closed_block_number(state, self.handler_id, number)?;
+        let mut proposals = Proposals::load_from_state(state, self.handler_id)?;
+        let mut scheduled = ScheduledParams::load_from_state(state, self.handler_id)?;
+        if proposals.is_empty() && scheduled.is_empty() {
+            return Ok(())
+        }
+
+        for proposal in proposals.remove_finished(number) {
+            if proposal.activation > number {
+                scheduled.insert(proposal.activation, proposal.params);
+            }
+        }
+        if let Some(params) = scheduled.activate(number + 1) {
+            cinfo!(ENGINE, "The common params are changed from the block {}", number + 1);
+            save_active_params(state, self.handler_id, &params)?;
+        }
+
+        proposals.save_to_state(state)?;
+        scheduled.save_to_state(state)?;
+        Ok(())
+    }
+}
+
+/// Returns the params which the governance of the handler id changed for the blocks after the given state.
+/// It's `None` if the governance is not enabled or hasn't changed the params yet.
+pub fn active_params(handler_id: Option<u64>, state: &TopLevelState) -> ActionHandlerResult<Option<CommonParams>> {
+    match handler_id {
+        Some(handler_id) => load_active_params(state, handler_id),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cstate::tests::helpers;
+    use rlp::Encodable;
+
+    use super::*;
+    use crate::header::Header as BlockHeader;
+
+    fn execute(governance: &Governance, state: &mut TopLevelState, sender: &Address, action: Action) -> bool {
+        governance.execute(&action.rlp_bytes(), state, sender).is_ok()
+    }
+
+    fn close_block(governance: &Governance, state: &mut TopLevelState, number: BlockNumber) {
+        let mut header = BlockHeader::default();
+        header.set_number(number);
+        governance.on_close_block(state, &header).unwrap();
+    }
+
+    fn params_with_max_body_size(max_body_size: usize) -> CommonParams {
+        let mut params = CommonParams::default();
+        params.snapshot_period = 16384;
+        params.max_body_size = max_body_size;
+        params
+    }
+
+    #[test]
+    fn approved_params_activate_at_the_activation_block() {
+        let voters: Vec<Address> = (0..3).map(|_| Address::random()).collect();
+        let governance = Governance::new(3, NetworkId::default(), Some(voters.clone()));
+        let mut state = helpers::get_temp_state();
+
+        assert!(execute(&governance, &mut state, &voters[0], Action::ProposeParams {
+            params: params_with_max_body_size(1024 * 1024),
+            activation: 5,
+        }));
+        close_block(&governance, &mut state, 1);
+        assert!(execute(&governance, &mut state, &voters[1], Action::ApproveParams {
+            id: 0,
+        }));
+        close_block(&governance, &mut state, 2);
+        close_block(&governance, &mut state, 3);
+        assert_eq!(None, active_params(Some(3), &state).unwrap());
+
+        close_block(&governance, &mut state, 4);
+        assert_eq!(Some(params_with_max_body_size(1024 * 1024)), active_params(Some(3), &state).unwrap());
+    }
+
+    #[test]
+    fn params_are_not_changed_without_majority() {
+        let voters: Vec<Address> = (0..4).map(|_| Address::random()).collect();
+        let governance = Governance::new(3, NetworkId::default(), Some(voters.clone()));
+        let mut state = helpers::get_temp_state();
+
+        assert!(execute(&governance, &mut state, &voters[0], Action::ProposeParams {
+            params: params_with_max_body_size(1024 * 1024),
+            activation: 3,
+        }));
+        assert!(execute(&governance, &mut state, &voters[1], Action::ApproveParams {
+            id: 0,
+        }));
+        close_block(&governance, &mut state, 1);
+        close_block(&governance, &mut state, 2);
+        // The proposal expired.
+        assert!(!execute(&governance, &mut state, &voters[2], Action::ApproveParams {
+            id: 0,
+        }));
+        close_block(&governance, &mut state, 3);
+        assert_eq!(None, active_params(Some(3), &state).unwrap());
+    }
+
+    #[test]
+    fn only_voters_can_vote() {
+        let voter = Address::random();
+        let governance = Governance::new(3, NetworkId::default(), Some(vec![voter]));
+        let mut state = helpers::get_temp_state();
+
+        assert!(!execute(&governance, &mut state, &Address::random(), Action::ProposeParams {
+            params: params_with_max_body_size(1024 * 1024),
+            activation: 5,
+        }));
+        assert!(execute(&governance, &mut state, &voter, Action::ProposeParams {
+            params: params_with_max_body_size(1024 * 1024),
+            activation: 5,
+        }));
+        assert!(!execute(&governance, &mut state, &Address::random(), Action::ApproveParams {
+            id: 0,
+        }));
+    }
+
+    #[test]
+    fn network_id_cannot_be_changed() {
+        let governance = Governance::new(3, "tc".into(), None);
+        let mut params = params_with_max_body_size(1024 * 1024);
+        params.network_id = "sc".into();
+        let action = Action::ProposeParams {
+            params,
+            activation: 5,
+        };
+        assert!(governance.verify(&action.rlp_bytes()).is_err());
+
+        let mut params = params_with_max_body_size(1024 * 1024);
+        params.network_id = "tc".into();
+        let action = Action::ProposeParams {
+            params,
+            activation: 5,
+        };
+        assert!(governance.verify(&action.rlp_bytes()).is_ok());
+    }
+
+    #[test]
+    fn params_which_stop_the_chain_are_rejected() {
+        let governance = Governance::new(3, NetworkId::default(), None);
+        let verify = |params| {
+            let action = Action::ProposeParams {
+                params,
+                activation: 5,
+            };
+            governance.verify(&action.rlp_bytes()).is_ok()
+        };
+        assert!(verify(params_with_max_body_size(MIN_MAX_BODY_SIZE)));
+        assert!(!verify(params_with_max_body_size(MIN_MAX_BODY_SIZE - 1)));
+        let mut params = params_with_max_body_size(MIN_MAX_BODY_SIZE);
+        params.snapshot_period = 0;
+        assert!(!verify(params));
+        let mut params = params_with_max_body_size(MIN_MAX_BODY_SIZE);
+        params.supply_tracking_activation = Some(10);
+        assert!(!verify(params));
+    }
+
+    #[test]
+    fn activation_must_be_after_the_next_block() {
+        let voter = Address::random();
+        let governance = Governance::new(3, NetworkId::default(), Some(vec![voter]));
+        let mut state = helpers::get_temp_state();
+        close_block(&governance, &mut state, 1);
+        close_block(&governance, &mut state, 2);
+
+        // The proposal is included in the block 3.
+        for activation in &[0, 3, 4, 3 + MAX_ACTIVATION_DELAY + 1] {
+            assert!(!execute(&governance, &mut state, &voter, Action::ProposeParams {
+                params: params_with_max_body_size(1024 * 1024),
+                activation: *activation,
+            }));
+        }
+        assert!(execute(&governance, &mut state, &voter, Action::ProposeParams {
+            params: params_with_max_body_size(1024 * 1024),
+            activation: 5,
+        }));
+        assert!(execute(&governance, &mut state, &voter, Action::ProposeParams {
+            params: params_with_max_body_size(1024 * 1024),
+            activation: 3 + MAX_ACTIVATION_DELAY,
+        }));
+    }
+}
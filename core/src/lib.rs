@@ -41,7 +41,6 @@ extern crate memorydb;
 extern crate num_cpus;
 extern crate primitives;
 extern crate rand;
-#[cfg(test)]
 extern crate rand_xorshift;
 extern crate rlp;
 extern crate rlp_compress;
@@ -65,6 +64,7 @@ mod consensus;
 mod db;
 pub mod encoded;
 mod error;
+mod governance;
 mod header;
 mod miner;
 mod scheme;
@@ -79,17 +79,21 @@ mod tests;
 
 pub use crate::account_provider::{AccountProvider, SignError as AccountProviderError};
 pub use crate::block::Block;
+pub use crate::blockchain::{Inconsistency, MIN_HISTORY};
 pub use crate::client::{
-    AssetClient, Balance, BlockChainClient, BlockInfo, ChainInfo, ChainNotify, Client, DatabaseClient, EngineClient,
-    EngineInfo, ExecuteClient, ImportBlock, MiningBlockChainClient, RegularKey, RegularKeyOwner, Seq, Shard, StateInfo,
-    TestBlockChainClient, TextClient,
+    AssetClient, Balance, BlockChainClient, BlockInfo, ChainInfo, ChainNotify, Client, ClientConfig, DatabaseClient,
+    EngineClient, EngineInfo, ExecuteClient, ImportBlock, MiningBlockChainClient, RegularKey, RegularKeyOwner, Seq,
+    Shard, StateInfo, Supply, TestBlockChainClient, TextClient,
+};
+pub use crate::consensus::{
+    stake, verify_certificate_chain, BitSet, CommitCertificate, ConsensusEvent, ConsensusListener, ConsensusState,
+    EngineType, Step, StepVotes,
 };
-pub use crate::consensus::EngineType;
 pub use crate::db::COL_STATE;
 pub use crate::error::{BlockImportError, Error, ImportError};
 pub use crate::header::{Header, Seal};
-pub use crate::miner::{Miner, MinerOptions, MinerService, Stratum, StratumConfig, StratumError};
+pub use crate::miner::{CpuMiner, Miner, MinerOptions, MinerService, Stratum, StratumConfig, StratumError};
 pub use crate::scheme::Scheme;
-pub use crate::service::ClientService;
+pub use crate::service::{check_database, repair_database, ClientService};
 pub use crate::transaction::{LocalizedTransaction, SignedTransaction, UnverifiedTransaction};
 pub use crate::types::{BlockId, TransactionId};
@@ -0,0 +1,240 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! In-process CPU miner for the PoW engines
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread;
+
+use parking_lot::{Condvar, Mutex};
+use primitives::{H256, U256};
+use rand::random;
+
+use crate::client::Client;
+use crate::header::Header;
+use crate::miner::work_notify::NotifyWork;
+use crate::miner::{Miner, MinerService};
+
+/// Searches the nonce of the sealing work on a pool of threads and submits the found seal.
+/// The threads live as long as the miner and wait for the next work between the searches.
+pub struct CpuMiner {
+    miner: Weak<Miner>,
+    shared: Arc<Shared>,
+}
+
+impl CpuMiner {
+    pub fn new(threads: usize, miner: &Arc<Miner>, client: &Arc<Client>) -> Self {
+        debug_assert_ne!(threads, 0);
+        let shared = Arc::new(Shared {
+            generation: AtomicUsize::new(0),
+            work: Mutex::new(None),
+            new_work: Condvar::new(),
+            is_closed: AtomicBool::new(false),
+        });
+        for index in 0..threads {
+            let worker = Worker {
+                index: index as u64,
+                threads: threads as u64,
+                miner: Arc::downgrade(miner),
+                client: Arc::downgrade(client),
+                shared: Arc::clone(&shared),
+            };
+            thread::Builder::new()
+                .name(format!("CPU miner #{}", index))
+                .spawn(move || worker.run())
+                .expect("Failed to create CPU miner thread.");
+        }
+        Self {
+            miner: Arc::downgrade(miner),
+            shared,
+        }
+    }
+}
+
+impl NotifyWork for CpuMiner {
+    fn notify(&self, pow_hash: H256, _target: U256) {
+        let header = match self.miner.upgrade().and_then(|miner| miner.sealing_work_header(pow_hash)) {
+            Some(header) => header,
+            None => {
+                cwarn!(MINER, "Cannot find the sealing work {}", pow_hash);
+                return
+            }
+        };
+        ctrace!(MINER, "Mining #{} ({})", header.number(), pow_hash);
+
+        let mut work = self.shared.work.lock();
+        let generation = self.shared.generation.fetch_add(1, Ordering::SeqCst).wrapping_add(1);
+        *work = Some(Work {
+            generation,
+            pow_hash,
+            header,
+            start: random(),
+        });
+        self.shared.new_work.notify_all();
+    }
+}
+
+impl Drop for CpuMiner {
+    fn drop(&mut self) {
+        let _work = self.shared.work.lock();
+        self.shared.is_closed.store(true, Ordering::SeqCst);
+        self.shared.generation.fetch_add(1, Ordering::SeqCst);
+        self.shared.new_work.notify_all();
+    }
+}
+
+/// The state shared by the miner and its workers.
+struct Shared {
+    /// Increased whenever the work changes, which stops the search of the previous work.
+    generation: AtomicUsize,
+    /// The latest work, which the workers wait for.
+    work: Mutex<Option<Work>>,
+    new_work: Condvar,
+    is_closed: AtomicBool,
+}
+
+#[derive(Clone)]
+struct Work {
+    generation: usize,
+    pow_hash: H256,
+    header: Header,
+    start: u64,
+}
+
+struct Worker {
+    index: u64,
+    threads: u64,
+    miner: Weak<Miner>,
+    client: Weak<Client>,
+    shared: Arc<Shared>,
+}
+
+impl Worker {
+    fn run(self) {
+        let mut last_generation = 0;
+        while let Some(work) = self.wait_work(last_generation) {
+            last_generation = work.generation;
+            self.search(work);
+        }
+    }
+
+    /// Blocks until a work newer than the last one arrives, or returns `None` if the miner is dropped.
+    fn wait_work(&self, last_generation: usize) -> Option<Work> {
+        let mut work = self.shared.work.lock();
+        loop {
+            if self.shared.is_closed.load(Ordering::SeqCst) {
+                return None
+            }
+            if let Some(ref work) = *work {
+                if work.generation != last_generation {
+                    return Some(work.clone())
+                }
+            }
+            self.shared.new_work.wait(&mut work);
+        }
+    }
+
+    fn is_outdated(&self, work: &Work) -> bool {
+        self.shared.generation.load(Ordering::SeqCst) != work.generation
+    }
+
+    fn search(&self, mut work: Work) {
+        let client = match self.client.upgrade() {
+            Some(client) => client,
+            None => return,
+        };
+        let mut nonce = work.start.wrapping_add(self.index);
+        while !self.is_outdated(&work) {
+            let current = nonce;
+            nonce = nonce.wrapping_add(self.threads);
+
+            let seal = match client.engine().pow_seal(&work.header, current) {
+                Some(seal) => seal,
+                None => continue,
+            };
+            work.header.set_seal(seal.clone());
+            if client.engine().verify_local_seal(&work.header).is_err() {
+                continue
+            }
+
+            // Only the first worker which finds the seal submits it.
+            let next_generation = work.generation.wrapping_add(1);
+            if self
+                .shared
+                .generation
+                .compare_exchange(work.generation, next_generation, Ordering::SeqCst, Ordering::SeqCst)
+                .is_err()
+            {
+                return
+            }
+            let miner = match self.miner.upgrade() {
+                Some(miner) => miner,
+                None => return,
+            };
+            cinfo!(MINER, "Found the seal of #{} with the nonce {}", work.header.number(), current);
+            if let Err(err) = miner.submit_seal(&*client, work.pow_hash, seal) {
+                cwarn!(MINER, "Cannot submit the seal of #{}: {:?}", work.header.number(), err);
+            }
+            // Request the next work like the external miners do, or the miner stops sealing.
+            miner.map_sealing_work(&*client, |_| ());
+            return
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use cio::IoChannel;
+
+    use super::*;
+    use crate::client::{BlockInfo, ChainInfo, ClientConfig};
+    use crate::db::NUM_COLUMNS;
+    use crate::miner::MinerOptions;
+    use crate::scheme::Scheme;
+    use crate::types::BlockId;
+
+    #[test]
+    fn mines_a_blake_pow_block() {
+        let scheme = Scheme::new_test_blake_pow();
+        let miner = Miner::new(
+            MinerOptions {
+                force_sealing: true,
+                ..Default::default()
+            },
+            &scheme,
+            None,
+        );
+        let db = Arc::new(::kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0)));
+        let client =
+            Client::try_new(&ClientConfig::default(), &scheme, db, Arc::clone(&miner), IoChannel::disconnected())
+                .unwrap();
+        miner.add_work_listener(Box::new(CpuMiner::new(2, &miner, &client)));
+
+        assert!(miner.map_sealing_work(&*client, |_| ()).is_some());
+        let deadline = Instant::now() + Duration::from_secs(60);
+        while client.chain_info().best_block_number == 0 {
+            assert!(Instant::now() < deadline, "No block is mined");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let header = client.block_header(&BlockId::Number(1)).unwrap().decode();
+        assert_eq!(scheme.genesis_header().hash(), *header.parent_hash());
+        assert!(client.engine().verify_local_seal(&header).is_ok());
+    }
+}
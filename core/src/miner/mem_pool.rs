@@ -17,8 +17,10 @@
 use std::cmp;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
 
 use ckey::{public_to_address, Public};
+use cstate::ActionHandler;
 use ctypes::transaction::{Action, ParcelError};
 use ctypes::BlockNumber;
 use heapsize::HeapSizeOf;
@@ -30,6 +32,7 @@ use time::get_time;
 
 use super::local_tranasctions::{LocalTransactionsList, Status as LocalTxStatus};
 use super::TransactionImportResult;
+use crate::codechain_machine::CodeChainMachine;
 use crate::transaction::SignedTransaction;
 
 /// Transaction with the same (sender, seq) can be replaced only if
@@ -546,6 +549,8 @@ pub struct MemPool {
     local_transactions: LocalTransactionsList,
     /// Next id that should be assigned to a transaction imported to the pool.
     next_transaction_id: u64,
+    /// Handlers which verify the custom actions.
+    action_handlers: Vec<Arc<ActionHandler>>,
 }
 
 impl Default for MemPool {
@@ -557,11 +562,11 @@ impl Default for MemPool {
 impl MemPool {
     /// Creates new instance of this Queue
     pub fn new() -> Self {
-        Self::with_limits(8192, usize::max_value())
+        Self::with_limits(8192, usize::max_value(), Vec::new())
     }
 
     /// Create new instance of this Queue with specified limits
-    pub fn with_limits(limit: usize, memory_limit: usize) -> Self {
+    pub fn with_limits(limit: usize, memory_limit: usize, action_handlers: Vec<Arc<ActionHandler>>) -> Self {
         let current = CurrentTxSet {
             by_priority: BTreeSet::new(),
             by_signer_public: Table::new(),
@@ -586,6 +591,7 @@ impl MemPool {
             last_seqs: HashMap::new(),
             local_transactions: LocalTransactionsList::default(),
             next_transaction_id: 0,
+            action_handlers,
         }
     }
 
@@ -810,7 +816,6 @@ impl MemPool {
     }
 
     /// Returns highest transaction seq for given signer.
-    #[allow(dead_code)]
     pub fn last_seq(&self, signer_public: &Public) -> Option<u64> {
         self.last_seqs.get(signer_public).cloned()
     }
@@ -917,6 +922,14 @@ impl MemPool {
 
         tx.check_low_s()?;
 
+        if let Action::Custom {
+            handler_id,
+            bytes,
+        } = &tx.action
+        {
+            CodeChainMachine::verify_custom_action(*handler_id, bytes, &self.action_handlers)?;
+        }
+
         Ok(())
     }
 
@@ -1376,6 +1389,7 @@ pub mod test {
     use primitives::H160;
 
     use super::*;
+    use crate::tests::helpers::TestActionHandler;
 
     #[test]
     fn origin_ordering() {
@@ -1703,6 +1717,46 @@ pub mod test {
         assert_eq!(fee + amount, item.cost());
     }
 
+    #[test]
+    fn custom_action_rejected_by_its_handler_is_not_added() {
+        let handler: Arc<ActionHandler> = Arc::new(TestActionHandler::new(3));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), vec![handler]);
+        let keypair = Random.generate().unwrap();
+        let fetch_account = |_: &Public| AccountDetails {
+            seq: 0,
+            balance: 1_000,
+        };
+        let timelock = TxTimelock {
+            block: None,
+            timestamp: None,
+        };
+        let custom = |seq, handler_id, bytes| {
+            let tx = Transaction {
+                seq,
+                fee: 10,
+                network_id: "tc".into(),
+                action: Action::Custom {
+                    handler_id,
+                    bytes,
+                },
+            };
+            SignedTransaction::new_with_sign(tx, keypair.private())
+        };
+
+        match mem_pool.add(custom(0, 3, vec![]), TxOrigin::External, 0, 0, timelock, &fetch_account) {
+            Err(ParcelError::InvalidCustomAction(_)) => {}
+            result => panic!("The empty payload must be rejected: {:?}", result),
+        }
+        match mem_pool.add(custom(0, 4, vec![1]), TxOrigin::External, 0, 0, timelock, &fetch_account) {
+            Err(ParcelError::InvalidCustomAction(_)) => {}
+            result => panic!("The unknown handler must be rejected: {:?}", result),
+        }
+        assert_eq!(
+            Ok(TransactionImportResult::Current),
+            mem_pool.add(custom(0, 3, vec![1]), TxOrigin::External, 0, 0, timelock, &fetch_account)
+        );
+    }
+
     #[test]
     fn fee_per_byte_order_simple() {
         let order1 = create_transaction_order(1_000_000_000, 100);
@@ -37,12 +37,12 @@ use super::{MinerService, MinerStatus, TransactionImportResult};
 use crate::account_provider::{AccountProvider, SignError};
 use crate::block::{Block, ClosedBlock, IsBlock};
 use crate::client::{
-    AccountData, BlockChain, BlockProducer, ImportSealedBlock, MiningBlockChainClient, RegularKey, RegularKeyOwner,
-    ResealTimer,
+    AccountData, BlockChain, BlockProducer, EngineInfo, ImportSealedBlock, MiningBlockChainClient, RegularKey,
+    RegularKeyOwner, ResealTimer,
 };
 use crate::consensus::{CodeChainEngine, EngineType};
 use crate::encoded;
-use crate::error::Error;
+use crate::error::{BlockError, Error};
 use crate::header::Header;
 use crate::scheme::Scheme;
 use crate::transaction::{SignedTransaction, UnverifiedTransaction};
@@ -137,7 +137,11 @@ impl Miner {
 
     fn new_raw(options: MinerOptions, scheme: &Scheme, accounts: Option<Arc<AccountProvider>>) -> Self {
         let mem_limit = options.mem_pool_memory_limit.unwrap_or_else(usize::max_value);
-        let mem_pool = Arc::new(RwLock::new(MemPool::with_limits(options.mem_pool_size, mem_limit)));
+        let mem_pool = Arc::new(RwLock::new(MemPool::with_limits(
+            options.mem_pool_size,
+            mem_limit,
+            scheme.engine.action_handlers().to_vec(),
+        )));
         let notifiers: Vec<Box<NotifyWork>> = if options.new_work_notify.is_empty() {
             Vec::new()
         } else {
@@ -183,6 +187,20 @@ impl Miner {
         self.map_pending_block(|b| b.header().clone(), latest_block_number)
     }
 
+    /// Get the header of the sealing work whose hash is `pow_hash`.
+    pub fn sealing_work_header(&self, pow_hash: H256) -> Option<Header> {
+        self.sealing_work.lock().queue.find_used_if(|b| b.hash() == pow_hash).map(|b| b.header().clone())
+    }
+
+    /// Get the seq of the next transaction of the signer, counting its transactions in the mem pool.
+    pub fn next_seq<C: AccountData>(&self, chain: &C, signer_public: &Public) -> u64 {
+        let latest_seq = chain.latest_seq(&public_to_address(signer_public));
+        match self.mem_pool.read().last_seq(signer_public) {
+            Some(last_seq) if last_seq >= latest_seq => last_seq + 1,
+            _ => latest_seq,
+        }
+    }
+
     pub fn get_options(&self) -> &MinerOptions {
         &self.options
     }
@@ -230,6 +248,14 @@ impl Miner {
         mem_pool: &mut MemPool,
     ) -> Vec<Result<TransactionImportResult, Error>> {
         let best_block_header = client.best_block_header().decode();
+        let best_block_hash = best_block_header.hash();
+        let common_params = match client.common_params(BlockId::Hash(best_block_hash)) {
+            Some(common_params) => common_params,
+            None => {
+                cwarn!(MINER, "Cannot find the common params of the best block {}", best_block_hash);
+                return transactions.iter().map(|_| Err(BlockError::UnknownParent(best_block_hash).into())).collect()
+            }
+        };
         let insertion_time = client.chain_info().best_block_number;
         let mut inserted = Vec::with_capacity(transactions.len());
 
@@ -243,7 +269,7 @@ impl Miner {
                 }
                 match self
                     .engine
-                    .verify_transaction_basic(&tx, &best_block_header)
+                    .verify_transaction_basic(&tx, &common_params, &best_block_header)
                     .and_then(|_| self.engine.verify_transaction_unordered(tx, &best_block_header))
                 {
                     Err(e) => {
@@ -381,8 +407,8 @@ impl Miner {
             (work, is_new)
         };
         if is_new {
-            if let Some((pow_hash, score, _number)) = work {
-                let target = self.engine.score_to_target(&score);
+            if let Some((pow_hash, score, number)) = work {
+                let target = self.engine.score_to_target(&score, number);
                 for notifier in self.notifiers.read().iter() {
                     notifier.notify(pow_hash, target)
                 }
@@ -398,7 +424,11 @@ impl Miner {
         chain: &C,
     ) -> Result<(ClosedBlock, Option<H256>), Error> {
         let (transactions, mut open_block, original_work_hash) = {
-            let max_body_size = self.engine.params().max_body_size;
+            let best_block_hash = chain.chain_info().best_block_hash;
+            let max_body_size = chain
+                .common_params(BlockId::Hash(best_block_hash))
+                .ok_or_else(|| BlockError::UnknownParent(best_block_hash))?
+                .max_body_size;
             let transactions = self.mem_pool.read().top_transactions(max_body_size);
             let mut sealing_work = self.sealing_work.lock();
             let last_work_hash = sealing_work.queue.peek_last_ref().map(|pb| pb.block().header().hash());
@@ -899,7 +929,7 @@ impl MinerService for Miner {
                         seq
                     })
                     .unwrap_or_else(|| {
-                        get_next_seq(self.ready_transactions().into_iter(), &addresses)
+                        get_next_seq(self.ready_transactions(client).into_iter(), &addresses)
                             .map(|seq| {
                                 cdebug!(RPC, "There are ready transactions for {}", platform_address);
                                 seq
@@ -919,9 +949,14 @@ impl MinerService for Miner {
         Ok((hash, seq))
     }
 
-    fn ready_transactions(&self) -> Vec<SignedTransaction> {
-        let max_body_size = self.engine.params().max_body_size;
-        self.mem_pool.read().top_transactions(max_body_size)
+    fn ready_transactions<C: EngineInfo>(&self, chain: &C) -> Vec<SignedTransaction> {
+        match chain.common_params(BlockId::Latest) {
+            Some(common_params) => self.mem_pool.read().top_transactions(common_params.max_body_size),
+            None => {
+                cwarn!(MINER, "Cannot find the common params of the best block");
+                Vec::new()
+            }
+        }
     }
 
     /// Get a list of all future transactions.
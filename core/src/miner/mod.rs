@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod cpu_miner;
 mod local_tranasctions;
 mod mem_pool;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
@@ -28,13 +29,14 @@ use ctypes::transaction::IncompleteTransaction;
 use cvm::ChainTimeInfo;
 use primitives::{Bytes, H256};
 
+pub use self::cpu_miner::CpuMiner;
 pub use self::miner::{AuthoringParams, Miner, MinerOptions};
 pub use self::stratum::{Config as StratumConfig, Error as StratumError, Stratum};
 use crate::account_provider::{AccountProvider, SignError};
 use crate::block::ClosedBlock;
 use crate::client::{
-    AccountData, BlockChain, BlockProducer, ImportSealedBlock, MiningBlockChainClient, RegularKey, RegularKeyOwner,
-    ResealTimer,
+    AccountData, BlockChain, BlockProducer, EngineInfo, ImportSealedBlock, MiningBlockChainClient, RegularKey,
+    RegularKeyOwner, ResealTimer,
 };
 use crate::consensus::EngineType;
 use crate::error::Error;
@@ -133,8 +135,8 @@ pub trait MinerService: Send + Sync {
         seq: Option<u64>,
     ) -> Result<(H256, u64), Error>;
 
-    /// Get a list of all pending transactions in the mem pool.
-    fn ready_transactions(&self) -> Vec<SignedTransaction>;
+    /// Get a list of all pending transactions in the mem pool which fit in the body of the next block.
+    fn ready_transactions<C: EngineInfo>(&self, chain: &C) -> Vec<SignedTransaction>;
 
     /// Get a list of all future transactions.
     fn future_transactions(&self) -> Vec<SignedTransaction>;
@@ -63,6 +63,13 @@ impl SealingQueue {
         self.in_use.clear();
     }
 
+    /// Return a reference to the used item which satisfies the predicate; it doesn't take the item.
+    pub fn find_used_if<P>(&self, predicate: P) -> Option<&ClosedBlock>
+    where
+        P: Fn(&ClosedBlock) -> bool, {
+        self.in_use.iter().find(|r| predicate(r))
+    }
+
     pub fn take_used_if<P>(&mut self, predicate: P) -> Option<ClosedBlock>
     where
         P: Fn(&ClosedBlock) -> bool, {
@@ -204,4 +211,21 @@ mod tests {
 
         assert!(q.take_used_if(|b| b.hash() == h).is_none());
     }
+
+    #[test]
+    fn find_used_without_taking() {
+        let mut q = SealingQueue::new(QUEUE_SIZE);
+        let b1 = create_closed_block(Address::from(1));
+        let b2 = create_closed_block(Address::from(2));
+        let h1 = b1.hash();
+        let h2 = b2.hash();
+
+        q.push(b1);
+        q.use_last_ref();
+        q.push(b2);
+
+        assert_eq!(q.find_used_if(|b| b.hash() == h1).unwrap().hash(), h1);
+        assert!(q.find_used_if(|b| b.hash() == h2).is_none());
+        assert!(q.take_used_if(|b| b.hash() == h1).is_some());
+    }
 }
@@ -14,16 +14,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::HashSet;
 use std::io::Read;
 use std::sync::Arc;
 
 use ccrypto::{blake256, BLAKE_NULL_RLP};
 use cjson;
-use ckey::{Address, NetworkId};
+use ckey::{Address, NetworkId, PlatformAddress};
 use cmerkle::TrieFactory;
 use cstate::{
-    ActionHandlerError, ActionHandlerResult, Metadata, MetadataAddress, Shard, ShardAddress, StateDB, StateResult,
-    StateWithCache, TopLevelState,
+    ActionHandler, ActionHandlerError, ActionHandlerResult, HitHandler, Metadata, MetadataAddress, Shard, ShardAddress,
+    StateDB, StateResult, StateWithCache, TopLevelState,
 };
 use ctypes::transaction::Error as TransactionError;
 use ctypes::ShardId;
@@ -38,11 +39,14 @@ use super::pod_state::{PodAccounts, PodShards};
 use super::seal::Generic as GenericSeal;
 use super::Genesis;
 use crate::codechain_machine::CodeChainMachine;
-use crate::consensus::{BlakePoW, CodeChainEngine, Cuckoo, NullEngine, SimplePoA, Solo, Tendermint};
+use crate::consensus::{
+    BlakePoW, CodeChainEngine, Cuckoo, NullEngine, SimplePoA, Solo, Tendermint, TendermintParams, TransitionEngine,
+};
 use crate::error::{Error, SchemeError};
+use crate::governance::Governance;
 use crate::header::Header;
 
-#[derive(Debug, PartialEq, Default, RlpEncodable)]
+#[derive(Clone, Debug, PartialEq, Default, RlpDecodable, RlpEncodable)]
 pub struct CommonParams {
     /// Maximum size of extra data.
     pub max_extra_data_size: usize,
@@ -72,6 +76,21 @@ pub struct CommonParams {
     pub max_body_size: usize,
     /// Snapshot creation period in unit of block numbers.
     pub snapshot_period: u64,
+    /// The block from which the CCC supply and the supplies of the asset schemes are tracked.
+    /// The tracking changes the state, so it must not be activated on an existing chain before every node upgrades.
+    pub supply_tracking_activation: Option<u64>,
+}
+
+impl CommonParams {
+    /// Returns true if the supplies are tracked in the block.
+    pub fn is_supply_tracked(&self, number: u64) -> bool {
+        self.supply_tracking_activation.map_or(false, |activation| activation <= number)
+    }
+
+    /// Returns true if the supplies are tracked from the genesis block, so the CCC supply is known.
+    pub fn is_supply_tracked_from_genesis(&self) -> bool {
+        self.supply_tracking_activation == Some(0)
+    }
 }
 
 impl From<cjson::scheme::Params> for CommonParams {
@@ -98,6 +117,7 @@ impl From<cjson::scheme::Params> for CommonParams {
             min_asset_unwrap_ccc_cost: p.min_unwrap_ccc_cost.into(),
             max_body_size: p.max_body_size.into(),
             snapshot_period: p.snapshot_period.into(),
+            supply_tracking_activation: p.supply_tracking_activation.map(Into::into),
         }
     }
 }
@@ -157,23 +177,66 @@ macro_rules! load_bundled {
 
 impl Scheme {
     // create an instance of an CodeChain state machine, minus consensus logic.
-    fn machine(_engine_scheme: &cjson::scheme::Engine, params: CommonParams) -> CodeChainMachine {
-        CodeChainMachine::new(params)
+    fn machine(
+        _engine_scheme: &cjson::scheme::Engine,
+        params: CommonParams,
+        action_handlers: Vec<Arc<ActionHandler>>,
+        governance_handler_id: Option<u64>,
+    ) -> CodeChainMachine {
+        let machine = CodeChainMachine::new(params, action_handlers);
+        match governance_handler_id {
+            Some(handler_id) => machine.with_governance(handler_id),
+            None => machine,
+        }
     }
 
     /// Convert engine scheme into a arc'd Engine of the right underlying type.
     /// TODO avoid this hard-coded nastiness - use dynamic-linked plugin framework instead.
-    fn engine(engine_scheme: cjson::scheme::Engine, params: CommonParams) -> Arc<CodeChainEngine> {
-        let machine = Self::machine(&engine_scheme, params);
-
-        match engine_scheme {
+    fn engine(
+        engine_scheme: cjson::scheme::Engine,
+        params: CommonParams,
+        action_handlers: Vec<Arc<ActionHandler>>,
+        governance_handler_id: Option<u64>,
+    ) -> Result<Arc<CodeChainEngine>, SchemeError> {
+        let machine = Self::machine(&engine_scheme, params, action_handlers, governance_handler_id);
+
+        Ok(match engine_scheme {
             cjson::scheme::Engine::Null(null) => Arc::new(NullEngine::new(null.params.into(), machine)),
             cjson::scheme::Engine::Solo(solo) => Arc::new(Solo::new(solo.params.into(), machine)),
             cjson::scheme::Engine::SimplePoA(simple_poa) => Arc::new(SimplePoA::new(simple_poa.params.into(), machine)),
-            cjson::scheme::Engine::Tendermint(tendermint) => Tendermint::new(tendermint.params.into(), machine),
+            cjson::scheme::Engine::Tendermint(tendermint) => {
+                TendermintParams::verify_scheme(&tendermint.params)?;
+                Tendermint::new(tendermint.params.into(), machine)
+            }
             cjson::scheme::Engine::Cuckoo(cuckoo) => Arc::new(Cuckoo::new(cuckoo.params.into(), machine)),
             cjson::scheme::Engine::BlakePoW(blake_pow) => Arc::new(BlakePoW::new(blake_pow.params.into(), machine)),
-        }
+        })
+    }
+
+    /// Convert the action handler schemes into the handlers enabled on the chain.
+    fn action_handlers(
+        action_handler_schemes: Vec<cjson::scheme::ActionHandler>,
+        params: &CommonParams,
+    ) -> Vec<Arc<ActionHandler>> {
+        action_handler_schemes
+            .into_iter()
+            .map(|action_handler_scheme| -> Arc<ActionHandler> {
+                match action_handler_scheme {
+                    cjson::scheme::ActionHandler::Hit(hit) => {
+                        let initial_counter =
+                            hit.params.and_then(|params| params.initial_counter).map_or(1, Into::into);
+                        Arc::new(HitHandler::with_params(hit.handler_id.into(), initial_counter))
+                    }
+                    cjson::scheme::ActionHandler::Governance(governance) => {
+                        let voters = governance
+                            .params
+                            .and_then(|params| params.voters)
+                            .map(|voters| voters.into_iter().map(PlatformAddress::into_address).collect::<Vec<_>>());
+                        Arc::new(Governance::new(governance.handler_id.into(), params.network_id, voters))
+                    }
+                }
+            })
+            .collect()
     }
 
     fn initialize_state(&self, db: StateDB) -> StateResult<StateDB> {
@@ -246,7 +309,7 @@ impl Scheme {
     fn initialize_action_handlers(&self, db: StateDB, root: H256) -> ActionHandlerResult<(StateDB, H256)> {
         // basic accounts in scheme.
         let mut top_level = TopLevelState::from_existing(db, root)?;
-        for handler in self.engine.action_handlers() {
+        for handler in self.engine.genesis_action_handlers() {
             handler.init(&mut top_level)?;
         }
         Ok(top_level.commit_and_into_db()?)
@@ -382,6 +445,11 @@ impl Scheme {
     pub fn genesis_accounts(&self) -> Vec<Address> {
         self.genesis_accounts.keys().cloned().collect()
     }
+
+    /// The amount of CCC given to the accounts in the genesis block.
+    pub fn genesis_ccc_supply(&self) -> u64 {
+        self.genesis_accounts.values().map(|account| account.balance).sum()
+    }
 }
 
 /// Load from JSON object.
@@ -389,7 +457,53 @@ fn load_from(s: cjson::scheme::Scheme) -> Result<Scheme, Error> {
     let g = Genesis::from(s.genesis);
     let GenericSeal(seal_rlp) = g.seal.into();
     let params = CommonParams::from(s.params);
-    let engine = Scheme::engine(s.engine, params);
+    let action_handler_schemes = s.action_handlers.unwrap_or_else(Vec::new);
+    let (governance_handler_id, is_stake_weighted) = {
+        let governance = action_handler_schemes.iter().find_map(|handler| match handler {
+            cjson::scheme::ActionHandler::Governance(governance) => Some(governance),
+            _ => None,
+        });
+        let is_stake_weighted = governance
+            .map_or(false, |governance| governance.params.as_ref().and_then(|params| params.voters.as_ref()).is_none());
+        (governance.map(|governance| u64::from(governance.handler_id)), is_stake_weighted)
+    };
+    // The stakes are kept only by the stake handler of Tendermint.
+    let has_stakes = match &s.engine {
+        cjson::scheme::Engine::Tendermint(_) => true,
+        _ => s.engine_transitions.iter().flatten().any(|transition| match transition.engine {
+            cjson::scheme::Engine::Tendermint(_) => true,
+            _ => false,
+        }),
+    };
+    if is_stake_weighted && !has_stakes {
+        return Err(SchemeError::InvalidActionHandlerParams("The stakeholders vote only on a Tendermint chain").into())
+    }
+
+    let action_handlers = Scheme::action_handlers(action_handler_schemes, &params);
+    let genesis_engine = Scheme::engine(s.engine, params.clone(), action_handlers.clone(), governance_handler_id)?;
+    let engine = match s.engine_transitions {
+        None => genesis_engine,
+        Some(transitions) => {
+            let mut engines = vec![(0, genesis_engine)];
+            for transition in transitions {
+                let activation: u64 = transition.activation.into();
+                if activation <= engines.last().expect("The genesis engine is always there").0 {
+                    return Err(SchemeError::InvalidEngineTransition(activation).into())
+                }
+                let engine =
+                    Scheme::engine(transition.engine, params.clone(), action_handlers.clone(), governance_handler_id)?;
+                engines.push((activation, engine));
+            }
+            Arc::new(TransitionEngine::new(engines))
+        }
+    };
+
+    let mut handler_ids = HashSet::new();
+    for handler in engine.action_handlers() {
+        if !handler_ids.insert(handler.handler_id()) {
+            return Err(SchemeError::DuplicatedActionHandler(handler.handler_id()).into())
+        }
+    }
 
     let mut s = Scheme {
         name: s.name.clone(),
@@ -437,4 +551,133 @@ mod tests {
         let result = genesis_header.extra_data();
         assert_eq!(&hash_of_common_params, result);
     }
+
+    fn load_null_scheme_with_action_handlers(action_handlers: &str) -> Result<Scheme, String> {
+        let json = include_str!("../../res/null.json");
+        let end = json.rfind('}').unwrap();
+        let json = format!("{}, \"actionHandlers\": {}}}", &json[..end], action_handlers);
+        Scheme::load(json.as_bytes())
+    }
+
+    fn load_null_scheme_with_engine_transitions(engine_transitions: &str) -> Result<Scheme, String> {
+        let json = include_str!("../../res/null.json");
+        let end = json.rfind('}').unwrap();
+        let json = format!("{}, \"engineTransitions\": {}}}", &json[..end], engine_transitions);
+        Scheme::load(json.as_bytes())
+    }
+
+    fn load_tendermint_scheme(params: &str) -> Result<Scheme, String> {
+        let json = include_str!("../../res/null.json");
+        let engine = format!(
+            r#""tendermint": {{ "params": {{
+                "validators": ["0xd8c897324f1663639479d96a038263c47d76a5847a1e991695d29568856b41afe789df7665146f94a6a081938d10ecbb368e4b01f6fab0fa353d6cb62363327f"],
+                {}
+            }} }}"#,
+            params
+        );
+        let json = json.replacen(
+            r#""null": {
+      "params": {}
+    }"#,
+            &engine,
+            1,
+        );
+        Scheme::load(json.as_bytes())
+    }
+
+    #[test]
+    fn engine_transitions_switch_the_engine_at_the_activation_block() {
+        let scheme = load_null_scheme_with_engine_transitions(
+            r#"[{ "activation": "0x0a", "engine": { "null": { "params": { "blockReward": "0x0d" } } } }]"#,
+        )
+        .unwrap();
+        assert_eq!(0, scheme.engine.block_reward(9));
+        assert_eq!(13, scheme.engine.block_reward(10));
+        assert_eq!(13, scheme.engine.block_reward(11));
+    }
+
+    #[test]
+    fn reward_schedule_and_fee_burn_ratio_in_engine_params() {
+        let scheme = load_null_scheme_with_engine_transitions(
+            r#"[{ "activation": "0x01", "engine": { "solo": { "params": {
+                "blockReward": "0x64",
+                "rewardSchedule": { "halving": { "interval": "0x0a" } },
+                "feeBurnRatio": 300
+            } } } }]"#,
+        )
+        .unwrap();
+        assert_eq!(100, scheme.engine.block_reward(9));
+        assert_eq!(50, scheme.engine.block_reward(10));
+        assert_eq!(25, scheme.engine.block_reward(20));
+        assert_eq!(0, scheme.engine.burned_fee(0, 100));
+        assert_eq!(30, scheme.engine.burned_fee(1, 100));
+    }
+
+    #[test]
+    fn engine_transitions_not_in_order_are_rejected() {
+        let result = load_null_scheme_with_engine_transitions(
+            r#"[
+                { "activation": "0x0a", "engine": { "null": { "params": {} } } },
+                { "activation": "0x0a", "engine": { "solo": { "params": {} } } }
+            ]"#,
+        );
+        assert!(result.is_err());
+
+        let result = load_null_scheme_with_engine_transitions(
+            r#"[{ "activation": "0x00", "engine": { "null": { "params": {} } } }]"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn election_without_max_num_of_validators_is_rejected() {
+        assert!(load_tendermint_scheme(r#""termLength": 10"#).is_err());
+        assert!(load_tendermint_scheme(r#""termLength": 10, "maxNumOfValidators": 0"#).is_err());
+        assert!(load_tendermint_scheme(r#""termLength": 10, "maxNumOfValidators": 3"#).is_ok());
+    }
+
+    #[test]
+    fn downtime_tracking_without_election_is_rejected() {
+        assert!(load_tendermint_scheme(r#""downtimeWindow": 10, "maxMissedBlocks": 3"#).is_err());
+        assert!(load_tendermint_scheme(r#""termLength": 10, "maxNumOfValidators": 3, "downtimeWindow": 10"#).is_err());
+        assert!(load_tendermint_scheme(
+            r#""termLength": 10, "maxNumOfValidators": 3, "downtimeWindow": 10, "maxMissedBlocks": 3"#
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn action_handlers_in_scheme_are_enabled() {
+        let scheme = load_null_scheme_with_action_handlers(r#"[{ "name": "hit", "handlerId": 5 }]"#).unwrap();
+        let handlers = scheme.engine.action_handlers();
+        assert_eq!(1, handlers.len());
+        assert_eq!("hit", handlers[0].name());
+        assert_eq!(5, handlers[0].handler_id());
+    }
+
+    #[test]
+    fn governance_in_scheme_is_enabled() {
+        let scheme = load_null_scheme_with_action_handlers(
+            r#"[{ "name": "governance", "handlerId": 4, "params": { "voters": ["tccq8qlwpt7xcs9lec3c8tyt3kqxlgsus8q4qp3m6ft"] } }]"#,
+        )
+        .unwrap();
+        let handlers = scheme.engine.action_handlers();
+        assert_eq!(1, handlers.len());
+        assert_eq!("governance", handlers[0].name());
+        assert_eq!(4, handlers[0].handler_id());
+        assert_eq!(Some(4), scheme.engine.machine().governance_handler_id());
+    }
+
+    #[test]
+    fn stake_weighted_governance_without_stakes_is_rejected() {
+        assert!(load_null_scheme_with_action_handlers(r#"[{ "name": "governance", "handlerId": 4 }]"#).is_err());
+    }
+
+    #[test]
+    fn duplicated_action_handlers_are_rejected() {
+        let result = load_null_scheme_with_action_handlers(
+            r#"[{ "name": "hit", "handlerId": 5 }, { "name": "hit", "handlerId": 5 }]"#,
+        );
+        assert!(result.is_err());
+    }
 }
@@ -19,10 +19,16 @@ use std::sync::Arc;
 
 use cio::{IoContext, IoHandler, IoHandlerResult, IoService};
 use cnetwork::NodeId;
+use cstate::{find_missing_nodes, StateDB};
+use ctypes::BlockNumber;
+use journaldb::{self, Algorithm};
+use kvdb::{DBTransaction, KeyValueDB};
 use kvdb_rocksdb::{Database, DatabaseConfig};
-use primitives::Bytes;
+use primitives::{Bytes, H256};
 
-use crate::client::{Client, ClientConfig};
+use crate::blockchain::{BlockChain, BlockChainConfig, HeaderProvider, Inconsistency};
+use crate::client::{Client, ClientConfig, Error as ClientError};
+use crate::db::COL_STATE;
 use crate::error::Error;
 use crate::miner::Miner;
 use crate::scheme::Scheme;
@@ -42,16 +48,7 @@ impl ClientService {
     ) -> Result<ClientService, Error> {
         let io_service = IoService::<ClientIoMessage>::start("Client")?;
 
-        let mut db_config = DatabaseConfig::with_columns(crate::db::NUM_COLUMNS);
-
-        db_config.memory_budget = config.db_cache_size;
-        db_config.compaction = config.db_compaction.compaction_profile(client_path);
-        db_config.wal = config.db_wal;
-
-        let db = Arc::new(
-            Database::open(&db_config, &client_path.to_str().expect("DB path could not be converted to string."))
-                .map_err(::client::Error::Database)?,
-        );
+        let db = open_database(config, client_path)?;
 
         let client = Client::try_new(config, &scheme, db, miner, io_service.channel())?;
 
@@ -73,6 +70,101 @@ impl ClientService {
     }
 }
 
+fn open_database(config: &ClientConfig, client_path: &Path) -> Result<Arc<KeyValueDB>, Error> {
+    let mut db_config = DatabaseConfig::with_columns(crate::db::NUM_COLUMNS);
+
+    db_config.memory_budget = config.db_cache_size;
+    db_config.compaction = config.db_compaction.compaction_profile(client_path);
+    db_config.wal = config.db_wal;
+
+    Ok(Arc::new(
+        Database::open(&db_config, &client_path.to_str().expect("DB path could not be converted to string."))
+            .map_err(ClientError::Database)?,
+    ))
+}
+
+/// Opens the database of the existing chain without initializing it,
+/// because `BlockChain::new` writes the genesis block into an uninitialized database.
+fn open_existing_chain(
+    config: &ClientConfig,
+    scheme: &Scheme,
+    client_path: &Path,
+) -> Result<(Arc<KeyValueDB>, BlockChain), Error> {
+    if !client_path.exists() {
+        return Err(ClientError::UninitializedDatabase.into())
+    }
+    let db = open_database(config, client_path)?;
+    if !BlockChain::is_initialized(&scheme.genesis_block(), &*db) {
+        return Err(ClientError::UninitializedDatabase.into())
+    }
+    let chain = open_chain(config, scheme, Arc::clone(&db));
+    Ok((db, chain))
+}
+
+fn open_chain(config: &ClientConfig, scheme: &Scheme, db: Arc<KeyValueDB>) -> BlockChain {
+    let chain_config = BlockChainConfig {
+        index_addresses: config.index_addresses,
+        history: config.history,
+    };
+    BlockChain::new(&scheme.genesis_block(), db, &chain_config)
+}
+
+fn check_state(chain: &BlockChain, db: Arc<KeyValueDB>, pruning: Algorithm, hash: &H256) -> Option<Inconsistency> {
+    let state_root = chain.block_header_data(hash)?.state_root();
+    let state_db = StateDB::new(journaldb::new(db, pruning, COL_STATE));
+    let nodes = find_missing_nodes(&state_db, &state_root);
+    if nodes.is_empty() {
+        None
+    } else {
+        Some(Inconsistency::MissingStateNodes {
+            block_hash: *hash,
+            nodes,
+        })
+    }
+}
+
+/// Checks the canonical chain and the state at the best block without starting the client.
+/// The database is not modified.
+pub fn check_database(config: &ClientConfig, scheme: &Scheme, client_path: &Path) -> Result<Vec<Inconsistency>, Error> {
+    let (db, chain) = open_existing_chain(config, scheme, client_path)?;
+
+    let mut inconsistencies = chain.check_consistency();
+    inconsistencies.extend(check_state(&chain, db, config.pruning, &chain.best_block_hash()));
+    Ok(inconsistencies)
+}
+
+/// Rewinds the best block to the last block before the inconsistencies, whose state is fully present.
+/// Returns the new best block, or `None` if the database is consistent.
+pub fn repair_database(
+    config: &ClientConfig,
+    scheme: &Scheme,
+    client_path: &Path,
+) -> Result<Option<(BlockNumber, H256)>, Error> {
+    let (db, chain) = open_existing_chain(config, scheme, client_path)?;
+
+    let best_block_hash = chain.best_block_hash();
+    let mut target = match chain.check_consistency().pop() {
+        Some(lowest) => {
+            let parent = chain.parent_hash_of(lowest.block_hash()).filter(|parent| chain.is_known_header(parent));
+            parent.ok_or_else(|| ClientError::Unrepairable(lowest))?
+        }
+        None => best_block_hash,
+    };
+    while let Some(inconsistency) = check_state(&chain, Arc::clone(&db), config.pruning, &target) {
+        target = chain.parent_hash_of(&target).ok_or_else(|| ClientError::Unrepairable(inconsistency))?;
+    }
+    if target == best_block_hash {
+        return Ok(None)
+    }
+
+    let number = chain.block_number(&target).expect("The target of the rewind exists");
+    let mut batch = DBTransaction::new();
+    chain.rewind(&mut batch, &target);
+    scheme.engine.rewind_consensus_state(&*db, &mut batch, number);
+    db.write(batch).map_err(ClientError::Database)?;
+    Ok(Some((number, target)))
+}
+
 /// Message type for external and internal events
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum ClientIoMessage {
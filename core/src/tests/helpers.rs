@@ -14,9 +14,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use cstate::StateDB;
+use ckey::Address;
+use cstate::{ActionHandler, ActionHandlerResult, StateDB, TopLevelState, TopState};
+use ctypes::invoice::Invoice;
+use ctypes::machine::Header as MachineHeader;
 use primitives::{Bytes, H256, U256};
-use rlp::{self, RlpStream};
+use rlp::{self, DecoderError, RlpStream};
 
 use crate::header::Header;
 use crate::scheme::Scheme;
@@ -60,3 +63,55 @@ pub fn get_good_dummy_block_hash() -> (H256, Bytes) {
 pub fn get_temp_state_db() -> StateDB {
     StateDB::new_with_memorydb()
 }
+
+/// Accepts the non-empty payloads and charges 10 for each byte of them.
+/// If `closing_address` is given, it issues the CCC as much as the block number to it when a block is closed.
+pub struct TestActionHandler {
+    pub handler_id: u64,
+    pub closing_address: Option<Address>,
+}
+
+impl TestActionHandler {
+    pub fn new(handler_id: u64) -> Self {
+        Self {
+            handler_id,
+            closing_address: None,
+        }
+    }
+}
+
+impl ActionHandler for TestActionHandler {
+    fn name(&self) -> &'static str {
+        "test"
+    }
+
+    fn handler_id(&self) -> u64 {
+        self.handler_id
+    }
+
+    fn init(&self, _state: &mut TopLevelState) -> ActionHandlerResult<()> {
+        Ok(())
+    }
+
+    fn execute(&self, _bytes: &[u8], _state: &mut TopLevelState, _sender: &Address) -> ActionHandlerResult<Invoice> {
+        Ok(Invoice::Success)
+    }
+
+    fn verify(&self, bytes: &[u8]) -> ActionHandlerResult<()> {
+        if bytes.is_empty() {
+            return Err(DecoderError::RlpIsTooShort.into())
+        }
+        Ok(())
+    }
+
+    fn min_cost(&self, bytes: &[u8]) -> u64 {
+        10 * bytes.len() as u64
+    }
+
+    fn on_close_block(&self, state: &mut TopLevelState, header: &MachineHeader) -> ActionHandlerResult<()> {
+        if let Some(address) = self.closing_address {
+            state.issue_ccc(&address, header.number())?;
+        }
+        Ok(())
+    }
+}
@@ -24,12 +24,14 @@ use primitives::{Bytes, H256};
 use rlp::UntrustedRlp;
 
 use crate::blockchain::BlockProvider;
-use crate::client::{BlockInfo, TransactionInfo};
+use crate::client::{BlockInfo, EngineInfo, TransactionInfo};
 use crate::consensus::CodeChainEngine;
 use crate::error::{BlockError, Error};
 use crate::header::Header;
+use crate::scheme::CommonParams;
 use crate::transaction::{SignedTransaction, UnverifiedTransaction};
 use crate::views::BlockView;
+use crate::BlockId;
 
 /// Preprocessed block data gathered in `verify_block_unordered` call
 pub struct PreverifiedBlock {
@@ -50,17 +52,40 @@ impl HeapSizeOf for PreverifiedBlock {
 }
 
 /// Phase 1 quick block verification. Only does checks that are cheap. Operates on a single block
+/// The checks which depend on the common params are done in phase 3, because the params can be changed by the parent.
 pub fn verify_block_basic(header: &Header, bytes: &[u8], engine: &CodeChainEngine) -> Result<(), Error> {
     verify_header_params(&header, engine)?;
     engine.verify_block_basic(&header)?;
 
-    let body_rlp = UntrustedRlp::new(bytes).at(1)?;
-    if body_rlp.as_raw().len() > engine.params().max_body_size {
+    for t in UntrustedRlp::new(bytes).at(1)?.iter() {
+        t.as_val::<UnverifiedTransaction>()?;
+    }
+    Ok(())
+}
+
+/// Check the block against the common params active at the block.
+fn verify_block_with_params(
+    block: &[u8],
+    header: &Header,
+    engine: &CodeChainEngine,
+    common_params: &CommonParams,
+) -> Result<(), Error> {
+    let max_extra_data_size = common_params.max_extra_data_size;
+    if header.number() != 0 && header.extra_data().len() > max_extra_data_size {
+        return Err(From::from(BlockError::ExtraDataOutOfBounds(OutOfBounds {
+            min: None,
+            max: Some(max_extra_data_size),
+            found: header.extra_data().len(),
+        })))
+    }
+
+    let body_rlp = UntrustedRlp::new(block).at(1)?;
+    if body_rlp.as_raw().len() > common_params.max_body_size {
         return Err(BlockError::BodySizeIsTooBig.into())
     }
 
     for t in body_rlp.iter().map(|rlp| rlp.as_val::<UnverifiedTransaction>()) {
-        engine.verify_transaction_basic(&t?, &header)?;
+        engine.verify_transaction_basic(&t?, common_params, &header)?;
     }
     Ok(())
 }
@@ -82,15 +107,6 @@ pub fn verify_header_params(header: &Header, engine: &CodeChainEngine) -> Result
             found: header.number(),
         })))
     }
-    let max_extra_data_size = engine.max_extra_data_size();
-    if header.number() != 0 && header.extra_data().len() > max_extra_data_size {
-        return Err(From::from(BlockError::ExtraDataOutOfBounds(OutOfBounds {
-            min: None,
-            max: Some(max_extra_data_size),
-            found: header.extra_data().len(),
-        })))
-    }
-
     const ACCEPTABLE_DRIFT_SECS: u64 = 15;
     let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
     let max_time = now.as_secs() + ACCEPTABLE_DRIFT_SECS;
@@ -163,7 +179,7 @@ pub fn verify_block_unordered(
 }
 
 /// Parameters for full verification of block family
-pub struct FullFamilyParams<'a, C: BlockInfo + TransactionInfo + 'a> {
+pub struct FullFamilyParams<'a, C: BlockInfo + TransactionInfo + EngineInfo + 'a> {
     /// Serialized block bytes
     pub block_bytes: &'a [u8],
 
@@ -178,7 +194,7 @@ pub struct FullFamilyParams<'a, C: BlockInfo + TransactionInfo + 'a> {
 }
 
 /// Phase 3 verification. Check block information against parent and uncles.
-pub fn verify_block_family<C: BlockInfo + TransactionInfo>(
+pub fn verify_block_family<C: BlockInfo + TransactionInfo + EngineInfo>(
     block: &[u8],
     header: &Header,
     parent: &Header,
@@ -195,6 +211,12 @@ pub fn verify_block_family<C: BlockInfo + TransactionInfo>(
         None => return Ok(()),
     };
 
+    let common_params = params
+        .client
+        .common_params(BlockId::Hash(parent.hash()))
+        .ok_or_else(|| BlockError::UnknownParent(parent.hash()))?;
+    verify_block_with_params(block, header, engine, &common_params)?;
+
     for tx in params.transactions {
         engine.machine().verify_transaction(tx, header, params.client, true)?;
     }
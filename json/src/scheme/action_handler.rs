@@ -0,0 +1,144 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::PlatformAddress;
+
+use crate::uint::Uint;
+
+/// Custom action handler deserialization.
+/// The `name` field selects the handler.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "name")]
+pub enum ActionHandler {
+    #[serde(rename = "hit")]
+    Hit(HitHandler),
+    #[serde(rename = "governance")]
+    Governance(GovernanceHandler),
+}
+
+impl ActionHandler {
+    pub fn handler_id(&self) -> u64 {
+        match self {
+            ActionHandler::Hit(hit) => hit.handler_id.into(),
+            ActionHandler::Governance(governance) => governance.handler_id.into(),
+        }
+    }
+}
+
+/// Hit handler deserialization.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HitHandler {
+    pub handler_id: Uint,
+    /// Genesis params.
+    pub params: Option<HitParams>,
+}
+
+/// Hit handler params deserialization.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HitParams {
+    /// The value of the counter at genesis.
+    pub initial_counter: Option<Uint>,
+}
+
+/// Governance handler deserialization.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GovernanceHandler {
+    pub handler_id: Uint,
+    pub params: Option<GovernanceParams>,
+}
+
+/// Governance handler params deserialization.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GovernanceParams {
+    /// The accounts which vote on the common params.
+    /// The stakeholders vote with their stakes if it's omitted.
+    pub voters: Option<Vec<PlatformAddress>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use primitives::U256;
+    use serde_json;
+
+    use super::*;
+
+    #[test]
+    fn action_handler_deserialization() {
+        let s = r#"[
+            {
+                "name": "hit",
+                "handlerId": 1
+            },
+            {
+                "name": "hit",
+                "handlerId": "0x03",
+                "params": {
+                    "initialCounter": 10
+                }
+            }
+        ]"#;
+
+        let deserialized: Vec<ActionHandler> = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized, vec![
+            ActionHandler::Hit(HitHandler {
+                handler_id: Uint(U256::from(1)),
+                params: None,
+            }),
+            ActionHandler::Hit(HitHandler {
+                handler_id: Uint(U256::from(3)),
+                params: Some(HitParams {
+                    initial_counter: Some(Uint(U256::from(10))),
+                }),
+            }),
+        ]);
+        assert_eq!(3, deserialized[1].handler_id());
+    }
+
+    #[test]
+    fn governance_handler_deserialization() {
+        let s = r#"[
+            {
+                "name": "governance",
+                "handlerId": 4,
+                "params": {
+                    "voters": ["tccq8txq9uafdg8y2de9m2tdkhsfsj3m9nluq94hyan"]
+                }
+            }
+        ]"#;
+
+        let deserialized: Vec<ActionHandler> = serde_json::from_str(s).unwrap();
+        let voter = PlatformAddress::from_str("tccq8txq9uafdg8y2de9m2tdkhsfsj3m9nluq94hyan").unwrap();
+        assert_eq!(deserialized, vec![ActionHandler::Governance(GovernanceHandler {
+            handler_id: Uint(U256::from(4)),
+            params: Some(GovernanceParams {
+                voters: Some(vec![voter]),
+            }),
+        })]);
+        assert_eq!(4, deserialized[0].handler_id());
+    }
+
+    #[test]
+    fn unknown_action_handler_is_rejected() {
+        let s = r#"[{ "name": "unknown", "handlerId": 1 }]"#;
+        assert!(serde_json::from_str::<Vec<ActionHandler>>(s).is_err());
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::{DifficultyAlgorithm, RewardSchedule};
 use crate::uint::Uint;
 
 #[derive(Debug, PartialEq, Deserialize)]
@@ -21,7 +22,13 @@ use crate::uint::Uint;
 pub struct BlakePoWParams {
     /// Block reward.
     pub block_reward: Option<Uint>,
+    /// How the block reward changes. It's constant if not given.
+    pub reward_schedule: Option<RewardSchedule>,
+    /// The per mille of the transaction fees which are burned instead of being paid.
+    pub fee_burn_ratio: Option<Uint>,
     pub min_score: Option<Uint>,
+    /// How the score is adjusted. The homestead rule is used if not given.
+    pub difficulty_algorithm: Option<DifficultyAlgorithm>,
     pub block_interval: Option<Uint>,
     pub recommended_confirmation: Option<Uint>,
 }
@@ -46,7 +53,8 @@ mod tests {
                 "blockReward": "0x0d",
                 "minScore" : "0x020000",
                 "blockInterval" : "120",
-                "recommendedConfirmation" : 15
+                "recommendedConfirmation" : 15,
+                "difficultyAlgorithm": { "lwma": { "window": 30 } }
             }
         }"#;
 
@@ -55,5 +63,11 @@ mod tests {
         assert_eq!(deserialized.params.min_score, Some(Uint(U256::from(0x0002_0000))));
         assert_eq!(deserialized.params.block_interval, Some(Uint(U256::from(120))));
         assert_eq!(Some(Uint(15.into())), deserialized.params.recommended_confirmation);
+        assert_eq!(
+            Some(DifficultyAlgorithm::Lwma {
+                window: Uint(30.into())
+            }),
+            deserialized.params.difficulty_algorithm
+        );
     }
 }
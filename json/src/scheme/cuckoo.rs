@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::{DifficultyAlgorithm, RewardSchedule};
 use crate::uint::Uint;
 
 #[derive(Debug, PartialEq, Deserialize)]
@@ -21,8 +22,14 @@ use crate::uint::Uint;
 pub struct CuckooParams {
     /// Block reward.
     pub block_reward: Option<Uint>,
+    /// How the block reward changes. It's constant if not given.
+    pub reward_schedule: Option<RewardSchedule>,
+    /// The per mille of the transaction fees which are burned instead of being paid.
+    pub fee_burn_ratio: Option<Uint>,
     pub block_interval: Option<Uint>,
     pub min_score: Option<Uint>,
+    /// How the score is adjusted. The homestead rule is used if not given.
+    pub difficulty_algorithm: Option<DifficultyAlgorithm>,
     pub max_vertex: Option<Uint>,
     pub max_edge: Option<Uint>,
     pub cycle_length: Option<Uint>,
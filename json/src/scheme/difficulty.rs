@@ -0,0 +1,53 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::uint::Uint;
+
+/// How the score of a PoW block is adjusted by the recent block times.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DifficultyAlgorithm {
+    /// The score of the parent changes by 1/2048 of it for each block interval.
+    Homestead,
+    /// The linearly weighted moving average of the recent `window` blocks.
+    Lwma {
+        window: Uint,
+    },
+    /// The score is always `minScore`.
+    Fixed,
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::U256;
+    use serde_json;
+
+    use super::DifficultyAlgorithm;
+    use crate::uint::Uint;
+
+    #[test]
+    fn difficulty_algorithm_deserialization() {
+        let s = r#"["homestead", "fixed", { "lwma": { "window": 60 } }]"#;
+        let deserialized: Vec<DifficultyAlgorithm> = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized, vec![
+            DifficultyAlgorithm::Homestead,
+            DifficultyAlgorithm::Fixed,
+            DifficultyAlgorithm::Lwma {
+                window: Uint(U256::from(60)),
+            },
+        ]);
+    }
+}
@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::{BlakePoW, Cuckoo, NullEngine, SimplePoA, Solo, Tendermint};
+use crate::uint::Uint;
 
 /// Engine deserialization.
 #[derive(Debug, PartialEq, Deserialize)]
@@ -29,11 +30,23 @@ pub enum Engine {
     BlakePoW(BlakePoW),
 }
 
+/// Engine which replaces the previous one from the activation block.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineTransition {
+    /// The number of the first block handled by the engine.
+    pub activation: Uint,
+    pub engine: Engine,
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Engine;
+    use primitives::U256;
     use serde_json;
 
+    use super::{Engine, EngineTransition};
+    use crate::uint::Uint;
+
     #[test]
     fn engine_deserialization() {
         let s = r#"{
@@ -121,4 +134,24 @@ mod tests {
             _ => panic!(),
         };
     }
+
+    #[test]
+    fn engine_transition_deserialization() {
+        let s = r#"{
+            "activation": "0x64",
+            "engine": {
+                "blakePoW": {
+                    "params": {
+                        "blockReward": "0x0d"
+                    }
+                }
+            }
+        }"#;
+        let deserialized: EngineTransition = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.activation, Uint(U256::from(100)));
+        match deserialized.engine {
+            Engine::BlakePoW(_) => {}
+            _ => panic!(),
+        };
+    }
 }
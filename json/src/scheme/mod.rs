@@ -15,12 +15,15 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod account;
+mod action_handler;
 mod blake_pow;
 mod cuckoo;
+mod difficulty;
 mod engine;
 mod genesis;
 mod null_engine;
 mod params;
+mod reward;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod scheme;
 mod seal;
@@ -31,12 +34,15 @@ mod state;
 mod tendermint;
 
 pub use self::account::Account;
+pub use self::action_handler::{ActionHandler, GovernanceHandler, GovernanceParams, HitHandler, HitParams};
 pub use self::blake_pow::{BlakePoW, BlakePoWParams};
 pub use self::cuckoo::{Cuckoo, CuckooParams};
-pub use self::engine::Engine;
+pub use self::difficulty::DifficultyAlgorithm;
+pub use self::engine::{Engine, EngineTransition};
 pub use self::genesis::Genesis;
 pub use self::null_engine::{NullEngine, NullEngineParams};
 pub use self::params::Params;
+pub use self::reward::RewardSchedule;
 pub use self::scheme::Scheme;
 pub use self::seal::{Seal, TendermintSeal};
 pub use self::shard::Shard;
@@ -53,6 +53,9 @@ pub struct Params {
     pub max_body_size: Uint,
     /// Snapshot creation period in unit of block numbers.
     pub snapshot_period: Uint,
+    /// The block from which the CCC supply and the supplies of the asset schemes are tracked.
+    /// They are not tracked if it is omitted.
+    pub supply_tracking_activation: Option<Uint>,
 }
 
 #[cfg(test)]
@@ -86,7 +89,8 @@ mod tests {
             "minDecomposeAssetCost" : 23,
             "minUnwrapCccCost" : 24,
             "maxBodySize" : 4194304,
-            "snapshotPeriod": 16384
+            "snapshotPeriod": 16384,
+            "supplyTrackingActivation": 100
         }"#;
 
         let deserialized: Params = serde_json::from_str(s).unwrap();
@@ -111,5 +115,6 @@ mod tests {
         assert_eq!(deserialized.min_unwrap_ccc_cost, Uint(24.into()));
         assert_eq!(deserialized.max_body_size, Uint(4_194_304.into()));
         assert_eq!(deserialized.snapshot_period, Uint(16_384.into()));
+        assert_eq!(deserialized.supply_tracking_activation, Some(Uint(100.into())));
     }
 }
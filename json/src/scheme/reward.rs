@@ -0,0 +1,70 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::uint::Uint;
+
+/// How the block reward changes as the chain grows.
+/// The reward of the first period is the `blockReward` of the engine params.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RewardSchedule {
+    /// The reward is halved every `interval` blocks.
+    Halving {
+        interval: Uint,
+    },
+    /// The reward decreases by `rate` per mille every `interval` blocks.
+    Decay {
+        interval: Uint,
+        rate: Uint,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::U256;
+    use serde_json;
+
+    use super::RewardSchedule;
+    use crate::uint::Uint;
+
+    #[test]
+    fn halving_deserialization() {
+        let s = r#"{
+            "halving": {
+                "interval": "0x100"
+            }
+        }"#;
+        let deserialized: RewardSchedule = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized, RewardSchedule::Halving {
+            interval: Uint(U256::from(0x100)),
+        });
+    }
+
+    #[test]
+    fn decay_deserialization() {
+        let s = r#"{
+            "decay": {
+                "interval": 1000,
+                "rate": 50
+            }
+        }"#;
+        let deserialized: RewardSchedule = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized, RewardSchedule::Decay {
+            interval: Uint(U256::from(1000)),
+            rate: Uint(U256::from(50)),
+        });
+    }
+}
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{Accounts, Engine, Genesis, Params, Shards};
+use super::{Accounts, ActionHandler, Engine, EngineTransition, Genesis, Params, Shards};
 use serde_json;
 use serde_json::Error;
 use std::io::Read;
@@ -29,6 +29,8 @@ pub struct Scheme {
     pub data_dir: Option<String>,
     /// Engine.
     pub engine: Engine,
+    /// Engines which replace the genesis engine, ordered by their activation blocks.
+    pub engine_transitions: Option<Vec<EngineTransition>>,
     /// Scheme params.
     pub params: Params,
     /// Genesis header.
@@ -36,6 +38,8 @@ pub struct Scheme {
     /// Genesis state.
     pub accounts: Accounts,
     pub shards: Shards,
+    /// Custom action handlers.
+    pub action_handlers: Option<Vec<ActionHandler>>,
     /// Boot nodes.
     pub nodes: Option<Vec<String>>,
 }
@@ -122,9 +126,13 @@ mod tests {
                 "tccq8txq9uafdg8y2de9m2tdkhsfsj3m9nluq94hyan": { "balance": "1606938044258990275541962092341162602522202993782792835301376", "seq": "1048576" }
             },
             "shards": {
-            }
+            },
+            "actionHandlers": [
+                { "name": "hit", "handlerId": 1 }
+            ]
         }"#;
-        let _deserialized: Scheme = serde_json::from_str(s).unwrap();
+        let deserialized: Scheme = serde_json::from_str(s).unwrap();
+        assert_eq!(1, deserialized.action_handlers.unwrap().len());
         // TODO: validate all fields
     }
 }
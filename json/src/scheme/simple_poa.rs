@@ -16,6 +16,7 @@
 
 use ckey::Public;
 
+use super::RewardSchedule;
 use crate::uint::Uint;
 
 /// Authority params deserialization.
@@ -26,6 +27,14 @@ pub struct SimplePoAParams {
     pub validators: Vec<Public>,
     /// Block reward.
     pub block_reward: Option<Uint>,
+    /// How the block reward changes. It's constant if not given.
+    pub reward_schedule: Option<RewardSchedule>,
+    /// The per mille of the transaction fees which are burned instead of being paid.
+    pub fee_burn_ratio: Option<Uint>,
+    /// The number of blocks in an epoch.
+    /// The validators vote to change the validators at the end of each epoch.
+    /// The validators are fixed if it's not given.
+    pub epoch_length: Option<Uint>,
 }
 
 /// Authority engine deserialization.
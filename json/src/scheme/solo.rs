@@ -16,6 +16,7 @@
 
 use std::collections::HashMap;
 
+use super::RewardSchedule;
 use crate::uint::Uint;
 
 /// Solo params deserialization.
@@ -24,6 +25,10 @@ use crate::uint::Uint;
 pub struct SoloParams {
     /// Block reward.
     pub block_reward: Option<Uint>,
+    /// How the block reward changes. It's constant if not given.
+    pub reward_schedule: Option<RewardSchedule>,
+    /// The per mille of the transaction fees which are burned instead of being paid.
+    pub fee_burn_ratio: Option<Uint>,
     #[serde(flatten)]
     pub action_handlers: SoloActionHandlersParams,
 }
@@ -18,6 +18,7 @@ use std::collections::HashMap;
 
 use ckey::{PlatformAddress, Public};
 
+use super::RewardSchedule;
 use crate::uint::Uint;
 
 /// Tendermint params deserialization.
@@ -40,10 +41,35 @@ pub struct TendermintParams {
     pub timeout_precommit_delta: Option<Uint>,
     /// Commit step timeout in milliseconds.
     pub timeout_commit: Option<Uint>,
+    /// Timeout in milliseconds of the aggregation of the precommits.
+    /// The proposer seals the block with the signatures of the precommits after it.
+    pub timeout_aggregation: Option<Uint>,
     /// Reward per block.
     pub block_reward: Option<Uint>,
+    /// How the block reward changes. It's constant if not given.
+    pub reward_schedule: Option<RewardSchedule>,
+    /// The per mille of the transaction fees which are burned instead of being paid.
+    pub fee_burn_ratio: Option<Uint>,
     /// How much tokens are distributed at Genesis?
     pub genesis_stakes: Option<HashMap<PlatformAddress, u64>>,
+    /// The number of blocks in a term.
+    /// The validators are elected by the delegated stakes at the end of each term.
+    /// The validators are fixed if it's not given.
+    pub term_length: Option<Uint>,
+    /// The maximum number of the elected validators. It's required with `term_length`.
+    pub max_num_of_validators: Option<Uint>,
+    /// The number of blocks in which the missed precommits of the validators are counted.
+    /// It requires `term_length` and `max_missed_blocks`. The downtime is not tracked if it's not given.
+    pub downtime_window: Option<Uint>,
+    /// The validators which missed more precommits than this in a window are jailed.
+    /// It's required with `downtime_window`.
+    pub max_missed_blocks: Option<Uint>,
+    /// The height from which the proposer is picked in proportion to the voting power.
+    /// The validators take turns in order if it's not given.
+    pub weighted_proposer_activation: Option<Uint>,
+    /// The block number from which the proposer may seal a block with the precommits aggregated into a single
+    /// signature. The precommits are never aggregated if it's not given.
+    pub aggregated_seal_activation: Option<Uint>,
 }
 
 /// Tendermint engine deserialization.
@@ -72,5 +98,68 @@ mod tests {
         let deserialized: Tendermint = serde_json::from_str(s).unwrap();
         let vs = vec![Public::from_str("2a8a69439f2396c9a328289fdc3905d9736da9e14eb1a282cfd2c036cc21a17a5d05595160b7924e5ecf3f2628b440e601f3a531e92fa81571a70e6c695b2d08").unwrap()];
         assert_eq!(deserialized.params.validators, vs);
+        assert_eq!(deserialized.params.term_length, None);
+        assert_eq!(deserialized.params.weighted_proposer_activation, None);
+        assert_eq!(deserialized.params.aggregated_seal_activation, None);
+    }
+
+    #[test]
+    fn tendermint_with_election_deserialization() {
+        let s = r#"{
+            "params": {
+                "validators": ["0x2a8a69439f2396c9a328289fdc3905d9736da9e14eb1a282cfd2c036cc21a17a5d05595160b7924e5ecf3f2628b440e601f3a531e92fa81571a70e6c695b2d08"],
+                "termLength": 100,
+                "maxNumOfValidators": 30
+            }
+        }"#;
+
+        let deserialized: Tendermint = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.params.term_length, Some(100.into()));
+        assert_eq!(deserialized.params.max_num_of_validators, Some(30.into()));
+        assert_eq!(deserialized.params.downtime_window, None);
+    }
+
+    #[test]
+    fn tendermint_with_weighted_proposer_deserialization() {
+        let s = r#"{
+            "params": {
+                "validators": ["0x2a8a69439f2396c9a328289fdc3905d9736da9e14eb1a282cfd2c036cc21a17a5d05595160b7924e5ecf3f2628b440e601f3a531e92fa81571a70e6c695b2d08"],
+                "weightedProposerActivation": 500
+            }
+        }"#;
+
+        let deserialized: Tendermint = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.params.weighted_proposer_activation, Some(500.into()));
+    }
+
+    #[test]
+    fn tendermint_with_downtime_deserialization() {
+        let s = r#"{
+            "params": {
+                "validators": ["0x2a8a69439f2396c9a328289fdc3905d9736da9e14eb1a282cfd2c036cc21a17a5d05595160b7924e5ecf3f2628b440e601f3a531e92fa81571a70e6c695b2d08"],
+                "termLength": 100,
+                "downtimeWindow": 1000,
+                "maxMissedBlocks": 500
+            }
+        }"#;
+
+        let deserialized: Tendermint = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.params.downtime_window, Some(1000.into()));
+        assert_eq!(deserialized.params.max_missed_blocks, Some(500.into()));
+    }
+
+    #[test]
+    fn tendermint_with_aggregated_seal_deserialization() {
+        let s = r#"{
+            "params": {
+                "validators": ["0x2a8a69439f2396c9a328289fdc3905d9736da9e14eb1a282cfd2c036cc21a17a5d05595160b7924e5ecf3f2628b440e601f3a531e92fa81571a70e6c695b2d08"],
+                "timeoutAggregation": 300,
+                "aggregatedSealActivation": 1000
+            }
+        }"#;
+
+        let deserialized: Tendermint = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.params.timeout_aggregation, Some(300.into()));
+        assert_eq!(deserialized.params.aggregated_seal_activation, Some(1000.into()));
     }
 }
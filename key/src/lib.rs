@@ -59,7 +59,9 @@ pub use crate::platform_address::PlatformAddress;
 pub use crate::private::Private;
 pub use crate::random::Random;
 pub use crate::schnorr::{
-    recover_schnorr, sign_schnorr, verify_schnorr, verify_schnorr_address, SchnorrSignature, SCHNORR_SIGNATURE_LENGTH,
+    aggregate_schnorr_publics, combine_publics, combine_schnorr_signatures, generate_schnorr_nonce_pair,
+    recover_schnorr, sign_schnorr, sign_schnorr_aggregated_partial, sign_schnorr_partial, verify_schnorr,
+    verify_schnorr_address, SchnorrSignature, SCHNORR_SIGNATURE_LENGTH,
 };
 use primitives::{H256, H512};
 pub use rustc_serialize::hex;
@@ -20,13 +20,14 @@ use std::hash::{Hash, Hasher};
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 
+use crypto::blake256;
 use primitives::H512;
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 use rustc_hex::{FromHex, ToHex};
 use secp256k1::{key, schnorr, Error as SecpError, Message as SecpMessage};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{public_to_address, Address, Error, Message, Private, Public, SECP256K1};
+use crate::{public_to_address, Address, Error, KeyPair, Message, Private, Public, SECP256K1};
 
 pub const SCHNORR_SIGNATURE_LENGTH: usize = 64;
 
@@ -192,13 +193,7 @@ pub fn sign_schnorr(private: &Private, message: &Message) -> Result<SchnorrSigna
 
 pub fn verify_schnorr(public: &Public, signature: &SchnorrSignature, message: &Message) -> Result<bool, Error> {
     let context = &SECP256K1;
-    let pdata: [u8; 65] = {
-        let mut temp = [4u8; 65];
-        temp[1..65].copy_from_slice(&**public);
-        temp
-    };
-
-    let publ = key::PublicKey::from_slice(context, &pdata)?;
+    let publ = to_secp_public(public)?;
     let sig = schnorr::Signature::deserialize(&signature.0);
     match context.verify_schnorr(&SecpMessage::from_slice(&message[..])?, &sig, &publ) {
         Ok(_) => Ok(true),
@@ -222,19 +217,145 @@ pub fn recover_schnorr(signature: &SchnorrSignature, message: &Message) -> Resul
 
     let sig = schnorr::Signature::deserialize(&signature.0);
     let pubkey = context.recover_schnorr(&SecpMessage::from_slice(&message[..])?, &sig)?;
-    let serialized = pubkey.serialize_vec(context, false);
+    Ok(from_secp_public(&pubkey))
+}
+
+/// Generates the secret and public nonce of a signer for the multi-party Schnorr signature.
+/// The secret nonce must not be revealed.
+pub fn generate_schnorr_nonce_pair(private: &Private, message: &Message) -> Result<(Private, Public), Error> {
+    let context = &SECP256K1;
+    let sec = key::SecretKey::from_slice(context, &private)?;
+    let (secnonce, pubnonce) = context.generate_schnorr_nonce_pair(&SecpMessage::from_slice(&message[..])?, &sec)?;
+    Ok((Private::from(secnonce), from_secp_public(&pubnonce)))
+}
+
+/// Signs the message as a part of the multi-party Schnorr signature.
+/// `other_nonces` is the sum of the public nonces of the other signers, which is made by `combine_publics`.
+pub fn sign_schnorr_partial(
+    private: &Private,
+    nonce: &Private,
+    other_nonces: &Public,
+    message: &Message,
+) -> Result<SchnorrSignature, Error> {
+    let context = &SECP256K1;
+    let sec = key::SecretKey::from_slice(context, &private)?;
+    let secnonce = key::SecretKey::from_slice(context, &nonce)?;
+    let others = to_secp_public(other_nonces)?;
+    let s = context.sign_schnorr_partial(&SecpMessage::from_slice(&message[..])?, &sec, &others, &secnonce)?;
+
+    let mut data = [0; 64];
+    data.copy_from_slice(&s.serialize()[0..64]);
+    Ok(SchnorrSignature(data))
+}
+
+/// Signs the message as a part of the signature which is verified with the key made by `aggregate_schnorr_publics`.
+/// `signers` are the public keys of all the signers in the order given to `aggregate_schnorr_publics`.
+pub fn sign_schnorr_aggregated_partial(
+    private: &Private,
+    signers: &[Public],
+    nonce: &Private,
+    other_nonces: &Public,
+    message: &Message,
+) -> Result<SchnorrSignature, Error> {
+    let context = &SECP256K1;
+    let public = *KeyPair::from_private(*private)?.public();
+    if !signers.contains(&public) {
+        return Err(Error::InvalidPublic)
+    }
+    let mut sec = key::SecretKey::from_slice(context, &private)?;
+    sec.mul_assign(context, &aggregation_coefficient(signers, &public)?)?;
+    sign_schnorr_partial(&Private::from(sec), nonce, other_nonces, message)
+}
+
+/// Combines the partial signatures of all the signers.
+/// The combined signature is verified by `verify_schnorr` with the sum of the public keys of the signers,
+/// or with the aggregated key if the partial signatures are made by `sign_schnorr_aggregated_partial`.
+pub fn combine_schnorr_signatures(signatures: &[SchnorrSignature]) -> Result<SchnorrSignature, Error> {
+    let context = &SECP256K1;
+    let partials: Vec<_> = signatures.iter().map(|signature| schnorr::Signature::deserialize(&signature.0)).collect();
+    let s = context.combine_schnorr_partial(&partials)?;
+
+    let mut data = [0; 64];
+    data.copy_from_slice(&s.serialize()[0..64]);
+    Ok(SchnorrSignature(data))
+}
+
+/// Sums up the public nonces.
+/// Don't use it for the public keys of the signers: a signer who chooses its key after seeing the others can make the
+/// sum its own key. Use `aggregate_schnorr_publics` instead.
+pub fn combine_publics(publics: &[Public]) -> Result<Public, Error> {
+    let context = &SECP256K1;
+    let (first, rest) = publics.split_first().ok_or(Error::InvalidPublic)?;
+    let mut sum = to_secp_public(first)?;
+    for public in rest {
+        sum.add_assign(context, &to_secp_public(public)?)?;
+    }
+    Ok(from_secp_public(&sum))
+}
+
+/// Aggregates the public keys of the signers into the key which verifies their combined signature.
+/// Each key is multiplied by a coefficient committing to all the keys before they are summed up, so no signer can
+/// cancel the keys of the others by choosing its own key after seeing them.
+/// The key of a single signer is not changed, so the signature of a single signer is its aggregated signature.
+pub fn aggregate_schnorr_publics(publics: &[Public]) -> Result<Public, Error> {
+    let context = &SECP256K1;
+    if publics.len() == 1 {
+        return Ok(publics[0])
+    }
+    let mut sum: Option<key::PublicKey> = None;
+    for public in publics {
+        let mut weighted = to_secp_public(public)?;
+        weighted.mul_assign(context, &aggregation_coefficient(publics, public)?)?;
+        match sum.as_mut() {
+            Some(sum) => sum.add_assign(context, &weighted)?,
+            None => sum = Some(weighted),
+        }
+    }
+    sum.map(|sum| from_secp_public(&sum)).ok_or(Error::InvalidPublic)
+}
+
+/// The coefficient of the key is the hash of the key and all the keys of the signers.
+fn aggregation_coefficient(publics: &[Public], public: &Public) -> Result<key::SecretKey, Error> {
+    if publics.len() == 1 {
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        return Ok(key::SecretKey::from_slice(&SECP256K1, &one)?)
+    }
+    let all: Vec<u8> = publics.iter().flat_map(|public| public.iter().cloned()).collect();
+    let mut input = blake256(&all).to_vec();
+    input.extend_from_slice(&**public);
+    Ok(key::SecretKey::from_slice(&SECP256K1, &blake256(&input))?)
+}
+
+fn to_secp_public(public: &Public) -> Result<key::PublicKey, Error> {
+    let pdata: [u8; 65] = {
+        let mut temp = [4u8; 65];
+        temp[1..65].copy_from_slice(&**public);
+        temp
+    };
+    Ok(key::PublicKey::from_slice(&SECP256K1, &pdata)?)
+}
+
+fn from_secp_public(pubkey: &key::PublicKey) -> Public {
+    let serialized = pubkey.serialize_vec(&SECP256K1, false);
 
     let mut public = Public::default();
     public.copy_from_slice(&serialized[1..65]);
-    Ok(public)
+    public
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
-    use super::{recover_schnorr, sign_schnorr, verify_schnorr, verify_schnorr_address, SchnorrSignature};
-    use crate::{Generator, Message, Random};
+    use secp256k1::key;
+
+    use super::{
+        aggregate_schnorr_publics, combine_publics, combine_schnorr_signatures, from_secp_public,
+        generate_schnorr_nonce_pair, recover_schnorr, sign_schnorr, sign_schnorr_aggregated_partial,
+        sign_schnorr_partial, to_secp_public, verify_schnorr, verify_schnorr_address, SchnorrSignature,
+    };
+    use crate::{Generator, KeyPair, Message, Random, SECP256K1};
 
     #[test]
     fn signature_to_and_from_str() {
@@ -269,4 +390,137 @@ mod tests {
         let signature = sign_schnorr(keypair.private(), &message).unwrap();
         assert!(verify_schnorr_address(&keypair.address(), &signature, &message).unwrap());
     }
+
+    fn sign_multi_party(keypairs: &[KeyPair], message: &Message) -> SchnorrSignature {
+        let nonces: Vec<_> =
+            keypairs.iter().map(|keypair| generate_schnorr_nonce_pair(keypair.private(), message).unwrap()).collect();
+        let partials: Vec<_> = keypairs
+            .iter()
+            .enumerate()
+            .map(|(i, keypair)| {
+                let other_nonces: Vec<_> =
+                    nonces.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, (_, public))| *public).collect();
+                let other_nonces = combine_publics(&other_nonces).unwrap();
+                sign_schnorr_partial(keypair.private(), &nonces[i].0, &other_nonces, message).unwrap()
+            })
+            .collect();
+        combine_schnorr_signatures(&partials).unwrap()
+    }
+
+    #[test]
+    fn combined_signature_is_verified_with_combined_public() {
+        let keypairs: Vec<_> = (0..4).map(|_| Random.generate().unwrap()).collect();
+        let publics: Vec<_> = keypairs.iter().map(|keypair| *keypair.public()).collect();
+        let message = Message::random();
+        let signature = sign_multi_party(&keypairs, &message);
+
+        let combined = combine_publics(&publics).unwrap();
+        assert!(verify_schnorr(&combined, &signature, &message).unwrap());
+        assert_eq!(combined, recover_schnorr(&signature, &message).unwrap());
+    }
+
+    #[test]
+    fn combined_signature_is_not_verified_without_all_signers() {
+        let keypairs: Vec<_> = (0..4).map(|_| Random.generate().unwrap()).collect();
+        let publics: Vec<_> = keypairs.iter().map(|keypair| *keypair.public()).collect();
+        let message = Message::random();
+        let signature = sign_multi_party(&keypairs, &message);
+
+        assert!(!verify_schnorr(&combine_publics(&publics[..3]).unwrap(), &signature, &message).unwrap());
+        for public in &publics {
+            assert!(!verify_schnorr(public, &signature, &message).unwrap());
+        }
+        assert!(!verify_schnorr(&combine_publics(&publics).unwrap(), &signature, &Message::random()).unwrap());
+    }
+
+    #[test]
+    fn combine_no_publics() {
+        assert!(combine_publics(&[]).is_err());
+    }
+
+    fn sign_aggregated(keypairs: &[KeyPair], message: &Message) -> SchnorrSignature {
+        let publics: Vec<_> = keypairs.iter().map(|keypair| *keypair.public()).collect();
+        let nonces: Vec<_> = keypairs.iter().map(|_| Random.generate().unwrap()).collect();
+        let partials: Vec<_> = keypairs
+            .iter()
+            .enumerate()
+            .map(|(i, keypair)| {
+                let other_nonces: Vec<_> =
+                    nonces.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, nonce)| *nonce.public()).collect();
+                let other_nonces = combine_publics(&other_nonces).unwrap();
+                sign_schnorr_aggregated_partial(
+                    keypair.private(),
+                    &publics,
+                    nonces[i].private(),
+                    &other_nonces,
+                    message,
+                )
+                .unwrap()
+            })
+            .collect();
+        combine_schnorr_signatures(&partials).unwrap()
+    }
+
+    #[test]
+    fn aggregated_signature_is_verified_with_aggregated_public() {
+        let keypairs: Vec<_> = (0..4).map(|_| Random.generate().unwrap()).collect();
+        let publics: Vec<_> = keypairs.iter().map(|keypair| *keypair.public()).collect();
+        let message = Message::random();
+        let signature = sign_aggregated(&keypairs, &message);
+
+        let aggregated = aggregate_schnorr_publics(&publics).unwrap();
+        assert!(verify_schnorr(&aggregated, &signature, &message).unwrap());
+        assert!(!verify_schnorr(&combine_publics(&publics).unwrap(), &signature, &message).unwrap());
+        assert!(!verify_schnorr(&aggregate_schnorr_publics(&publics[..3]).unwrap(), &signature, &message).unwrap());
+        assert!(!verify_schnorr(&aggregated, &signature, &Message::random()).unwrap());
+    }
+
+    #[test]
+    fn aggregated_public_of_a_single_signer_is_its_public() {
+        let keypair = Random.generate().unwrap();
+        let message = Message::random();
+        let aggregated = aggregate_schnorr_publics(&[*keypair.public()]).unwrap();
+        assert_eq!(*keypair.public(), aggregated);
+        assert!(verify_schnorr(&aggregated, &sign_schnorr(keypair.private(), &message).unwrap(), &message).unwrap());
+    }
+
+    #[test]
+    fn only_signers_sign_aggregated_partial() {
+        let keypairs: Vec<_> = (0..2).map(|_| Random.generate().unwrap()).collect();
+        let publics: Vec<_> = keypairs.iter().map(|keypair| *keypair.public()).collect();
+        let outsider = Random.generate().unwrap();
+        let nonce = Random.generate().unwrap();
+        let other_nonce = Random.generate().unwrap();
+        assert!(sign_schnorr_aggregated_partial(
+            outsider.private(),
+            &publics,
+            nonce.private(),
+            other_nonce.public(),
+            &Message::random()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rogue_key_does_not_control_aggregated_public() {
+        let honest = Random.generate().unwrap();
+        let attacker = Random.generate().unwrap();
+        // rogue = attacker - honest, so the plain sum of the keys is the attacker's key.
+        // The order of the curve minus one.
+        let minus_one: [u8; 32] = [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, 0xba, 0xae,
+            0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x40,
+        ];
+        let minus_one = key::SecretKey::from_slice(&SECP256K1, &minus_one).unwrap();
+        let mut rogue = to_secp_public(honest.public()).unwrap();
+        rogue.mul_assign(&SECP256K1, &minus_one).unwrap();
+        rogue.add_assign(&SECP256K1, &to_secp_public(attacker.public()).unwrap()).unwrap();
+        let publics = [*honest.public(), from_secp_public(&rogue)];
+
+        let message = Message::random();
+        let forged = sign_schnorr(attacker.private(), &message).unwrap();
+        assert_eq!(*attacker.public(), combine_publics(&publics).unwrap());
+        assert!(verify_schnorr(&combine_publics(&publics).unwrap(), &forged, &message).unwrap());
+        assert!(!verify_schnorr(&aggregate_schnorr_publics(&publics).unwrap(), &forged, &message).unwrap());
+    }
 }
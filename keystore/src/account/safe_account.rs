@@ -15,7 +15,10 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use ccrypto;
-use ckey::{sign, sign_schnorr, Address, KeyPair, Message, Password, Public, SchnorrSignature, Signature};
+use ckey::{
+    sign, sign_schnorr, sign_schnorr_aggregated_partial, Address, KeyPair, Message, Password, Private, Public,
+    SchnorrSignature, Signature,
+};
 
 use super::crypto::Crypto;
 use crate::account::Version;
@@ -115,6 +118,19 @@ impl SafeAccount {
         sign_schnorr(&secret.into(), message).map_err(From::from)
     }
 
+    /// Sign a message as a part of the aggregated Schnorr signature of the signers.
+    pub fn sign_schnorr_aggregated_partial(
+        &self,
+        password: &Password,
+        signers: &[Public],
+        nonce: &Private,
+        other_nonces: &Public,
+        message: &Message,
+    ) -> Result<SchnorrSignature, Error> {
+        let secret = self.crypto.secret(password)?;
+        sign_schnorr_aggregated_partial(&secret.into(), signers, nonce, other_nonces, message).map_err(From::from)
+    }
+
     /// Derive public key.
     pub fn public(&self, password: &Password) -> Result<Public, Error> {
         let secret = self.crypto.secret(password)?;
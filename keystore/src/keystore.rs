@@ -20,7 +20,7 @@ use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 use ccrypto::KEY_ITERATIONS;
-use ckey::{Address, KeyPair, Message, Password, Public, SchnorrSignature, Secret, Signature};
+use ckey::{Address, KeyPair, Message, Password, Private, Public, SchnorrSignature, Secret, Signature};
 use parking_lot::{Mutex, RwLock};
 
 use crate::account::SafeAccount;
@@ -110,6 +110,18 @@ impl SimpleSecretStore for KeyStore {
     ) -> Result<SchnorrSignature, Error> {
         self.get(account)?.sign_schnorr(password, message)
     }
+
+    fn sign_schnorr_aggregated_partial(
+        &self,
+        account: &Address,
+        password: &Password,
+        signers: &[Public],
+        nonce: &Private,
+        other_nonces: &Public,
+        message: &Message,
+    ) -> Result<SchnorrSignature, Error> {
+        self.get(account)?.sign_schnorr_aggregated_partial(password, signers, nonce, other_nonces, message)
+    }
 }
 
 impl SecretStore for KeyStore {
@@ -408,6 +420,24 @@ impl SimpleSecretStore for KeyMultiStore {
             None => Err(Error::InvalidPassword),
         }
     }
+
+    fn sign_schnorr_aggregated_partial(
+        &self,
+        account: &Address,
+        password: &Password,
+        signers: &[Public],
+        nonce: &Private,
+        other_nonces: &Public,
+        message: &Message,
+    ) -> Result<SchnorrSignature, Error> {
+        let accounts = self.get_matching(account, password)?;
+        match accounts.first() {
+            Some(ref account) => {
+                account.sign_schnorr_aggregated_partial(password, signers, nonce, other_nonces, message)
+            }
+            None => Err(Error::InvalidPassword),
+        }
+    }
 }
 
 #[cfg(test)]
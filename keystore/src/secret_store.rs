@@ -32,7 +32,7 @@
 
 use std::path::PathBuf;
 
-use ckey::{Address, Message, Password, Public, SchnorrSignature, Secret, Signature};
+use ckey::{Address, Message, Password, Private, Public, SchnorrSignature, Secret, Signature};
 
 use crate::json::{OpaqueKeyFile, Uuid};
 use crate::{Error, OpaqueSecret};
@@ -62,6 +62,16 @@ pub trait SimpleSecretStore: Send + Sync {
         password: &Password,
         message: &Message,
     ) -> Result<SchnorrSignature, Error>;
+    /// Sign a message with given account as a part of the aggregated Schnorr signature of the signers.
+    fn sign_schnorr_aggregated_partial(
+        &self,
+        account: &Address,
+        password: &Password,
+        signers: &[Public],
+        nonce: &Private,
+        other_nonces: &Public,
+        message: &Message,
+    ) -> Result<SchnorrSignature, Error>;
 }
 
 /// Secret Store API
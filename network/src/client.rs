@@ -150,6 +150,13 @@ impl Client {
         extensions.iter().map(|(name, extension)| (name.to_string(), extension.versions().to_vec())).collect()
     }
 
+    /// Returns the highest version of the extension which the peer also supports.
+    pub fn negotiate_version(&self, name: &str, versions: &[u64]) -> Option<u64> {
+        let extensions = self.extensions.read();
+        let extension = extensions.get(name)?;
+        extension.versions().iter().filter(|version| versions.contains(version)).max().cloned()
+    }
+
     define_method!(on_node_added; id, &NodeId; version, u64);
     define_broadcast_method!(on_node_removed; id, &NodeId);
 
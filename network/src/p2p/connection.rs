@@ -106,6 +106,10 @@ impl EstablishedConnection {
         self.enqueue(Message::Negotiation(NegotiationMessage::allowed(seq, version)));
     }
 
+    fn enqueue_negotiation_denied(&mut self, seq: Seq) {
+        self.enqueue(Message::Negotiation(NegotiationMessage::denied(seq)));
+    }
+
     fn enqueue_extension_message(&mut self, extension_name: String, need_encryption: bool, message: &[u8]) {
         const VERSION: u64 = 0;
         let message = if need_encryption {
@@ -666,6 +670,20 @@ impl Connection {
         }
     }
 
+    pub fn enqueue_negotiation_denied(&self, seq: u64) -> bool {
+        let mut state = self.state.write();
+        match &mut *state {
+            State::WaitAck(_) => false,
+            State::WaitSync(_) => false,
+            State::Established(connection) => {
+                connection.enqueue_negotiation_denied(seq);
+                true
+            }
+            State::Disconnecting(_) => false,
+            State::Intermediate => unreachable!(),
+        }
+    }
+
     pub fn enqueue_extension_message(&self, extension_name: &str, need_encryption: bool, data: &[u8]) -> bool {
         let mut state = self.state.write();
         match &mut *state {
@@ -261,6 +261,15 @@ impl Connections {
         }
     }
 
+    pub fn enqueue_negotiation_denied(&self, token: StreamToken, seq: u64) -> bool {
+        let connections = self.connections.read();
+        if let Some(connection) = connections.get(&token) {
+            connection.enqueue_negotiation_denied(seq)
+        } else {
+            false
+        }
+    }
+
     pub fn enqueue_extension_message(
         &self,
         token: StreamToken,
@@ -29,7 +29,7 @@ use token_generator::TokenGenerator;
 
 use super::connections::{ConnectionType, Connections, ReceivedMessage};
 use super::listener::Listener;
-use super::message::{HandshakeMessage, Message as NetworkMessage, Version};
+use super::message::{HandshakeMessage, Message as NetworkMessage};
 use super::stream::Stream;
 use super::NegotiationBody;
 use crate::addr::convert_to_node_id;
@@ -265,17 +265,24 @@ impl Handler {
                 match msg.body() {
                     NegotiationBody::Request {
                         ref extension_name,
-                        ..
+                        ref extension_versions,
                     } => {
                         let seq = msg.seq();
-                        // FIXME: version negotiation
-                        const VERSION: Version = 0;
-                        if self.connections.enqueue_negotiation_allowed(stream, seq, VERSION) {
-                            let node_id =
-                                self.connections.node_id(stream).ok_or_else(|| Error::InvalidStream(stream))?;
-                            client.on_node_added(&extension_name, &node_id, VERSION);
-                        } else {
-                            return Err(format!("Cannot enqueue negotiation message for {}", stream).into())
+                        match client.negotiate_version(&extension_name, &extension_versions) {
+                            Some(version) => {
+                                if self.connections.enqueue_negotiation_allowed(stream, seq, version) {
+                                    let node_id =
+                                        self.connections.node_id(stream).ok_or_else(|| Error::InvalidStream(stream))?;
+                                    client.on_node_added(&extension_name, &node_id, version);
+                                } else {
+                                    return Err(format!("Cannot enqueue negotiation message for {}", stream).into())
+                                }
+                            }
+                            None => {
+                                if !self.connections.enqueue_negotiation_denied(stream, seq) {
+                                    return Err(format!("Cannot enqueue negotiation message for {}", stream).into())
+                                }
+                            }
                         }
                     }
                     NegotiationBody::Allowed(extension_version) => {
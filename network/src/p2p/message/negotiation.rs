@@ -63,7 +63,6 @@ impl Message {
         }
     }
 
-    #[allow(dead_code)]
     pub fn denied(seq: Seq) -> Self {
         Self {
             version: 0,
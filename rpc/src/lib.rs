@@ -28,6 +28,7 @@ extern crate codechain_vm as cvm;
 extern crate jsonrpc_core;
 extern crate jsonrpc_http_server;
 extern crate jsonrpc_ipc_server;
+extern crate jsonrpc_pubsub;
 extern crate jsonrpc_ws_server;
 extern crate kvdb;
 extern crate kvdb_rocksdb as rocksdb;
@@ -18,10 +18,14 @@
 use jsonrpc_core;
 use jsonrpc_http_server::{self, Host, Server as HttpServer, ServerBuilder as HttpServerBuilder};
 use jsonrpc_ipc_server::{Server as IpcServer, ServerBuilder as IpcServerBuilder};
-use jsonrpc_ws_server::{Error as WsError, Server as WsServer, ServerBuilder as WsServerBuilder};
+use jsonrpc_pubsub::Session;
+use jsonrpc_ws_server::{Error as WsError, RequestContext, Server as WsServer, ServerBuilder as WsServerBuilder};
 use std::default::Default;
 use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
+
+use super::v1::Metadata;
 
 /// Start http server asynchronously and returns result with `Server` handle on success or an error.
 pub fn start_http<M: jsonrpc_core::Metadata>(
@@ -60,13 +64,16 @@ where
 }
 
 /// Start WS server and return `Server` handle.
-pub fn start_ws<M: jsonrpc_core::Metadata>(
+/// Each connection gets a session to receive the subscriptions.
+pub fn start_ws(
     addr: &SocketAddr,
-    handler: jsonrpc_core::MetaIoHandler<M>,
+    handler: jsonrpc_core::MetaIoHandler<Metadata>,
     max_connections: usize,
-) -> Result<WsServer, WsError>
-where
-    M: Default, {
-    // FIXME: Add Hosts, Origins and Session States
-    WsServerBuilder::new(handler).max_connections(max_connections).start(addr)
+) -> Result<WsServer, WsError> {
+    // FIXME: Add Hosts and Origins
+    WsServerBuilder::with_meta_extractor(handler, |context: &RequestContext| Metadata {
+        session: Some(Arc::new(Session::new(context.sender()))),
+    })
+    .max_connections(max_connections)
+    .start(addr)
 }
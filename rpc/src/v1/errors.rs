@@ -23,6 +23,7 @@ use ckeystore::Error as KeystoreError;
 use cnetwork::control::Error as NetworkControlError;
 use cstate::{ActionHandlerError, StateError};
 use ctypes::transaction::ParcelError;
+use ctypes::BlockNumber;
 use kvdb::Error as KVDBError;
 use rlp::DecoderError;
 
@@ -55,6 +56,9 @@ mod codes {
     pub const ASSET_TRANSACTION_ONLY_IN_EXECUTE_TRANSACITON: i64 = -32047;
     pub const STATE_NOT_EXIST: i64 = -32048;
     pub const ACTION_DATA_HANDLER_NOT_FOUND: i64 = -32049;
+    pub const ADDRESS_INDEX_DISABLED: i64 = -32050;
+    pub const TOO_MANY_ITEMS_REQUESTED: i64 = -32051;
+    pub const BLOCK_PRUNED: i64 = -32052;
     pub const UNKNOWN_ERROR: i64 = -32099;
 }
 
@@ -274,6 +278,14 @@ pub fn action_data_handler_not_found() -> Error {
     }
 }
 
+pub fn action_data_key_missing() -> Error {
+    Error {
+        code: ErrorCode::InvalidParams,
+        message: "The key of the custom action data is required with a handler id".into(),
+        data: None,
+    }
+}
+
 pub fn action_data_handler_error(error: ActionHandlerError) -> Error {
     Error {
         code: ErrorCode::ServerError(codes::UNKNOWN_ERROR),
@@ -282,6 +294,30 @@ pub fn action_data_handler_error(error: ActionHandlerError) -> Error {
     }
 }
 
+pub fn address_index_disabled() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::ADDRESS_INDEX_DISABLED),
+        message: "Transactions are not indexed by addresses. Run CodeChain with --index-addresses to enable it".into(),
+        data: None,
+    }
+}
+
+pub fn too_many_transactions_requested(max: usize) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::TOO_MANY_ITEMS_REQUESTED),
+        message: format!("Cannot request more than {} transactions at once", max),
+        data: None,
+    }
+}
+
+pub fn block_pruned(pruned_until: BlockNumber) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::BLOCK_PRUNED),
+        message: format!("The bodies and the invoices of the blocks up to #{} are pruned", pruned_until),
+        data: None,
+    }
+}
+
 /// Internal error signifying a logic error in code.
 /// Should not be used when function can just fail
 /// because of invalid parameters or incomplete node state.
@@ -18,7 +18,7 @@ use std::sync::Arc;
 
 use ccore::{
     AssetClient, BlockId, EngineInfo, ExecuteClient, MinerService, MiningBlockChainClient, RegularKey, RegularKeyOwner,
-    Shard, SignedTransaction, TextClient, UnverifiedTransaction,
+    Shard, SignedTransaction, Supply, TextClient, TransactionId, UnverifiedTransaction,
 };
 use cjson::bytes::Bytes;
 use cjson::uint::Uint;
@@ -36,9 +36,18 @@ use super::super::errors;
 use super::super::traits::Chain;
 use super::super::types::{Block, BlockNumberAndHash, Text, Transaction, UnsignedTransaction};
 
+const MAX_TRANSACTIONS_PER_PAGE: usize = 100;
+
 pub struct ChainClient<C, M>
 where
-    C: AssetClient + MiningBlockChainClient + Shard + RegularKey + RegularKeyOwner + ExecuteClient + EngineInfo,
+    C: AssetClient
+        + MiningBlockChainClient
+        + Shard
+        + Supply
+        + RegularKey
+        + RegularKeyOwner
+        + ExecuteClient
+        + EngineInfo,
     M: MinerService, {
     client: Arc<C>,
     miner: Arc<M>,
@@ -49,6 +58,7 @@ where
     C: AssetClient
         + MiningBlockChainClient
         + Shard
+        + Supply
         + RegularKey
         + RegularKeyOwner
         + ExecuteClient
@@ -62,6 +72,22 @@ where
             miner,
         }
     }
+
+    /// Returns the pruned error instead of `None` if the block is pruned.
+    fn unless_block_pruned<T>(&self, id: &BlockId, value: Option<T>) -> Result<Option<T>> {
+        if value.is_none() && self.client.is_pruned(id) {
+            return Err(errors::block_pruned(self.client.pruned_until()))
+        }
+        Ok(value)
+    }
+
+    /// Returns the pruned error instead of `None` if the block of the parcel is pruned.
+    fn unless_parcel_pruned<T>(&self, id: &TransactionId, value: Option<T>) -> Result<Option<T>> {
+        match self.client.transaction_block(id) {
+            Some(block_hash) => self.unless_block_pruned(&BlockId::Hash(block_hash), value),
+            None => Ok(value),
+        }
+    }
 }
 
 impl<C, M> Chain for ChainClient<C, M>
@@ -69,6 +95,7 @@ where
     C: AssetClient
         + MiningBlockChainClient
         + Shard
+        + Supply
         + RegularKey
         + RegularKeyOwner
         + ExecuteClient
@@ -103,11 +130,13 @@ where
     }
 
     fn get_transaction(&self, transaction_hash: H256) -> Result<Option<Transaction>> {
-        Ok(self.client.parcel(&transaction_hash.into()).map(|tx| tx.into()))
+        let id = transaction_hash.into();
+        self.unless_parcel_pruned(&id, self.client.parcel(&id).map(|tx| tx.into()))
     }
 
     fn get_invoice(&self, transaction_hash: H256) -> Result<Option<Invoice>> {
-        Ok(self.client.parcel_invoice(&transaction_hash.into()))
+        let id = transaction_hash.into();
+        self.unless_parcel_pruned(&id, self.client.parcel_invoice(&id))
     }
 
     fn get_transaction_by_tracker(&self, tracker: H256) -> Result<Option<Transaction>> {
@@ -118,6 +147,23 @@ where
         Ok(self.client.transaction_invoices(&tracker))
     }
 
+    fn get_transactions_by_address(
+        &self,
+        address: PlatformAddress,
+        skip: Option<usize>,
+        limit: Option<usize>,
+    ) -> Result<Vec<Transaction>> {
+        let address = address.try_address().map_err(errors::core)?;
+        let limit = limit.unwrap_or(MAX_TRANSACTIONS_PER_PAGE);
+        if limit > MAX_TRANSACTIONS_PER_PAGE {
+            return Err(errors::too_many_transactions_requested(MAX_TRANSACTIONS_PER_PAGE))
+        }
+        self.client
+            .transactions_by_address(address, skip.unwrap_or(0), limit)
+            .map(|transactions| transactions.into_iter().map(Into::into).collect())
+            .ok_or_else(errors::address_index_disabled)
+    }
+
     fn get_asset_scheme_by_tracker(
         &self,
         tracker: H256,
@@ -142,7 +188,7 @@ where
             .client
             .get_text(transaction_hash, block_id)
             .map_err(errors::transaction_state)?
-            .map(|text| Text::from_core(text, self.client.common_params().network_id)))
+            .map(|text| Text::from_core(text, self.client.network_id())))
     }
 
     fn get_asset(&self, transaction_hash: H256, index: usize, block_number: Option<u64>) -> Result<Option<OwnedAsset>> {
@@ -181,7 +227,7 @@ where
 
     fn get_regular_key_owner(&self, public: Public, block_number: Option<u64>) -> Result<Option<PlatformAddress>> {
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
-        let network_id = self.client.common_params().network_id;
+        let network_id = self.client.network_id();
         Ok(self
             .client
             .regular_key_owner(&public_to_address(&public), block_id.into())
@@ -202,6 +248,16 @@ where
         Ok(self.client.shard_root(shard_id, block_id.into()))
     }
 
+    fn get_ccc_supply(&self, block_number: Option<u64>) -> Result<Option<Uint>> {
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        Ok(self.client.ccc_supply(block_id.into()).map(Into::into))
+    }
+
+    fn get_wrapped_ccc_supply(&self, block_number: Option<u64>) -> Result<Option<Uint>> {
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        Ok(self.client.wrapped_ccc_supply(block_id.into()).map(Into::into))
+    }
+
     fn get_best_block_number(&self) -> Result<BlockNumber> {
         Ok(self.client.chain_info().best_block_number)
     }
@@ -219,17 +275,15 @@ where
     }
 
     fn get_block_by_number(&self, block_number: u64) -> Result<Option<Block>> {
-        Ok(self
-            .client
-            .block(&BlockId::Number(block_number))
-            .map(|block| Block::from_core(block.decode(), self.client.common_params().network_id)))
+        let id = BlockId::Number(block_number);
+        let block = self.client.block(&id).map(|block| Block::from_core(block.decode(), self.client.network_id()));
+        self.unless_block_pruned(&id, block)
     }
 
     fn get_block_by_hash(&self, block_hash: H256) -> Result<Option<Block>> {
-        Ok(self
-            .client
-            .block(&BlockId::Hash(block_hash))
-            .map(|block| Block::from_core(block.decode(), self.client.common_params().network_id)))
+        let id = BlockId::Hash(block_hash);
+        let block = self.client.block(&id).map(|block| Block::from_core(block.decode(), self.client.network_id()));
+        self.unless_block_pruned(&id, block)
     }
 
     fn get_pending_transactions(&self) -> Result<Vec<Transaction>> {
@@ -237,11 +291,11 @@ where
     }
 
     fn get_mining_reward(&self, block_number: u64) -> Result<Option<u64>> {
-        Ok(self.client.mining_reward(block_number))
+        self.unless_block_pruned(&BlockId::Number(block_number), self.client.mining_reward(block_number))
     }
 
     fn get_network_id(&self) -> Result<NetworkId> {
-        Ok(self.client.common_params().network_id)
+        Ok(self.client.network_id())
     }
 
     fn execute_transaction(&self, tx: UnsignedTransaction, sender: PlatformAddress) -> Result<Invoice> {
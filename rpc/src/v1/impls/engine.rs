@@ -16,15 +16,19 @@
 
 use std::sync::Arc;
 
-use ccore::{BlockId, EngineInfo, MinerService, StateInfo};
-use cjson::bytes::{Bytes, WithoutPrefix};
-use ckey::PlatformAddress;
+use ccore::{stake, BlockId, EngineInfo, MinerService, StateInfo};
+use cjson::bytes::Bytes;
+use ckey::{public_to_address, PlatformAddress};
 use cstate::FindActionHandler;
 
 use jsonrpc_core::Result;
 
 use super::super::errors;
 use super::super::traits::Engine;
+use super::super::types::{
+    CommitCertificate, ConsensusState, CustomActionData, CustomActionHandler, Delegator, Delegators, Liveness,
+    Prisoner, Validator,
+};
 
 pub struct EngineClient<C, M>
 where
@@ -61,7 +65,7 @@ where
         if author.is_zero() {
             Ok(None)
         } else {
-            let network_id = self.client.common_params().network_id;
+            let network_id = self.client.network_id();
             Ok(Some(PlatformAddress::new_v1(network_id, author)))
         }
     }
@@ -72,19 +76,114 @@ where
 
     fn get_custom_action_data(
         &self,
-        handler_id: u64,
-        key_fragment: Bytes,
+        handler_id: Option<u64>,
+        key_fragment: Option<Bytes>,
         block_number: Option<u64>,
-    ) -> Result<Option<WithoutPrefix<Bytes>>> {
+    ) -> Result<CustomActionData> {
+        let handler_id = match handler_id {
+            Some(handler_id) => handler_id,
+            None => {
+                return Ok(CustomActionData::Handlers(
+                    self.client
+                        .action_handlers()
+                        .iter()
+                        .map(|handler| CustomActionHandler {
+                            name: handler.name().to_string(),
+                            handler_id: handler.handler_id(),
+                        })
+                        .collect(),
+                ))
+            }
+        };
+        let key_fragment = key_fragment.ok_or_else(errors::action_data_key_missing)?;
         let handler =
             self.client.find_action_handler_for(handler_id).ok_or_else(errors::action_data_handler_not_found)?;
         let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
         let state = self.client.state_at(block_id).ok_or_else(errors::state_not_exist)?;
 
         match handler.query(&key_fragment, &state) {
-            Ok(Some(action_data)) => Ok(Some(Bytes::new(action_data).into_without_prefix())),
-            Ok(None) => Ok(None),
+            Ok(Some(action_data)) => Ok(CustomActionData::Data(Some(Bytes::new(action_data).into_without_prefix()))),
+            Ok(None) => Ok(CustomActionData::Data(None)),
             Err(e) => Err(errors::action_data_handler_error(e)),
         }
     }
+    fn get_delegators(&self, block_number: Option<u64>) -> Result<Vec<Delegators>> {
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        let state = self.client.state_at(block_id).ok_or_else(errors::state_not_exist)?;
+        let network_id = self.client.network_id();
+
+        let delegators = stake::get_delegators(&state).map_err(errors::action_data_handler_error)?;
+        Ok(delegators
+            .into_iter()
+            .map(|(delegatee, delegators)| Delegators {
+                delegatee: PlatformAddress::new_v1(network_id, delegatee),
+                delegators: delegators
+                    .into_iter()
+                    .map(|(address, quantity)| Delegator {
+                        address: PlatformAddress::new_v1(network_id, address),
+                        quantity,
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+
+    fn get_liveness(&self, block_number: Option<u64>) -> Result<Vec<Liveness>> {
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        let state = self.client.state_at(block_id).ok_or_else(errors::state_not_exist)?;
+        let network_id = self.client.network_id();
+
+        let liveness = stake::get_liveness(&state).map_err(errors::action_data_handler_error)?;
+        Ok(liveness
+            .into_iter()
+            .map(|(address, counter)| Liveness {
+                address: PlatformAddress::new_v1(network_id, address),
+                signed: counter.signed,
+                missed: counter.missed,
+            })
+            .collect())
+    }
+
+    fn get_jailed(&self, block_number: Option<u64>) -> Result<Vec<Prisoner>> {
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        let state = self.client.state_at(block_id).ok_or_else(errors::state_not_exist)?;
+        let network_id = self.client.network_id();
+
+        let jailed = stake::get_jailed(&state).map_err(errors::action_data_handler_error)?;
+        Ok(jailed
+            .into_iter()
+            .map(|(address, reason)| Prisoner {
+                address: PlatformAddress::new_v1(network_id, address),
+                reason: match reason {
+                    stake::JailReason::DoubleVote => "doubleVote",
+                    stake::JailReason::Downtime => "downtime",
+                }
+                .to_string(),
+            })
+            .collect())
+    }
+
+    fn get_commit_certificate(&self, block_number: u64) -> Result<Option<CommitCertificate>> {
+        Ok(self.client.commit_certificate(block_number).map(Into::into))
+    }
+
+    fn get_validators(&self, block_number: Option<u64>) -> Result<Option<Vec<Validator>>> {
+        let block_id = block_number.map(BlockId::Number).unwrap_or(BlockId::Latest);
+        let network_id = self.client.network_id();
+
+        Ok(self.client.validators(block_id).map(|validators| {
+            validators
+                .into_iter()
+                .map(|public| Validator {
+                    address: PlatformAddress::new_v1(network_id, public_to_address(&public)),
+                    public,
+                })
+                .collect()
+        }))
+    }
+
+    fn get_consensus_state(&self) -> Result<Option<ConsensusState>> {
+        let network_id = self.client.network_id();
+        Ok(self.client.consensus_state().map(|state| ConsensusState::from_core(state, network_id)))
+    }
 }
@@ -0,0 +1,102 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::Arc;
+use std::thread;
+
+use ccore::EngineInfo;
+use jsonrpc_core::futures::Future;
+use jsonrpc_core::Result;
+use jsonrpc_macros::pubsub::{Sink, Subscriber};
+use jsonrpc_pubsub::SubscriptionId;
+use parking_lot::Mutex;
+
+use super::super::traits::EngineSubscription;
+use super::super::types::ConsensusEvent;
+use super::super::Metadata;
+
+/// The number of events which wait for the subscription thread. The events above it are dropped.
+const MAX_PENDING_EVENTS: usize = 1024;
+
+pub struct EngineSubscriptionClient {
+    sinks: Arc<Mutex<HashMap<u64, Sink<ConsensusEvent>>>>,
+    next_id: AtomicUsize,
+}
+
+impl EngineSubscriptionClient {
+    pub fn new<C>(client: &C) -> Self
+    where
+        C: EngineInfo, {
+        let sinks: Arc<Mutex<HashMap<u64, Sink<ConsensusEvent>>>> = Default::default();
+        // The events are pushed in another thread not to block the consensus engine.
+        let (sender, receiver) = mpsc::sync_channel(MAX_PENDING_EVENTS);
+        let sender = Mutex::new(sender);
+        client.add_consensus_listener(Box::new(move |event| {
+            if let Err(TrySendError::Full(event)) = sender.lock().try_send(*event) {
+                cwarn!(RPC, "The engine subscribers are too slow. {:?} is dropped", event);
+            }
+        }));
+        let notified_sinks = Arc::clone(&sinks);
+        thread::Builder::new()
+            .name("engine subscription".to_string())
+            .spawn(move || {
+                for event in receiver {
+                    let event = ConsensusEvent::from(event);
+                    let notifications: Vec<_> =
+                        notified_sinks.lock().iter().map(|(id, sink)| (*id, sink.notify(Ok(event.clone())))).collect();
+                    // The notifications are sent without the lock, so a slow session doesn't block the others from
+                    // subscribing or unsubscribing.
+                    let closed: Vec<u64> = notifications
+                        .into_iter()
+                        .filter_map(|(id, notification)| notification.wait().err().map(|_| id))
+                        .collect();
+                    // The sinks of the closed sessions are dropped.
+                    if !closed.is_empty() {
+                        let mut sinks = notified_sinks.lock();
+                        for id in closed {
+                            sinks.remove(&id);
+                        }
+                    }
+                }
+            })
+            .expect("Cannot spawn the engine subscription thread");
+        Self {
+            sinks,
+            next_id: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl EngineSubscription for EngineSubscriptionClient {
+    type Metadata = Metadata;
+
+    fn subscribe(&self, _meta: Metadata, subscriber: Subscriber<ConsensusEvent>) {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) as u64;
+        if let Ok(sink) = subscriber.assign_id(SubscriptionId::Number(id)) {
+            self.sinks.lock().insert(id, sink);
+        }
+    }
+
+    fn unsubscribe(&self, id: SubscriptionId) -> Result<bool> {
+        match id {
+            SubscriptionId::Number(id) => Ok(self.sinks.lock().remove(&id).is_some()),
+            SubscriptionId::String(_) => Ok(false),
+        }
+    }
+}
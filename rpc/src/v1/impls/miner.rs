@@ -64,7 +64,8 @@ where
         self.miner
             .map_sealing_work(&*self.client, |b| {
                 let pow_hash = b.hash();
-                let target = self.client.score_to_target(b.block().header().score());
+                let header = b.block().header();
+                let target = self.client.score_to_target(header.score(), header.number());
 
                 Ok(Work {
                     pow_hash,
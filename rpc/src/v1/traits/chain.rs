@@ -48,6 +48,11 @@ build_rpc_trait! {
         # [rpc(name = "chain_getInvoicesByTracker")]
         fn get_invoices_by_tracker(&self, H256) -> Result<Vec<Invoice>>;
 
+        /// Gets transactions which involve the given account, starting from the most recent one.
+        # [rpc(name = "chain_getTransactionsByAddress")]
+        fn get_transactions_by_address(&self, PlatformAddress, Option<usize>, Option<usize>)
+            -> Result<Vec<Transaction>>;
+
         /// Gets asset scheme with given transaction tracker.
         # [rpc(name = "chain_getAssetSchemeByTracker")]
         fn get_asset_scheme_by_tracker(&self, H256, ShardId, Option<u64>) -> Result<Option<AssetScheme>>;
@@ -96,6 +101,14 @@ build_rpc_trait! {
         # [rpc(name = "chain_getShardRoot")]
         fn get_shard_root(&self, ShardId, Option<u64>) -> Result<Option<H256>>;
 
+        /// Gets the amount of CCC in the accounts and in the shards
+        # [rpc(name = "chain_getCCCSupply")]
+        fn get_ccc_supply(&self, Option<u64>) -> Result<Option<Uint>>;
+
+        /// Gets the amount of CCC wrapped in the shards
+        # [rpc(name = "chain_getWrappedCCCSupply")]
+        fn get_wrapped_ccc_supply(&self, Option<u64>) -> Result<Option<Uint>>;
+
         /// Gets number of best block.
         # [rpc(name = "chain_getBestBlockNumber")]
         fn get_best_block_number(&self) -> Result<BlockNumber>;
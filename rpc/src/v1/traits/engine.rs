@@ -14,11 +14,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use cjson::bytes::{Bytes, WithoutPrefix};
+use cjson::bytes::Bytes;
 use ckey::PlatformAddress;
 
 use jsonrpc_core::Result;
 
+use super::super::types::{
+    CommitCertificate, ConsensusState, CustomActionData, Delegators, Liveness, Prisoner, Validator,
+};
+
 build_rpc_trait! {
     pub trait Engine {
         /// Gets the reward of the given block number
@@ -34,7 +38,32 @@ build_rpc_trait! {
         fn get_recommended_confirmation(&self) -> Result<u32>;
 
         /// Gets custom action data for given custom action handler id and rlp encoded key.
+        /// Gets the custom action handlers enabled on the chain if the handler id is null.
         # [rpc(name = "engine_getCustomActionData")]
-        fn get_custom_action_data(&self, u64, Bytes, Option<u64>) -> Result<Option<WithoutPrefix<Bytes>>>;
+        fn get_custom_action_data(&self, Option<u64>, Option<Bytes>, Option<u64>) -> Result<CustomActionData>;
+
+        /// Gets the delegators of each validator in Tendermint stake.
+        # [rpc(name = "engine_getDelegators")]
+        fn get_delegators(&self, Option<u64>) -> Result<Vec<Delegators>>;
+
+        /// Gets the liveness counters of the validators in the current downtime window of Tendermint.
+        # [rpc(name = "engine_getLiveness")]
+        fn get_liveness(&self, Option<u64>) -> Result<Vec<Liveness>>;
+
+        /// Gets the jailed accounts in Tendermint stake.
+        # [rpc(name = "engine_getJailed")]
+        fn get_jailed(&self, Option<u64>) -> Result<Vec<Prisoner>>;
+
+        /// Gets the proof that the block is committed by Tendermint.
+        # [rpc(name = "engine_getCommitCertificate")]
+        fn get_commit_certificate(&self, u64) -> Result<Option<CommitCertificate>>;
+
+        /// Gets the validators of the child of the given block.
+        # [rpc(name = "engine_getValidators")]
+        fn get_validators(&self, Option<u64>) -> Result<Option<Vec<Validator>>>;
+
+        /// Gets the round which Tendermint is in.
+        # [rpc(name = "engine_getConsensusState")]
+        fn get_consensus_state(&self) -> Result<Option<ConsensusState>>;
     }
 }
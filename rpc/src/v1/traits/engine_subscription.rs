@@ -0,0 +1,37 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use jsonrpc_core::Result;
+use jsonrpc_macros::pubsub::Subscriber;
+use jsonrpc_pubsub::SubscriptionId;
+
+use super::super::types::ConsensusEvent;
+
+build_rpc_trait! {
+    pub trait EngineSubscription {
+        type Metadata;
+
+        # [pubsub(name = "engine_subscription")] {
+            /// Subscribes to the step transitions and the timeouts of Tendermint.
+            # [rpc(name = "engine_subscribe")]
+            fn subscribe(&self, Self::Metadata, Subscriber<ConsensusEvent>);
+
+            /// Unsubscribes from the existing subscription.
+            # [rpc(name = "engine_unsubscribe")]
+            fn unsubscribe(&self, SubscriptionId) -> Result<bool>;
+        }
+    }
+}
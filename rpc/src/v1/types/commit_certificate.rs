@@ -0,0 +1,64 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::CommitCertificate as CoreCommitCertificate;
+use cjson::bytes::Bytes;
+use ckey::{Public, SchnorrSignature};
+use primitives::H256;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Validator {
+    public: Public,
+    weight: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitCertificate {
+    block_hash: H256,
+    block_number: u64,
+    /// The RLP encoded header.
+    header: Bytes,
+    validators: Vec<Validator>,
+    view: u64,
+    precommit_bitset: Bytes,
+    precommits: Vec<SchnorrSignature>,
+    /// The nodes of the state trie proving the validators of the next block.
+    next_validators_proof: Vec<Bytes>,
+}
+
+impl From<CoreCommitCertificate> for CommitCertificate {
+    fn from(certificate: CoreCommitCertificate) -> Self {
+        CommitCertificate {
+            block_hash: certificate.header.hash(),
+            block_number: certificate.header.number(),
+            header: Bytes::new(::rlp::encode(&certificate.header).into_vec()),
+            validators: certificate
+                .validators
+                .into_iter()
+                .map(|(public, weight)| Validator {
+                    public,
+                    weight,
+                })
+                .collect(),
+            view: certificate.view as u64,
+            precommit_bitset: Bytes::new(certificate.precommit_bitset.as_slice().to_vec()),
+            precommits: certificate.precommits,
+            next_validators_proof: certificate.next_validators_proof.into_iter().map(Bytes::new).collect(),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::{
+    ConsensusEvent as CoreConsensusEvent, ConsensusState as CoreConsensusState, Step as CoreStep,
+    StepVotes as CoreStepVotes,
+};
+use cjson::bytes::Bytes;
+use ckey::{NetworkId, PlatformAddress};
+use primitives::H256;
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Step {
+    Propose,
+    Prevote,
+    Precommit,
+    Commit,
+}
+
+impl From<CoreStep> for Step {
+    fn from(step: CoreStep) -> Self {
+        match step {
+            CoreStep::Propose => Step::Propose,
+            CoreStep::Prevote => Step::Prevote,
+            CoreStep::Precommit => Step::Precommit,
+            CoreStep::Commit => Step::Commit,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewBlock {
+    view: u64,
+    block_hash: H256,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepVotes {
+    step: Step,
+    bitset: Bytes,
+    signers: Vec<PlatformAddress>,
+}
+
+impl StepVotes {
+    fn from_core(votes: CoreStepVotes, network_id: NetworkId) -> Self {
+        Self {
+            step: votes.step.into(),
+            bitset: Bytes::new(votes.bitset.as_slice().to_vec()),
+            signers: votes.signers.into_iter().map(|signer| PlatformAddress::new_v1(network_id, signer)).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsensusState {
+    height: u64,
+    view: u64,
+    step: Step,
+    proposer: PlatformAddress,
+    locked: Option<ViewBlock>,
+    valid: Option<ViewBlock>,
+    proposal: Option<H256>,
+    votes: Vec<StepVotes>,
+    /// Milliseconds left until the current step times out.
+    remaining_timeout: Option<u64>,
+}
+
+impl ConsensusState {
+    pub fn from_core(state: CoreConsensusState, network_id: NetworkId) -> Self {
+        let view_block = |(view, block_hash): (usize, H256)| ViewBlock {
+            view: view as u64,
+            block_hash,
+        };
+        Self {
+            height: state.height as u64,
+            view: state.view as u64,
+            step: state.step.into(),
+            proposer: PlatformAddress::new_v1(network_id, state.proposer),
+            locked: state.locked.map(view_block),
+            valid: state.valid.map(view_block),
+            proposal: state.proposal,
+            votes: state.votes.into_iter().map(|votes| StepVotes::from_core(votes, network_id)).collect(),
+            remaining_timeout: state
+                .timeout
+                .map(|timeout| timeout.as_secs() * 1000 + u64::from(timeout.subsec_millis())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ConsensusEvent {
+    StepChanged {
+        height: u64,
+        view: u64,
+        step: Step,
+    },
+    Timeout {
+        height: u64,
+        view: u64,
+        step: Step,
+    },
+}
+
+impl From<CoreConsensusEvent> for ConsensusEvent {
+    fn from(event: CoreConsensusEvent) -> Self {
+        match event {
+            CoreConsensusEvent::StepChanged {
+                height,
+                view,
+                step,
+            } => ConsensusEvent::StepChanged {
+                height: height as u64,
+                view: view as u64,
+                step: step.into(),
+            },
+            CoreConsensusEvent::Timeout {
+                height,
+                view,
+                step,
+            } => ConsensusEvent::Timeout {
+                height: height as u64,
+                view: view as u64,
+                step: step.into(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ccore::BitSet;
+    use ckey::Address;
+    use serde_json::{self, json};
+
+    use super::*;
+
+    #[test]
+    fn serialize_consensus_state() {
+        let network_id: NetworkId = "tc".into();
+        let (proposer, voter) = (Address::random(), Address::random());
+        let bitset = BitSet::new_with_indices(&[0, 2]);
+        let state = CoreConsensusState {
+            height: 3,
+            view: 1,
+            step: CoreStep::Prevote,
+            proposer,
+            locked: None,
+            valid: Some((0, H256::from(7))),
+            proposal: Some(H256::from(9)),
+            votes: vec![CoreStepVotes {
+                step: CoreStep::Prevote,
+                bitset,
+                signers: vec![proposer, voter],
+            }],
+            timeout: Some(Duration::from_millis(1_500)),
+        };
+
+        let mut bitset_bytes = vec![0; bitset.as_slice().len()];
+        bitset_bytes[0] = 0b101;
+        assert_eq!(
+            json!({
+                "height": 3,
+                "view": 1,
+                "step": "prevote",
+                "proposer": PlatformAddress::new_v1(network_id, proposer),
+                "locked": null,
+                "valid": {
+                    "view": 0,
+                    "blockHash": H256::from(7),
+                },
+                "proposal": H256::from(9),
+                "votes": [{
+                    "step": "prevote",
+                    "bitset": Bytes::new(bitset_bytes),
+                    "signers": [PlatformAddress::new_v1(network_id, proposer), PlatformAddress::new_v1(network_id, voter)],
+                }],
+                "remainingTimeout": 1_500,
+            }),
+            serde_json::to_value(ConsensusState::from_core(state, network_id)).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_consensus_event() {
+        let event = ConsensusEvent::from(CoreConsensusEvent::Timeout {
+            height: 5,
+            view: 2,
+            step: CoreStep::Precommit,
+        });
+        assert_eq!(
+            json!({
+                "type": "timeout",
+                "height": 5,
+                "view": 2,
+                "step": "precommit",
+            }),
+            serde_json::to_value(event).unwrap()
+        );
+    }
+}
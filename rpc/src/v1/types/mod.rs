@@ -18,12 +18,16 @@ mod action;
 mod asset_input;
 mod asset_output;
 mod block;
+mod commit_certificate;
+mod consensus_state;
 mod order;
 mod text;
 mod transaction;
 mod unsigned_transaction;
 mod work;
 
+use cjson::bytes::{Bytes, WithoutPrefix};
+use ckey::{PlatformAddress, Public};
 use primitives::H256;
 
 use self::asset_input::{AssetOutPoint, AssetTransferInput};
@@ -33,6 +37,8 @@ use self::order::OrderOnTransfer;
 pub use self::action::{Action, ActionWithId};
 pub use self::block::Block;
 pub use self::block::BlockNumberAndHash;
+pub use self::commit_certificate::CommitCertificate;
+pub use self::consensus_state::{ConsensusEvent, ConsensusState};
 pub use self::text::Text;
 pub use self::transaction::Transaction;
 pub use self::unsigned_transaction::UnsignedTransaction;
@@ -49,3 +55,53 @@ pub struct SendTransactionResult {
     pub hash: H256,
     pub seq: u64,
 }
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomActionHandler {
+    pub name: String,
+    pub handler_id: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum CustomActionData {
+    Data(Option<WithoutPrefix<Bytes>>),
+    Handlers(Vec<CustomActionHandler>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Delegator {
+    pub address: PlatformAddress,
+    pub quantity: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Delegators {
+    pub delegatee: PlatformAddress,
+    pub delegators: Vec<Delegator>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Liveness {
+    pub address: PlatformAddress,
+    pub signed: u64,
+    pub missed: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Prisoner {
+    pub address: PlatformAddress,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Validator {
+    pub address: PlatformAddress,
+    pub public: Public,
+}
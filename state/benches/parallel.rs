@@ -0,0 +1,167 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![feature(test)]
+
+extern crate codechain_key as ckey;
+extern crate codechain_state as cstate;
+extern crate codechain_types as ctypes;
+extern crate primitives;
+extern crate test;
+
+use ckey::{Generator, Public, Random};
+use cstate::tests::helpers::{get_temp_state, get_test_client};
+use cstate::{AssetSchemeAddress, StateWithCache, TopLevelState, TopState};
+use ctypes::invoice::Invoice;
+use ctypes::transaction::{
+    Action, AssetMintOutput, AssetOutPoint, AssetTransferInput, AssetTransferOutput, Transaction,
+};
+use ctypes::ShardId;
+use primitives::{H160, H256};
+use test::Bencher;
+
+const NUMBER_OF_SHARDS: ShardId = 8;
+const TRANSFERS_PER_SHARD: usize = 64;
+const AMOUNT: u64 = 1000;
+const FEE: u64 = 10;
+
+// The hash of the lock script [0x30, 0x01], which always succeeds.
+const LOCK_SCRIPT_HASH: &str = "b042ad154a3359d276835c903587ebafefea22af";
+
+/// A committed state with an asset in each shard, and the transfers that move the assets around in their shards.
+struct Block {
+    state: TopLevelState,
+    signer_public: Public,
+    transactions: Vec<Transaction>,
+}
+
+fn mint(shard_id: ShardId) -> Action {
+    Action::MintAsset {
+        network_id: "tc".into(),
+        shard_id,
+        metadata: format!("asset in shard {}", shard_id),
+        approver: None,
+        administrator: None,
+        allowed_script_hashes: vec![],
+        output: Box::new(AssetMintOutput {
+            lock_script_hash: H160::from(LOCK_SCRIPT_HASH),
+            parameters: vec![],
+            amount: Some(AMOUNT),
+        }),
+        approvals: vec![],
+    }
+}
+
+fn transfer(tracker: H256, asset_type: H256) -> Action {
+    Action::TransferAsset {
+        network_id: "tc".into(),
+        burns: vec![],
+        inputs: vec![AssetTransferInput {
+            prev_out: AssetOutPoint {
+                tracker,
+                index: 0,
+                asset_type,
+                amount: AMOUNT,
+            },
+            timelock: None,
+            lock_script: vec![0x30, 0x01],
+            unlock_script: vec![],
+        }],
+        outputs: vec![AssetTransferOutput {
+            lock_script_hash: H160::from(LOCK_SCRIPT_HASH),
+            parameters: vec![],
+            asset_type,
+            amount: AMOUNT,
+        }],
+        orders: vec![],
+        approvals: vec![],
+    }
+}
+
+fn prepare() -> Block {
+    let key_pair = Random.generate().unwrap();
+    let owner = key_pair.address();
+    let signer_public = *key_pair.public();
+
+    let mut state = get_temp_state();
+    state.issue_ccc(&owner, 1_000_000_000).unwrap();
+    for _ in 0..NUMBER_OF_SHARDS {
+        state.create_shard(0, &owner).unwrap();
+    }
+
+    let mut seq = 0;
+    let mut trackers = Vec::new();
+    for shard_id in 0..NUMBER_OF_SHARDS {
+        let action = mint(shard_id);
+        let tracker = action.tracker().unwrap();
+        let tx = Transaction {
+            seq,
+            fee: FEE,
+            network_id: "tc".into(),
+            action,
+        };
+        assert_eq!(Ok(Invoice::Success), state.apply(&tx, &H256::random(), &signer_public, &get_test_client()));
+        seq += 1;
+        trackers.push((tracker, H256::from(AssetSchemeAddress::new(tracker, shard_id))));
+    }
+    state.commit().unwrap();
+
+    let mut transactions = Vec::with_capacity(TRANSFERS_PER_SHARD * trackers.len());
+    for _ in 0..TRANSFERS_PER_SHARD {
+        for (tracker, asset_type) in trackers.iter_mut() {
+            let action = transfer(*tracker, *asset_type);
+            *tracker = action.tracker().unwrap();
+            transactions.push(Transaction {
+                seq,
+                fee: FEE,
+                network_id: "tc".into(),
+                action,
+            });
+            seq += 1;
+        }
+    }
+
+    Block {
+        state,
+        signer_public,
+        transactions,
+    }
+}
+
+#[bench]
+fn apply_transfers_sequentially(b: &mut Bencher) {
+    let block = prepare();
+    let client = get_test_client();
+    b.iter(|| {
+        let mut state = block.state.clone();
+        for tx in &block.transactions {
+            assert_eq!(Ok(Invoice::Success), state.apply(tx, &H256::random(), &block.signer_public, &client));
+        }
+    });
+}
+
+#[bench]
+fn apply_transfers_in_parallel(b: &mut Bencher) {
+    let block = prepare();
+    let client = get_test_client();
+    let transactions: Vec<_> =
+        block.transactions.iter().map(|tx| (tx, H256::random(), block.signer_public)).collect();
+    b.iter(|| {
+        let mut state = block.state.clone();
+        let invoices = state.apply_in_parallel(&transactions, &client).unwrap();
+        assert!(invoices.iter().all(|invoice| *invoice == Invoice::Success));
+    });
+}
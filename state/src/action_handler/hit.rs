@@ -22,35 +22,61 @@ use rlp::{self, Decodable, Encodable, UntrustedRlp};
 use super::{ActionDataKeyBuilder, ActionHandler, ActionHandlerResult};
 use crate::{TopLevelState, TopState, TopStateView};
 
-const CUSTOM_ACTION_HANDLER_ID: u64 = 1;
+const DEFAULT_HANDLER_ID: u64 = 1;
+const DEFAULT_INITIAL_COUNTER: u32 = 1;
 
 #[derive(RlpDecodable)]
 pub struct HitAction {
     increase: u8,
 }
 
-#[derive(Clone, Default)]
-pub struct HitHandler {}
+#[derive(Clone)]
+pub struct HitHandler {
+    handler_id: u64,
+    initial_counter: u32,
+}
+
+impl Default for HitHandler {
+    fn default() -> Self {
+        Self::with_params(DEFAULT_HANDLER_ID, DEFAULT_INITIAL_COUNTER)
+    }
+}
 
 impl HitHandler {
     pub fn new() -> Self {
         Self::default()
     }
 
+    pub fn with_params(handler_id: u64, initial_counter: u32) -> Self {
+        Self {
+            handler_id,
+            initial_counter,
+        }
+    }
+
     fn address(&self) -> H256 {
-        ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"metadata hit").into_key()
+        ActionDataKeyBuilder::new(self.handler_id, 1).append(&"metadata hit").into_key()
     }
 }
 
 impl ActionHandler for HitHandler {
+    fn name(&self) -> &'static str {
+        "hit"
+    }
+
     fn handler_id(&self) -> u64 {
-        CUSTOM_ACTION_HANDLER_ID
+        self.handler_id
     }
 
     fn init(&self, state: &mut TopLevelState) -> ActionHandlerResult<()> {
         let existing = state.action_data(&self.address());
         debug_assert_eq!(Ok(None), existing);
-        state.update_action_data(&self.address(), 1u32.rlp_bytes().to_vec())?;
+        state.update_action_data(&self.address(), self.initial_counter.rlp_bytes().to_vec())?;
+        Ok(())
+    }
+
+    fn verify(&self, bytes: &[u8]) -> ActionHandlerResult<()> {
+        HitAction::decode(&UntrustedRlp::new(bytes))?;
         Ok(())
     }
 
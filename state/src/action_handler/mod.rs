@@ -23,6 +23,7 @@ use ccrypto::blake256;
 use ckey::Address;
 use cmerkle::TrieError;
 use ctypes::invoice::Invoice;
+use ctypes::machine::Header;
 use ctypes::transaction::ParcelError;
 use primitives::H256;
 use rlp::{DecoderError, Encodable, RlpStream};
@@ -31,10 +32,28 @@ use super::TopStateView;
 use crate::{StateError, TopLevelState};
 
 pub trait ActionHandler: Send + Sync {
+    /// The name used to enable the handler in the scheme.
+    fn name(&self) -> &'static str;
     fn handler_id(&self) -> u64;
     fn init(&self, state: &mut TopLevelState) -> ActionHandlerResult<()>;
     fn execute(&self, bytes: &[u8], state: &mut TopLevelState, sender: &Address) -> ActionHandlerResult<Invoice>;
 
+    /// Checks the payload before the transaction gets into the mem pool or a block.
+    fn verify(&self, _bytes: &[u8]) -> ActionHandlerResult<()> {
+        Ok(())
+    }
+
+    /// The minimum fee of the action.
+    /// The fee must not be less than this nor the `min_custom_transaction_cost` of the chain.
+    fn min_cost(&self, _bytes: &[u8]) -> u64 {
+        0
+    }
+
+    /// Called after the transactions of a block are applied.
+    fn on_close_block(&self, _state: &mut TopLevelState, _header: &Header) -> ActionHandlerResult<()> {
+        Ok(())
+    }
+
     fn query(&self, key_fragment: &[u8], state: &TopLevelState) -> ActionHandlerResult<Option<Vec<u8>>> {
         let key = ActionDataKeyBuilder::key_from_fragment(self.handler_id(), key_fragment);
         let some_action_data = state.action_data(&key)?.map(Vec::from);
@@ -46,6 +65,11 @@ pub trait FindActionHandler {
     fn find_action_handler_for(&self, _id: u64) -> Option<&Arc<ActionHandler>> {
         None
     }
+
+    /// The action handlers enabled on the chain.
+    fn action_handlers(&self) -> &[Arc<ActionHandler>] {
+        &[]
+    }
 }
 
 pub type ActionHandlerResult<T> = Result<T, ActionHandlerError>;
@@ -0,0 +1,80 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccrypto::BLAKE_NULL_RLP;
+use cmerkle::Node;
+use hashdb::HashDB;
+use primitives::H256;
+
+use crate::item::shard::Shard;
+
+/// Walks the top level state trie and the tries of the shards under the given root,
+/// and returns the hashes of the nodes which the database doesn't have.
+pub fn find_missing_nodes(db: &HashDB, root: &H256) -> Vec<H256> {
+    let mut missing = Vec::new();
+    let mut stack = vec![*root];
+    while let Some(key) = stack.pop() {
+        if key == BLAKE_NULL_RLP {
+            continue
+        }
+        let value = match db.get(&key) {
+            Some(value) => value,
+            None => {
+                missing.push(key);
+                continue
+            }
+        };
+        match Node::decoded(&value) {
+            Some(Node::Branch(_, children)) => stack.extend(children.iter().filter_map(|child| *child)),
+            Some(Node::Leaf(_, item)) => stack.extend(Shard::root_of_encoded(item)),
+            None => {}
+        }
+    }
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::Address;
+    use cmerkle::{TrieDBMut, TrieMut};
+    use rlp::Encodable;
+
+    use super::*;
+    use crate::StateDB;
+
+    #[test]
+    fn nodes_of_the_shard_trie_are_walked() {
+        let mut db = StateDB::new_with_memorydb();
+
+        let mut shard_root = H256::new();
+        {
+            let mut trie = TrieDBMut::new(&mut db, &mut shard_root);
+            trie.insert(&[1u8; 32], &[2u8; 40]).unwrap();
+            trie.insert(&[3u8; 32], &[4u8; 40]).unwrap();
+        }
+        let mut root = H256::new();
+        {
+            let mut trie = TrieDBMut::new(&mut db, &mut root);
+            let shard = Shard::new(shard_root, vec![Address::random()], vec![]);
+            trie.insert(&[5u8; 32], &shard.rlp_bytes()).unwrap();
+            trie.insert(&[6u8; 32], &[7u8; 40]).unwrap();
+        }
+        assert_eq!(Vec::<H256>::new(), find_missing_nodes(&db, &root));
+
+        db.remove(&shard_root);
+        assert_eq!(vec![shard_root], find_missing_nodes(&db, &root));
+    }
+}
@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod missing_nodes;
 mod state_db;
 
-pub use self::state_db::StateDB;
+pub use self::missing_nodes::find_missing_nodes;
+pub use self::state_db::{RemoteNodes, StateDB};
@@ -34,7 +34,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use ctypes::ShardId;
-use hashdb::{AsHashDB, HashDB};
+use hashdb::{DBValue, HashDB};
 use journaldb::{self, Algorithm, JournalDB};
 use kvdb::DBTransaction;
 use kvdb_memorydb;
@@ -44,12 +44,22 @@ use util_error::UtilError;
 use crate::cache::{GlobalCache, ShardCache, TopCache};
 use crate::impls::TopLevelState;
 
+/// The source of the trie nodes which are not in the local database.
+/// The light clients fetch them from the full nodes.
+pub trait RemoteNodes: Send + Sync {
+    /// Returns the node whose hash is `hash`. The implementation must check that the node matches the hash.
+    /// `path` is the nibbles of the key below the node, so the nodes on it can be fetched together.
+    fn fetch(&self, hash: &H256, path: &[u8]) -> Option<DBValue>;
+}
+
 /// State database abstraction.
 pub struct StateDB {
     /// Backing database.
     db: Box<JournalDB>,
     cache: GlobalCache,
     current_hash: Option<H256>,
+    /// Looked up when a node is missing in the backing database.
+    remote: Option<Arc<RemoteNodes>>,
 }
 
 impl StateDB {
@@ -59,9 +69,15 @@ impl StateDB {
             db,
             cache: Default::default(),
             current_hash: None,
+            remote: None,
         }
     }
 
+    /// Makes the missing nodes fetched from the `remote`.
+    pub fn set_remote(&mut self, remote: Arc<RemoteNodes>) {
+        self.remote = Some(remote);
+    }
+
     pub fn new_with_memorydb() -> Self {
         let memorydb = Arc::new(kvdb_memorydb::create(0));
         let db = journaldb::new(memorydb, Algorithm::Archive, None);
@@ -120,18 +136,50 @@ impl StateDB {
             db: self.db.boxed_clone(),
             cache,
             current_hash,
+            remote: self.remote.clone(),
         }
     }
 }
 
-impl AsHashDB for StateDB {
-    /// Conversion method to interpret self as `HashDB` reference
-    fn as_hashdb(&self) -> &HashDB {
-        self.db.as_hashdb()
+impl HashDB for StateDB {
+    fn keys(&self) -> HashMap<H256, i32> {
+        self.db.keys()
+    }
+
+    fn get(&self, key: &H256) -> Option<DBValue> {
+        self.get_on_path(key, &[], 0)
+    }
+
+    fn get_on_path(&self, key: &H256, path: &[u8], offset: usize) -> Option<DBValue> {
+        self.db.get(key).or_else(|| {
+            let remote = self.remote.as_ref()?;
+            let nibbles: Vec<u8> = (offset..path.len() * 2)
+                .map(|index| {
+                    let byte = path[index / 2];
+                    if index % 2 == 0 {
+                        byte >> 4
+                    } else {
+                        byte & 0b1111
+                    }
+                })
+                .collect();
+            remote.fetch(key, &nibbles)
+        })
+    }
+
+    fn contains(&self, key: &H256) -> bool {
+        self.get(key).is_some()
+    }
+
+    fn insert(&mut self, value: &[u8]) -> H256 {
+        self.db.insert(value)
+    }
+
+    fn emplace(&mut self, key: H256, value: DBValue) {
+        self.db.emplace(key, value)
     }
 
-    /// Conversion method to interpret self as mutable `HashDB` reference
-    fn as_hashdb_mut(&mut self) -> &mut HashDB {
-        self.db.as_hashdb_mut()
+    fn remove(&mut self, key: &H256) {
+        self.db.remove(key)
     }
 }
@@ -43,6 +43,8 @@ pub struct ShardLevelState<'db> {
     cache: &'db mut ShardCache,
     id_of_checkpoints: Vec<CheckpointId>,
     shard_id: ShardId,
+    /// Whether the supplies of the asset schemes are tracked.
+    track_supply: bool,
 }
 
 impl<'db> ShardLevelState<'db> {
@@ -55,6 +57,7 @@ impl<'db> ShardLevelState<'db> {
             cache,
             id_of_checkpoints: Default::default(),
             shard_id,
+            track_supply: true,
         })
     }
 
@@ -64,6 +67,7 @@ impl<'db> ShardLevelState<'db> {
         db: &'db mut RefCell<StateDB>,
         root: H256,
         cache: &'db mut ShardCache,
+        track_supply: bool,
     ) -> cmerkle::Result<Self> {
         if !db.borrow().as_hashdb().contains(&root) {
             return Err(TrieError::InvalidStateRoot(root))
@@ -75,6 +79,7 @@ impl<'db> ShardLevelState<'db> {
             cache,
             id_of_checkpoints: Default::default(),
             shard_id,
+            track_supply,
         })
     }
 
@@ -226,6 +231,7 @@ impl<'db> ShardLevelState<'db> {
             &asset_scheme_address,
             metadata.to_string(),
             amount,
+            self.initial_supply(amount),
             *approver,
             *administrator,
             allowed_script_hashes.to_vec(),
@@ -290,6 +296,9 @@ impl<'db> ShardLevelState<'db> {
             self.kill_asset(&asset_address);
             deleted_asset.push((asset_address, input.prev_out.amount));
         }
+        for burn in burns {
+            self.burn_supply(&burn.prev_out.asset_type, burn.prev_out.amount)?;
+        }
         let mut created_asset = Vec::with_capacity(outputs.len());
         for (index, output) in outputs.iter().enumerate() {
             self.check_output_script_hash(output)?;
@@ -675,6 +684,7 @@ impl<'db> ShardLevelState<'db> {
                 &asset_scheme_address,
                 format!("{{\"name\":\"Wrapped CCC\",\"description\":\"Wrapped CCC in shard {}\"}}", self.shard_id),
                 ::std::u64::MAX,
+                self.initial_supply(0),
                 None,
                 None,
                 Vec::new(),
@@ -690,6 +700,10 @@ impl<'db> ShardLevelState<'db> {
             );
         }
 
+        if self.track_supply {
+            self.get_asset_scheme_mut(&asset_scheme_address)?.increase_supply(amount);
+        }
+
         let asset_address = OwnedAssetAddress::new(*tx_hash, 0, self.shard_id);
         let asset = self.create_asset(
             &asset_address,
@@ -716,10 +730,30 @@ impl<'db> ShardLevelState<'db> {
 
         let (_, asset_address) = self.check_input_asset(burn, sender, &approvers)?;
         self.kill_asset(&asset_address);
+        self.burn_supply(&burn.prev_out.asset_type, burn.prev_out.amount)?;
         ctrace!(TX, "Removed Wrapped CCC asset {:?}, amount {:?}", asset_address, burn.prev_out.amount);
         Ok(())
     }
 
+    /// The supply of a new asset scheme, which is `None` before the supply is tracked.
+    fn initial_supply(&self, amount: u64) -> Option<u64> {
+        if self.track_supply {
+            Some(amount)
+        } else {
+            None
+        }
+    }
+
+    fn burn_supply(&self, asset_type: &H256, amount: u64) -> StateResult<()> {
+        if !self.track_supply {
+            return Ok(())
+        }
+        let asset_scheme_address =
+            AssetSchemeAddress::from_hash(*asset_type).expect("Asset type must be the valid format");
+        self.get_asset_scheme_mut(&asset_scheme_address)?.decrease_supply(amount);
+        Ok(())
+    }
+
     fn kill_asset(&mut self, account: &OwnedAssetAddress) {
         self.cache.remove_asset(account);
     }
@@ -728,18 +762,21 @@ impl<'db> ShardLevelState<'db> {
         self.cache.remove_asset_scheme(account);
     }
 
+    // FIXME: Remove this clippy config
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
     pub fn create_asset_scheme(
         &self,
         a: &AssetSchemeAddress,
         metadata: String,
         amount: u64,
+        supply: Option<u64>,
         approver: Option<Address>,
         administrator: Option<Address>,
         allowed_script_hashes: Vec<H160>,
         pool: Vec<Asset>,
     ) -> cmerkle::Result<AssetScheme> {
         let mut asset_scheme = self.get_asset_scheme_mut(a)?;
-        asset_scheme.init(metadata, amount, approver, administrator, allowed_script_hashes, pool);
+        asset_scheme.init(metadata, amount, supply, approver, administrator, allowed_script_hashes, pool);
         Ok(asset_scheme.clone())
     }
 
@@ -1153,6 +1190,9 @@ mod tests {
             (asset: (transfer_tracker, 2, SHARD_ID) => { asset_type: asset_type, amount: 15 }),
             (asset: (burn_tracker, 0, SHARD_ID))
         ]);
+
+        let asset_scheme_address = AssetSchemeAddress::new(mint_tracker, SHARD_ID);
+        assert_eq!(Some(amount - 5), state.asset_scheme(&asset_scheme_address).unwrap().unwrap().supply());
     }
 
 
@@ -55,8 +55,8 @@ use crate::cache::{ShardCache, TopCache};
 use crate::checkpoint::{CheckpointId, StateWithCheckpoint};
 use crate::traits::{ShardState, ShardStateView, StateWithCache, TopState, TopStateView};
 use crate::{
-    Account, ActionData, FindActionHandler, Metadata, MetadataAddress, RegularAccount, RegularAccountAddress, Shard,
-    ShardAddress, ShardLevelState, StateDB, StateError, StateResult, Text,
+    Account, ActionData, ActionHandlerError, FindActionHandler, Metadata, MetadataAddress, RegularAccount,
+    RegularAccountAddress, Shard, ShardAddress, ShardLevelState, StateDB, StateError, StateResult, Text,
 };
 #[cfg(test)]
 use crate::{Asset, AssetSchemeAddress, OwnedAssetAddress};
@@ -90,6 +90,8 @@ pub struct TopLevelState {
     top_cache: TopCache,
     shard_caches: HashMap<ShardId, ShardCache>,
     id_of_checkpoints: Vec<CheckpointId>,
+    /// Whether the CCC supply and the supplies of the asset schemes are tracked.
+    track_supply: bool,
 }
 
 impl TopStateView for TopLevelState {
@@ -229,7 +231,9 @@ impl TopLevelState {
         // init trie and reset root too null
         let _ = TrieFactory::create(db.as_hashdb_mut(), &mut root);
 
-        Self::from_existing(db, root).expect("The empty trie root was initialized")
+        let mut state = Self::from_existing(db, root).expect("The empty trie root was initialized");
+        state.set_supply_tracking(true);
+        state
     }
 
     /// Creates new state with existing state root
@@ -247,11 +251,25 @@ impl TopLevelState {
             top_cache,
             shard_caches,
             id_of_checkpoints: Default::default(),
+            track_supply: false,
         };
 
         Ok(state)
     }
 
+    /// Returns the nodes proving the action data of the key against the committed state root.
+    pub fn action_data_proof(&self, key: &H256) -> TrieResult<Vec<Bytes>> {
+        let db = self.db.borrow();
+        let trie = TrieFactory::readonly(db.as_hashdb(), &self.root)?;
+        trie.get_proof(key)
+    }
+
+    /// Tracks the CCC supply and the supplies of the asset schemes from now on.
+    /// It changes the encoding of the metadata and the asset schemes, so it must be enabled at the same block on every node.
+    pub fn set_supply_tracking(&mut self, track_supply: bool) {
+        self.track_supply = track_supply;
+    }
+
     /// Execute a given tranasction, charging tranasction fee.
     /// This will change the state accordingly.
     pub fn apply<C: ChainTimeInfo + FindActionHandler>(
@@ -278,6 +296,150 @@ impl TopLevelState {
         }
     }
 
+    /// Execute the given transactions, applying the shard transactions of independent shards concurrently.
+    ///
+    /// The transactions are split into the runs of consecutive shard transactions and the other transactions.
+    /// The other transactions change the top-level state, so each of them is applied alone between the runs.
+    /// The result is the same as applying the transactions one by one.
+    pub fn apply_in_parallel<C: ChainTimeInfo + FindActionHandler + Sync>(
+        &mut self,
+        transactions: &[(&Transaction, H256, Public)],
+        client: &C,
+    ) -> StateResult<Vec<Invoice>> {
+        let mut invoices = Vec::with_capacity(transactions.len());
+        let mut rest = transactions;
+        while !rest.is_empty() {
+            let run = rest.iter().take_while(|(tx, ..)| is_parallelizable(&tx.action)).count();
+            if run == 0 {
+                let (tx, signed_hash, signer_public) = &rest[0];
+                invoices.push(self.apply(tx, signed_hash, signer_public, client)?);
+                rest = &rest[1..];
+            } else {
+                invoices.extend(self.apply_shard_transactions_in_parallel(&rest[..run], client)?);
+                rest = &rest[run..];
+            }
+        }
+        Ok(invoices)
+    }
+
+    /// Execute the given shard transactions, applying the ones of independent shards concurrently.
+    ///
+    /// The fees are charged in order first. Then the shard transactions are grouped by the shards they touch,
+    /// and each group is applied on its own thread. The shard caches and the invoices are merged back in order.
+    fn apply_shard_transactions_in_parallel<C: ChainTimeInfo + FindActionHandler + Sync>(
+        &mut self,
+        transactions: &[(&Transaction, H256, Public)],
+        client: &C,
+    ) -> StateResult<Vec<Invoice>> {
+        debug_assert!(self.id_of_checkpoints.is_empty(), "Cannot apply transactions in parallel inside a checkpoint");
+
+        let mut results: Vec<Option<StateResult<Invoice>>> = transactions.iter().map(|_| None).collect();
+        let mut jobs = Vec::with_capacity(transactions.len());
+        for (index, (tx, _, signer_public)) in transactions.iter().enumerate() {
+            let fee_payer = self.public_to_owner_address(signer_public)?;
+
+            self.create_checkpoint(FEE_CHECKPOINT);
+            if let Err(err) = self.charge_fee(tx, &fee_payer) {
+                self.revert_to_checkpoint(FEE_CHECKPOINT);
+                return Err(err)
+            }
+            self.discard_checkpoint(FEE_CHECKPOINT);
+
+            match self.shard_transaction_with_approvers(&tx.action) {
+                Ok((transaction, approvers)) => jobs.push(ShardJob {
+                    index,
+                    transaction,
+                    sender: fee_payer,
+                    approvers,
+                }),
+                Err(StateError::Parcel(err)) => results[index] = Some(Ok(Invoice::Failure(err))),
+                Err(StateError::Transaction(err)) => results[index] = Some(Ok(Invoice::Failure(err.into()))),
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut groups = Vec::new();
+        for (shard_ids, members) in group_by_shards(&jobs) {
+            let mut shards = HashMap::with_capacity(shard_ids.len());
+            for shard_id in shard_ids {
+                // A transaction touching an unknown shard fails in its group.
+                if let Some(root) = self.shard_root(shard_id)? {
+                    let users = self.shard_users(shard_id)?.expect("Shard must exist");
+                    let cache = self.shard_caches.remove(&shard_id).unwrap_or_default();
+                    shards.insert(shard_id, ShardContext {
+                        root,
+                        users,
+                        cache,
+                        track_supply: self.track_supply,
+                    });
+                }
+            }
+            let group_jobs: Vec<&ShardJob> = members.into_iter().map(|member| &jobs[member]).collect();
+            groups.push((self.db.borrow().clone(&self.root), shards, group_jobs));
+        }
+
+        let outcomes = crossbeam::scope(|scope| {
+            let handles: Vec<_> = groups
+                .into_iter()
+                .map(|(db, shards, group_jobs)| {
+                    scope.spawn(move |_| apply_shard_group(db, shards, &group_jobs, client))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Applying shard transactions must not panic"))
+                .collect::<Vec<_>>()
+        })
+        .expect("All threads are joined");
+
+        for (shards, group_results) in outcomes {
+            for (shard_id, context) in shards {
+                self.shard_caches.insert(shard_id, context.cache);
+            }
+            for (index, result) in group_results {
+                results[index] = Some(result);
+            }
+        }
+        results.into_iter().map(|result| result.expect("Every transaction has its result")).collect()
+    }
+
+    fn shard_transaction_with_approvers(&self, action: &Action) -> StateResult<(ShardTransaction, Vec<Address>)> {
+        let approvals = match action {
+            Action::MintAsset {
+                approvals,
+                ..
+            }
+            | Action::TransferAsset {
+                approvals,
+                ..
+            }
+            | Action::ChangeAssetScheme {
+                approvals,
+                ..
+            }
+            | Action::ComposeAsset {
+                approvals,
+                ..
+            }
+            | Action::DecomposeAsset {
+                approvals,
+                ..
+            } => approvals,
+            _ => unreachable!("Only the asset transactions are applied in parallel"),
+        };
+        let transaction = Option::<ShardTransaction>::from(action.clone()).expect("It's a shard transaction");
+
+        let transaction_tracker = transaction.tracker();
+        let approvers = approvals
+            .iter()
+            .map(|signature| {
+                let public = recover(&signature, &transaction_tracker)?;
+                self.public_to_owner_address(&public)
+            })
+            .collect::<StateResult<Vec<_>>>()?;
+        Ok((transaction, approvers))
+    }
+
     // Change the public to an owner address if it is a regular key.
     fn public_to_owner_address(&self, public: &Public) -> StateResult<Address> {
         Ok(if self.regular_account_exists_and_not_null(public)? {
@@ -297,20 +459,7 @@ impl TopLevelState {
         signer_public: &Public,
         client: &C,
     ) -> StateResult<Invoice> {
-        let seq = self.seq(fee_payer)?;
-
-        if tx.seq != seq {
-            return Err(ParcelError::InvalidSeq(Mismatch {
-                expected: seq,
-                found: tx.seq,
-            })
-            .into())
-        }
-
-        let fee = tx.fee;
-
-        self.inc_seq(fee_payer)?;
-        self.sub_balance(fee_payer, fee)?;
+        self.charge_fee(tx, fee_payer)?;
 
         // The failed transaction also must pay the fee and increase seq.
         self.create_checkpoint(ACTION_CHECKPOINT);
@@ -350,6 +499,22 @@ impl TopLevelState {
         }
     }
 
+    fn charge_fee(&mut self, tx: &Transaction, fee_payer: &Address) -> StateResult<()> {
+        let seq = self.seq(fee_payer)?;
+
+        if tx.seq != seq {
+            return Err(ParcelError::InvalidSeq(Mismatch {
+                expected: seq,
+                found: tx.seq,
+            })
+            .into())
+        }
+
+        self.inc_seq(fee_payer)?;
+        // The fee leaves the supply until the engine distributes it.
+        self.burn_ccc(fee_payer, tx.fee)
+    }
+
     fn apply_action<C: ChainTimeInfo + FindActionHandler>(
         &mut self,
         action: &Action,
@@ -538,9 +703,16 @@ impl TopLevelState {
                 handler_id,
                 bytes,
             } => {
-                let handler = client.find_action_handler_for(*handler_id).expect("Unknown custom parsel applied!");
-                let invoice = handler.execute(bytes, self, fee_payer).expect("Custom action handler execution failed");
-                Ok(invoice)
+                let handler = client
+                    .find_action_handler_for(*handler_id)
+                    .ok_or_else(|| ParcelError::InvalidCustomAction(format!("Unknown handler id {}", handler_id)))?;
+                match handler.execute(bytes, self, fee_payer) {
+                    Ok(invoice) => Ok(invoice),
+                    Err(ActionHandlerError::StateError(err)) => Err(err),
+                    Err(ActionHandlerError::DecoderError(err)) => {
+                        Err(ParcelError::InvalidCustomAction(err.to_string()).into())
+                    }
+                }
             }
         }
     }
@@ -572,9 +744,16 @@ impl TopLevelState {
             },
         };
 
-        let shard_cache = self.shard_caches.entry(shard_id).or_default();
-        let mut shard_level_state = ShardLevelState::from_existing(shard_id, &mut self.db, shard_root, shard_cache)?;
-        Ok(shard_level_state.apply(&transaction, sender, &shard_users, &[], client)?)
+        let invoice = {
+            let shard_cache = self.shard_caches.entry(shard_id).or_default();
+            let mut shard_level_state =
+                ShardLevelState::from_existing(shard_id, &mut self.db, shard_root, shard_cache, self.track_supply)?;
+            shard_level_state.apply(&transaction, sender, &shard_users, &[], client)?
+        };
+        if invoice == Invoice::Success && self.track_supply {
+            self.get_metadata_mut()?.increase_wrapped_ccc(amount);
+        }
+        Ok(invoice)
     }
 
     pub fn apply_shard_transaction<C: ChainTimeInfo>(
@@ -599,6 +778,9 @@ impl TopLevelState {
         if first_invoice == Invoice::Success {
             let unwrapped_amount = transaction.unwrapped_amount();
             self.add_balance(sender, unwrapped_amount)?;
+            if unwrapped_amount != 0 && self.track_supply {
+                self.get_metadata_mut()?.increase_unwrapped_ccc(unwrapped_amount);
+            }
         }
         Ok(first_invoice)
     }
@@ -615,7 +797,8 @@ impl TopLevelState {
         let shard_users = self.shard_users(shard_id)?.expect("Shard must exist");
 
         let shard_cache = self.shard_caches.entry(shard_id).or_default();
-        let mut shard_level_state = ShardLevelState::from_existing(shard_id, &mut self.db, shard_root, shard_cache)?;
+        let mut shard_level_state =
+            ShardLevelState::from_existing(shard_id, &mut self.db, shard_root, shard_cache, self.track_supply)?;
         shard_level_state.apply(&transaction.clone(), sender, &shard_users, approvers, client)
     }
 
@@ -628,7 +811,7 @@ impl TopLevelState {
         const DEFAULT_SHARD_ROOT: H256 = BLAKE_NULL_RLP;
         {
             let shard_cache = self.shard_caches.entry(shard_id).or_default();
-            ShardLevelState::from_existing(shard_id, &mut self.db, DEFAULT_SHARD_ROOT, shard_cache)?;
+            ShardLevelState::from_existing(shard_id, &mut self.db, DEFAULT_SHARD_ROOT, shard_cache, self.track_supply)?;
         }
 
         ctrace!(STATE, "shard({}) created. owners: {:?}, users: {:?}", shard_id, owners, users);
@@ -639,6 +822,15 @@ impl TopLevelState {
         Ok(())
     }
 
+    /// Subtracts `decr` from the balance of account `a` and from the CCC supply.
+    fn burn_ccc(&mut self, a: &Address, decr: u64) -> StateResult<()> {
+        self.sub_balance(a, decr)?;
+        if decr != 0 && self.track_supply {
+            self.get_metadata_mut()?.increase_burned_ccc(decr);
+        }
+        Ok(())
+    }
+
     fn get_account_mut(&self, a: &Address) -> TrieResult<RefMut<Account>> {
         debug_assert_eq!(Ok(false), self.regular_account_exists_and_not_null_by_address(a));
 
@@ -703,7 +895,15 @@ impl TopLevelState {
 
     #[cfg(test)]
     fn set_balance(&mut self, a: &Address, balance: u64) -> TrieResult<()> {
+        let prev_balance = self.balance(a)?;
         self.get_account_mut(a)?.set_balance(balance);
+        // Keep the supply consistent with the balances.
+        let mut metadata = self.get_metadata_mut()?;
+        if balance > prev_balance {
+            metadata.increase_issued_ccc(balance - prev_balance);
+        } else {
+            metadata.increase_burned_ccc(prev_balance - balance);
+        }
         Ok(())
     }
 
@@ -728,8 +928,23 @@ impl TopLevelState {
         match self.shard_root(shard_id)? {
             Some(shard_root) => {
                 let mut shard_cache = self.shard_caches.entry(shard_id).or_default();
-                let state = ShardLevelState::from_existing(shard_id, &mut self.db, shard_root, &mut shard_cache)?;
-                state.create_asset_scheme(a, metadata, amount, approver, administrator, allowed_script_hashes, pool)?;
+                let state = ShardLevelState::from_existing(
+                    shard_id,
+                    &mut self.db,
+                    shard_root,
+                    &mut shard_cache,
+                    self.track_supply,
+                )?;
+                state.create_asset_scheme(
+                    a,
+                    metadata,
+                    amount,
+                    Some(amount),
+                    approver,
+                    administrator,
+                    allowed_script_hashes,
+                    pool,
+                )?;
                 Ok(true)
             }
             None => Ok(false),
@@ -751,7 +966,13 @@ impl TopLevelState {
         match self.shard_root(shard_id)? {
             Some(shard_root) => {
                 let mut shard_cache = self.shard_caches.entry(shard_id).or_default();
-                let state = ShardLevelState::from_existing(shard_id, &mut self.db, shard_root, &mut shard_cache)?;
+                let state = ShardLevelState::from_existing(
+                    shard_id,
+                    &mut self.db,
+                    shard_root,
+                    &mut shard_cache,
+                    self.track_supply,
+                )?;
                 let a = OwnedAssetAddress::new(tx_hash, index, shard_id);
                 state.create_asset(&a, asset_type, lock_script_hash, parameters, amount, order_hash)?;
                 Ok(true)
@@ -771,6 +992,7 @@ impl Clone for TopLevelState {
             id_of_checkpoints: self.id_of_checkpoints.clone(),
             top_cache: self.top_cache.clone(),
             shard_caches: self.shard_caches.clone(),
+            track_supply: self.track_supply,
         }
     }
 }
@@ -792,6 +1014,21 @@ impl TopState for TopLevelState {
         Ok(())
     }
 
+    fn issue_ccc(&mut self, a: &Address, incr: u64) -> TrieResult<()> {
+        self.add_balance(a, incr)?;
+        if incr != 0 && self.track_supply {
+            self.get_metadata_mut()?.increase_issued_ccc(incr);
+        }
+        Ok(())
+    }
+
+    fn burn_fee(&mut self, amount: u64) -> TrieResult<()> {
+        if amount != 0 && self.track_supply {
+            self.get_metadata_mut()?.increase_burned_fee(amount);
+        }
+        Ok(())
+    }
+
     fn sub_balance(&mut self, a: &Address, decr: u64) -> StateResult<()> {
         ctrace!(STATE, "sub_balance({}, {}): {}", a, decr, self.balance(a)?);
         if decr == 0 {
@@ -856,7 +1093,7 @@ impl TopState for TopLevelState {
     }
 
     fn create_shard(&mut self, shard_creation_cost: u64, fee_payer: &Address) -> StateResult<()> {
-        self.sub_balance(fee_payer, shard_creation_cost)?;
+        self.burn_ccc(fee_payer, shard_creation_cost)?;
 
         let shard_id = {
             let mut metadata = self.get_metadata_mut()?;
@@ -937,6 +1174,138 @@ impl TopState for TopLevelState {
         *action_data = data.into();
         Ok(())
     }
+
+    fn remove_action_data(&mut self, key: &H256) {
+        self.top_cache.remove_action_data(key);
+    }
+}
+
+/// The actions that change nothing in the top-level state except the fee.
+fn is_parallelizable(action: &Action) -> bool {
+    match action {
+        Action::MintAsset {
+            ..
+        }
+        | Action::TransferAsset {
+            ..
+        }
+        | Action::ChangeAssetScheme {
+            ..
+        }
+        | Action::ComposeAsset {
+            ..
+        }
+        | Action::DecomposeAsset {
+            ..
+        } => true,
+        _ => false,
+    }
+}
+
+struct ShardJob {
+    index: usize,
+    transaction: ShardTransaction,
+    sender: Address,
+    approvers: Vec<Address>,
+}
+
+struct ShardContext {
+    root: H256,
+    users: Vec<Address>,
+    cache: ShardCache,
+    track_supply: bool,
+}
+
+/// Splits the jobs into groups that share no shard, returning the shards and the jobs of each group.
+/// The groups are ordered by their first jobs, and the jobs in a group keep their order.
+fn group_by_shards(jobs: &[ShardJob]) -> Vec<(Vec<ShardId>, Vec<usize>)> {
+    let mut groups: Vec<(Vec<ShardId>, Vec<usize>)> = Vec::new();
+    for (index, job) in jobs.iter().enumerate() {
+        let mut merged = (job.transaction.related_shards(), vec![index]);
+        let mut rest = Vec::with_capacity(groups.len());
+        for group in groups {
+            if group.0.iter().any(|shard_id| merged.0.contains(shard_id)) {
+                merged.0.extend(group.0);
+                merged.1.extend(group.1);
+            } else {
+                rest.push(group);
+            }
+        }
+        merged.0.sort();
+        merged.0.dedup();
+        merged.1.sort();
+        rest.push(merged);
+        groups = rest;
+    }
+    groups.sort_by_key(|(_, members)| members[0]);
+    groups
+}
+
+fn apply_shard_group<C: ChainTimeInfo>(
+    db: StateDB,
+    mut shards: HashMap<ShardId, ShardContext>,
+    jobs: &[&ShardJob],
+    client: &C,
+) -> (HashMap<ShardId, ShardContext>, Vec<(usize, StateResult<Invoice>)>) {
+    let mut db = RefCell::new(db);
+    let results = jobs.iter().map(|job| (job.index, apply_shard_job(&mut db, &mut shards, job, client))).collect();
+    (shards, results)
+}
+
+fn apply_shard_job<C: ChainTimeInfo>(
+    db: &mut RefCell<StateDB>,
+    shards: &mut HashMap<ShardId, ShardContext>,
+    job: &ShardJob,
+    client: &C,
+) -> StateResult<Invoice> {
+    let shard_ids = job.transaction.related_shards();
+    for shard_id in &shard_ids {
+        if let Some(context) = shards.get_mut(shard_id) {
+            context.cache.checkpoint();
+        }
+    }
+
+    let result = apply_shard_job_to_shards(db, shards, job, &shard_ids, client);
+
+    for shard_id in &shard_ids {
+        if let Some(context) = shards.get_mut(shard_id) {
+            if result.is_ok() {
+                context.cache.discard_checkpoint();
+            } else {
+                context.cache.revert_to_checkpoint();
+            }
+        }
+    }
+
+    match result {
+        Err(StateError::Parcel(err)) => Ok(Invoice::Failure(err)),
+        Err(StateError::Transaction(err)) => Ok(Invoice::Failure(err.into())),
+        result => result,
+    }
+}
+
+fn apply_shard_job_to_shards<C: ChainTimeInfo>(
+    db: &mut RefCell<StateDB>,
+    shards: &mut HashMap<ShardId, ShardContext>,
+    job: &ShardJob,
+    shard_ids: &[ShardId],
+    client: &C,
+) -> StateResult<Invoice> {
+    let mut first_invoice = None;
+    for shard_id in shard_ids {
+        let context = shards.get_mut(shard_id).ok_or_else(|| ParcelError::InvalidShardId(*shard_id))?;
+        let invoice = {
+            let mut shard_level_state =
+                ShardLevelState::from_existing(*shard_id, db, context.root, &mut context.cache, context.track_supply)?;
+            shard_level_state.apply(&job.transaction, &job.sender, &context.users, &job.approvers, client)?
+        };
+        match &first_invoice {
+            None => first_invoice = Some(invoice),
+            Some(first) if *first != invoice => return Err(ParcelError::InconsistentShardOutcomes.into()),
+            Some(_) => {}
+        }
+    }
+    Ok(first_invoice.expect("A shard transaction touches at least one shard"))
 }
 
 #[cfg(test)]
@@ -1844,6 +2213,7 @@ mod tests_tx {
             (account: sender => (seq: 1, balance: 100 - 11 - 30)),
             (asset: (tx_hash, 0, 0) => { asset_type: asset_type, amount: amount })
         ]);
+        assert_eq!(Ok(Some(amount)), state.wrapped_ccc_supply());
 
         let unwrap_ccc_tx =
             unwrap_ccc!(asset_transfer_input!(asset_out_point!(tx_hash, 0, asset_type, 30), vec![0x01]));
@@ -1855,6 +2225,8 @@ mod tests_tx {
             (account: sender => (seq: 2, balance: 100 - 11 - 30 - 11 + 30)),
             (asset: (tx_hash, 0, 0))
         ]);
+        assert_eq!(Ok(Some(0)), state.wrapped_ccc_supply());
+        assert_eq!(Ok(Some(100 - 11 - 11)), state.ccc_supply(0));
     }
 
     #[test]
@@ -2471,4 +2843,150 @@ mod tests_tx {
             (shard: 0 => owners: owners, users: old_users)
         ]);
     }
+
+    #[test]
+    fn apply_in_parallel_is_same_as_sequential_application() {
+        let (sender, sender_public, _) = address();
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (shard: 0 => owners: [sender]),
+            (shard: 1 => owners: [sender]),
+            (shard: 2 => owners: [sender]),
+            (metadata: shards: 3),
+            (account: sender => balance: 1000)
+        ]);
+        state.commit().unwrap();
+
+        let lock_script_hash = H160::from("b042ad154a3359d276835c903587ebafefea22af");
+        let mint_in_shard = |shard_id: ShardId| {
+            let output = Box::new(asset_mint_output!(lock_script_hash, amount: 30));
+            let mut mint = mint_asset!(output, "metadata".to_string());
+            if let Action::MintAsset {
+                shard_id: ref mut id,
+                ..
+            } = mint
+            {
+                *id = shard_id;
+            }
+            mint
+        };
+        let mints: Vec<_> = (0..3).map(mint_in_shard).collect();
+        let asset_types: Vec<H256> = mints
+            .iter()
+            .enumerate()
+            .map(|(shard_id, mint)| AssetSchemeAddress::new(mint.tracker().unwrap(), shard_id as ShardId).into())
+            .collect();
+        let out_points: Vec<_> = mints
+            .iter()
+            .zip(&asset_types)
+            .map(|(mint, asset_type)| asset_out_point!(mint.tracker().unwrap(), 0, *asset_type, 30))
+            .collect();
+
+        let transfer_in_shard_0 = transfer_asset!(
+            inputs: asset_transfer_inputs![(out_points[0].clone(), vec![0x30, 0x01])],
+            asset_transfer_outputs![(lock_script_hash, asset_types[0], 30)]
+        );
+        let transfer_with_wrong_script = transfer_asset!(
+            inputs: asset_transfer_inputs![(out_points[1].clone(), vec![0x01])],
+            asset_transfer_outputs![(lock_script_hash, asset_types[1], 30)]
+        );
+        let transfer_across_shards = transfer_asset!(
+            inputs: asset_transfer_inputs![
+                (out_points[1].clone(), vec![0x30, 0x01]),
+                (out_points[2].clone(), vec![0x30, 0x01])
+            ],
+            asset_transfer_outputs![(lock_script_hash, asset_types[1], 30), (lock_script_hash, asset_types[2], 30)]
+        );
+        let mint_in_invalid_shard = mint_in_shard(100);
+
+        let mut actions = mints;
+        actions.push(transfer_in_shard_0);
+        actions.push(transfer_with_wrong_script);
+        actions.push(transfer_across_shards);
+        actions.push(mint_in_invalid_shard);
+        let transactions: Vec<_> = actions
+            .into_iter()
+            .enumerate()
+            .map(|(seq, action)| transaction!(seq: seq as u64, fee: 10, action))
+            .collect();
+
+        let mut sequential = state.clone();
+        let sequential_invoices: Vec<_> = transactions
+            .iter()
+            .map(|tx| sequential.apply(tx, &H256::random(), &sender_public, &get_test_client()).unwrap())
+            .collect();
+
+        let mut parallel = state.clone();
+        let transactions: Vec<_> = transactions.iter().map(|tx| (tx, H256::random(), sender_public)).collect();
+        let parallel_invoices = parallel.apply_in_parallel(&transactions, &get_test_client()).unwrap();
+
+        assert_eq!(sequential_invoices, parallel_invoices);
+        assert_eq!(Invoice::Success, parallel_invoices[5]);
+        assert_eq!(Invoice::Failure(ParcelError::InvalidShardId(100)), parallel_invoices[6]);
+        assert_ne!(Invoice::Success, parallel_invoices[4]);
+        assert_eq!(sequential.commit(), parallel.commit());
+        check_top_level_state!(parallel, [
+            (account: sender => (seq: 7, balance: 1000 - 7 * 10))
+        ]);
+    }
+
+    #[test]
+    fn apply_in_parallel_applies_the_other_transactions_in_order() {
+        let (sender, sender_public, _) = address();
+        let receiver = Address::random();
+
+        let mut state = get_temp_state();
+        set_top_level_state!(state, [
+            (shard: 0 => owners: [sender]),
+            (metadata: shards: 1),
+            (account: sender => balance: 1000)
+        ]);
+        state.commit().unwrap();
+
+        let lock_script_hash = H160::from("b042ad154a3359d276835c903587ebafefea22af");
+        let mint_in_shard = |shard_id: ShardId, metadata: &str| {
+            let output = Box::new(asset_mint_output!(lock_script_hash, amount: 30));
+            let mut mint = mint_asset!(output, metadata.to_string());
+            if let Action::MintAsset {
+                shard_id: ref mut id,
+                ..
+            } = mint
+            {
+                *id = shard_id;
+            }
+            mint
+        };
+        // The second mint is valid only after the shard is created.
+        let actions = vec![
+            mint_in_shard(0, "first"),
+            Action::CreateShard,
+            mint_in_shard(1, "second"),
+            pay!(receiver, 10),
+            mint_in_shard(0, "third"),
+        ];
+        let transactions: Vec<_> = actions
+            .into_iter()
+            .enumerate()
+            .map(|(seq, action)| transaction!(seq: seq as u64, fee: 10, action))
+            .collect();
+
+        let mut sequential = state.clone();
+        let sequential_invoices: Vec<_> = transactions
+            .iter()
+            .map(|tx| sequential.apply(tx, &H256::random(), &sender_public, &get_test_client()).unwrap())
+            .collect();
+
+        let mut parallel = state.clone();
+        let transactions: Vec<_> = transactions.iter().map(|tx| (tx, H256::random(), sender_public)).collect();
+        let parallel_invoices = parallel.apply_in_parallel(&transactions, &get_test_client()).unwrap();
+
+        assert_eq!(sequential_invoices, parallel_invoices);
+        assert_eq!(vec![Invoice::Success; 5], parallel_invoices);
+        assert_eq!(sequential.commit(), parallel.commit());
+        check_top_level_state!(parallel, [
+            (account: receiver => (seq: 0, balance: 10)),
+            (shard: 1 => owners: [sender])
+        ]);
+    }
 }
@@ -34,6 +34,7 @@ pub struct AssetScheme {
     administrator: Option<Address>,
     allowed_script_hashes: Vec<H160>,
     pool: Vec<Asset>,
+    supply: Option<u64>,
 }
 
 impl AssetScheme {
@@ -51,6 +52,7 @@ impl AssetScheme {
             administrator,
             allowed_script_hashes,
             pool: Vec::new(),
+            supply: Some(amount),
         }
     }
 
@@ -69,6 +71,7 @@ impl AssetScheme {
             administrator,
             allowed_script_hashes,
             pool,
+            supply: Some(amount),
         }
     }
 
@@ -80,6 +83,23 @@ impl AssetScheme {
         self.amount
     }
 
+    /// The amount of the assets that are not burnt yet.
+    ///
+    /// The assets in the pool of a composed asset are counted as well.
+    /// It is `None` for the asset schemes created before the supply was tracked.
+    pub fn supply(&self) -> Option<u64> {
+        self.supply
+    }
+
+    pub fn increase_supply(&mut self, amount: u64) {
+        self.supply = self.supply.map(|supply| supply + amount);
+    }
+
+    pub fn decrease_supply(&mut self, amount: u64) {
+        // The supply which is inconsistent with the burnt assets is no longer tracked.
+        self.supply = self.supply.and_then(|supply| supply.checked_sub(amount));
+    }
+
     pub fn approver(&self) -> &Option<Address> {
         &self.approver
     }
@@ -109,6 +129,7 @@ impl AssetScheme {
         &mut self,
         metadata: String,
         amount: u64,
+        supply: Option<u64>,
         approver: Option<Address>,
         administrator: Option<Address>,
         allowed_script_hashes: Vec<H160>,
@@ -120,6 +141,7 @@ impl AssetScheme {
         assert_eq!(None, self.administrator);
         self.metadata = metadata;
         self.amount = amount;
+        self.supply = supply;
         self.approver = approver;
         self.administrator = administrator;
         self.allowed_script_hashes = allowed_script_hashes;
@@ -154,20 +176,29 @@ impl Default for AssetScheme {
 
 impl Encodable for AssetScheme {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list(7)
-            .append(&PREFIX)
-            .append(&self.metadata)
-            .append(&self.amount)
-            .append(&self.approver)
-            .append(&self.administrator)
-            .append_list(&self.allowed_script_hashes)
-            .append_list(&self.pool);
+        // The supply is omitted while it is not tracked to keep the encoding of the existing asset schemes.
+        s.begin_list(if self.supply.is_some() {
+            8
+        } else {
+            7
+        })
+        .append(&PREFIX)
+        .append(&self.metadata)
+        .append(&self.amount)
+        .append(&self.approver)
+        .append(&self.administrator)
+        .append_list(&self.allowed_script_hashes)
+        .append_list(&self.pool);
+        if let Some(supply) = self.supply {
+            s.append(&supply);
+        }
     }
 }
 
 impl Decodable for AssetScheme {
     fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
-        if rlp.item_count()? != 7 {
+        let item_count = rlp.item_count()?;
+        if item_count != 7 && item_count != 8 {
             return Err(DecoderError::RlpInvalidLength)
         }
 
@@ -183,6 +214,11 @@ impl Decodable for AssetScheme {
             administrator: rlp.val_at(4)?,
             allowed_script_hashes: rlp.list_at(5)?,
             pool: rlp.list_at(6)?,
+            supply: if item_count == 8 {
+                Some(rlp.val_at(7)?)
+            } else {
+                None
+            },
         })
     }
 }
@@ -271,4 +307,47 @@ mod tests {
         let asset_scheme_address = AssetSchemeAddress::from_hash(hash).unwrap();
         assert_eq!(shard_id, asset_scheme_address.shard_id());
     }
+
+    #[test]
+    fn encode_and_decode_asset_scheme() {
+        let mut asset_scheme = AssetScheme::new("metadata".to_string(), 100, None, None, Vec::new());
+        asset_scheme.decrease_supply(30);
+        let decoded = ::rlp::decode::<AssetScheme>(&asset_scheme.rlp_bytes());
+        assert_eq!(asset_scheme, decoded);
+        assert_eq!(Some(70), decoded.supply());
+    }
+
+    #[test]
+    fn decode_asset_scheme_encoded_before_the_supply_was_tracked() {
+        let approver = Some(Address::random());
+        let allowed_script_hashes = vec![H160::random()];
+        let pool: Vec<Asset> = Vec::new();
+        let mut legacy = RlpStream::new_list(7);
+        legacy
+            .append(&PREFIX)
+            .append(&"metadata")
+            .append(&100u64)
+            .append(&approver)
+            .append(&None::<Address>)
+            .append_list(&allowed_script_hashes)
+            .append_list(&pool);
+        let legacy = legacy.out();
+
+        let mut decoded = ::rlp::decode::<AssetScheme>(&legacy);
+        assert_eq!(100, decoded.amount());
+        assert_eq!(&approver, decoded.approver());
+        assert_eq!(&allowed_script_hashes[..], decoded.allowed_script_hashes());
+        assert_eq!(None, decoded.supply());
+        assert_eq!(legacy, decoded.rlp_bytes().to_vec());
+
+        decoded.decrease_supply(30);
+        assert_eq!(None, decoded.supply());
+    }
+
+    #[test]
+    fn decreasing_the_supply_below_zero_stops_tracking_it() {
+        let mut asset_scheme = AssetScheme::new("metadata".to_string(), 100, None, None, Vec::new());
+        asset_scheme.decrease_supply(101);
+        assert_eq!(None, asset_scheme.supply());
+    }
 }
@@ -24,12 +24,22 @@ use crate::CacheableItem;
 #[derive(Clone, Debug)]
 pub struct Metadata {
     number_of_shards: ShardId,
+    issued_ccc: u64,
+    burned_ccc: u64,
+    wrapped_ccc: u64,
+    unwrapped_ccc: u64,
+    burned_fee: u64,
 }
 
 impl Metadata {
     pub fn new(number_of_shards: ShardId) -> Self {
         Self {
             number_of_shards,
+            issued_ccc: 0,
+            burned_ccc: 0,
+            wrapped_ccc: 0,
+            unwrapped_ccc: 0,
+            burned_fee: 0,
         }
     }
 
@@ -43,10 +53,67 @@ impl Metadata {
         r
     }
 
+    /// The total amount of CCC issued after the genesis block, such as the block rewards and the distributed fees.
+    pub fn issued_ccc(&self) -> u64 {
+        self.issued_ccc
+    }
+
+    pub fn increase_issued_ccc(&mut self, amount: u64) {
+        self.issued_ccc += amount;
+    }
+
+    /// The total amount of CCC burned, such as the paid fees and the shard creation costs.
+    pub fn burned_ccc(&self) -> u64 {
+        self.burned_ccc
+    }
+
+    pub fn increase_burned_ccc(&mut self, amount: u64) {
+        self.burned_ccc += amount;
+    }
+
+    /// The total amount of CCC wrapped in the shards.
+    pub fn wrapped_ccc(&self) -> u64 {
+        self.wrapped_ccc
+    }
+
+    pub fn increase_wrapped_ccc(&mut self, amount: u64) {
+        self.wrapped_ccc += amount;
+    }
+
+    /// The total amount of wrapped CCC unwrapped from the shards.
+    pub fn unwrapped_ccc(&self) -> u64 {
+        self.unwrapped_ccc
+    }
+
+    pub fn increase_unwrapped_ccc(&mut self, amount: u64) {
+        self.unwrapped_ccc += amount;
+    }
+
+    /// The amount of CCC wrapped in the shards.
+    ///
+    /// It is `None` if more CCC is unwrapped than is tracked as wrapped,
+    /// which happens on the chains that wrapped CCC before the wrapping was tracked.
+    pub fn wrapped_ccc_supply(&self) -> Option<u64> {
+        self.wrapped_ccc.checked_sub(self.unwrapped_ccc)
+    }
+
+    /// The total amount of the fees burned instead of being paid to the block authors.
+    pub fn burned_fee(&self) -> u64 {
+        self.burned_fee
+    }
+
+    pub fn increase_burned_fee(&mut self, amount: u64) {
+        self.burned_fee += amount;
+    }
+
     #[cfg(test)]
     pub fn set_number_of_shards(&mut self, number_of_shards: ShardId) {
         self.number_of_shards = number_of_shards;
     }
+
+    fn counters(&self) -> [u64; 5] {
+        [self.issued_ccc, self.burned_ccc, self.wrapped_ccc, self.unwrapped_ccc, self.burned_fee]
+    }
 }
 
 impl Default for Metadata {
@@ -59,7 +126,7 @@ impl CacheableItem for Metadata {
     type Address = MetadataAddress;
 
     fn is_null(&self) -> bool {
-        self.number_of_shards == 0
+        self.number_of_shards == 0 && self.counters().iter().all(|counter| *counter == 0)
     }
 }
 
@@ -67,13 +134,20 @@ const PREFIX: u8 = super::METADATA_PREFIX;
 
 impl Encodable for Metadata {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list(2).append(&PREFIX).append(&self.number_of_shards);
+        // The trailing counters are omitted while they are zero to keep the state roots of the existing chains.
+        let counters = self.counters();
+        let number_of_counters = counters.iter().rposition(|counter| *counter != 0).map_or(0, |index| index + 1);
+        s.begin_list(2 + number_of_counters).append(&PREFIX).append(&self.number_of_shards);
+        for counter in &counters[..number_of_counters] {
+            s.append(counter);
+        }
     }
 }
 
 impl Decodable for Metadata {
     fn decode(rlp: &UntrustedRlp) -> Result<Self, DecoderError> {
-        if rlp.item_count()? != 2 {
+        let item_count = rlp.item_count()?;
+        if item_count < 2 || item_count > 7 {
             return Err(DecoderError::RlpInvalidLength)
         }
         let prefix = rlp.val_at::<u8>(0)?;
@@ -81,8 +155,20 @@ impl Decodable for Metadata {
             cdebug!(STATE, "{} is not an expected prefix for asset", prefix);
             return Err(DecoderError::Custom("Unexpected prefix"))
         }
+        let counter_at = |index: usize| -> Result<u64, DecoderError> {
+            if index < item_count {
+                rlp.val_at(index)
+            } else {
+                Ok(0)
+            }
+        };
         Ok(Self {
             number_of_shards: rlp.val_at(1)?,
+            issued_ccc: counter_at(2)?,
+            burned_ccc: counter_at(3)?,
+            wrapped_ccc: counter_at(4)?,
+            unwrapped_ccc: counter_at(5)?,
+            burned_fee: counter_at(6)?,
         })
     }
 }
@@ -102,6 +188,66 @@ impl MetadataAddress {
 mod tests {
     use super::*;
 
+    #[test]
+    fn encode_and_decode_metadata() {
+        let mut metadata = Metadata::new(3);
+        metadata.increase_issued_ccc(1000);
+        metadata.increase_burned_ccc(10);
+        metadata.increase_wrapped_ccc(300);
+        metadata.increase_unwrapped_ccc(100);
+        let decoded = ::rlp::decode::<Metadata>(&metadata.rlp_bytes());
+        assert_eq!(3, *decoded.number_of_shards());
+        assert_eq!(1000, decoded.issued_ccc());
+        assert_eq!(10, decoded.burned_ccc());
+        assert_eq!(Some(200), decoded.wrapped_ccc_supply());
+        assert_eq!(0, decoded.burned_fee());
+    }
+
+    #[test]
+    fn encode_and_decode_metadata_with_burned_fee() {
+        let mut metadata = Metadata::new(3);
+        metadata.increase_burned_fee(70);
+        let encoded = metadata.rlp_bytes();
+        assert_eq!(7, ::rlp::UntrustedRlp::new(&encoded).item_count().unwrap());
+        let decoded = ::rlp::decode::<Metadata>(&encoded);
+        assert_eq!(0, decoded.issued_ccc());
+        assert_eq!(70, decoded.burned_fee());
+    }
+
+    #[test]
+    fn counters_are_omitted_while_they_are_zero() {
+        let metadata = Metadata::new(3);
+        assert_eq!(2, ::rlp::UntrustedRlp::new(&metadata.rlp_bytes()).item_count().unwrap());
+
+        let mut metadata = Metadata::new(3);
+        metadata.increase_burned_ccc(1);
+        assert_eq!(4, ::rlp::UntrustedRlp::new(&metadata.rlp_bytes()).item_count().unwrap());
+    }
+
+    #[test]
+    fn metadata_without_counters_is_encoded_as_before() {
+        // The metadata encoded before the supply was tracked.
+        let mut legacy = RlpStream::new_list(2);
+        legacy.append(&PREFIX).append(&(3 as ShardId));
+        let legacy = legacy.out();
+
+        let decoded = ::rlp::decode::<Metadata>(&legacy);
+        assert_eq!(3, *decoded.number_of_shards());
+        assert_eq!(0, decoded.issued_ccc());
+        assert_eq!(0, decoded.burned_ccc());
+        assert_eq!(Some(0), decoded.wrapped_ccc_supply());
+        assert_eq!(0, decoded.burned_fee());
+        assert_eq!(legacy, decoded.rlp_bytes().to_vec());
+    }
+
+    #[test]
+    fn unwrapping_more_than_tracked_does_not_underflow() {
+        let mut metadata = Metadata::new(1);
+        metadata.increase_wrapped_ccc(10);
+        metadata.increase_unwrapped_ccc(30);
+        assert_eq!(None, metadata.wrapped_ccc_supply());
+    }
+
     #[test]
     fn parse_fail_return_none() {
         let hash = {
@@ -22,6 +22,7 @@ extern crate codechain_logger as clogger;
 extern crate codechain_key as ckey;
 extern crate codechain_types as ctypes;
 extern crate codechain_vm as cvm;
+extern crate crossbeam;
 extern crate hashdb;
 extern crate journaldb;
 extern crate kvdb;
@@ -55,7 +56,7 @@ pub use crate::action_handler::{
     ActionDataKeyBuilder, ActionHandler, ActionHandlerError, ActionHandlerResult, FindActionHandler, HitHandler,
 };
 pub use crate::checkpoint::{CheckpointId, StateWithCheckpoint};
-pub use crate::db::StateDB;
+pub use crate::db::{find_missing_nodes, RemoteNodes, StateDB};
 pub use crate::error::Error as StateError;
 pub use crate::impls::{ShardLevelState, TopLevelState};
 pub use crate::item::account::Account;
@@ -65,3 +65,267 @@ pub mod helpers {
         TestClient {}
     }
 }
+
+#[cfg(test)]
+mod supply {
+    use ckey::{Address, Generator, KeyPair, Random};
+    use ctypes::invoice::Invoice;
+    use ctypes::transaction::{
+        Action, AssetMintOutput, AssetOutPoint, AssetTransferInput, AssetTransferOutput, ShardTransaction, Transaction,
+    };
+    use ctypes::ShardId;
+    use primitives::{H160, H256};
+
+    use super::helpers::{get_temp_state, get_test_client};
+    use crate::{AssetSchemeAddress, TopLevelState, TopState, TopStateView};
+
+    const SHARD_ID: ShardId = 0;
+    const BLOCK_REWARD: u64 = 50;
+
+    struct Chain {
+        state: TopLevelState,
+        author: Address,
+        accounts: Vec<KeyPair>,
+        seqs: Vec<u64>,
+    }
+
+    impl Chain {
+        fn new(number_of_accounts: usize, initial_balance: u64) -> Self {
+            let mut state = get_temp_state();
+            let accounts: Vec<_> = (0..number_of_accounts).map(|_| Random.generate().unwrap()).collect();
+            for account in &accounts {
+                state.issue_ccc(&account.address(), initial_balance).unwrap();
+            }
+            Self {
+                state,
+                author: Address::random(),
+                accounts,
+                seqs: vec![0; number_of_accounts],
+            }
+        }
+
+        /// Applies the transaction in its own block and distributes the fee like the engines do.
+        fn apply(&mut self, signer: usize, fee: u64, action: Action) -> H256 {
+            self.apply_burning_fee(signer, fee, 0, action)
+        }
+
+        /// Applies the transaction and burns `burned_fee` of the fee instead of distributing it.
+        fn apply_burning_fee(&mut self, signer: usize, fee: u64, burned_fee: u64, action: Action) -> H256 {
+            let tx = Transaction {
+                seq: self.seqs[signer],
+                fee,
+                network_id: "tc".into(),
+                action,
+            };
+            let public = *self.accounts[signer].public();
+            assert_eq!(Ok(Invoice::Success), self.state.apply(&tx, &H256::random(), &public, &get_test_client()));
+            self.seqs[signer] += 1;
+
+            self.state.issue_ccc(&self.author, fee - burned_fee + BLOCK_REWARD).unwrap();
+            self.state.burn_fee(burned_fee).unwrap();
+            tx.hash()
+        }
+
+        fn address(&self, index: usize) -> Address {
+            self.accounts[index].address()
+        }
+
+        fn sum_of_balances(&self) -> u64 {
+            self.accounts
+                .iter()
+                .map(|account| account.address())
+                .chain(Some(self.author))
+                .map(|address| self.state.balance(&address).unwrap())
+                .sum()
+        }
+
+        fn asset_supply(&self, asset_type: H256) -> u64 {
+            let address = AssetSchemeAddress::from_hash(asset_type).unwrap();
+            self.state.asset_scheme(SHARD_ID, &address).unwrap().map_or(0, |scheme| scheme.supply().unwrap())
+        }
+
+        fn check_invariants(&self) {
+            let wrapped_ccc_type = AssetSchemeAddress::new_with_zero_suffix(SHARD_ID).into();
+            let wrapped_ccc_supply = self.state.wrapped_ccc_supply().unwrap().unwrap();
+            assert_eq!(Some(self.sum_of_balances() + wrapped_ccc_supply), self.state.ccc_supply(0).unwrap());
+            assert_eq!(self.asset_supply(wrapped_ccc_type), wrapped_ccc_supply);
+        }
+    }
+
+    fn input(prev_out: AssetOutPoint, lock_script: Vec<u8>) -> AssetTransferInput {
+        AssetTransferInput {
+            prev_out,
+            timelock: None,
+            lock_script,
+            unlock_script: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn supply_follows_the_balances_and_the_assets() {
+        // The hash of the script which is unlocked with an empty unlock script.
+        let lock_script_hash = H160::from("b042ad154a3359d276835c903587ebafefea22af");
+        let lock_script = vec![0x30, 0x01];
+        // The hash of the script which can only be burnt.
+        let lock_script_hash_burn = H160::from("ca5d3fa0a6887285ef6aa85cb12960a2b6706e00");
+        let lock_script_burn = vec![0x01];
+
+        let mut chain = Chain::new(2, 1_000);
+        chain.check_invariants();
+        assert_eq!(Ok(Some(2_000)), chain.state.ccc_supply(0));
+
+        // The shard creation cost is burnt.
+        chain.apply(0, 10, Action::CreateShard);
+        chain.check_invariants();
+        assert_eq!(Ok(Some(2_000 - 1 + BLOCK_REWARD)), chain.state.ccc_supply(0));
+
+        let receiver = chain.address(1);
+        chain.apply(0, 10, Action::Pay {
+            receiver,
+            amount: 100,
+        });
+        chain.check_invariants();
+
+        let wrap_tx_hash = chain.apply(1, 10, Action::WrapCCC {
+            shard_id: SHARD_ID,
+            lock_script_hash: lock_script_hash_burn,
+            parameters: Vec::new(),
+            amount: 300,
+        });
+        chain.check_invariants();
+        assert_eq!(Ok(Some(300)), chain.state.wrapped_ccc_supply());
+
+        let mint = Action::MintAsset {
+            network_id: "tc".into(),
+            shard_id: SHARD_ID,
+            metadata: "metadata".to_string(),
+            approver: None,
+            administrator: None,
+            allowed_script_hashes: Vec::new(),
+            output: Box::new(AssetMintOutput {
+                lock_script_hash,
+                parameters: Vec::new(),
+                amount: Some(100),
+            }),
+            approvals: Vec::new(),
+        };
+        let mint_tracker = Option::<ShardTransaction>::from(mint.clone()).unwrap().tracker();
+        let asset_type: H256 = AssetSchemeAddress::new(mint_tracker, SHARD_ID).into();
+        chain.apply(0, 10, mint);
+        chain.check_invariants();
+        assert_eq!(100, chain.asset_supply(asset_type));
+
+        let transfer = Action::TransferAsset {
+            network_id: "tc".into(),
+            burns: Vec::new(),
+            inputs: vec![input(
+                AssetOutPoint {
+                    tracker: mint_tracker,
+                    index: 0,
+                    asset_type,
+                    amount: 100,
+                },
+                lock_script,
+            )],
+            outputs: vec![
+                AssetTransferOutput {
+                    lock_script_hash,
+                    parameters: Vec::new(),
+                    asset_type,
+                    amount: 60,
+                },
+                AssetTransferOutput {
+                    lock_script_hash: lock_script_hash_burn,
+                    parameters: Vec::new(),
+                    asset_type,
+                    amount: 40,
+                },
+            ],
+            orders: Vec::new(),
+            approvals: Vec::new(),
+        };
+        let transfer_tracker = Option::<ShardTransaction>::from(transfer.clone()).unwrap().tracker();
+        chain.apply(0, 10, transfer);
+        chain.check_invariants();
+        assert_eq!(100, chain.asset_supply(asset_type));
+
+        chain.apply(0, 10, Action::TransferAsset {
+            network_id: "tc".into(),
+            burns: vec![input(
+                AssetOutPoint {
+                    tracker: transfer_tracker,
+                    index: 1,
+                    asset_type,
+                    amount: 40,
+                },
+                lock_script_burn.clone(),
+            )],
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            orders: Vec::new(),
+            approvals: Vec::new(),
+        });
+        chain.check_invariants();
+        assert_eq!(60, chain.asset_supply(asset_type));
+
+        let wrapped_ccc_type = AssetSchemeAddress::new_with_zero_suffix(SHARD_ID).into();
+        chain.apply(1, 10, Action::UnwrapCCC {
+            network_id: "tc".into(),
+            burn: input(
+                AssetOutPoint {
+                    tracker: wrap_tx_hash,
+                    index: 0,
+                    asset_type: wrapped_ccc_type,
+                    amount: 300,
+                },
+                lock_script_burn,
+            ),
+            approvals: Vec::new(),
+        });
+        chain.check_invariants();
+        assert_eq!(Ok(Some(0)), chain.state.wrapped_ccc_supply());
+        assert_eq!(Ok(Some(2_000 - 1 + 7 * BLOCK_REWARD)), chain.state.ccc_supply(0));
+    }
+    #[test]
+    fn burned_fees_are_not_issued() {
+        let mut chain = Chain::new(2, 1_000);
+        let receiver = chain.address(1);
+        chain.apply_burning_fee(0, 10, 3, Action::Pay {
+            receiver,
+            amount: 100,
+        });
+        chain.check_invariants();
+        assert_eq!(Ok(Some(2_000 - 3 + BLOCK_REWARD)), chain.state.ccc_supply(0));
+        assert_eq!(Ok(3), chain.state.burned_fee());
+
+        let receiver = chain.address(0);
+        chain.apply_burning_fee(1, 10, 10, Action::Pay {
+            receiver,
+            amount: 100,
+        });
+        chain.check_invariants();
+        assert_eq!(Ok(Some(2_000 - 13 + 2 * BLOCK_REWARD)), chain.state.ccc_supply(0));
+        assert_eq!(Ok(13), chain.state.burned_fee());
+    }
+
+    #[test]
+    fn supply_is_not_tracked_before_the_activation() {
+        let mut chain = Chain::new(2, 1_000);
+        chain.state.set_supply_tracking(false);
+
+        chain.apply_burning_fee(0, 10, 3, Action::CreateShard);
+        chain.apply(1, 10, Action::WrapCCC {
+            shard_id: SHARD_ID,
+            lock_script_hash: H160::random(),
+            parameters: Vec::new(),
+            amount: 300,
+        });
+        assert_eq!(Ok(Some(2_000)), chain.state.ccc_supply(0));
+        assert_eq!(Ok(Some(0)), chain.state.wrapped_ccc_supply());
+        assert_eq!(Ok(0), chain.state.burned_fee());
+
+        let wrapped_ccc = AssetSchemeAddress::new_with_zero_suffix(SHARD_ID);
+        let scheme = chain.state.asset_scheme(SHARD_ID, &wrapped_ccc).unwrap().unwrap();
+        assert_eq!(None, scheme.supply());
+    }
+}
@@ -89,6 +89,27 @@ pub trait TopStateView {
         Ok(*self.metadata()?.expect("Metadata must exist").number_of_shards())
     }
 
+    /// Get the amount of CCC in the accounts and in the shards as wrapped CCC.
+    ///
+    /// `genesis_supply` is the amount of CCC given to the accounts in the genesis block.
+    /// It is `None` if more CCC is burned than is tracked, which happens on the chains
+    /// that were created before the supply was tracked.
+    fn ccc_supply(&self, genesis_supply: u64) -> TrieResult<Option<u64>> {
+        let (issued, burned) =
+            self.metadata()?.map_or((0, 0), |metadata| (metadata.issued_ccc(), metadata.burned_ccc()));
+        Ok((genesis_supply + issued).checked_sub(burned))
+    }
+
+    /// Get the amount of CCC wrapped in the shards.
+    fn wrapped_ccc_supply(&self) -> TrieResult<Option<u64>> {
+        Ok(self.metadata()?.map_or(Some(0), |metadata| metadata.wrapped_ccc_supply()))
+    }
+
+    /// Get the total amount of the burned fees.
+    fn burned_fee(&self) -> TrieResult<u64> {
+        Ok(self.metadata()?.map_or(0, |metadata| metadata.burned_fee()))
+    }
+
     fn shard(&self, shard_id: ShardId) -> TrieResult<Option<Shard>>;
     fn shard_state<'db>(&'db self, shard_id: ShardId) -> TrieResult<Option<Box<ShardStateView + 'db>>>;
 
@@ -154,6 +175,12 @@ pub trait TopState {
 
     /// Add `incr` to the balance of account `a`.
     fn add_balance(&mut self, a: &Address, incr: u64) -> TrieResult<()>;
+    /// Add newly issued `incr` CCC to the balance of account `a`.
+    /// The block reward and the distributed fees are issued by this.
+    fn issue_ccc(&mut self, a: &Address, incr: u64) -> TrieResult<()>;
+    /// Add `amount` to the total of the burned fees.
+    /// The fees are not issued to anyone, so the CCC supply decreases by them.
+    fn burn_fee(&mut self, amount: u64) -> TrieResult<()>;
     /// Subtract `decr` from the balance of account `a`.
     fn sub_balance(&mut self, a: &Address, decr: u64) -> StateResult<()>;
     /// Subtracts `by` from the balance of `from` and adds it to that of `to`.
@@ -177,6 +204,7 @@ pub trait TopState {
     fn remove_text(&mut self, key: &H256, sig: &Signature) -> StateResult<()>;
 
     fn update_action_data(&mut self, key: &H256, data: Bytes) -> StateResult<()>;
+    fn remove_action_data(&mut self, key: &H256);
 }
 
 pub trait StateWithCache {
@@ -17,6 +17,7 @@
 use std::collections::{HashMap, HashSet};
 
 use ccore::{Header, UnverifiedTransaction};
+use ctypes::BlockNumber;
 use primitives::H256;
 
 use super::super::message::RequestMessage;
@@ -24,6 +25,7 @@ use super::super::message::RequestMessage;
 #[derive(Clone)]
 struct Target {
     hash: H256,
+    number: BlockNumber,
     parent_hash: H256,
     parcels_root: H256,
     parent_root: H256,
@@ -33,6 +35,8 @@ pub struct BodyDownloader {
     targets: Vec<Target>,
     downloading: HashSet<H256>,
     downloaded: HashMap<H256, Vec<UnverifiedTransaction>>,
+    /// The target which no peer can serve, which is already reported.
+    unservable: Option<H256>,
 }
 
 impl BodyDownloader {
@@ -41,13 +45,18 @@ impl BodyDownloader {
             targets: Vec::new(),
             downloading: HashSet::new(),
             downloaded: HashMap::new(),
+            unservable: None,
         }
     }
 
-    pub fn create_request(&mut self) -> Option<RequestMessage> {
+    /// Creates a request for the bodies which the peer has. The peer pruned the bodies up to `pruned_until`.
+    pub fn create_request(&mut self, pruned_until: BlockNumber) -> Option<RequestMessage> {
         const MAX_BODY_REQEUST_LENGTH: usize = 128;
         let mut hashes = Vec::new();
         for t in &self.targets {
+            if t.number <= pruned_until {
+                continue
+            }
             if !self.downloading.contains(&t.hash) && !self.downloaded.contains_key(&t.hash) {
                 hashes.push(t.hash);
             }
@@ -63,6 +72,20 @@ impl BodyDownloader {
         }
     }
 
+    /// Returns the first target which is not downloaded, if every peer pruned it and it is not reported yet.
+    /// `pruned_until` is the lowest block number up to which the peers pruned the bodies.
+    pub fn unservable_target(&mut self, pruned_until: BlockNumber) -> Option<(BlockNumber, H256)> {
+        let (number, hash) = {
+            let target = self.targets.iter().find(|t| !self.downloaded.contains_key(&t.hash))?;
+            (target.number, target.hash)
+        };
+        if number > pruned_until || self.unservable == Some(hash) {
+            return None
+        }
+        self.unservable = Some(hash);
+        Some((number, hash))
+    }
+
     pub fn import_bodies(&mut self, hashes: Vec<H256>, bodies: Vec<Vec<UnverifiedTransaction>>) {
         for (hash, body) in hashes.into_iter().zip(bodies) {
             if self.downloading.remove(&hash) {
@@ -81,6 +104,7 @@ impl BodyDownloader {
         ctrace!(SYNC, "Add download target: {}", header.hash());
         self.targets.push(Target {
             hash: header.hash(),
+            number: header.number(),
             parent_hash: parent.hash(),
             parcels_root: *header.transactions_root(),
             parent_root: *parent.transactions_root(),
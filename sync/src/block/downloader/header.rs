@@ -20,6 +20,7 @@ use std::time::Instant;
 
 use ccore::encoded::Header;
 use ccore::{BlockChainClient, BlockId};
+use ctypes::BlockNumber;
 use primitives::{H256, U256};
 
 use super::super::message::RequestMessage;
@@ -41,6 +42,7 @@ pub struct HeaderDownloader {
 
     total_score: U256,
     best_hash: H256,
+    pruned_until: BlockNumber,
 
     pivot: Pivot,
     request_time: Option<Instant>,
@@ -53,7 +55,12 @@ impl HeaderDownloader {
         self.total_score
     }
 
-    pub fn new(client: Arc<BlockChainClient>, total_score: U256, best_hash: H256) -> Self {
+    /// The peer pruned the bodies of the blocks up to this number.
+    pub fn pruned_until(&self) -> BlockNumber {
+        self.pruned_until
+    }
+
+    pub fn new(client: Arc<BlockChainClient>, total_score: U256, best_hash: H256, pruned_until: BlockNumber) -> Self {
         let best_header_hash = client.best_block_header().hash();
         let best_score = client.block_total_score(&BlockId::Latest).expect("Best block always exist");
 
@@ -62,6 +69,7 @@ impl HeaderDownloader {
 
             total_score,
             best_hash,
+            pruned_until,
 
             pivot: Pivot {
                 hash: best_header_hash,
@@ -73,9 +81,10 @@ impl HeaderDownloader {
         }
     }
 
-    pub fn update(&mut self, total_score: U256, best_hash: H256) {
+    pub fn update(&mut self, total_score: U256, best_hash: H256, pruned_until: BlockNumber) {
         self.total_score = total_score;
         self.best_hash = best_hash;
+        self.pruned_until = pruned_until;
     }
 
     fn is_valid(&self) -> bool {
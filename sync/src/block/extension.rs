@@ -16,20 +16,23 @@
 
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::{Duration as StdDuration, Instant};
 
 use ccore::encoded::Header as EncodedHeader;
 use ccore::{
-    Block, BlockChainClient, BlockId, BlockImportError, BlockInfo, ChainInfo, ChainNotify, Client, Header, ImportBlock,
-    ImportError, Seal, UnverifiedTransaction,
+    Block, BlockChainClient, BlockId, BlockImportError, BlockInfo, ChainInfo, ChainNotify, Client, DatabaseClient,
+    Header, ImportBlock, ImportError, Seal, UnverifiedTransaction,
 };
 use cnetwork::{Api, NetworkExtension, NodeId};
-use cstate::FindActionHandler;
+use cstate::{FindActionHandler, RemoteNodes};
 use ctimer::{TimeoutHandler, TimerToken};
 use ctypes::transaction::Action;
 use ctypes::BlockNumber;
-use parking_lot::{Mutex, RwLock};
-use primitives::{H256, U256};
+use hashdb::HashDB;
+use kvdb::DBValue;
+use parking_lot::{Condvar, Mutex, RwLock};
+use primitives::{Bytes, H256, U256};
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
 use rlp::{Encodable, UntrustedRlp};
@@ -39,6 +42,7 @@ use token_generator::TokenGenerator;
 use super::super::block::BlockSyncInfo;
 use super::downloader::{BodyDownloader, HeaderDownloader};
 use super::message::{Message, RequestMessage, ResponseMessage};
+use super::state_nodes::{nodes_on_path, store_nodes, verify_nodes_on_path, MAX_PATH_NIBBLES, MAX_STATE_NODES};
 
 const SYNC_TIMER_TOKEN: TimerToken = 0;
 const SYNC_EXPIRE_TOKEN_BEGIN: TimerToken = SYNC_TIMER_TOKEN + 1;
@@ -50,6 +54,14 @@ const SYNC_EXPIRE_REQUEST_INTERVAL: i64 = 15000;
 
 const SNAPSHOT_PERIOD: u64 = (1 << 14);
 
+const STATE_NODE_FETCH_TIMEOUT_MS: u64 = 5000;
+const STATE_NODE_FETCH_TRIALS: usize = 3;
+
+/// The status has the block number up to which the bodies are pruned since the version 1.
+const STATUS_WITH_PRUNED_UNTIL_VERSION: u64 = 1;
+/// The state nodes can be requested since the version 1.
+const STATE_NODES_VERSION: u64 = 1;
+
 #[derive(Debug, PartialEq)]
 pub struct TokenInfo {
     node_id: NodeId,
@@ -66,11 +78,17 @@ pub struct Extension {
     client: Arc<Client>,
     api: RwLock<Option<Arc<Api>>>,
     last_request: AtomicUsize,
+    /// The light client downloads only the headers.
+    light: bool,
+    /// The versions of the protocol which are negotiated with the peers.
+    peer_versions: RwLock<HashMap<NodeId, u64>>,
+    fetched_nodes: Mutex<HashMap<u64, Vec<Bytes>>>,
+    node_fetched: Condvar,
 }
 
 impl Extension {
     #![cfg_attr(feature = "cargo-clippy", allow(clippy::new_ret_no_self))]
-    pub fn new(client: Arc<Client>) -> Arc<Self> {
+    pub fn new(client: Arc<Client>, light: bool) -> Arc<Self> {
         Arc::new(Self {
             requests: RwLock::new(HashMap::new()),
             header_downloaders: RwLock::new(HashMap::new()),
@@ -81,14 +99,60 @@ impl Extension {
             client,
             api: RwLock::new(None),
             last_request: AtomicUsize::new(0),
+            light,
+            peer_versions: RwLock::new(HashMap::new()),
+            fetched_nodes: Mutex::new(HashMap::new()),
+            node_fetched: Condvar::new(),
         })
     }
 
+    /// Returns the trie node fetcher which the light client's state DB uses.
+    pub fn state_node_fetcher(this: &Arc<Self>) -> StateNodeFetcher {
+        StateNodeFetcher {
+            extension: Arc::downgrade(this),
+        }
+    }
+
     fn send_message(&self, id: &NodeId, message: &Message) {
         let api = self.api.read();
         api.as_ref().expect("Api must exist").send(id, &*message.rlp_bytes());
     }
 
+    /// Sends the status in the format of the version which is negotiated with the peer.
+    fn send_status(&self, id: &NodeId) {
+        let version = self.peer_versions.read().get(id).cloned().unwrap_or(0);
+        let pruned_until = if version >= STATUS_WITH_PRUNED_UNTIL_VERSION {
+            Some(self.pruned_until())
+        } else {
+            None
+        };
+        let chain_info = self.client.chain_info();
+        self.send_message(
+            id,
+            &Message::Status {
+                total_score: chain_info.highest_score,
+                best_hash: chain_info.best_block_hash,
+                genesis_hash: chain_info.genesis_hash,
+                pruned_until,
+            },
+        );
+    }
+
+    fn broadcast_status(&self) {
+        for id in self.header_downloaders.read().keys() {
+            self.send_status(id);
+        }
+    }
+
+    /// The light client doesn't have any body, so it announces that the bodies up to the best header are pruned.
+    fn pruned_until(&self) -> BlockNumber {
+        if self.light {
+            self.client.best_header().number()
+        } else {
+            self.client.pruned_until()
+        }
+    }
+
     fn dismiss_request(&self, id: &NodeId, request_id: u64) {
         if let Some(requests) = self.requests.write().get_mut(id) {
             requests.retain(|(i, _)| *i != request_id);
@@ -104,6 +168,13 @@ impl Extension {
     }
 
     fn send_body_request(&self, id: &NodeId) {
+        if self.light {
+            return
+        }
+        let pruned_until = match self.header_downloaders.read().get(id) {
+            Some(peer) => peer.pruned_until(),
+            None => return,
+        };
         if let Some(requests) = self.requests.write().get_mut(id) {
             let have_body_request = {
                 requests.iter().any(|r| match r {
@@ -115,7 +186,7 @@ impl Extension {
                 return
             }
 
-            if let Some(request) = self.body_downloader.lock().create_request() {
+            if let Some(request) = self.body_downloader.lock().create_request(pruned_until) {
                 let request_id = self.last_request.fetch_add(1, Ordering::Relaxed) as u64;
                 requests.push((request_id, request.clone()));
                 self.send_message(id, &Message::Request(request_id, request));
@@ -139,6 +210,77 @@ impl Extension {
     fn send_response(&self, id: &NodeId, request_id: u64, response: ResponseMessage) {
         self.send_message(id, &Message::Response(request_id, response));
     }
+
+    /// Fetches the node and the nodes below it on the path at once.
+    fn fetch_state_nodes(&self, hash: &H256, path: &[u8]) -> Option<DBValue> {
+        let mut peer_ids: Vec<_> = {
+            let peer_versions = self.peer_versions.read();
+            self.header_downloaders
+                .read()
+                .keys()
+                .filter(|id| peer_versions.get(*id).map_or(false, |version| *version >= STATE_NODES_VERSION))
+                .cloned()
+                .collect()
+        };
+        peer_ids.shuffle(&mut thread_rng());
+
+        for id in peer_ids.into_iter().take(STATE_NODE_FETCH_TRIALS) {
+            let request_id = self.last_request.fetch_add(1, Ordering::Relaxed) as u64;
+            let request = RequestMessage::StateNodes {
+                hash: *hash,
+                path: path.to_vec(),
+            };
+            match self.requests.write().get_mut(&id) {
+                Some(requests) => requests.push((request_id, request.clone())),
+                None => continue,
+            }
+            self.send_message(&id, &Message::Request(request_id, request));
+
+            let deadline = Instant::now() + StdDuration::from_millis(STATE_NODE_FETCH_TIMEOUT_MS);
+            let nodes = {
+                let mut fetched_nodes = self.fetched_nodes.lock();
+                loop {
+                    if let Some(nodes) = fetched_nodes.remove(&request_id) {
+                        break Some(nodes)
+                    }
+                    if self.node_fetched.wait_until(&mut fetched_nodes, deadline).timed_out() {
+                        break fetched_nodes.remove(&request_id)
+                    }
+                }
+            };
+            let nodes = match nodes {
+                Some(nodes) => verify_nodes_on_path(hash, path, nodes),
+                None => {
+                    cdebug!(SYNC, "Peer #{} didn't send the state node {} in time", id, hash);
+                    self.dismiss_request(&id, request_id);
+                    continue
+                }
+            };
+            let node = match nodes.first() {
+                Some((_, node)) => DBValue::from_slice(node),
+                None => {
+                    cdebug!(SYNC, "Peer #{} didn't send a valid state node for {}", id, hash);
+                    continue
+                }
+            };
+            if let Err(err) = store_nodes(self.client.database(), self.client.pruning(), &nodes) {
+                cwarn!(SYNC, "Cannot store the state nodes below {}: {}", hash, err);
+            }
+            return Some(node)
+        }
+        None
+    }
+}
+
+/// Fetches the trie nodes, which the light client doesn't have, from the peers.
+pub struct StateNodeFetcher {
+    extension: Weak<Extension>,
+}
+
+impl RemoteNodes for StateNodeFetcher {
+    fn fetch(&self, hash: &H256, path: &[u8]) -> Option<DBValue> {
+        self.extension.upgrade()?.fetch_state_nodes(hash, path)
+    }
 }
 
 impl NetworkExtension for Extension {
@@ -150,7 +292,7 @@ impl NetworkExtension for Extension {
     }
 
     fn versions(&self) -> &[u64] {
-        const VERSIONS: &[u64] = &[0];
+        const VERSIONS: &[u64] = &[0, STATUS_WITH_PRUNED_UNTIL_VERSION];
         &VERSIONS
     }
 
@@ -159,6 +301,10 @@ impl NetworkExtension for Extension {
         api.set_timer(SYNC_TIMER_TOKEN, Duration::milliseconds(SYNC_TIMER_INTERVAL)).expect("Timer set succeeds");
         *api_lock = Some(api);
 
+        if self.light {
+            cinfo!(SYNC, "Sync extension initialized in the light mode");
+            return
+        }
         let mut header = self.client.best_header();
         let mut hollow_headers = vec![header.decode()];
         while self.client.block_body(&BlockId::Hash(header.hash())).is_none() {
@@ -177,22 +323,15 @@ impl NetworkExtension for Extension {
         cinfo!(SYNC, "Sync extension initialized");
     }
 
-    fn on_node_added(&self, id: &NodeId, _version: u64) {
+    fn on_node_added(&self, id: &NodeId, version: u64) {
         let mut requests = self.requests.write();
         let mut tokens = self.tokens.write();
         let mut tokens_info = self.tokens_info.write();
         let mut token_generator = self.token_generator.lock();
 
-        cinfo!(SYNC, "New peer detected #{}", id);
-        let chain_info = self.client.chain_info();
-        self.send_message(
-            id,
-            &Message::Status {
-                total_score: chain_info.highest_score,
-                best_hash: chain_info.best_block_hash,
-                genesis_hash: chain_info.genesis_hash,
-            },
-        );
+        cinfo!(SYNC, "New peer detected #{} (version {})", id, version);
+        self.peer_versions.write().insert(*id, version);
+        self.send_status(id);
 
         let token = token_generator.gen().expect("Token generator is full");
         let token_info = TokenInfo {
@@ -218,6 +357,7 @@ impl NetworkExtension for Extension {
 
         cinfo!(SYNC, "Peer removed #{}", id);
         header_downloaders.remove(id);
+        self.peer_versions.write().remove(id);
 
         requests.remove(id);
         if let Some(token) = tokens.remove(id) {
@@ -233,7 +373,8 @@ impl NetworkExtension for Extension {
                     total_score,
                     best_hash,
                     genesis_hash,
-                } => self.on_peer_status(id, total_score, best_hash, genesis_hash),
+                    pruned_until,
+                } => self.on_peer_status(id, total_score, best_hash, genesis_hash, pruned_until),
                 Message::Request(request_id, request) => self.on_peer_request(id, request_id, request),
                 Message::Response(request_id, response) => self.on_peer_response(id, request_id, response),
             }
@@ -268,6 +409,18 @@ impl TimeoutHandler for Extension {
                         self.send_body_request(&id);
                     }
                 }
+
+                if !self.light {
+                    // The body which every peer pruned cannot be downloaded, so the sync stalls at the block
+                    // until a peer which keeps it connects.
+                    let pruned_until =
+                        self.header_downloaders.read().values().map(HeaderDownloader::pruned_until).min();
+                    if let Some(pruned_until) = pruned_until {
+                        if let Some((number, hash)) = self.body_downloader.lock().unservable_target(pruned_until) {
+                            cwarn!(SYNC, "No peer can serve block #{} ({}), every peer pruned its body", number, hash);
+                        }
+                    }
+                }
             }
             SYNC_EXPIRE_TOKEN_BEGIN...SYNC_EXPIRE_TOKEN_END => {
                 let (id, request_id) = {
@@ -322,6 +475,11 @@ impl ChainNotify for Extension {
                 peer.mark_as_imported(imported.clone());
             }
         }
+        if self.light {
+            // The light client announces the new best header, since it doesn't import blocks.
+            self.broadcast_status();
+            return
+        }
         let mut headers_to_download: Vec<_> = enacted
             .into_iter()
             .map(|hash| self.client.block_header(&BlockId::Hash(hash)).expect("Enacted header must exist"))
@@ -354,37 +512,41 @@ impl ChainNotify for Extension {
         self.body_downloader.lock().remove_target(&imported);
         self.body_downloader.lock().remove_target(&invalid);
 
-
-        let chain_info = self.client.chain_info();
-        let peer_ids = self.header_downloaders.read();
-        for id in peer_ids.keys() {
-            self.send_message(
-                id,
-                &Message::Status {
-                    total_score: chain_info.highest_score,
-                    best_hash: chain_info.best_block_hash,
-                    genesis_hash: chain_info.genesis_hash,
-                },
-            );
-        }
+        self.broadcast_status();
     }
 }
 
 impl Extension {
-    fn on_peer_status(&self, from: &NodeId, total_score: U256, best_hash: H256, genesis_hash: H256) {
+    fn on_peer_status(
+        &self,
+        from: &NodeId,
+        total_score: U256,
+        best_hash: H256,
+        genesis_hash: H256,
+        pruned_until: Option<BlockNumber>,
+    ) {
+        // The peers of the version 0 don't prune the bodies.
+        let pruned_until = pruned_until.unwrap_or(0);
         // Validity check
         if genesis_hash != self.client.chain_info().genesis_hash {
             cinfo!(SYNC, "Genesis hash mismatch with peer {}", from);
             return
         }
 
-        cinfo!(SYNC, "Peer #{} status update: total_score: {}, best_hash: {}", from, total_score, best_hash);
+        cinfo!(
+            SYNC,
+            "Peer #{} status update: total_score: {}, best_hash: {}, pruned_until: {}",
+            from,
+            total_score,
+            best_hash,
+            pruned_until
+        );
 
         let mut peers = self.header_downloaders.write();
         if peers.contains_key(from) {
-            peers.get_mut(from).unwrap().update(total_score, best_hash);
+            peers.get_mut(from).unwrap().update(total_score, best_hash, pruned_until);
         } else {
-            peers.insert(*from, HeaderDownloader::new(self.client.clone(), total_score, best_hash));
+            peers.insert(*from, HeaderDownloader::new(self.client.clone(), total_score, best_hash, pruned_until));
         }
     }
 
@@ -410,6 +572,10 @@ impl Extension {
                 block_hash,
                 tree_root,
             } => self.create_state_chunk_response(block_hash, tree_root),
+            RequestMessage::StateNodes {
+                hash,
+                path,
+            } => self.create_state_nodes_response(hash, path),
         };
 
         self.send_response(from, id, response);
@@ -420,7 +586,8 @@ impl Extension {
             RequestMessage::Headers {
                 ..
             } => true,
-            RequestMessage::Bodies(hashes) => !hashes.is_empty(),
+            // The light client doesn't have the bodies to serve.
+            RequestMessage::Bodies(hashes) => !self.light && !hashes.is_empty(),
             RequestMessage::StateHead(hash) => match self.client.block_number(&BlockId::Hash(*hash)) {
                 Some(number) if number % SNAPSHOT_PERIOD == 0 => true,
                 _ => false,
@@ -436,6 +603,11 @@ impl Extension {
                 // FIXME:  check tree_root
                 unimplemented!()
             }
+            // The light client doesn't have the state to serve.
+            RequestMessage::StateNodes {
+                path,
+                ..
+            } => !self.light && path.len() <= MAX_PATH_NIBBLES && path.iter().all(|nibble| *nibble < 16),
         }
     }
 
@@ -466,6 +638,12 @@ impl Extension {
         unimplemented!()
     }
 
+    fn create_state_nodes_response(&self, hash: H256, path: Vec<u8>) -> ResponseMessage {
+        let state_db = self.client.state_db().read();
+        let nodes = nodes_on_path(|hash| state_db.get(hash).map(|node| node.to_vec()), &hash, &path);
+        ResponseMessage::StateNodes(nodes)
+    }
+
     fn on_peer_response(&self, from: &NodeId, id: u64, mut response: ResponseMessage) {
         let last_request = self.requests.read()[from].iter().find(|(i, _)| *i == id).cloned();
         if let Some((_, request)) = last_request {
@@ -502,6 +680,11 @@ impl Extension {
                     self.dismiss_request(from, id);
                     self.on_body_response(hashes, bodies);
                 }
+                ResponseMessage::StateNodes(nodes) => {
+                    self.dismiss_request(from, id);
+                    self.fetched_nodes.lock().insert(id, nodes);
+                    self.node_fetched.notify_all();
+                }
                 _ => unimplemented!(),
             }
         }
@@ -554,6 +737,12 @@ impl Extension {
                 },
                 ResponseMessage::StateChunk(..),
             ) => unimplemented!(),
+            (
+                RequestMessage::StateNodes {
+                    ..
+                },
+                ResponseMessage::StateNodes(nodes),
+            ) => nodes.len() <= MAX_STATE_NODES,
             _ => false,
         }
     }
@@ -642,3 +831,50 @@ impl BlockSyncInfo for Extension {
         self.header_downloaders.read().keys().cloned().collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ccore::{ClientConfig, ClientService, Miner, MinerOptions, Scheme};
+    use tempfile::{tempdir, TempDir};
+
+    use super::*;
+
+    fn new_extension(light: bool) -> (Arc<Extension>, ClientService, TempDir) {
+        let scheme = Scheme::new_test_solo();
+        let miner = Miner::new(MinerOptions::default(), &scheme, None);
+        let config = ClientConfig {
+            light,
+            ..Default::default()
+        };
+        let dir = tempdir().unwrap();
+        let service = ClientService::start(&config, &scheme, dir.path(), miner).unwrap();
+        (Extension::new(service.client(), light), service, dir)
+    }
+
+    #[test]
+    fn light_client_does_not_download_the_bodies() {
+        let (extension, service, _dir) = new_extension(true);
+        let genesis = service.client().block_header(&BlockId::Number(0)).unwrap();
+        let hash = H256::from(1);
+        // The full client downloads the body of every enacted header, but the light client skips it.
+        extension.new_headers(vec![hash], vec![], vec![hash, genesis.hash()], vec![], vec![], 0, None);
+        assert_eq!(None, extension.body_downloader.lock().create_request(0));
+    }
+
+    #[test]
+    fn light_client_refuses_to_serve_the_bodies_and_the_state_nodes() {
+        let (light, _light_service, _light_dir) = new_extension(true);
+        let (full, full_service, _full_dir) = new_extension(false);
+        let genesis = full_service.client().block_header(&BlockId::Number(0)).unwrap();
+
+        let bodies = RequestMessage::Bodies(vec![genesis.hash()]);
+        let state_nodes = RequestMessage::StateNodes {
+            hash: genesis.state_root(),
+            path: vec![0, 1],
+        };
+        assert!(full.is_valid_request(&bodies));
+        assert!(full.is_valid_request(&state_nodes));
+        assert!(!light.is_valid_request(&bodies));
+        assert!(!light.is_valid_request(&state_nodes));
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use ctypes::BlockNumber;
 use primitives::{H256, U256};
 use rlp::{Decodable, DecoderError, Encodable, RlpStream, UntrustedRlp};
 
@@ -32,6 +33,8 @@ const MESSAGE_ID_GET_STATE_HEAD: u8 = 0x06;
 const MESSAGE_ID_STATE_HEAD: u8 = 0x07;
 const MESSAGE_ID_GET_STATE_CHUNK: u8 = 0x08;
 const MESSAGE_ID_STATE_CHUNK: u8 = 0x09;
+const MESSAGE_ID_GET_STATE_NODES: u8 = 0x0a;
+const MESSAGE_ID_STATE_NODES: u8 = 0x0b;
 
 #[derive(Debug, PartialEq)]
 pub enum Message {
@@ -39,6 +42,9 @@ pub enum Message {
         total_score: U256,
         best_hash: H256,
         genesis_hash: H256,
+        /// The bodies of the blocks up to this number are pruned.
+        /// It's `None` in the status of the version 0, which doesn't have it.
+        pruned_until: Option<BlockNumber>,
     },
     Request(u64, RequestMessage),
     Response(u64, ResponseMessage),
@@ -51,14 +57,23 @@ impl Encodable for Message {
                 total_score,
                 best_hash,
                 genesis_hash,
+                pruned_until,
             } => {
                 s.begin_list(2);
                 s.append(&MESSAGE_ID_STATUS);
 
-                s.begin_list(3);
+                let len = if pruned_until.is_some() {
+                    4
+                } else {
+                    3
+                };
+                s.begin_list(len);
                 s.append(total_score);
                 s.append(best_hash);
                 s.append(genesis_hash);
+                if let Some(pruned_until) = pruned_until {
+                    s.append(pruned_until);
+                }
             }
             Message::Request(request_id, request) => {
                 s.begin_list(3);
@@ -85,14 +100,17 @@ impl Decodable for Message {
             }
             let message = rlp.at(1)?;
 
-            if message.item_count()? != 3 {
-                return Err(DecoderError::RlpIncorrectListLen)
-            }
+            let pruned_until = match message.item_count()? {
+                3 => None,
+                4 => Some(message.val_at(3)?),
+                _ => return Err(DecoderError::RlpIncorrectListLen),
+            };
 
             Ok(Message::Status {
                 total_score: message.val_at(0)?,
                 best_hash: message.val_at(1)?,
                 genesis_hash: message.val_at(2)?,
+                pruned_until,
             })
         } else {
             if rlp.item_count()? != 3 {
@@ -104,10 +122,13 @@ impl Decodable for Message {
                 MESSAGE_ID_GET_HEADERS
                 | MESSAGE_ID_GET_BODIES
                 | MESSAGE_ID_GET_STATE_HEAD
-                | MESSAGE_ID_GET_STATE_CHUNK => Ok(Message::Request(request_id, RequestMessage::decode(id, &message)?)),
-                MESSAGE_ID_HEADERS | MESSAGE_ID_BODIES | MESSAGE_ID_STATE_HEAD | MESSAGE_ID_STATE_CHUNK => {
-                    Ok(Message::Response(request_id, ResponseMessage::decode(id, &message)?))
-                }
+                | MESSAGE_ID_GET_STATE_CHUNK
+                | MESSAGE_ID_GET_STATE_NODES => Ok(Message::Request(request_id, RequestMessage::decode(id, &message)?)),
+                MESSAGE_ID_HEADERS
+                | MESSAGE_ID_BODIES
+                | MESSAGE_ID_STATE_HEAD
+                | MESSAGE_ID_STATE_CHUNK
+                | MESSAGE_ID_STATE_NODES => Ok(Message::Response(request_id, ResponseMessage::decode(id, &message)?)),
                 _ => Err(DecoderError::Custom("Unknown message id detected")),
             }
         }
@@ -126,6 +147,13 @@ mod tests {
             total_score: U256::default(),
             best_hash: H256::default(),
             genesis_hash: H256::default(),
+            pruned_until: None,
+        });
+        rlp_encode_and_decode_test!(Message::Status {
+            total_score: U256::default(),
+            best_hash: H256::default(),
+            genesis_hash: H256::default(),
+            pruned_until: Some(1000),
         });
     }
 
@@ -140,4 +168,17 @@ mod tests {
         let request_id = 10;
         rlp_encode_and_decode_test!(Message::Request(request_id, RequestMessage::StateHead(H256::random())));
     }
+
+    #[test]
+    fn state_nodes_rlp() {
+        let request_id = 10;
+        rlp_encode_and_decode_test!(Message::Request(
+            request_id,
+            RequestMessage::StateNodes {
+                hash: H256::random(),
+                path: vec![3, 4],
+            }
+        ));
+        rlp_encode_and_decode_test!(Message::Response(request_id, ResponseMessage::StateNodes(vec![vec![0xc0]])));
+    }
 }
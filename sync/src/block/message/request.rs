@@ -30,6 +30,12 @@ pub enum RequestMessage {
         block_hash: H256,
         tree_root: H256,
     },
+    /// The trie node of the hash and the nodes below it on the path, which is given in nibbles.
+    /// The light clients use them to read the state.
+    StateNodes {
+        hash: H256,
+        path: Vec<u8>,
+    },
 }
 
 impl Encodable for RequestMessage {
@@ -58,6 +64,14 @@ impl Encodable for RequestMessage {
                 s.append(block_hash);
                 s.append(tree_root);
             }
+            RequestMessage::StateNodes {
+                hash,
+                path,
+            } => {
+                s.begin_list(2);
+                s.append(hash);
+                s.append(path);
+            }
         };
     }
 }
@@ -73,6 +87,9 @@ impl RequestMessage {
             RequestMessage::StateChunk {
                 ..
             } => super::MESSAGE_ID_GET_STATE_CHUNK,
+            RequestMessage::StateNodes {
+                ..
+            } => super::MESSAGE_ID_GET_STATE_NODES,
         }
     }
 
@@ -103,6 +120,15 @@ impl RequestMessage {
                     tree_root: rlp.val_at(1)?,
                 }
             }
+            super::MESSAGE_ID_GET_STATE_NODES => {
+                if rlp.item_count()? != 2 {
+                    return Err(DecoderError::RlpIncorrectListLen)
+                }
+                RequestMessage::StateNodes {
+                    hash: rlp.val_at(0)?,
+                    path: rlp.val_at(1)?,
+                }
+            }
             _ => return Err(DecoderError::Custom("Unknown message id detected")),
         };
 
@@ -151,4 +177,13 @@ mod tests {
         };
         assert_eq!(message, decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
     }
+
+    #[test]
+    fn request_state_nodes_message_rlp() {
+        let message = RequestMessage::StateNodes {
+            hash: H256::default(),
+            path: vec![1, 15, 0],
+        };
+        assert_eq!(message, decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
+    }
 }
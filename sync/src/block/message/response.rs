@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use ccore::{Header, UnverifiedTransaction};
+use primitives::Bytes;
 use rlp::{DecoderError, Encodable, RlpStream, UntrustedRlp};
 
 #[derive(Debug, PartialEq)]
@@ -23,6 +24,8 @@ pub enum ResponseMessage {
     Bodies(Vec<Vec<UnverifiedTransaction>>),
     StateHead(Vec<u8>),
     StateChunk(Vec<u8>),
+    /// The requested trie node followed by the nodes below it on the path. It's empty if the node is not found.
+    StateNodes(Vec<Bytes>),
 }
 
 impl Encodable for ResponseMessage {
@@ -45,6 +48,9 @@ impl Encodable for ResponseMessage {
                 s.begin_list(1);
                 s.append(bytes);
             }
+            ResponseMessage::StateNodes(nodes) => {
+                s.append_list::<Bytes, _>(nodes);
+            }
         };
     }
 }
@@ -60,6 +66,7 @@ impl ResponseMessage {
             ResponseMessage::StateChunk {
                 ..
             } => super::MESSAGE_ID_STATE_CHUNK,
+            ResponseMessage::StateNodes(..) => super::MESSAGE_ID_STATE_NODES,
         }
     }
 
@@ -85,6 +92,7 @@ impl ResponseMessage {
                 }
                 ResponseMessage::StateChunk(rlp.val_at(0)?)
             }
+            super::MESSAGE_ID_STATE_NODES => ResponseMessage::StateNodes(rlp.as_list()?),
             _ => return Err(DecoderError::Custom("Unknown message id detected")),
         };
 
@@ -132,4 +140,10 @@ mod tests {
         let message = ResponseMessage::StateChunk(vec![]);
         assert_eq!(message, decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
     }
+
+    #[test]
+    fn state_nodes_message_rlp() {
+        let message = ResponseMessage::StateNodes(vec![vec![0xc0], vec![]]);
+        assert_eq!(message, decode_bytes(message.message_id(), message.rlp_bytes().as_ref()));
+    }
 }
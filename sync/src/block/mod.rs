@@ -17,10 +17,11 @@
 mod downloader;
 mod extension;
 mod message;
+mod state_nodes;
 
 use cnetwork::NodeId;
 
-pub use self::extension::Extension as BlockSyncExtension;
+pub use self::extension::{Extension as BlockSyncExtension, StateNodeFetcher};
 
 pub trait BlockSyncInfo: Send + Sync {
     fn get_peers(&self) -> Vec<NodeId>;
@@ -0,0 +1,237 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use ccore::COL_STATE;
+use ccrypto::blake256;
+use journaldb::{self, Algorithm};
+use kvdb::{DBTransaction, DBValue, KeyValueDB};
+use primitives::{Bytes, H256};
+use rlp::UntrustedRlp;
+use util_error::UtilError;
+
+/// The maximum number of the nodes in a response.
+pub const MAX_STATE_NODES: usize = 256;
+
+/// The number of the nibbles in a path of the trie.
+pub const MAX_PATH_NIBBLES: usize = 64;
+
+/// Returns the node of the hash and the nodes below it on the path, which is given in nibbles.
+pub fn nodes_on_path<F>(get: F, hash: &H256, path: &[u8]) -> Vec<Bytes>
+where
+    F: Fn(&H256) -> Option<Bytes>, {
+    let mut nodes = Vec::new();
+    let mut next = Some((*hash, path));
+    while let Some((hash, path)) = next {
+        if nodes.len() == MAX_STATE_NODES {
+            break
+        }
+        let node = match get(&hash) {
+            Some(node) => node,
+            None => break,
+        };
+        next = child_on_path(&node, path);
+        nodes.push(node);
+    }
+    nodes
+}
+
+/// Returns the nodes on the path from the node of the hash, with their hashes.
+/// The nodes after the first one which is not on the path are dropped.
+pub fn verify_nodes_on_path(hash: &H256, path: &[u8], nodes: Vec<Bytes>) -> Vec<(H256, Bytes)> {
+    let mut verified = Vec::new();
+    let mut next = Some((*hash, path));
+    for node in nodes {
+        let (hash, path) = match next {
+            Some(next) => next,
+            None => break,
+        };
+        if blake256(&node) != hash {
+            break
+        }
+        next = child_on_path(&node, path);
+        verified.push((hash, node));
+    }
+    verified
+}
+
+/// Stores the fetched nodes through the journal DB, so they are read like the other state nodes.
+pub fn store_nodes(backing: Arc<KeyValueDB>, pruning: Algorithm, nodes: &[(H256, Bytes)]) -> Result<(), UtilError> {
+    let mut journal = journaldb::new(backing, pruning, COL_STATE);
+    for (hash, node) in nodes {
+        if !journal.contains(hash) {
+            journal.emplace(*hash, DBValue::from_slice(node));
+        }
+    }
+    let mut batch = DBTransaction::new();
+    journal.inject(&mut batch)?;
+    journal.backing().write(batch)?;
+    Ok(())
+}
+
+/// Returns the child of the branch node on the path, and the rest of the path below it.
+fn child_on_path<'a>(node: &[u8], path: &'a [u8]) -> Option<(H256, &'a [u8])> {
+    let rlp = UntrustedRlp::new(node);
+    // Only the branch nodes have children.
+    if rlp.item_count().ok()? != 17 {
+        return None
+    }
+    let partial = partial_nibbles(rlp.at(0).ok()?.data().ok()?)?;
+    if !path.starts_with(&partial) {
+        return None
+    }
+    let (index, rest) = path[partial.len()..].split_first()?;
+    if *index >= 16 {
+        return None
+    }
+    let child = rlp.at(usize::from(*index) + 1).ok()?;
+    if child.is_empty() {
+        return None
+    }
+    Some((child.as_val().ok()?, rest))
+}
+
+/// Decodes the nibbles of the partial path of a node, which are hex-prefix encoded.
+fn partial_nibbles(encoded: &[u8]) -> Option<Vec<u8>> {
+    let (first, rest) = encoded.split_first()?;
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    if first & 0b1_0000 == 0b1_0000 {
+        nibbles.push(first & 0b1111);
+    }
+    for byte in rest {
+        nibbles.push(byte >> 4);
+        nibbles.push(byte & 0b1111);
+    }
+    Some(nibbles)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use cmerkle::{Trie, TrieDB, TrieDBMut, TrieMut};
+    use cstate::{RemoteNodes, StateDB};
+    use hashdb::{AsHashDB, HashDB};
+    use journaldb::JournalDB;
+    use kvdb_memorydb;
+    use trie_standardmap::{Alphabet, StandardMap, ValueMode};
+
+    use super::*;
+
+    fn nibbles(key: &[u8]) -> Vec<u8> {
+        blake256(key).iter().flat_map(|byte| vec![byte >> 4, byte & 0b1111]).collect()
+    }
+
+    fn full_trie() -> (Box<JournalDB>, H256, Vec<(Bytes, Bytes)>) {
+        let mut seed = H256::new();
+        let entries = StandardMap {
+            alphabet: Alphabet::Custom(b"@QWERTYUIOPASDFGHJKLZXCVBNM[/]^_".to_vec()),
+            min_key: 5,
+            journal_key: 0,
+            value_mode: ValueMode::Index,
+            count: 1000,
+        }
+        .make_with(&mut seed);
+
+        let mut root = H256::new();
+        let mut jdb = journaldb::new(Arc::new(kvdb_memorydb::create(1)), Algorithm::Archive, COL_STATE);
+        {
+            let mut trie = TrieDBMut::new(jdb.as_hashdb_mut(), &mut root);
+            for (key, value) in &entries {
+                trie.insert(key, value).unwrap();
+            }
+        }
+        (jdb, root, entries)
+    }
+
+    #[test]
+    fn nodes_on_path_reach_the_leaf() {
+        let (jdb, root, entries) = full_trie();
+        let get = |hash: &H256| jdb.get(hash).map(|node| node.to_vec());
+        for (key, _) in entries.iter().take(10) {
+            let path = nibbles(key);
+            let nodes = nodes_on_path(get, &root, &path);
+            assert!(nodes.len() > 1);
+            // The last one is the leaf.
+            assert_eq!(2, UntrustedRlp::new(nodes.last().unwrap()).item_count().unwrap());
+
+            let verified = verify_nodes_on_path(&root, &path, nodes.clone());
+            assert_eq!(nodes, verified.into_iter().map(|(_, node)| node).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn nodes_off_the_path_are_dropped() {
+        let (jdb, root, entries) = full_trie();
+        let get = |hash: &H256| jdb.get(hash).map(|node| node.to_vec());
+        let path = nibbles(&entries[0].0);
+        let nodes = nodes_on_path(get, &root, &path);
+        let other_nodes = nodes_on_path(get, &root, &nibbles(&entries[1].0));
+
+        let mut tampered = nodes.clone();
+        tampered[1] = vec![0x80];
+        assert_eq!(1, verify_nodes_on_path(&root, &path, tampered).len());
+
+        let mut extended = nodes.clone();
+        extended.push(other_nodes.last().unwrap().clone());
+        assert_eq!(nodes.len(), verify_nodes_on_path(&root, &path, extended).len());
+
+        assert!(verify_nodes_on_path(&H256::random(), &path, nodes).is_empty());
+    }
+
+    struct Peer {
+        full: Box<JournalDB>,
+        light: Arc<KeyValueDB>,
+        requests: AtomicUsize,
+    }
+
+    impl RemoteNodes for Peer {
+        fn fetch(&self, hash: &H256, path: &[u8]) -> Option<DBValue> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let nodes = nodes_on_path(|hash| self.full.get(hash).map(|node| node.to_vec()), hash, path);
+            let verified = verify_nodes_on_path(hash, path, nodes);
+            store_nodes(Arc::clone(&self.light), Algorithm::Archive, &verified).unwrap();
+            verified.first().map(|(_, node)| DBValue::from_slice(node))
+        }
+    }
+
+    #[test]
+    fn light_state_fetches_the_path_at_once() {
+        let (full, root, entries) = full_trie();
+        let key = &entries[0].0;
+        let value = TrieDB::try_new(full.as_hashdb(), &root).unwrap().get(key).unwrap();
+        let light: Arc<KeyValueDB> = Arc::new(kvdb_memorydb::create(1));
+        let peer = Arc::new(Peer {
+            full,
+            light: Arc::clone(&light),
+            requests: AtomicUsize::new(0),
+        });
+        let mut state_db = StateDB::new(journaldb::new(Arc::clone(&light), Algorithm::Archive, COL_STATE));
+        state_db.set_remote(Arc::clone(&peer) as Arc<RemoteNodes>);
+
+        let trie = TrieDB::try_new(&state_db, &root).unwrap();
+        assert_eq!(1, peer.requests.load(Ordering::SeqCst));
+
+        assert!(value.is_some());
+        assert_eq!(value, trie.get(key).unwrap());
+        assert_eq!(2, peer.requests.load(Ordering::SeqCst));
+
+        // The fetched nodes are stored.
+        assert_eq!(value, trie.get(key).unwrap());
+        assert_eq!(2, peer.requests.load(Ordering::SeqCst));
+    }
+}
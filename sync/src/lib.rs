@@ -17,6 +17,7 @@
 extern crate parking_lot;
 
 extern crate codechain_core as ccore;
+extern crate codechain_crypto as ccrypto;
 extern crate codechain_merkle as cmerkle;
 #[macro_use]
 extern crate codechain_logger as clogger;
@@ -25,7 +26,6 @@ extern crate codechain_state as cstate;
 extern crate codechain_timer as ctimer;
 extern crate codechain_types as ctypes;
 
-#[cfg(test)]
 extern crate hashdb;
 extern crate journaldb;
 extern crate kvdb;
@@ -49,7 +49,7 @@ mod block;
 mod parcel;
 mod snapshot;
 
-pub use crate::block::{BlockSyncExtension, BlockSyncInfo};
+pub use crate::block::{BlockSyncExtension, BlockSyncInfo, StateNodeFetcher};
 pub use crate::parcel::ParcelSyncExtension;
 pub use crate::snapshot::SnapshotService;
 
@@ -19,7 +19,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use std::thread::spawn;
 
-use ccore::{BlockChainClient, BlockId, BlockInfo, ChainInfo, ChainNotify, Client, DatabaseClient};
+use ccore::{BlockChainClient, BlockId, BlockInfo, ChainInfo, ChainNotify, Client, DatabaseClient, EngineInfo};
 
 use primitives::H256;
 
@@ -30,17 +30,14 @@ pub struct Service {
     client: Arc<Client>,
     /// Snapshot root directory
     root_dir: String,
-    /// Snapshot creation period in unit of block numbers
-    period: u64,
 }
 
 impl Service {
     #![cfg_attr(feature = "cargo-clippy", allow(clippy::new_ret_no_self))]
-    pub fn new(client: Arc<Client>, root_dir: String, period: u64) -> Arc<Self> {
+    pub fn new(client: Arc<Client>, root_dir: String) -> Arc<Self> {
         Arc::new(Self {
             client,
             root_dir,
-            period,
         })
     }
 }
@@ -57,12 +54,20 @@ impl ChainNotify for Service {
         _duration: u64,
     ) {
         let best_number = self.client.chain_info().best_block_number;
+        // Snapshot creation period in unit of block numbers
+        let period = match self.client.common_params(BlockId::Latest) {
+            Some(common_params) => common_params.snapshot_period,
+            None => {
+                cerror!(SNAPSHOT, "Cannot find the common params of the best block");
+                return
+            }
+        };
         let is_checkpoint = enacted
             .iter()
             .map(|hash| self.client.block_number(&BlockId::Hash(*hash)).expect("Enacted block must exist"))
-            .any(|number| number % self.period == 0);
-        if is_checkpoint && best_number > self.period {
-            let number = (best_number / self.period - 1) * self.period;
+            .any(|number| number % period == 0);
+        if is_checkpoint && best_number > period {
+            let number = (best_number / period - 1) * period;
             let header = self.client.block_header(&BlockId::Number(number)).expect("Snapshot target must exist");
 
             let db = self.client.database();
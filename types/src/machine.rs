@@ -85,4 +85,8 @@ pub trait WithBalances: Machine {
 
     /// Increment the balance of an account in the state of the live block.
     fn add_balance(&self, live: &mut Self::LiveBlock, address: &Address, amount: u64) -> Result<(), Self::Error>;
+
+    /// Record the fee burned in the live block.
+    /// The fee was already subtracted from the balances of the payers.
+    fn burn_fee(&self, live: &mut Self::LiveBlock, amount: u64) -> Result<(), Self::Error>;
 }
@@ -74,6 +74,8 @@ pub enum Error {
     TextVerificationFail(String),
     TextNotExist,
     TextContentTooBig,
+    /// The custom action is rejected by its handler
+    InvalidCustomAction(String),
 }
 
 const ERROR_ID_TX_ALREADY_IMPORTED: u8 = 1u8;
@@ -99,6 +101,7 @@ const ERROR_ID_ZERO_AMOUNT: u8 = 23u8;
 const ERROR_ID_TEXT_VERIFICATION_FAIL: u8 = 24u8;
 const ERROR_ID_TEXT_NOT_EXIST: u8 = 25u8;
 const ERROR_ID_TEXT_CONTENT_TOO_BIG: u8 = 26u8;
+const ERROR_ID_INVALID_CUSTOM_ACTION: u8 = 27u8;
 
 impl Error {
     fn item_count(&self) -> usize {
@@ -130,6 +133,7 @@ impl Error {
             Error::TextVerificationFail(_) => 2,
             Error::TextNotExist => 1,
             Error::TextContentTooBig => 1,
+            Error::InvalidCustomAction(_) => 2,
         }
     }
 }
@@ -169,6 +173,7 @@ impl Encodable for Error {
             Error::TextVerificationFail(err) => s.append(&ERROR_ID_TEXT_VERIFICATION_FAIL).append(err),
             Error::TextNotExist => s.append(&ERROR_ID_TEXT_NOT_EXIST),
             Error::TextContentTooBig => s.append(&ERROR_ID_TEXT_CONTENT_TOO_BIG),
+            Error::InvalidCustomAction(err) => s.append(&ERROR_ID_INVALID_CUSTOM_ACTION).append(err),
         };
     }
 }
@@ -207,6 +212,7 @@ impl Decodable for Error {
             ERROR_ID_TEXT_VERIFICATION_FAIL => Error::TextVerificationFail(rlp.val_at(1)?),
             ERROR_ID_TEXT_NOT_EXIST => Error::TextNotExist,
             ERROR_ID_TEXT_CONTENT_TOO_BIG => Error::TextContentTooBig,
+            ERROR_ID_INVALID_CUSTOM_ACTION => Error::InvalidCustomAction(rlp.val_at(1)?),
             _ => return Err(DecoderError::Custom("Invalid parcel error")),
         };
         if rlp.item_count()? != error.item_count() {
@@ -251,6 +257,7 @@ impl Display for Error {
             Error::TextVerificationFail(err) => format!("Text verification has failed: {}", err),
             Error::TextNotExist => "The text does not exist".to_string(),
             Error::TextContentTooBig => "The content of the text is too big".into(),
+            Error::InvalidCustomAction(err) => format!("The custom action is invalid: {}", err),
         };
 
         f.write_fmt(format_args!("Parcel error ({})", msg))
@@ -34,6 +34,13 @@ pub trait HashDB: AsHashDB + Send + Sync {
     /// hash is not known.
     fn get(&self, key: &H256) -> Option<DBValue>;
 
+    /// Like `get()`, but it's given the path of the key which the trie looks up. The nibbles from `offset` of `path`
+    /// lead to the node and the nodes below it. A database which fetches the missing nodes from elsewhere uses them to
+    /// fetch the nodes on the path at once.
+    fn get_on_path(&self, key: &H256, _path: &[u8], _offset: usize) -> Option<DBValue> {
+        self.get(key)
+    }
+
     /// Check for the existance of a hash-key.
     fn contains(&self, key: &H256) -> bool;
 
@@ -246,6 +246,7 @@ pub struct Database {
     db: RwLock<Option<DBAndColumns>>,
     config: DatabaseConfig,
     write_opts: WriteOptions,
+    sync_write_opts: WriteOptions,
     read_opts: ReadOptions,
     block_opts: BlockBasedOptions,
     path: String,
@@ -328,6 +329,8 @@ impl Database {
         if !config.wal {
             write_opts.disable_wal(true);
         }
+        let mut sync_write_opts = WriteOptions::new();
+        sync_write_opts.set_sync(true);
         let mut read_opts = ReadOptions::new();
         read_opts.set_verify_checksums(false);
 
@@ -388,6 +391,7 @@ impl Database {
             })),
             config: *config,
             write_opts,
+            sync_write_opts,
             overlay: RwLock::new((0..=num_cols).map(|_| HashMap::new()).collect()),
             flushing: RwLock::new((0..=num_cols).map(|_| HashMap::new()).collect()),
             flushing_lock: Mutex::new(false),
@@ -504,6 +508,15 @@ impl Database {
 
     /// Commit parcel to database.
     pub fn write(&self, tr: DBTransaction) -> Result<()> {
+        self.write_with_opts(tr, &self.write_opts)
+    }
+
+    /// Commit parcel to database and wait until it reaches the disk.
+    pub fn write_sync(&self, tr: DBTransaction) -> Result<()> {
+        self.write_with_opts(tr, &self.sync_write_opts)
+    }
+
+    fn write_with_opts(&self, tr: DBTransaction, write_opts: &WriteOptions) -> Result<()> {
         match *self.db.read() {
             Some(DBAndColumns {
                 ref db,
@@ -537,7 +550,7 @@ impl Database {
                     }
                 }
 
-                check_for_corruption(&self.path, db.write_opt(batch, &self.write_opts)).map_err(Into::into)
+                check_for_corruption(&self.path, db.write_opt(batch, write_opts)).map_err(Into::into)
             }
             None => Err("Database is closed".into()),
         }
@@ -784,6 +797,10 @@ impl KeyValueDB for Database {
         Database::write(self, transaction)
     }
 
+    fn write_sync(&self, transaction: DBTransaction) -> Result<()> {
+        Database::write_sync(self, transaction)
+    }
+
     fn flush(&self) -> Result<()> {
         Database::flush(self)
     }
@@ -164,6 +164,11 @@ pub trait KeyValueDB: Sync + Send {
         self.flush()
     }
 
+    /// Write a parcel of changes to the backing store and wait until it is persisted on the disk.
+    fn write_sync(&self, transaction: DBTransaction) -> Result<()> {
+        self.write(transaction)
+    }
+
     /// Flush all buffered data.
     fn flush(&self) -> Result<()>;
 
@@ -40,7 +40,7 @@ pub mod triehash;
 
 pub use crate::node::Node;
 pub use crate::skewed::skewed_merkle_root;
-pub use crate::triedb::TrieDB;
+pub use crate::triedb::{verify_proof, TrieDB};
 pub use crate::triedbmut::TrieDBMut;
 
 /// Trie Errors.
@@ -53,6 +53,8 @@ pub enum TrieError {
     InvalidStateRoot(H256),
     /// Trie item not found in the database,
     IncompleteDatabase(H256),
+    /// The proof doesn't have the valid node of the hash.
+    InvalidProof(H256),
 }
 
 impl fmt::Display for TrieError {
@@ -60,6 +62,7 @@ impl fmt::Display for TrieError {
         match self {
             TrieError::InvalidStateRoot(root) => write!(f, "Invalid state root: {}", root),
             TrieError::IncompleteDatabase(missing) => write!(f, "Database missing expected key: {}", missing),
+            TrieError::InvalidProof(hash) => write!(f, "Proof missing the valid node: {}", hash),
         }
     }
 }
@@ -16,7 +16,8 @@
 
 use ccrypto::blake256;
 use hashdb::HashDB;
-use primitives::H256;
+use primitives::{Bytes, H256};
+use rlp::UntrustedRlp;
 
 use crate::nibbleslice::NibbleSlice;
 use crate::node::Node as RlpNode;
@@ -71,6 +72,32 @@ impl<'db> TrieDB<'db> {
         self.db
     }
 
+    /// Returns the nodes on the path from the root to the key.
+    /// They prove the value of the key, or that the key is not in the trie.
+    pub fn get_proof(&self, key: &[u8]) -> crate::Result<Vec<Bytes>> {
+        let path = blake256(key);
+        let mut path = NibbleSlice::new(&path);
+        let mut proof = Vec::new();
+        let mut cur_node_hash = Some(*self.root);
+        while let Some(hash) = cur_node_hash {
+            let node_rlp = self.db.get(&hash).ok_or_else(|| TrieError::IncompleteDatabase(hash))?;
+            cur_node_hash = match RlpNode::decoded(&node_rlp) {
+                Some(RlpNode::Branch(partial, children)) => {
+                    if path.starts_with(&partial) {
+                        let child = children[path.mid(partial.len()).at(0) as usize];
+                        path = path.mid(partial.len() + 1);
+                        child
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            };
+            proof.push(node_rlp.to_vec());
+        }
+        Ok(proof)
+    }
+
     /// Get auxiliary
     fn get_aux<Q: Query>(
         &self,
@@ -80,7 +107,10 @@ impl<'db> TrieDB<'db> {
     ) -> crate::Result<Option<Q::Item>> {
         match cur_node_hash {
             Some(hash) => {
-                let node_rlp = self.db.get(&hash).ok_or_else(|| TrieError::IncompleteDatabase(hash))?;
+                let node_rlp = self
+                    .db
+                    .get_on_path(&hash, path.data, path.offset)
+                    .ok_or_else(|| TrieError::IncompleteDatabase(hash))?;
 
                 match RlpNode::decoded(&node_rlp) {
                     Some(RlpNode::Leaf(partial, value)) => {
@@ -122,6 +152,70 @@ impl<'db> Trie for TrieDB<'db> {
     }
 }
 
+/// Verifies the proof made by `TrieDB::get_proof` against the root, and returns the value of the key.
+pub fn verify_proof(root: &H256, key: &[u8], proof: &[Bytes]) -> crate::Result<Option<Bytes>> {
+    let path = blake256(key);
+    let mut path = NibbleSlice::new(&path);
+    let mut expected_hash = *root;
+    for (index, node_rlp) in proof.iter().enumerate() {
+        if blake256(node_rlp) != expected_hash || !is_valid_node(node_rlp) {
+            return Err(TrieError::InvalidProof(expected_hash))
+        }
+        let is_last = index + 1 == proof.len();
+        let child = match RlpNode::decoded(node_rlp) {
+            Some(RlpNode::Leaf(partial, value)) => {
+                if !is_last {
+                    return Err(TrieError::InvalidProof(expected_hash))
+                }
+                return Ok(if partial == path {
+                    Some(value.to_vec())
+                } else {
+                    None
+                })
+            }
+            Some(RlpNode::Branch(partial, children)) => {
+                if path.starts_with(&partial) && partial.len() < path.len() {
+                    let child = children[path.mid(partial.len()).at(0) as usize];
+                    path = path.mid(partial.len() + 1);
+                    child
+                } else {
+                    None
+                }
+            }
+            // The empty trie
+            None => {
+                if !is_last {
+                    return Err(TrieError::InvalidProof(expected_hash))
+                }
+                return Ok(None)
+            }
+        };
+        match child {
+            Some(child) => expected_hash = child,
+            None if is_last => return Ok(None),
+            None => return Err(TrieError::InvalidProof(expected_hash)),
+        }
+    }
+    Err(TrieError::InvalidProof(expected_hash))
+}
+
+/// Checks that the node can be decoded without panicking.
+fn is_valid_node(node_rlp: &[u8]) -> bool {
+    let rlp = UntrustedRlp::new(node_rlp);
+    if rlp.is_data() {
+        return rlp.is_empty()
+    }
+    let partial_is_valid = || rlp.at(0).and_then(|partial| partial.data()).map_or(false, |data| !data.is_empty());
+    match rlp.item_count() {
+        Ok(2) => partial_is_valid() && rlp.at(1).and_then(|value| value.data()).is_ok(),
+        Ok(17) => {
+            partial_is_valid()
+                && (1..17).all(|i| rlp.at(i).map_or(false, |child| child.is_empty() || child.as_val::<H256>().is_ok()))
+        }
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,4 +237,30 @@ mod tests {
         assert_eq!(t.get(b"B"), Ok(Some(DBValue::from_slice(b"ABCBA"))));
         assert_eq!(t.get(b"C"), Ok(None));
     }
+
+    #[test]
+    fn proof() {
+        let mut memdb = MemoryDB::new();
+        let mut root = H256::new();
+        {
+            let mut t = TrieDBMut::new(&mut memdb, &mut root);
+            t.insert(b"A", b"ABC").unwrap();
+            t.insert(b"B", b"ABCBA").unwrap();
+            t.insert(b"C", b"ABCBAB").unwrap();
+        }
+
+        let t = TrieDB::try_new(&memdb, &root).unwrap();
+        let proof = t.get_proof(b"A").unwrap();
+        assert_eq!(Ok(Some(b"ABC".to_vec())), verify_proof(&root, b"A", &proof));
+        // The proof of a key doesn't prove the other keys.
+        assert!(verify_proof(&root, b"B", &proof) != Ok(Some(b"ABCBA".to_vec())));
+
+        let proof = t.get_proof(b"D").unwrap();
+        assert_eq!(Ok(None), verify_proof(&root, b"D", &proof));
+
+        let mut proof = t.get_proof(b"B").unwrap();
+        proof.pop();
+        assert!(verify_proof(&root, b"B", &proof).is_err());
+        assert!(verify_proof(&H256::zero(), b"B", &t.get_proof(b"B").unwrap()).is_err());
+    }
 }
@@ -296,6 +296,32 @@ extern "C" {
         msg32: *const c_uchar,
     ) -> c_int;
 
+    pub fn secp256k1_schnorr_generate_nonce_pair(
+        cx: *const Context,
+        pubnonce: *mut PublicKey,
+        privnonce32: *mut c_uchar,
+        msg32: *const c_uchar,
+        sk: *const c_uchar,
+        noncefn: NonceFn,
+        noncedata: *const c_void,
+    ) -> c_int;
+
+    pub fn secp256k1_schnorr_partial_sign(
+        cx: *const Context,
+        sig64: *mut c_uchar,
+        msg32: *const c_uchar,
+        sk: *const c_uchar,
+        pubnonce_others: *const PublicKey,
+        secnonce32: *const c_uchar,
+    ) -> c_int;
+
+    pub fn secp256k1_schnorr_partial_combine(
+        cx: *const Context,
+        sig64: *mut c_uchar,
+        sig64sin: *const *const c_uchar,
+        n: usize,
+    ) -> c_int;
+
     // EC
     pub fn secp256k1_ec_seckey_verify(cx: *const Context, sk: *const c_uchar) -> c_int;
 
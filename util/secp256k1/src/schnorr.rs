@@ -101,14 +101,88 @@ impl Secp256k1 {
         };
         Ok(PublicKey::from(pk))
     }
+
+    /// Generates the secret and public nonce of a signer for a multiparty Schnorr signature.
+    /// The secret nonce must not be revealed.
+    pub fn generate_schnorr_nonce_pair(&self, msg: &Message, sk: &SecretKey) -> Result<(SecretKey, PublicKey), Error> {
+        if self.caps == ContextFlag::VerifyOnly || self.caps == ContextFlag::None {
+            return Err(Error::IncapableContext)
+        }
+
+        let mut secnonce = [0; constants::SECRET_KEY_SIZE];
+        let mut pubnonce = unsafe { ffi::PublicKey::blank() };
+        unsafe {
+            if ffi::secp256k1_schnorr_generate_nonce_pair(
+                self.ctx,
+                &mut pubnonce,
+                secnonce.as_mut_ptr(),
+                msg.as_ptr(),
+                sk.as_ptr(),
+                ffi::secp256k1_nonce_function_rfc6979,
+                ptr::null(),
+            ) != 1
+            {
+                return Err(Error::InvalidSecretKey)
+            }
+        }
+        Ok((SecretKey::from(secnonce), PublicKey::from(pubnonce)))
+    }
+
+    /// Creates a partial Schnorr signature with the secret nonce of the signer and the sum of the public nonces of the
+    /// other signers. The partial signatures of all signers are combined by `combine_schnorr_partial`.
+    pub fn sign_schnorr_partial(
+        &self,
+        msg: &Message,
+        sk: &SecretKey,
+        other_pubnonces: &PublicKey,
+        secnonce: &SecretKey,
+    ) -> Result<Signature, Error> {
+        if self.caps == ContextFlag::VerifyOnly || self.caps == ContextFlag::None {
+            return Err(Error::IncapableContext)
+        }
+
+        let mut ret: Signature = unsafe { mem::uninitialized() };
+        unsafe {
+            if ffi::secp256k1_schnorr_partial_sign(
+                self.ctx,
+                ret.as_mut_ptr(),
+                msg.as_ptr(),
+                sk.as_ptr(),
+                other_pubnonces.as_ptr(),
+                secnonce.as_ptr(),
+            ) != 1
+            {
+                return Err(Error::InvalidSignature)
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Combines the partial Schnorr signatures into a signature which is verified with the sum of the public keys of
+    /// the signers.
+    pub fn combine_schnorr_partial(&self, sigs: &[Signature]) -> Result<Signature, Error> {
+        if sigs.is_empty() {
+            return Err(Error::InvalidSignature)
+        }
+
+        let inputs: Vec<*const u8> = sigs.iter().map(|sig| sig.as_ptr()).collect();
+        let mut ret: Signature = unsafe { mem::uninitialized() };
+        unsafe {
+            if ffi::secp256k1_schnorr_partial_combine(self.ctx, ret.as_mut_ptr(), inputs.as_ptr(), inputs.len()) != 1 {
+                return Err(Error::InvalidSignature)
+            }
+        }
+        Ok(ret)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::Signature;
+    use key::PublicKey;
     use rand::{thread_rng, RngCore};
     use ContextFlag;
-    use Error::IncapableContext;
+    use Error::{IncapableContext, IncorrectSignature};
     use Message;
     use Secp256k1;
 
@@ -164,6 +238,50 @@ mod tests {
         assert!(s.verify_schnorr(&msg, &sig, &pk).is_ok());
     }
 
+    #[test]
+    fn combine_partial_signatures() {
+        let mut s = Secp256k1::new();
+        s.randomize(&mut thread_rng());
+
+        let mut msg = [0u8; 32];
+        thread_rng().fill_bytes(&mut msg);
+        let msg = Message::from_slice(&msg).unwrap();
+
+        let keypairs: Vec<_> = (0..3).map(|_| s.generate_keypair(&mut thread_rng()).unwrap()).collect();
+        let nonces: Vec<_> = keypairs.iter().map(|(sk, _)| s.generate_schnorr_nonce_pair(&msg, sk).unwrap()).collect();
+
+        let partials: Vec<_> = keypairs
+            .iter()
+            .enumerate()
+            .map(|(i, (sk, _))| {
+                let mut others: Option<PublicKey> = None;
+                for (_, pubnonce) in nonces.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, nonce)| nonce) {
+                    match others.as_mut() {
+                        Some(sum) => sum.add_assign(&s, pubnonce).unwrap(),
+                        None => others = Some(*pubnonce),
+                    }
+                }
+                s.sign_schnorr_partial(&msg, sk, &others.unwrap(), &nonces[i].0).unwrap()
+            })
+            .collect();
+        let sig = s.combine_schnorr_partial(&partials).unwrap();
+
+        let mut pk = keypairs[0].1;
+        for (_, other) in &keypairs[1..] {
+            pk.add_assign(&s, other).unwrap();
+        }
+        assert!(s.verify_schnorr(&msg, &sig, &pk).is_ok());
+        assert_eq!(s.recover_schnorr(&msg, &sig), Ok(pk));
+
+        // The signature doesn't belong to a part of the signers.
+        let mut partial_pk = keypairs[0].1;
+        partial_pk.add_assign(&s, &keypairs[1].1).unwrap();
+        assert_eq!(s.verify_schnorr(&msg, &sig, &partial_pk), Err(IncorrectSignature));
+        for (_, pk) in &keypairs {
+            assert_eq!(s.verify_schnorr(&msg, &sig, pk), Err(IncorrectSignature));
+        }
+    }
+
     #[test]
     fn deserialize() {
         let mut s = Secp256k1::new();